@@ -2,12 +2,34 @@ program-about = Web archive software suite
 
 input-warc-file-help = Path to WARC file
 input-json-file-help = Path to JSON file
+input-arc-file-help = Path to ARC file
+input-wacz-file-help = Path to WACZ file
+input-har-file-help = Path to HAR file
 output-file-help = Path to output file
 output-dir-help = Path of directory to write files
 output-compression-format-help = Apply compression to the output
+auto-compression-format-help = Apply compression to the output ("auto" uses the format of the first input)
+max-size-help = Start a new file when the file reaches the size in bytes
+max-records-help = Start a new file when the file reaches the number of records
+rewrite-warcinfo-help = Change the WARC-Filename field of warcinfo records to the new filename
+mapping-file-help = Write the old and new location of each record to a file
+mapping-file-help-long = Write the old and new location of each record to a file.
+
+    Each line of the file is a JSON object with the fields "source_file", "source_offset", "target_file", "target_offset", and "target_length". This can be used for updating indexes.
 output-as-json-help = Format the output as JSON
 output-warc-file-help = Path to output WARC file
+output-wacz-file-help = Path to output WACZ file
 allow-overwrite-help = Allow overwriting existing files
+recover-help = Skip malformed records and corrupt data
+recover-help-long =
+    Skip malformed records and corrupt data.
+
+    When an error occurs, the file is scanned for the next compressed member or WARC record and reading continues. The skipped byte ranges are logged as warnings.
+jobs-help = Number of files to process at the same time
+jobs-help-long =
+    Number of files to process at the same time.
+
+    Output is written in the same order as the input files.
 
 verbose-help = Print informative messages
 verbose-help-long = Print informative messages such as progress bars or status updates. The log level is also adjusted to "info" if not set.
@@ -44,23 +66,120 @@ warc-dump-about = Transform WARC files to JSON formatted output
 warc-list-about = Listing of file contents using header fields
 warc-load-about = Transform JSON formatted input to WARC file
 warc-pack-about = Repackages WARC files
-warc-pack-about-long = Repackages WARC files by splitting or joining them.
+warc-pack-about-long = Repackages WARC files by recompressing them.
+
+    Records are copied without modification to the output using the compression format given by --compress. When using compression, each record is individually compressed (multistream) so that records can be accessed by offset.
+
+    With --output-directory, each input file is written to a file of the same name in the directory, with the compression extension changed to match the output format. Otherwise, the input files are joined into a single output.
+
+    Output files are read again afterwards to check that the block digests still match.
+warc-pack-zstd-dictionary-help = Train a dictionary from the input records and store it in the zstd output
+warc-filter-about = Select records matching an expression
+warc-filter-about-long = Select records matching an expression.
+
+    Records matching the expression are written to the output file. An expression is made of comparisons such as `WARC-Type == response` or `http.status >= 400` combined with "and", "or", "not", and parentheses. The operators are ==, !=, <, <=, >, >= and ~ and !~ for regular expression matching. Values containing spaces or operator characters can be quoted.
+
+    A field name is a WARC header field name, or one of "http.status", "http.mime", or "url.host" which are taken from the HTTP response and the target URI.
+
+    Records that are individually compressed are copied without recompression when the output format is the same. Input files must be seekable files; standard input is not supported.
+warc-filter-expression-help = Expression that records must match
+warc-filter-concurrent-help = Also include records related through WARC-Concurrent-To
+warc-merge-about = Combine WARC files into size-bounded files
+warc-merge-about-long = Combine WARC files into size-bounded files.
 
-    This command can be used to recompress, split, and join WARC files.
+    Records from all the input files are copied in order to a series of files in the output directory. A new file is started when the file reaches --max-size or --max-records. Without limits, all the records are written to a single file. Files are named PREFIX-TIMESTAMP-SERIAL-HOSTNAME.warc with the extension of the compression format.
 
-    Although it is safe to concatenate WARC files without the use of a WARC aware tool, recompression and splitting is not. When using compression, each record should be individually compressed (multistream). When splitting files, WARC consuming software may expect records such "warcinfo" to be first or "request" and "response" records to be in the same file. This command will attempt to automatically handle them.
+    Records that are individually compressed are copied without recompression when the output format is the same. Input files must be seekable files; standard input is not supported.
+warc-merge-prefix-help = Filename prefix of the output files
+warc-split-about = Split WARC files into smaller files
+warc-split-about-long = Split WARC files into smaller files.
+
+    Each input file is split into a series of files in the output directory. A new file is started when the file reaches --max-size or --max-records. Files are named PREFIX-TIMESTAMP-SERIAL-HOSTNAME.warc with the extension of the compression format. The prefix defaults to the name of the input file.
+
+    Records that are individually compressed are copied without recompression when the output format is the same. Input files must be seekable files; standard input is not supported.
+warc-split-prefix-help = Filename prefix of the output files [default: name of the input file]
+warc-get-about = Show a single record
+warc-get-about-long = Show a single record by its position or record ID.
+
+    The header of the record is written as it appears in the file. With --payload, the header is followed by the payload. For HTTP responses, the HTTP header is removed and the body is decoded from any transfer and content encoding.
+
+    When the input is a file, --offset seeks directly to the record. Otherwise, the records are read until the record is found. Offsets are positions in the (compressed) file as shown by the "index" command.
+warc-get-offset-help = Position of the record in the file
+warc-get-record-id-help = Value of the WARC-Record-ID field of the record
+warc-get-payload-help = Also write the decoded payload of the record
+warc-stats-about = Summarize the contents of WARC files
+warc-stats-about-long = Summarize the contents of WARC files.
+
+    The summary includes the number of records by type, the number of documents by MIME type and host, the number of HTTP responses by status class, the digest algorithms used, the total compressed and uncompressed sizes, and the range of record dates. Documents are response, resource, and revisit records.
 warc-extract-about = Decode and extract documents to files
 warc-extract-about-long = Decode and extract documents to files.
 
     This command will attempt to decode and extract as many documents as possible from response and resource records. By default, the files will be placed in directories similar to its original URL.
 
+    Revisit records are extracted using the payload of the original record they refer to. The original record is looked up by record ID, or by payload digest and URL, in all the given input files. The input files are read an additional time to index them when the first revisit record is found.
+
+    Documents can be selected with --accept, --reject, --accept-pattern, and --reject-pattern similar to the options of Wget. A document is extracted if it matches the accept lists (if any) and not the reject lists, and its URL matches the accept patterns (if any) and not the reject patterns.
+
     This command does *not* recreate a website for local browsing; this command is intended for use as an "unzipping" tool.
+warc-extract-accept-help = Comma-separated list of file name suffixes, wildcard patterns, or media types to extract
+warc-extract-accept-help-long =
+    Comma-separated list of file name suffixes, wildcard patterns, or media types to extract.
+
+    An entry containing "/" is a media type such as "text/html" or "image/*". An entry containing "*", "?", or "[" is a wildcard pattern matched against the file name of the URL. Otherwise, the entry is a suffix of the file name such as "html". The file name of a URL ending with "/" is "index.html". Entries are case-insensitive.
+warc-extract-reject-help = Comma-separated list of file name suffixes, wildcard patterns, or media types to skip
+warc-extract-accept-pattern-help = Regular expression that URLs of documents to extract must match
+warc-extract-reject-pattern-help = Regular expression that URLs of documents to extract must not match
 warc-checksum-about = Verifies checksums
 warc-checksum-about-long = Verifies WARC record checksums.
 
     This processes each WARC record for a "WARC-Block-Digest" field. If the record includes this field, the checksum is computed for the record's block.
 
     The output is formatted as the record's ID, a space, and one of "ok", "fail", or "skip".
+warc-index-about = Generate an index of records
+warc-index-about-long = Generate an index of records.
+
+    This command produces a CDXJ formatted index which allows replay software to locate records within WARC files. Each line consists of the URL in SURT form, a 14-digit timestamp, and a JSON object containing the URL, media type, HTTP status code, payload digest, record length, record offset, and filename.
+
+    Alternatively, the legacy 11-field CDX format ("N b a m s k r M S V g") can be produced. Unavailable values are written as "-".
+
+    Response, revisit, resource, and metadata records are indexed. For compressed files, the offsets are useful only if each record is compressed individually.
+warc-validate-about = Check WARC files for conformance
+warc-validate-about-long = Check WARC files for conformance to the WARC 1.0 and 1.1 specifications.
+
+    This command reports problems such as missing mandatory fields, malformed field values, wrong Content-Length values, and mismatched block or payload digests. Each problem is reported with the filename, the record's position in the file, and the record ID.
+
+    References to records that are not found in the given files and records not compressed individually are reported as warnings. The program exits with a non-zero code if any errors are found.
+warc-convert-arc-about = Convert ARC files to WARC file
+warc-convert-arc-about-long = Convert ARC files to WARC file.
+
+    Each ARC file's version block is converted to a "warcinfo" record and a "metadata" record describing the original file. Records with HTTP URLs are converted to "response" records and other records are converted to "resource" records. The original IP address and date are kept and digests are computed.
+warc-to-har-about = Convert HTTP requests and responses in WARC files to a HAR file
+warc-to-har-about-long = Convert HTTP requests and responses in WARC files to a HAR file.
+
+    HAR is a JSON format that can be loaded in web browser developer tools. Each "response" record is paired with its "request" record using the WARC-Concurrent-To field. Timings are estimated from the WARC-Date of the records. Bodies that are not text are encoded in Base64.
+
+    All transactions are held in memory until the file is written.
+warc-from-har-about = Convert HAR files to WARC file
+warc-from-har-about-long = Convert HAR files to WARC file.
+
+    Each HAR file is converted to a "warcinfo" record and a "metadata" record describing the original file and the browser that created it. Each entry is converted to a "request" record and a "response" record containing HTTP/1.1 messages reconstructed from the headers and content. Digests are computed.
+
+    HAR files store decoded bodies, so the Content-Encoding and Transfer-Encoding fields are removed and the Content-Length field is replaced. Entries without a response are skipped.
+warc-wacz-create-about = Package WARC files into a WACZ file
+warc-wacz-create-about-long = Package WARC files into a WACZ file.
+
+    A WACZ file is a ZIP file containing WARC files, a CDXJ index, a list of pages, and a "datapackage.json" file with SHA-256 digests of the contents. It is intended for use with browser-based replay software.
+
+    Pages are HTML documents from successful HTTP responses. For compressed WARC files, each record must be compressed individually so the index can be used. Use "warc pack" to recompress files that are not.
+warc-wacz-create-title-help = Title of the collection
+warc-wacz-verify-about = Verify WACZ file contents
+warc-wacz-verify-about-long = Verify WACZ file contents.
+
+    The sizes and digests of the files listed in "datapackage.json" are checked.
+
+    The output is formatted as the WACZ filename, a colon, the path of the file within the WACZ file, a space, and one of "ok", "fail", "missing", or "skip".
 
 warc-list-show-field-with-name-help = Show values with the given field name
 warc-list-include-file-help = Include filename and file position
+warc-index-sort-help = Sort the lines of the index
+warc-index-format-help = Format of the index
@@ -5,6 +5,11 @@ use std::{
     net::SocketAddr,
     path::{Path, PathBuf},
     str::FromStr,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc::{self, SyncSender},
+        Mutex,
+    },
 };
 
 use anyhow::Context;
@@ -68,7 +73,7 @@ impl OutputStream {
             opts.write(true);
 
             if overwrite {
-                opts.create(true);
+                opts.create(true).truncate(true);
             } else {
                 opts.create_new(true);
             }
@@ -146,6 +151,133 @@ impl MultiInput {
             None => Ok(None),
         }
     }
+
+    /// Advances the progress bar by the size of the given input file.
+    pub fn inc_progress_bar(&self, path: &Path) {
+        let file_size = std::fs::metadata(path)
+            .map(|metadata| metadata.len())
+            .unwrap_or_default();
+        self.progress_bar.inc(file_size);
+    }
+
+    /// Calls `process` for each remaining file using `jobs` threads.
+    ///
+    /// The output of each file is written to `output` in the order of the
+    /// input files. Output of files that are ahead of the first unfinished
+    /// file is held until it can be written.
+    pub fn for_each_file<F>(
+        &mut self,
+        jobs: usize,
+        output: &mut dyn Write,
+        process: F,
+    ) -> anyhow::Result<()>
+    where
+        F: Fn(&Path, InputStream, &mut dyn Write) -> anyhow::Result<()> + Sync,
+    {
+        if jobs <= 1 {
+            while let Some((path, file)) = self.next_file()? {
+                process(&path, file, output)?;
+            }
+
+            return Ok(());
+        }
+
+        let paths = Vec::from(std::mem::take(&mut self.pending_paths));
+        let mut senders = Vec::with_capacity(paths.len());
+        let mut receivers = Vec::with_capacity(paths.len());
+
+        for _ in &paths {
+            let (sender, receiver) = mpsc::sync_channel(OUTPUT_CHANNEL_CAPACITY);
+            senders.push(Mutex::new(Some(sender)));
+            receivers.push(receiver);
+        }
+
+        let next_index = AtomicUsize::new(0);
+        let is_cancelled = AtomicBool::new(false);
+
+        let worker = || loop {
+            let index = next_index.fetch_add(1, Ordering::Relaxed);
+
+            if index >= paths.len() || is_cancelled.load(Ordering::Relaxed) {
+                break;
+            }
+
+            let path = &paths[index];
+            let sender = senders[index].lock().unwrap().take().unwrap();
+            let mut writer = ChannelWriter::new(sender);
+
+            tracing::info!(?path, "reading file");
+
+            let result = InputStream::open(path)
+                .with_context(|| format!("failed to open file {path:?}"))
+                .and_then(|file| process(path, file, &mut writer))
+                .and_then(|_| Ok(writer.flush()?));
+
+            if let Err(error) = result {
+                is_cancelled.store(true, Ordering::Relaxed);
+                writer.send_error(error);
+            }
+        };
+
+        std::thread::scope(|scope| {
+            for _ in 0..jobs.min(paths.len()) {
+                scope.spawn(worker);
+            }
+
+            for receiver in receivers {
+                for chunk in receiver {
+                    output.write_all(&chunk?)?;
+                }
+            }
+
+            Ok(())
+        })
+    }
+}
+
+const OUTPUT_CHANNEL_CAPACITY: usize = 16;
+const OUTPUT_CHUNK_SIZE: usize = 65536;
+
+// Sends the output of a file in chunks to the thread writing the output.
+struct ChannelWriter {
+    sender: SyncSender<anyhow::Result<Vec<u8>>>,
+    buffer: Vec<u8>,
+}
+
+impl ChannelWriter {
+    fn new(sender: SyncSender<anyhow::Result<Vec<u8>>>) -> Self {
+        Self {
+            sender,
+            buffer: Vec::new(),
+        }
+    }
+
+    fn send_error(mut self, error: anyhow::Error) {
+        let _ = self.flush();
+        let _ = self.sender.send(Err(error));
+    }
+}
+
+impl Write for ChannelWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+
+        if self.buffer.len() >= OUTPUT_CHUNK_SIZE {
+            self.flush()?;
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        if !self.buffer.is_empty() {
+            self.sender
+                .send(Ok(std::mem::take(&mut self.buffer)))
+                .map_err(|_| std::io::Error::from(std::io::ErrorKind::BrokenPipe))?;
+        }
+
+        Ok(())
+    }
 }
 
 fn get_total_file_size(paths: &[PathBuf]) -> anyhow::Result<u64> {
@@ -0,0 +1,40 @@
+use std::path::PathBuf;
+
+use clap::ArgMatches;
+use webaves::{
+    arc::{ARCConverter, ARCReader},
+    warc::WARCWriter,
+};
+
+use crate::argtypes::{MultiInput, OutputStream};
+
+pub fn handle_convert_arc_command(
+    global_matches: &ArgMatches,
+    sub_matches: &ArgMatches,
+) -> anyhow::Result<()> {
+    let compression_format = super::get_compression_format(sub_matches);
+    let output_path = sub_matches.get_one::<PathBuf>("output").unwrap();
+    let warc_filename = output_path
+        .file_name()
+        .filter(|_| output_path.as_os_str() != "-")
+        .map(|filename| filename.to_string_lossy().to_string());
+    let mut multi_input = MultiInput::from_args(global_matches, sub_matches)?;
+    let output = OutputStream::from_args(sub_matches)?;
+    let mut writer = WARCWriter::new_compressed(output, compression_format, Default::default());
+    writer.set_digest_algorithm(Some("sha1"))?;
+
+    let mut converter = ARCConverter::new();
+
+    while let Some((path, file)) = multi_input.next_file()? {
+        let mut reader = ARCReader::new(file)?;
+        let count = converter.convert(&mut reader, &mut writer, warc_filename.as_deref())?;
+
+        tracing::info!(?path, count, "converted file");
+
+        multi_input.inc_progress_bar(&path);
+    }
+
+    multi_input.progress_bar.finish_and_clear();
+
+    Ok(())
+}
@@ -1,8 +1,14 @@
-use std::io::{BufRead, BufReader, Write};
+use std::{
+    io::{BufRead, BufReader, Write},
+    path::Path,
+};
 
 use clap::ArgMatches;
 use serde::{Deserialize, Serialize};
-use webaves::{header::HeaderMap, warc::WARCWriter};
+use webaves::{
+    header::HeaderMap,
+    warc::{HeaderMetadata, WARCWriter},
+};
 
 use crate::argtypes::{MultiInput, OutputStream};
 
@@ -31,34 +37,34 @@ pub fn handle_dump_command(
     global_matches: &ArgMatches,
     sub_matches: &ArgMatches,
 ) -> anyhow::Result<()> {
-    read_warc_files_loop(
-        global_matches,
-        sub_matches,
-        |_input_path, output, metadata| {
-            let metadata_string = serde_json::to_string(&DumpElement::Header {
-                version: metadata.version(),
-                fields: metadata.fields(),
-            })?;
-            output.write_all(metadata_string.as_bytes())?;
-            output.write_all(b"\n")?;
-            Ok(())
-        },
-        |output, buffer, amount| {
-            let block_string = serde_json::to_string(&DumpElement::Block {
-                data: &buffer[0..amount],
-            })?;
-            output.write_all(block_string.as_bytes())?;
-            output.write_all(b"\n")?;
-
-            Ok(())
-        },
-        |output| {
-            let end_string = serde_json::to_string(&DumpElement::EndOfRecord)?;
-            output.write_all(end_string.as_bytes())?;
-            output.write_all(b"\n")?;
-            Ok(())
-        },
-    )
+    read_warc_files_loop(global_matches, sub_matches, || {
+        (
+            |_input_path: &Path, output: &mut dyn Write, metadata: &HeaderMetadata| {
+                let metadata_string = serde_json::to_string(&DumpElement::Header {
+                    version: metadata.version(),
+                    fields: metadata.fields(),
+                })?;
+                output.write_all(metadata_string.as_bytes())?;
+                output.write_all(b"\n")?;
+                Ok(())
+            },
+            |output: &mut dyn Write, buffer: &[u8], amount: usize| {
+                let block_string = serde_json::to_string(&DumpElement::Block {
+                    data: &buffer[0..amount],
+                })?;
+                output.write_all(block_string.as_bytes())?;
+                output.write_all(b"\n")?;
+
+                Ok(())
+            },
+            |output: &mut dyn Write| {
+                let end_string = serde_json::to_string(&DumpElement::EndOfRecord)?;
+                output.write_all(end_string.as_bytes())?;
+                output.write_all(b"\n")?;
+                Ok(())
+            },
+        )
+    })
 }
 
 pub fn handle_load_command(
@@ -2,6 +2,7 @@ use std::{
     fs::OpenOptions,
     io::{Read, Write},
     path::{Path, PathBuf},
+    sync::{Arc, Mutex},
 };
 
 use clap::ArgMatches;
@@ -9,40 +10,156 @@ use indicatif::ProgressBar;
 use url::Url;
 use webaves::{
     io::SourceCountRead,
-    warc::{extract::ExtractorDispatcher, BlockReader, HeaderMapExt, WARCReader},
+    warc::{
+        extract::{ExtractionFilter, ExtractorDispatcher},
+        BlockReader, HeaderMapExt, HeaderMetadata, RevisitIndex, WARCReader, WARCRecordType,
+    },
 };
 
 use crate::argtypes::MultiInput;
 
+struct ExtractContext<'a> {
+    output_dir: &'a Path,
+    progress_bar: &'a ProgressBar,
+    revisit_index: &'a LazyRevisitIndex<'a>,
+    filter: &'a ExtractionFilter,
+    rename_lock: &'a Mutex<()>,
+}
+
 pub fn handle_extract_command(
     global_matches: &ArgMatches,
     sub_matches: &ArgMatches,
 ) -> anyhow::Result<()> {
+    let recover = sub_matches.get_one::<bool>("recover").cloned().unwrap();
+    let jobs = super::get_jobs(sub_matches);
+    let filter = build_filter(sub_matches)?;
     let mut multi_input = MultiInput::from_args(global_matches, sub_matches)?;
     let output_dir = sub_matches.get_one::<PathBuf>("output_directory").unwrap();
-
-    while let Some((_path, file)) = multi_input.next_file()? {
+    let progress_bar = multi_input.progress_bar.clone();
+    let rename_lock = Mutex::new(());
+    let input_paths = multi_input.input_paths.clone();
+    let revisit_index = LazyRevisitIndex::new(&input_paths, &progress_bar, recover);
+    let context = ExtractContext {
+        output_dir,
+        progress_bar: &progress_bar,
+        revisit_index: &revisit_index,
+        filter: &filter,
+        rename_lock: &rename_lock,
+    };
+
+    multi_input.for_each_file(jobs, &mut std::io::sink(), |_path, file, _output| {
         let mut reader = WARCReader::new(file)?;
+        reader.set_recovery(recover);
 
         loop {
-            let has_more =
-                process_extract_record(&multi_input.progress_bar, &mut reader, output_dir)?;
+            let has_more = process_extract_record(&context, &mut reader)?;
 
             if !has_more {
                 break;
             }
         }
-    }
+
+        Ok(())
+    })?;
 
     multi_input.progress_bar.finish_and_clear();
 
     Ok(())
 }
 
+fn build_filter(sub_matches: &ArgMatches) -> anyhow::Result<ExtractionFilter> {
+    let mut filter = ExtractionFilter::new();
+
+    for list in sub_matches.get_many::<String>("accept").unwrap_or_default() {
+        filter.add_accept(list)?;
+    }
+
+    for list in sub_matches.get_many::<String>("reject").unwrap_or_default() {
+        filter.add_reject(list)?;
+    }
+
+    for pattern in sub_matches
+        .get_many::<String>("accept_pattern")
+        .unwrap_or_default()
+    {
+        filter.add_accept_pattern(pattern)?;
+    }
+
+    for pattern in sub_matches
+        .get_many::<String>("reject_pattern")
+        .unwrap_or_default()
+    {
+        filter.add_reject_pattern(pattern)?;
+    }
+
+    Ok(filter)
+}
+
+// Revisit records may refer to records in any of the input files, so all
+// of them are indexed when the first revisit record is found. Inputs without
+// revisit records are not read twice.
+struct LazyRevisitIndex<'a> {
+    input_paths: &'a [PathBuf],
+    progress_bar: &'a ProgressBar,
+    recover: bool,
+    index: Mutex<Option<Arc<RevisitIndex>>>,
+}
+
+impl<'a> LazyRevisitIndex<'a> {
+    fn new(input_paths: &'a [PathBuf], progress_bar: &'a ProgressBar, recover: bool) -> Self {
+        Self {
+            input_paths,
+            progress_bar,
+            recover,
+            index: Mutex::new(None),
+        }
+    }
+
+    fn get(&self) -> anyhow::Result<Arc<RevisitIndex>> {
+        let mut index = self.index.lock().unwrap();
+
+        if index.is_none() {
+            *index = Some(Arc::new(self.build()?));
+        }
+
+        Ok(index.clone().unwrap())
+    }
+
+    fn build(&self) -> anyhow::Result<RevisitIndex> {
+        let mut index = RevisitIndex::new();
+
+        tracing::info!("building revisit index");
+
+        for path in self.input_paths {
+            if path.as_os_str() == "-" {
+                continue;
+            }
+
+            let file_size = std::fs::metadata(path)
+                .map(|metadata| metadata.len())
+                .unwrap_or_default();
+            self.progress_bar.inc_length(file_size);
+
+            if let Err(error) = index.add_file(path) {
+                if self.recover {
+                    tracing::warn!(?path, %error, "could not index file for revisit records");
+                } else {
+                    return Err(error.into());
+                }
+            }
+
+            self.progress_bar.inc(file_size);
+        }
+
+        tracing::debug!(count = index.len(), "revisit index built");
+
+        Ok(index)
+    }
+}
+
 fn process_extract_record<'a, 'b, R: Read>(
-    progress_bar: &ProgressBar,
+    context: &ExtractContext,
     reader: &'b mut WARCReader<'a, R>,
-    output_dir: &Path,
 ) -> anyhow::Result<bool> {
     let metadata = reader.begin_record()?;
 
@@ -54,20 +171,48 @@ fn process_extract_record<'a, 'b, R: Read>(
     buf.resize(16384, 0);
 
     let metadata = metadata.unwrap();
+    let url = metadata.fields().get_parsed::<Url>("WARC-Target-URI")?;
+
+    if let (Some(WARCRecordType::Revisit), Some(url)) = (metadata.record_type(), &url) {
+        let revisit_index = context.revisit_index.get()?;
+
+        if revisit_index.find(&metadata).is_some() {
+            let mut block_reader = reader.read_block();
+            extract_revisit_record(context, &revisit_index, url, &metadata, &mut block_reader)?;
+            reader.end_record()?;
+
+            return Ok(true);
+        }
+
+        tracing::warn!(%url, "original record of revisit record not found");
+    }
 
     let block_reader = reader.read_block();
     let mut extractor = ExtractorDispatcher::new(block_reader);
     extractor.add_default_extractors();
-    let url = metadata.fields().get_parsed::<Url>("WARC-Target-URI")?;
+    extractor.set_filter(context.filter);
 
     if extractor.can_accept_any(&metadata) && url.is_some() {
         let url = url.as_ref().unwrap();
         tracing::debug!(%url, "extractor begin");
-        extractor.begin(&metadata)?;
-        extract_record_with_extractor(url, output_dir, extractor, progress_bar)?;
+
+        if extractor.begin(&metadata)? {
+            let mut previous_offset = extractor.get_ref().source_read_count();
+            let progress = |block_reader: &BlockReader<R>| {
+                let current_offset = block_reader.source_read_count();
+                context.progress_bar.inc(current_offset - previous_offset);
+                previous_offset = current_offset;
+            };
+
+            extract_record_with_extractor(context, url, extractor, progress)?;
+        } else {
+            tracing::debug!(%url, "rejected by filter");
+            let mut block_reader = extractor.into_inner();
+            extract_record_nothing(&mut block_reader, context.progress_bar)?;
+        }
     } else {
         let mut block_reader = extractor.into_inner();
-        extract_record_nothing(&mut block_reader, progress_bar)?;
+        extract_record_nothing(&mut block_reader, context.progress_bar)?;
     }
 
     reader.end_record()?;
@@ -75,32 +220,75 @@ fn process_extract_record<'a, 'b, R: Read>(
     Ok(true)
 }
 
-fn extract_record_with_extractor<'a, 's, R: Read>(
+// The revisit record is extracted as if it were the original response with
+// the revisit's HTTP header.
+fn extract_revisit_record<R: Read>(
+    context: &ExtractContext,
+    revisit_index: &RevisitIndex,
     url: &Url,
-    output_dir: &Path,
-    mut extractor: ExtractorDispatcher<'a, BlockReader<'a, 's, R>>,
-    progress_bar: &ProgressBar,
+    metadata: &HeaderMetadata,
+    block_reader: &mut BlockReader<R>,
 ) -> anyhow::Result<()> {
+    let previous_offset = block_reader.source_read_count();
+    let result = revisit_index.open(metadata, &mut *block_reader);
+    context
+        .progress_bar
+        .inc(block_reader.source_read_count() - previous_offset);
+
+    let (combined_metadata, combined_reader) = match result {
+        Ok(value) => value,
+        Err(error) => {
+            tracing::warn!(%url, %error, "could not open original record of revisit record");
+            extract_record_nothing(block_reader, context.progress_bar)?;
+
+            return Ok(());
+        }
+    };
+
+    let mut extractor = ExtractorDispatcher::new(combined_reader);
+    extractor.add_default_extractors();
+    extractor.set_filter(context.filter);
+
+    if extractor.can_accept_any(&combined_metadata) {
+        tracing::debug!(%url, "extractor begin revisit");
+
+        if extractor.begin(&combined_metadata)? {
+            extract_record_with_extractor(context, url, extractor, |_| {})?;
+        } else {
+            tracing::debug!(%url, "rejected by filter");
+        }
+    }
+
+    Ok(())
+}
+
+fn extract_record_with_extractor<'a, S, F>(
+    context: &ExtractContext,
+    url: &Url,
+    mut extractor: ExtractorDispatcher<'a, S>,
+    mut progress: F,
+) -> anyhow::Result<()>
+where
+    S: 'a + Read,
+    F: FnMut(&S),
+{
     let mut buf = Vec::new();
     buf.resize(16384, 0);
 
+    let output_dir = context.output_dir;
     let temp_path = output_dir.join(format!("{}.tmp", webaves::uuid::new_v7().as_hyphenated()));
     let path = output_dir.join(webaves::download::url_to_path_buf(url));
-    let path = webaves::download::remove_path_conflict(path);
-
-    if let Some(parent) = path.parent() {
-        std::fs::create_dir_all(parent)?;
-    }
 
     tracing::info!(?path, %url, "extracting file");
 
+    std::fs::create_dir_all(output_dir)?;
+
     let mut file = OpenOptions::new()
         .create_new(true)
         .write(true)
         .open(&temp_path)?;
 
     loop {
-        let previous_offset = extractor.get_ref().source_read_count();
         let amount = extractor.read(&mut buf)?;
 
         if amount == 0 {
@@ -108,14 +296,19 @@ fn extract_record_with_extractor<'a, 's, R: Read>(
         }
 
         file.write_all(&buf[0..amount])?;
-
-        let current_offset = extractor.get_ref().source_read_count();
-        progress_bar.inc(current_offset - previous_offset);
+        progress(extractor.get_ref());
     }
 
     extractor.finish()?;
 
-    std::fs::rename(temp_path, path)?;
+    // Files may be extracted concurrently, so the path is checked for
+    // conflicts just before it is used.
+    let _guard = context.rename_lock.lock().unwrap();
+    let new_path = webaves::download::move_without_conflict(temp_path, &path)?;
+
+    if new_path != path {
+        tracing::debug!(?new_path, "renamed file to avoid conflict");
+    }
 
     Ok(())
 }
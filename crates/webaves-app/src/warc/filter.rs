@@ -0,0 +1,52 @@
+use clap::ArgMatches;
+use webaves::warc::{
+    filter::{FilterExpression, RecordFilter},
+    WARCWriter,
+};
+
+use crate::argtypes::{InputStream, MultiInput, OutputStream};
+
+pub fn handle_filter_command(
+    global_matches: &ArgMatches,
+    sub_matches: &ArgMatches,
+) -> anyhow::Result<()> {
+    let expression = sub_matches
+        .get_one::<String>("expression")
+        .unwrap()
+        .parse::<FilterExpression>()?;
+    let include_concurrent = sub_matches.get_one::<bool>("concurrent").cloned().unwrap();
+    let mut multi_input = MultiInput::from_args(global_matches, sub_matches)?;
+
+    if multi_input
+        .input_paths
+        .iter()
+        .any(|path| path.as_os_str() == "-")
+    {
+        anyhow::bail!("filtering standard input is not supported");
+    }
+
+    let compression_format =
+        super::get_auto_compression_format(sub_matches, &multi_input.input_paths[0])?;
+    let output = OutputStream::from_args(sub_matches)?;
+    let mut writer = WARCWriter::new_compressed(output, compression_format, Default::default());
+
+    let mut filter = RecordFilter::new(expression);
+    filter.set_include_concurrent(include_concurrent);
+
+    while let Some((path, file)) = multi_input.next_file()? {
+        let file = match file {
+            InputStream::File(file) => file,
+            InputStream::Stdin(_) => unreachable!(),
+        };
+
+        let count = filter.filter_stream(file, &mut writer)?;
+
+        tracing::info!(?path, count, "filtered file");
+
+        multi_input.inc_progress_bar(&path);
+    }
+
+    multi_input.progress_bar.finish_and_clear();
+
+    Ok(())
+}
@@ -0,0 +1,119 @@
+use std::{
+    io::{Read, Write},
+    path::PathBuf,
+};
+
+use anyhow::Context;
+use clap::ArgMatches;
+use webaves::{
+    header::HeaderFormatter,
+    warc::{
+        extract::{Classifier, HTTPClassifier, HTTPExtractor},
+        HeaderMetadata, WARCReader,
+    },
+};
+
+use crate::argtypes::{InputStream, OutputStream};
+
+pub fn handle_get_command(
+    _global_matches: &ArgMatches,
+    sub_matches: &ArgMatches,
+) -> anyhow::Result<()> {
+    let path = sub_matches.get_one::<PathBuf>("input").unwrap();
+    let offset = sub_matches.get_one::<u64>("offset").cloned();
+    let record_id = sub_matches
+        .get_one::<String>("record_id")
+        .map(|value| normalize_record_id(value));
+    let payload = sub_matches.get_one::<bool>("payload").cloned().unwrap();
+
+    let input = InputStream::open(path).with_context(|| format!("failed to open file {path:?}"))?;
+    let mut output = OutputStream::from_args(sub_matches)?;
+
+    match (input, offset) {
+        (InputStream::File(file), Some(offset)) => {
+            tracing::debug!(offset, "seek to record");
+
+            let mut reader = WARCReader::open_at(file, offset)?;
+            let metadata = reader
+                .begin_record()?
+                .with_context(|| format!("no record at offset {offset}"))?;
+
+            write_record(&mut reader, &metadata, payload, &mut output)?;
+        }
+        (input, _) => {
+            let mut reader = WARCReader::new(input)?;
+
+            loop {
+                let metadata = match reader.begin_record()? {
+                    Some(metadata) => metadata,
+                    None => anyhow::bail!("record not found"),
+                };
+
+                let is_match = match (&record_id, offset) {
+                    (Some(record_id), _) => {
+                        metadata
+                            .fields()
+                            .get_str("WARC-Record-ID")
+                            .map(normalize_record_id)
+                            .as_ref()
+                            == Some(record_id)
+                    }
+                    (None, Some(offset)) => metadata.raw_file_offset() == offset,
+                    (None, None) => unreachable!(),
+                };
+
+                if is_match {
+                    write_record(&mut reader, &metadata, payload, &mut output)?;
+                    break;
+                }
+
+                std::io::copy(&mut reader.read_block(), &mut std::io::sink())?;
+                reader.end_record()?;
+            }
+        }
+    }
+
+    output.flush()?;
+
+    Ok(())
+}
+
+// Removes the optional angle brackets around the ID.
+fn normalize_record_id(value: &str) -> String {
+    value
+        .trim()
+        .trim_start_matches('<')
+        .trim_end_matches('>')
+        .to_string()
+}
+
+// Writes the header as it appears in the file followed by the payload if
+// requested.
+fn write_record<R: Read, W: Write>(
+    reader: &mut WARCReader<'_, R>,
+    metadata: &HeaderMetadata,
+    payload: bool,
+    output: &mut W,
+) -> anyhow::Result<()> {
+    let mut header_formatter = HeaderFormatter::new();
+    header_formatter.set_use_raw(true);
+
+    output.write_all(metadata.version().as_bytes())?;
+    output.write_all(b"\r\n")?;
+    header_formatter.format_header(metadata.fields(), &mut *output)?;
+    output.write_all(b"\r\n")?;
+
+    if payload {
+        let block = reader.read_block();
+
+        if HTTPClassifier.can_accept(metadata) {
+            let mut extractor = HTTPExtractor::new(block)?;
+            std::io::copy(&mut extractor, output)?;
+        } else {
+            let mut block = block;
+            std::io::copy(&mut block, output)?;
+        }
+    }
+
+    Ok(())
+}
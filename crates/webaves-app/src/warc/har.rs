@@ -0,0 +1,74 @@
+use std::{io::Write, path::PathBuf};
+
+use clap::ArgMatches;
+use webaves::{
+    har::{HARConverter, HARExporter, HAR},
+    warc::{WARCReader, WARCWriter},
+};
+
+use crate::argtypes::{MultiInput, OutputStream};
+
+pub fn handle_to_har_command(
+    global_matches: &ArgMatches,
+    sub_matches: &ArgMatches,
+) -> anyhow::Result<()> {
+    let mut multi_input = MultiInput::from_args(global_matches, sub_matches)?;
+    let mut output = OutputStream::from_args(sub_matches)?;
+    let mut exporter = HARExporter::new();
+
+    while let Some((path, file)) = multi_input.next_file()? {
+        let mut reader = WARCReader::new(file)?;
+        exporter.add_reader(&mut reader)?;
+
+        tracing::info!(?path, count = exporter.entry_count(), "read file");
+
+        multi_input.inc_progress_bar(&path);
+    }
+
+    multi_input.progress_bar.finish_and_clear();
+
+    serde_json::to_writer_pretty(&mut output, &exporter.finish())?;
+    output.write_all(b"\n")?;
+
+    Ok(())
+}
+
+pub fn handle_from_har_command(
+    global_matches: &ArgMatches,
+    sub_matches: &ArgMatches,
+) -> anyhow::Result<()> {
+    let compression_format = super::get_compression_format(sub_matches);
+    let output_path = sub_matches.get_one::<PathBuf>("output").unwrap();
+    let warc_filename = output_path
+        .file_name()
+        .filter(|_| output_path.as_os_str() != "-")
+        .map(|filename| filename.to_string_lossy().to_string());
+    let mut multi_input = MultiInput::from_args(global_matches, sub_matches)?;
+    let output = OutputStream::from_args(sub_matches)?;
+    let mut writer = WARCWriter::new_compressed(output, compression_format, Default::default());
+    writer.set_digest_algorithm(Some("sha1"))?;
+
+    let mut converter = HARConverter::new();
+
+    while let Some((path, file)) = multi_input.next_file()? {
+        let har: HAR = serde_json::from_reader(file)?;
+        let har_filename = path
+            .file_name()
+            .filter(|_| path.as_os_str() != "-")
+            .map(|filename| filename.to_string_lossy().to_string());
+        let count = converter.convert(
+            &har,
+            &mut writer,
+            har_filename.as_deref(),
+            warc_filename.as_deref(),
+        )?;
+
+        tracing::info!(?path, count, "converted file");
+
+        multi_input.inc_progress_bar(&path);
+    }
+
+    multi_input.progress_bar.finish_and_clear();
+
+    Ok(())
+}
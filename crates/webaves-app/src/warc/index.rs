@@ -0,0 +1,86 @@
+use std::io::Write;
+
+use clap::ArgMatches;
+use webaves::{
+    io::SourceCountRead,
+    warc::{
+        index::{sort_entries, CDXJWriter, CDXWriter, IndexEntry, Indexer},
+        WARCReader,
+    },
+};
+
+use crate::argtypes::{MultiInput, OutputStream};
+
+pub fn handle_index_command(
+    global_matches: &ArgMatches,
+    sub_matches: &ArgMatches,
+) -> anyhow::Result<()> {
+    let sort = sub_matches.get_one::<bool>("sort").cloned().unwrap();
+    let mut multi_input = MultiInput::from_args(global_matches, sub_matches)?;
+    let output = OutputStream::from_args(sub_matches)?;
+    let format = sub_matches.get_one::<String>("format").unwrap();
+    let mut writer = IndexWriter::new(format, output)?;
+    let mut entries = Vec::new();
+
+    while let Some((path, file)) = multi_input.next_file()? {
+        let filename = path
+            .file_name()
+            .unwrap_or(path.as_os_str())
+            .to_string_lossy()
+            .into_owned();
+        let reader = WARCReader::new(file)?;
+        let mut indexer = Indexer::new(reader, filename);
+        let mut previous_offset = 0;
+
+        while let Some(entry) = indexer.next_entry()? {
+            if sort {
+                entries.push(entry);
+            } else {
+                writer.write_entry(&entry)?;
+            }
+
+            let current_offset = indexer.source_read_count();
+            multi_input
+                .progress_bar
+                .inc(current_offset - previous_offset);
+            previous_offset = current_offset;
+        }
+    }
+
+    if sort {
+        sort_entries(&mut entries);
+
+        for entry in &entries {
+            writer.write_entry(entry)?;
+        }
+    }
+
+    multi_input.progress_bar.finish_and_clear();
+
+    Ok(())
+}
+
+enum IndexWriter<W: Write> {
+    Cdxj(CDXJWriter<W>),
+    Cdx(CDXWriter<W>),
+}
+
+impl<W: Write> IndexWriter<W> {
+    fn new(format: &str, stream: W) -> anyhow::Result<Self> {
+        match format {
+            "cdx" => {
+                let mut writer = CDXWriter::new(stream);
+                writer.write_header()?;
+                Ok(Self::Cdx(writer))
+            }
+            _ => Ok(Self::Cdxj(CDXJWriter::new(stream))),
+        }
+    }
+
+    fn write_entry(&mut self, entry: &IndexEntry) -> std::io::Result<()> {
+        match self {
+            Self::Cdxj(writer) => writer.write_entry(entry),
+            Self::Cdx(writer) => writer.write_entry(entry),
+        }
+    }
+}
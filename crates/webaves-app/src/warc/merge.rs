@@ -0,0 +1,169 @@
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use clap::ArgMatches;
+use webaves::{
+    compress::CompressionFormat,
+    warc::{RecordMapping, RecordMerger, RotatingWARCWriter},
+};
+
+use crate::argtypes::{InputStream, MultiInput, OutputStream};
+
+const WARC_EXTENSIONS: [&str; 3] = [".gz", ".zst", ".warc"];
+
+pub fn handle_merge_command(
+    global_matches: &ArgMatches,
+    sub_matches: &ArgMatches,
+) -> anyhow::Result<()> {
+    let mut multi_input = open_input(global_matches, sub_matches)?;
+    let prefix = sub_matches.get_one::<String>("prefix").unwrap();
+    let compression_format =
+        super::get_auto_compression_format(sub_matches, &multi_input.input_paths[0])?;
+    let mut writer = create_writer(sub_matches, prefix, compression_format);
+    let merger = create_merger(sub_matches);
+    let mut mapping_output = open_mapping_output(sub_matches)?;
+
+    while let Some((path, file)) = multi_input.next_file()? {
+        let file = match file {
+            InputStream::File(file) => file,
+            InputStream::Stdin(_) => unreachable!(),
+        };
+
+        let mappings = merger.merge_stream(file, &mut writer)?;
+
+        tracing::info!(?path, count = mappings.len(), "merged file");
+
+        write_mappings(&mut mapping_output, &path, &mappings)?;
+        multi_input.inc_progress_bar(&path);
+    }
+
+    writer.close()?;
+    multi_input.progress_bar.finish_and_clear();
+
+    Ok(())
+}
+
+pub fn handle_split_command(
+    global_matches: &ArgMatches,
+    sub_matches: &ArgMatches,
+) -> anyhow::Result<()> {
+    let mut multi_input = open_input(global_matches, sub_matches)?;
+    let merger = create_merger(sub_matches);
+    let mut mapping_output = open_mapping_output(sub_matches)?;
+
+    while let Some((path, file)) = multi_input.next_file()? {
+        let file = match file {
+            InputStream::File(file) => file,
+            InputStream::Stdin(_) => unreachable!(),
+        };
+
+        let prefix = match sub_matches.get_one::<String>("prefix") {
+            Some(prefix) => prefix.clone(),
+            None => make_prefix(&path)?,
+        };
+        let compression_format = super::get_auto_compression_format(sub_matches, &path)?;
+        let mut writer = create_writer(sub_matches, &prefix, compression_format);
+
+        let mappings = merger.merge_stream(file, &mut writer)?;
+        writer.close()?;
+
+        tracing::info!(?path, count = mappings.len(), "split file");
+
+        write_mappings(&mut mapping_output, &path, &mappings)?;
+        multi_input.inc_progress_bar(&path);
+    }
+
+    multi_input.progress_bar.finish_and_clear();
+
+    Ok(())
+}
+
+fn open_input(global_matches: &ArgMatches, sub_matches: &ArgMatches) -> anyhow::Result<MultiInput> {
+    let multi_input = MultiInput::from_args(global_matches, sub_matches)?;
+
+    if multi_input
+        .input_paths
+        .iter()
+        .any(|path| path.as_os_str() == "-")
+    {
+        anyhow::bail!("reading standard input is not supported");
+    }
+
+    Ok(multi_input)
+}
+
+fn create_writer<'a>(
+    sub_matches: &ArgMatches,
+    prefix: &str,
+    compression_format: CompressionFormat,
+) -> RotatingWARCWriter<'a> {
+    let output_dir = sub_matches.get_one::<PathBuf>("output_directory").unwrap();
+    let mut writer = RotatingWARCWriter::new(output_dir, prefix);
+    writer.set_compression(compression_format, Default::default());
+    writer.set_max_file_size(sub_matches.get_one::<u64>("max_size").cloned());
+    writer.set_max_record_count(sub_matches.get_one::<u64>("max_records").cloned());
+    writer.set_warcinfo_enabled(false);
+    writer
+}
+
+fn create_merger(sub_matches: &ArgMatches) -> RecordMerger {
+    let mut merger = RecordMerger::new();
+    merger.set_rewrite_warcinfo(
+        sub_matches
+            .get_one::<bool>("rewrite_warcinfo")
+            .cloned()
+            .unwrap(),
+    );
+    merger
+}
+
+fn make_prefix(path: &Path) -> anyhow::Result<String> {
+    let mut prefix = path
+        .file_name()
+        .with_context(|| format!("no filename for {path:?}"))?
+        .to_string_lossy()
+        .to_string();
+
+    for extension in WARC_EXTENSIONS {
+        if let Some(stem) = prefix.strip_suffix(extension) {
+            prefix = stem.to_string();
+        }
+    }
+
+    Ok(prefix)
+}
+
+fn open_mapping_output(sub_matches: &ArgMatches) -> anyhow::Result<Option<OutputStream>> {
+    let path = match sub_matches.get_one::<PathBuf>("mapping") {
+        Some(path) => path,
+        None => return Ok(None),
+    };
+    let output = OutputStream::open(path, false)
+        .with_context(|| format!("failed to create file {path:?}"))?;
+
+    Ok(Some(output))
+}
+
+fn write_mappings(
+    output: &mut Option<OutputStream>,
+    source_path: &Path,
+    mappings: &[RecordMapping],
+) -> anyhow::Result<()> {
+    if let Some(output) = output {
+        for mapping in mappings {
+            let line = serde_json::json!({
+                "source_file": source_path,
+                "source_offset": mapping.source_offset,
+                "target_file": mapping.target.path,
+                "target_offset": mapping.target.offset,
+                "target_length": mapping.target.length,
+            });
+            writeln!(output, "{}", line)?;
+        }
+    }
+
+    Ok(())
+}
@@ -1,24 +1,42 @@
+mod convert;
 mod dump;
 mod extract;
+mod filter;
+mod get;
+mod har;
+mod index;
+mod merge;
+mod pack;
 mod read;
+mod stats;
+mod validate;
+mod wacz;
 
-use std::path::PathBuf;
+use std::{
+    fs::File,
+    path::{Path, PathBuf},
+};
 
-use clap::{Arg, ArgAction, ArgMatches, Command};
+use anyhow::Context;
+use clap::{Arg, ArgAction, ArgGroup, ArgMatches, Command};
 
-use webaves::compress::CompressionFormat;
+use webaves::{compress::CompressionFormat, warc::WARCReader};
 
 pub fn create_command<'h>() -> Command<'h> {
     let dump_command = Command::new("dump")
         .about(crate::message::static_text("warc-dump-about"))
         .arg(input_warc_file_arg())
         .arg(output_file_arg())
-        .arg(allow_overwrite_arg());
+        .arg(allow_overwrite_arg())
+        .arg(recover_arg())
+        .arg(jobs_arg());
     let list_command = Command::new("list")
         .about(crate::message::static_text("warc-list-about"))
         .arg(input_warc_file_arg())
         .arg(output_file_arg())
         .arg(allow_overwrite_arg())
+        .arg(recover_arg())
+        .arg(jobs_arg())
         .arg(
             Arg::new("name")
                 .long("name")
@@ -51,48 +69,160 @@ pub fn create_command<'h>() -> Command<'h> {
         .arg(allow_overwrite_arg());
 
     let pack_command = Command::new("pack")
-        .hide(true)
         .about(crate::message::static_text("warc-pack-about"))
         .long_about(crate::message::static_text("warc-pack-about-long"))
         .arg(input_warc_file_arg())
         .arg(compression_format_arg())
         .arg(output_warc_file_arg())
         .arg(output_dir_arg().conflicts_with("output"))
+        .arg(allow_overwrite_arg())
+        .arg(
+            Arg::new("zstd_dictionary")
+                .long("zstd-dictionary")
+                .action(ArgAction::SetTrue)
+                .help(crate::message::static_text(
+                    "warc-pack-zstd-dictionary-help",
+                )),
+        );
+
+    let filter_command = Command::new("filter")
+        .about(crate::message::static_text("warc-filter-about"))
+        .long_about(crate::message::static_text("warc-filter-about-long"))
+        .arg(input_warc_file_arg())
+        .arg(
+            Arg::new("expression")
+                .long("expression")
+                .short('e')
+                .required(true)
+                .takes_value(true)
+                .help(crate::message::static_text("warc-filter-expression-help")),
+        )
+        .arg(
+            Arg::new("concurrent")
+                .long("concurrent")
+                .action(ArgAction::SetTrue)
+                .help(crate::message::static_text("warc-filter-concurrent-help")),
+        )
+        .arg(auto_compression_format_arg())
+        .arg(output_warc_file_arg())
+        .arg(allow_overwrite_arg());
+
+    let merge_command = Command::new("merge")
+        .about(crate::message::static_text("warc-merge-about"))
+        .long_about(crate::message::static_text("warc-merge-about-long"))
+        .arg(input_warc_file_arg())
+        .arg(output_dir_arg().default_value("."))
+        .arg(
+            Arg::new("prefix")
+                .long("prefix")
+                .takes_value(true)
+                .default_value("merged")
+                .help(crate::message::static_text("warc-merge-prefix-help")),
+        )
+        .arg(max_size_arg())
+        .arg(max_records_arg())
+        .arg(auto_compression_format_arg())
+        .arg(rewrite_warcinfo_arg())
+        .arg(mapping_file_arg());
+
+    let split_command = Command::new("split")
+        .about(crate::message::static_text("warc-split-about"))
+        .long_about(crate::message::static_text("warc-split-about-long"))
+        .arg(input_warc_file_arg())
+        .arg(output_dir_arg().default_value("."))
+        .arg(
+            Arg::new("prefix")
+                .long("prefix")
+                .takes_value(true)
+                .help(crate::message::static_text("warc-split-prefix-help")),
+        )
+        .arg(max_size_arg())
+        .arg(max_records_arg())
+        .group(
+            ArgGroup::new("limits")
+                .args(&["max_size", "max_records"])
+                .multiple(true)
+                .required(true),
+        )
+        .arg(auto_compression_format_arg())
+        .arg(rewrite_warcinfo_arg())
+        .arg(mapping_file_arg());
+
+    let get_command = Command::new("get")
+        .about(crate::message::static_text("warc-get-about"))
+        .long_about(crate::message::static_text("warc-get-about-long"))
+        .arg(input_warc_file_arg().multiple_values(false))
+        .arg(
+            Arg::new("offset")
+                .long("offset")
+                .takes_value(true)
+                .value_parser(clap::value_parser!(u64))
+                .help(crate::message::static_text("warc-get-offset-help")),
+        )
+        .arg(
+            Arg::new("record_id")
+                .long("record-id")
+                .takes_value(true)
+                .help(crate::message::static_text("warc-get-record-id-help")),
+        )
+        .group(
+            ArgGroup::new("location")
+                .args(&["offset", "record_id"])
+                .required(true),
+        )
+        .arg(
+            Arg::new("payload")
+                .long("payload")
+                .action(ArgAction::SetTrue)
+                .help(crate::message::static_text("warc-get-payload-help")),
+        )
+        .arg(output_file_arg())
         .arg(allow_overwrite_arg());
 
+    let stats_command = Command::new("stats")
+        .about(crate::message::static_text("warc-stats-about"))
+        .long_about(crate::message::static_text("warc-stats-about-long"))
+        .arg(input_warc_file_arg())
+        .arg(output_file_arg())
+        .arg(allow_overwrite_arg())
+        .arg(output_as_json_arg());
+
     let extract_command = Command::new("extract")
         .about(crate::message::static_text("warc-extract-about"))
         .long_about(crate::message::static_text("warc-extract-about-long"))
         .arg(input_warc_file_arg())
         .arg(output_dir_arg())
         .arg(allow_overwrite_arg().hide(true))
+        .arg(recover_arg())
+        .arg(jobs_arg())
         .arg(
             Arg::new("accept")
                 .long("accept")
-                .takes_value(true)
-                .multiple_values(true)
-                .hide(true),
+                .action(ArgAction::Append)
+                .help(crate::message::static_text("warc-extract-accept-help"))
+                .long_help(crate::message::static_text("warc-extract-accept-help-long")),
         )
         .arg(
             Arg::new("accept_pattern")
                 .long("accept-pattern")
-                .takes_value(true)
-                .multiple_values(true)
-                .hide(true),
+                .action(ArgAction::Append)
+                .help(crate::message::static_text(
+                    "warc-extract-accept-pattern-help",
+                )),
         )
         .arg(
             Arg::new("reject")
                 .long("reject")
-                .takes_value(true)
-                .multiple_values(true)
-                .hide(true),
+                .action(ArgAction::Append)
+                .help(crate::message::static_text("warc-extract-reject-help")),
         )
         .arg(
             Arg::new("reject_pattern")
                 .long("reject-pattern")
-                .takes_value(true)
-                .multiple_values(true)
-                .hide(true),
+                .action(ArgAction::Append)
+                .help(crate::message::static_text(
+                    "warc-extract-reject-pattern-help",
+                )),
         );
 
     let checksum_command = Command::new("checksum")
@@ -100,6 +230,79 @@ pub fn create_command<'h>() -> Command<'h> {
         .long_about(crate::message::static_text("warc-checksum-about-long"))
         .arg(input_warc_file_arg())
         .arg(output_file_arg())
+        .arg(allow_overwrite_arg())
+        .arg(recover_arg())
+        .arg(jobs_arg());
+
+    let index_command = Command::new("index")
+        .about(crate::message::static_text("warc-index-about"))
+        .long_about(crate::message::static_text("warc-index-about-long"))
+        .arg(input_warc_file_arg())
+        .arg(output_file_arg())
+        .arg(allow_overwrite_arg())
+        .arg(
+            Arg::new("sort")
+                .long("sort")
+                .action(ArgAction::SetTrue)
+                .help(crate::message::static_text("warc-index-sort-help")),
+        )
+        .arg(
+            Arg::new("format")
+                .long("format")
+                .value_parser(["cdxj", "cdx"])
+                .default_value("cdxj")
+                .help(crate::message::static_text("warc-index-format-help")),
+        );
+
+    let validate_command = Command::new("validate")
+        .about(crate::message::static_text("warc-validate-about"))
+        .long_about(crate::message::static_text("warc-validate-about-long"))
+        .arg(input_warc_file_arg())
+        .arg(output_file_arg())
+        .arg(allow_overwrite_arg())
+        .arg(output_as_json_arg());
+
+    let convert_arc_command = Command::new("convert-arc")
+        .about(crate::message::static_text("warc-convert-arc-about"))
+        .long_about(crate::message::static_text("warc-convert-arc-about-long"))
+        .arg(input_arc_file_arg())
+        .arg(compression_format_arg())
+        .arg(output_warc_file_arg())
+        .arg(allow_overwrite_arg());
+
+    let to_har_command = Command::new("to-har")
+        .about(crate::message::static_text("warc-to-har-about"))
+        .long_about(crate::message::static_text("warc-to-har-about-long"))
+        .arg(input_warc_file_arg())
+        .arg(output_file_arg())
+        .arg(allow_overwrite_arg());
+
+    let from_har_command = Command::new("from-har")
+        .about(crate::message::static_text("warc-from-har-about"))
+        .long_about(crate::message::static_text("warc-from-har-about-long"))
+        .arg(input_har_file_arg())
+        .arg(compression_format_arg())
+        .arg(output_warc_file_arg())
+        .arg(allow_overwrite_arg());
+
+    let wacz_create_command = Command::new("wacz-create")
+        .about(crate::message::static_text("warc-wacz-create-about"))
+        .long_about(crate::message::static_text("warc-wacz-create-about-long"))
+        .arg(input_warc_file_arg())
+        .arg(output_wacz_file_arg())
+        .arg(allow_overwrite_arg())
+        .arg(
+            Arg::new("title")
+                .long("title")
+                .takes_value(true)
+                .help(crate::message::static_text("warc-wacz-create-title-help")),
+        );
+
+    let wacz_verify_command = Command::new("wacz-verify")
+        .about(crate::message::static_text("warc-wacz-verify-about"))
+        .long_about(crate::message::static_text("warc-wacz-verify-about-long"))
+        .arg(input_wacz_file_arg())
+        .arg(output_file_arg())
         .arg(allow_overwrite_arg());
 
     Command::new("warc")
@@ -110,8 +313,20 @@ pub fn create_command<'h>() -> Command<'h> {
         .subcommand(list_command)
         .subcommand(load_command)
         .subcommand(pack_command)
+        .subcommand(filter_command)
+        .subcommand(merge_command)
+        .subcommand(split_command)
+        .subcommand(get_command)
+        .subcommand(stats_command)
         .subcommand(extract_command)
         .subcommand(checksum_command)
+        .subcommand(index_command)
+        .subcommand(validate_command)
+        .subcommand(convert_arc_command)
+        .subcommand(to_har_command)
+        .subcommand(from_har_command)
+        .subcommand(wacz_create_command)
+        .subcommand(wacz_verify_command)
 }
 
 fn input_warc_file_arg<'h>() -> Arg<'h> {
@@ -130,6 +345,30 @@ fn input_json_file_arg<'h>() -> Arg<'h> {
         .help(crate::message::static_text("input-json-file-help"))
 }
 
+fn input_arc_file_arg<'h>() -> Arg<'h> {
+    Arg::new("input")
+        .required(true)
+        .multiple_values(true)
+        .value_parser(clap::value_parser!(PathBuf))
+        .help(crate::message::static_text("input-arc-file-help"))
+}
+
+fn input_har_file_arg<'h>() -> Arg<'h> {
+    Arg::new("input")
+        .required(true)
+        .multiple_values(true)
+        .value_parser(clap::value_parser!(PathBuf))
+        .help(crate::message::static_text("input-har-file-help"))
+}
+
+fn input_wacz_file_arg<'h>() -> Arg<'h> {
+    Arg::new("input")
+        .required(true)
+        .multiple_values(true)
+        .value_parser(clap::value_parser!(PathBuf))
+        .help(crate::message::static_text("input-wacz-file-help"))
+}
+
 fn output_file_arg<'h>() -> Arg<'h> {
     Arg::new("output")
         .long("output")
@@ -150,6 +389,16 @@ fn output_warc_file_arg<'h>() -> Arg<'h> {
         .help(crate::message::static_text("output-warc-file-help"))
 }
 
+fn output_wacz_file_arg<'h>() -> Arg<'h> {
+    Arg::new("output")
+        .long("output")
+        .short('o')
+        .takes_value(true)
+        .required(true)
+        .value_parser(clap::value_parser!(PathBuf))
+        .help(crate::message::static_text("output-wacz-file-help"))
+}
+
 fn output_dir_arg<'h>() -> Arg<'h> {
     Arg::new("output_directory")
         .long("output-directory")
@@ -173,6 +422,57 @@ fn allow_overwrite_arg<'h>() -> Arg<'h> {
         .help(crate::message::static_text("allow-overwrite-help"))
 }
 
+fn recover_arg<'h>() -> Arg<'h> {
+    Arg::new("recover")
+        .long("recover")
+        .action(ArgAction::SetTrue)
+        .help(crate::message::static_text("recover-help"))
+        .long_help(crate::message::static_text("recover-help-long"))
+}
+
+fn jobs_arg<'h>() -> Arg<'h> {
+    Arg::new("jobs")
+        .long("jobs")
+        .short('j')
+        .takes_value(true)
+        .value_parser(clap::value_parser!(u64).range(1..))
+        .default_value("1")
+        .help(crate::message::static_text("jobs-help"))
+        .long_help(crate::message::static_text("jobs-help-long"))
+}
+
+fn max_size_arg<'h>() -> Arg<'h> {
+    Arg::new("max_size")
+        .long("max-size")
+        .takes_value(true)
+        .value_parser(clap::value_parser!(u64).range(1..))
+        .help(crate::message::static_text("max-size-help"))
+}
+
+fn max_records_arg<'h>() -> Arg<'h> {
+    Arg::new("max_records")
+        .long("max-records")
+        .takes_value(true)
+        .value_parser(clap::value_parser!(u64).range(1..))
+        .help(crate::message::static_text("max-records-help"))
+}
+
+fn rewrite_warcinfo_arg<'h>() -> Arg<'h> {
+    Arg::new("rewrite_warcinfo")
+        .long("rewrite-warcinfo")
+        .action(ArgAction::SetTrue)
+        .help(crate::message::static_text("rewrite-warcinfo-help"))
+}
+
+fn mapping_file_arg<'h>() -> Arg<'h> {
+    Arg::new("mapping")
+        .long("mapping")
+        .takes_value(true)
+        .value_parser(clap::value_parser!(PathBuf))
+        .help(crate::message::static_text("mapping-file-help"))
+        .long_help(crate::message::static_text("mapping-file-help-long"))
+}
+
 fn compression_format_arg<'h>() -> Arg<'h> {
     Arg::new("compression_format")
         .long("compress")
@@ -183,22 +483,55 @@ fn compression_format_arg<'h>() -> Arg<'h> {
         ))
 }
 
+fn auto_compression_format_arg<'h>() -> Arg<'h> {
+    compression_format_arg()
+        .value_parser(["auto", "none", "gzip", "zstd"])
+        .default_value("auto")
+        .help(crate::message::static_text("auto-compression-format-help"))
+}
+
 pub fn run(global_matches: &ArgMatches, arg_matches: &ArgMatches) -> anyhow::Result<()> {
     match arg_matches.subcommand() {
         Some(("dump", sub_matches)) => dump::handle_dump_command(global_matches, sub_matches),
         Some(("list", sub_matches)) => read::handle_list_command(global_matches, sub_matches),
         Some(("load", sub_matches)) => dump::handle_load_command(global_matches, sub_matches),
-        Some(("pack", _sub_matches)) => todo!(),
+        Some(("pack", sub_matches)) => pack::handle_pack_command(global_matches, sub_matches),
+        Some(("filter", sub_matches)) => filter::handle_filter_command(global_matches, sub_matches),
+        Some(("merge", sub_matches)) => merge::handle_merge_command(global_matches, sub_matches),
+        Some(("split", sub_matches)) => merge::handle_split_command(global_matches, sub_matches),
+        Some(("get", sub_matches)) => get::handle_get_command(global_matches, sub_matches),
+        Some(("stats", sub_matches)) => stats::handle_stats_command(global_matches, sub_matches),
         Some(("extract", sub_matches)) => {
             extract::handle_extract_command(global_matches, sub_matches)
         }
         Some(("checksum", sub_matches)) => {
             read::handle_checksum_command(global_matches, sub_matches)
         }
+        Some(("index", sub_matches)) => index::handle_index_command(global_matches, sub_matches),
+        Some(("validate", sub_matches)) => {
+            validate::handle_validate_command(global_matches, sub_matches)
+        }
+        Some(("convert-arc", sub_matches)) => {
+            convert::handle_convert_arc_command(global_matches, sub_matches)
+        }
+        Some(("to-har", sub_matches)) => har::handle_to_har_command(global_matches, sub_matches),
+        Some(("from-har", sub_matches)) => {
+            har::handle_from_har_command(global_matches, sub_matches)
+        }
+        Some(("wacz-create", sub_matches)) => {
+            wacz::handle_wacz_create_command(global_matches, sub_matches)
+        }
+        Some(("wacz-verify", sub_matches)) => {
+            wacz::handle_wacz_verify_command(global_matches, sub_matches)
+        }
         _ => unreachable!(),
     }
 }
 
+fn get_jobs(arg_matches: &ArgMatches) -> usize {
+    arg_matches.get_one::<u64>("jobs").cloned().unwrap() as usize
+}
+
 fn get_compression_format(arg_matches: &ArgMatches) -> CompressionFormat {
     match arg_matches
         .get_one::<String>("compression_format")
@@ -211,3 +544,21 @@ fn get_compression_format(arg_matches: &ArgMatches) -> CompressionFormat {
         _ => unreachable!(),
     }
 }
+
+// Like get_compression_format() but "auto" uses the format of the given file.
+fn get_auto_compression_format(
+    arg_matches: &ArgMatches,
+    path: &Path,
+) -> anyhow::Result<CompressionFormat> {
+    match arg_matches
+        .get_one::<String>("compression_format")
+        .unwrap()
+        .as_str()
+    {
+        "auto" => {
+            let file = File::open(path).with_context(|| format!("failed to open file {path:?}"))?;
+            Ok(WARCReader::new(file)?.compression_format())
+        }
+        _ => Ok(get_compression_format(arg_matches)),
+    }
+}
@@ -0,0 +1,210 @@
+use std::{
+    fs::File,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use clap::ArgMatches;
+use webaves::{
+    compress::{train_zstd_dictionary, CompressionFormat, DEFAULT_ZSTD_DICTIONARY_SIZE},
+    warc::{
+        collect_dictionary_samples, pack_records,
+        validate::{ProblemKind, Severity, Validator},
+        WARCReader, WARCWriter,
+    },
+};
+
+use crate::argtypes::{MultiInput, OutputStream};
+
+const COMPRESSION_EXTENSIONS: [&str; 2] = [".gz", ".zst"];
+const DICTIONARY_SAMPLE_COUNT: usize = 1000;
+
+pub fn handle_pack_command(
+    global_matches: &ArgMatches,
+    sub_matches: &ArgMatches,
+) -> anyhow::Result<()> {
+    let compression_format = super::get_compression_format(sub_matches);
+    let overwrite = sub_matches.get_one::<bool>("overwrite").cloned().unwrap();
+    let output_dir = sub_matches.get_one::<PathBuf>("output_directory");
+    let use_dictionary = sub_matches
+        .get_one::<bool>("zstd_dictionary")
+        .cloned()
+        .unwrap();
+
+    if use_dictionary && compression_format != CompressionFormat::Zstd {
+        anyhow::bail!("a dictionary can only be used with zstd compression");
+    }
+
+    let mut multi_input = MultiInput::from_args(global_matches, sub_matches)?;
+
+    match output_dir {
+        Some(output_dir) => {
+            while let Some((path, file)) = multi_input.next_file()? {
+                let output_path = output_dir.join(make_filename(&path, compression_format)?);
+
+                if output_path == path {
+                    anyhow::bail!("output file {output_path:?} is the same as the input file");
+                }
+
+                let dictionary = if use_dictionary {
+                    train_dictionary(std::slice::from_ref(&path))?
+                } else {
+                    None
+                };
+                let output = OutputStream::open(&output_path, overwrite)
+                    .with_context(|| format!("failed to create file {output_path:?}"))?;
+                let mut writer =
+                    WARCWriter::new_compressed(output, compression_format, Default::default());
+
+                if let Some(dictionary) = dictionary {
+                    writer.write_zstd_dictionary(dictionary)?;
+                }
+
+                let count = pack_records(&mut WARCReader::new(file)?, &mut writer)?;
+
+                tracing::info!(?path, ?output_path, count, "packed file");
+
+                verify_output(&output_path, count)?;
+                multi_input.inc_progress_bar(&path);
+            }
+        }
+        None => {
+            let output_path = sub_matches.get_one::<PathBuf>("output").unwrap();
+            let dictionary = if use_dictionary {
+                train_dictionary(&multi_input.input_paths)?
+            } else {
+                None
+            };
+            let output = OutputStream::from_args(sub_matches)?;
+            let mut writer =
+                WARCWriter::new_compressed(output, compression_format, Default::default());
+            let mut total_count = 0;
+
+            if let Some(dictionary) = dictionary {
+                writer.write_zstd_dictionary(dictionary)?;
+            }
+
+            while let Some((path, file)) = multi_input.next_file()? {
+                let count = pack_records(&mut WARCReader::new(file)?, &mut writer)?;
+                total_count += count;
+
+                tracing::info!(?path, count, "packed file");
+
+                multi_input.inc_progress_bar(&path);
+            }
+
+            if let OutputStream::File(_) = writer.get_ref() {
+                verify_output(output_path, total_count)?;
+            } else {
+                tracing::warn!("verification skipped for standard output");
+            }
+        }
+    }
+
+    multi_input.progress_bar.finish_and_clear();
+
+    Ok(())
+}
+
+fn make_filename(path: &Path, compression_format: CompressionFormat) -> anyhow::Result<String> {
+    let filename = path
+        .file_name()
+        .with_context(|| format!("no filename for {path:?}"))?
+        .to_string_lossy();
+    let stem = COMPRESSION_EXTENSIONS
+        .iter()
+        .find_map(|extension| filename.strip_suffix(extension))
+        .unwrap_or(&filename);
+    let extension = match compression_format {
+        CompressionFormat::Gzip => ".gz",
+        CompressionFormat::Zstd => ".zst",
+        _ => "",
+    };
+
+    Ok(format!("{}{}", stem, extension))
+}
+
+// Trains a dictionary using records from the start of the files.
+//
+// Returns `None` if there is not enough data to train a dictionary.
+fn train_dictionary(paths: &[PathBuf]) -> anyhow::Result<Option<Vec<u8>>> {
+    let mut samples = Vec::new();
+
+    for path in paths {
+        if path.as_os_str() == "-" {
+            anyhow::bail!("a dictionary cannot be trained from standard input");
+        }
+
+        let file = File::open(path).with_context(|| format!("failed to open file {path:?}"))?;
+        let max_count = DICTIONARY_SAMPLE_COUNT.saturating_sub(samples.len());
+        samples.extend(collect_dictionary_samples(
+            &mut WARCReader::new(file)?,
+            max_count,
+        )?);
+    }
+
+    tracing::debug!(sample_count = samples.len(), "train dictionary");
+
+    match train_zstd_dictionary(&samples, DEFAULT_ZSTD_DICTIONARY_SIZE) {
+        Ok(dictionary) => Ok(Some(dictionary)),
+        Err(error) => {
+            tracing::warn!(%error, "dictionary not used: failed to train dictionary");
+            Ok(None)
+        }
+    }
+}
+
+// Reads the output again and checks that the block digests still match
+// and that all the records were written.
+fn verify_output(path: &Path, expected_count: u64) -> anyhow::Result<()> {
+    tracing::debug!(?path, "verify output");
+
+    let file = File::open(path).with_context(|| format!("failed to open file {path:?}"))?;
+    let mut validator = Validator::new();
+    validator.validate_file(&path.to_string_lossy(), file)?;
+
+    let mut error_count = 0;
+
+    for problem in validator.problems() {
+        let is_damage = matches!(
+            problem.kind,
+            ProblemKind::MalformedRecord
+                | ProblemKind::TrailingData
+                | ProblemKind::WrongContentLength
+                | ProblemKind::BlockDigestMismatch
+        );
+
+        if problem.severity == Severity::Error && is_damage {
+            tracing::error!(%problem, "verification error");
+            error_count += 1;
+        }
+    }
+
+    if error_count > 0 {
+        anyhow::bail!("verification of {path:?} failed with {error_count} errors");
+    }
+
+    let count = count_records(path)?;
+
+    if count != expected_count {
+        anyhow::bail!(
+            "verification of {path:?} failed: expected {expected_count} records, found {count}"
+        );
+    }
+
+    Ok(())
+}
+
+fn count_records(path: &Path) -> anyhow::Result<u64> {
+    let file = File::open(path).with_context(|| format!("failed to open file {path:?}"))?;
+    let mut reader = WARCReader::new(file)?;
+    let mut count = 0;
+
+    while reader.begin_record()?.is_some() {
+        std::io::copy(&mut reader.read_block(), &mut std::io::sink())?;
+        reader.end_record()?;
+        count += 1;
+    }
+
+    Ok(count)
+}
@@ -15,26 +15,33 @@ use webaves::{
 
 use crate::argtypes::{MultiInput, OutputStream};
 
-pub fn read_warc_files_loop<FH, FB, FF>(
+pub fn read_warc_files_loop<F, FH, FB, FF>(
     global_matches: &ArgMatches,
     sub_matches: &ArgMatches,
-    mut header_callback: FH,
-    mut body_callback: FB,
-    mut footer_callback: FF,
+    make_callbacks: F,
 ) -> anyhow::Result<()>
 where
-    FH: FnMut(&Path, &mut OutputStream, &HeaderMetadata) -> anyhow::Result<()>,
-    FB: FnMut(&mut OutputStream, &[u8], usize) -> anyhow::Result<()>,
-    FF: FnMut(&mut OutputStream) -> anyhow::Result<()>,
+    F: Fn() -> (FH, FB, FF) + Sync,
+    FH: FnMut(&Path, &mut dyn Write, &HeaderMetadata) -> anyhow::Result<()>,
+    FB: FnMut(&mut dyn Write, &[u8], usize) -> anyhow::Result<()>,
+    FF: FnMut(&mut dyn Write) -> anyhow::Result<()>,
 {
+    let recover = sub_matches.get_one::<bool>("recover").cloned().unwrap();
+    let jobs = super::get_jobs(sub_matches);
     let mut multi_input = MultiInput::from_args(global_matches, sub_matches)?;
     let mut output = OutputStream::from_args(sub_matches)?;
+    let progress_bar = multi_input.progress_bar.clone();
 
-    let mut buffer = Vec::new();
-    buffer.resize(16384, 0);
+    multi_input.for_each_file(jobs, &mut output, |path, file, output| {
+        // Callbacks are created for each file because files may be read
+        // concurrently.
+        let (mut header_callback, mut body_callback, mut footer_callback) = make_callbacks();
+
+        let mut buffer = Vec::new();
+        buffer.resize(16384, 0);
 
-    while let Some((path, file)) = multi_input.next_file()? {
         let mut reader = WARCReader::new(file)?;
+        reader.set_recovery(recover);
 
         loop {
             let metadata = reader.begin_record()?;
@@ -44,7 +51,7 @@ where
             }
 
             let metadata = metadata.unwrap();
-            header_callback(&path, &mut output, &metadata)?;
+            header_callback(path, output, &metadata)?;
 
             let mut block = reader.read_block();
             loop {
@@ -55,16 +62,16 @@ where
                     break;
                 }
 
-                body_callback(&mut output, &buffer, amount)?;
-                multi_input
-                    .progress_bar
-                    .inc(block.source_read_count() - previous_offset);
+                body_callback(output, &buffer, amount)?;
+                progress_bar.inc(block.source_read_count() - previous_offset);
             }
 
             reader.end_record()?;
-            footer_callback(&mut output)?;
+            footer_callback(output)?;
         }
-    }
+
+        Ok(())
+    })?;
 
     multi_input.progress_bar.finish_and_clear();
 
@@ -85,38 +92,38 @@ pub fn handle_list_command(
         .cloned()
         .unwrap();
 
-    read_warc_files_loop(
-        global_matches,
-        sub_matches,
-        |input_path, output, metadata| {
-            let mut line_buffer = Vec::new();
+    read_warc_files_loop(global_matches, sub_matches, || {
+        (
+            |input_path: &Path, output: &mut dyn Write, metadata: &HeaderMetadata| {
+                let mut line_buffer = Vec::new();
 
-            if include_file {
-                line_buffer.push(input_path.to_string_lossy().into_owned());
-                line_buffer.push(metadata.raw_file_offset().to_string());
-            }
+                if include_file {
+                    line_buffer.push(input_path.to_string_lossy().into_owned());
+                    line_buffer.push(metadata.raw_file_offset().to_string());
+                }
 
-            for name in &names {
-                match metadata.fields().get_str(name.as_str()) {
-                    Some(value) => line_buffer.push(value.to_string()),
-                    None => line_buffer.push("".to_string()),
+                for name in &names {
+                    match metadata.fields().get_str(name.as_str()) {
+                        Some(value) => line_buffer.push(value.to_string()),
+                        None => line_buffer.push("".to_string()),
+                    }
                 }
-            }
 
-            if is_json {
-                output.write_all(serde_json::to_string(&line_buffer)?.as_bytes())?;
-                output.write_all(b"\n")?;
-            } else {
-                let mut writer = csv::Writer::from_writer(Vec::new());
-                writer.serialize(&line_buffer)?;
-                output.write_all(&writer.into_inner()?)?;
-            }
+                if is_json {
+                    output.write_all(serde_json::to_string(&line_buffer)?.as_bytes())?;
+                    output.write_all(b"\n")?;
+                } else {
+                    let mut writer = csv::Writer::from_writer(Vec::new());
+                    writer.serialize(&line_buffer)?;
+                    output.write_all(&writer.into_inner()?)?;
+                }
 
-            Ok(())
-        },
-        |_output, _buffer, _amount| Ok(()),
-        |_output| Ok(()),
-    )
+                Ok(())
+            },
+            |_output: &mut dyn Write, _buffer: &[u8], _amount| Ok(()),
+            |_output: &mut dyn Write| Ok(()),
+        )
+    })
 }
 
 struct DigestData {
@@ -128,52 +135,54 @@ pub fn handle_checksum_command(
     global_matches: &ArgMatches,
     sub_matches: &ArgMatches,
 ) -> anyhow::Result<()> {
-    let digest_data: Rc<RefCell<Option<DigestData>>> = Rc::new(RefCell::new(None));
-
-    read_warc_files_loop(
-        global_matches,
-        sub_matches,
-        |_input_path, output, metadata| {
-            let record_id = metadata
-                .fields()
-                .get_str("WARC-Record-ID")
-                .unwrap_or_default();
-
-            if let Some((digest, expected_value)) = get_digest_from_header(metadata.fields()) {
-                *digest_data.borrow_mut() = Some(DigestData {
-                    digest,
-                    expected_value,
-                });
-            } else {
-                digest_data.borrow_mut().take();
-            }
-
-            write!(output, "{record_id} ")?;
+    read_warc_files_loop(global_matches, sub_matches, || {
+        let digest_data: Rc<RefCell<Option<DigestData>>> = Rc::new(RefCell::new(None));
+        let body_digest_data = digest_data.clone();
+        let footer_digest_data = digest_data.clone();
+
+        (
+            move |_input_path: &Path, output: &mut dyn Write, metadata: &HeaderMetadata| {
+                let record_id = metadata
+                    .fields()
+                    .get_str("WARC-Record-ID")
+                    .unwrap_or_default();
+
+                if let Some((digest, expected_value)) = get_digest_from_header(metadata.fields()) {
+                    *digest_data.borrow_mut() = Some(DigestData {
+                        digest,
+                        expected_value,
+                    });
+                } else {
+                    digest_data.borrow_mut().take();
+                }
 
-            Ok(())
-        },
-        |_output, buffer, amount| {
-            if let Some(data) = digest_data.borrow_mut().as_mut() {
-                data.digest.update(&buffer[0..amount]);
-            }
-            Ok(())
-        },
-        |output| {
-            if let Some(data) = digest_data.borrow_mut().take() {
-                let result = data.digest.finalize();
+                write!(output, "{record_id} ")?;
 
-                if result.as_ref() == data.expected_value {
-                    writeln!(output, "ok")?;
+                Ok(())
+            },
+            move |_output: &mut dyn Write, buffer: &[u8], amount: usize| {
+                if let Some(data) = body_digest_data.borrow_mut().as_mut() {
+                    data.digest.update(&buffer[0..amount]);
+                }
+                Ok(())
+            },
+            move |output: &mut dyn Write| {
+                if let Some(data) = footer_digest_data.borrow_mut().take() {
+                    let result = data.digest.finalize();
+
+                    if result.as_ref() == data.expected_value {
+                        writeln!(output, "ok")?;
+                    } else {
+                        writeln!(output, "fail")?;
+                    }
                 } else {
-                    writeln!(output, "fail")?;
+                    writeln!(output, "skip")?;
                 }
-            } else {
-                writeln!(output, "skip")?;
-            }
 
-            Ok(())
-        },
-    )
+                Ok(())
+            },
+        )
+    })
 }
 
 fn get_digest_from_header(header: &HeaderMap) -> Option<(Box<dyn DynDigest>, Vec<u8>)> {
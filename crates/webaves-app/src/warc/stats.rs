@@ -0,0 +1,90 @@
+use std::{collections::BTreeMap, io::Write};
+
+use clap::ArgMatches;
+use webaves::warc::{format_date, stats::Statistics, WARCReader};
+
+use crate::argtypes::{MultiInput, OutputStream};
+
+pub fn handle_stats_command(
+    global_matches: &ArgMatches,
+    sub_matches: &ArgMatches,
+) -> anyhow::Result<()> {
+    let is_json = sub_matches.get_one::<bool>("json").cloned().unwrap();
+    let mut multi_input = MultiInput::from_args(global_matches, sub_matches)?;
+    let mut output = OutputStream::from_args(sub_matches)?;
+    let mut stats = Statistics::new();
+
+    while let Some((path, file)) = multi_input.next_file()? {
+        stats.add_reader(&mut WARCReader::new(file)?)?;
+
+        multi_input.inc_progress_bar(&path);
+    }
+
+    multi_input.progress_bar.finish_and_clear();
+
+    if is_json {
+        output.write_all(serde_json::to_string(&stats)?.as_bytes())?;
+        output.write_all(b"\n")?;
+    } else {
+        write_text(&mut output, &stats)?;
+    }
+
+    Ok(())
+}
+
+fn write_text(output: &mut OutputStream, stats: &Statistics) -> anyhow::Result<()> {
+    let format_optional_date = |value: &Option<_>| match value {
+        Some(date) => format_date(date),
+        None => "-".to_string(),
+    };
+    let summary = [
+        ("Records", stats.record_count.to_string()),
+        ("Revisit records", stats.revisit_count.to_string()),
+        ("Compressed bytes", stats.compressed_bytes.to_string()),
+        ("Uncompressed bytes", stats.uncompressed_bytes.to_string()),
+        ("First date", format_optional_date(&stats.first_date)),
+        ("Last date", format_optional_date(&stats.last_date)),
+    ];
+
+    for (name, value) in summary {
+        writeln!(output, "{:<20} {}", name, value)?;
+    }
+
+    for (title, counts) in [
+        ("Record type", &stats.record_types),
+        ("MIME type", &stats.mime_types),
+        ("HTTP status", &stats.status_classes),
+        ("Digest algorithm", &stats.digest_algorithms),
+        ("Host", &stats.hosts),
+    ] {
+        writeln!(output)?;
+        write_counts(output, title, counts)?;
+    }
+
+    Ok(())
+}
+
+// Writes the counts as a table sorted by the highest count first.
+fn write_counts(
+    output: &mut OutputStream,
+    title: &str,
+    counts: &BTreeMap<String, u64>,
+) -> anyhow::Result<()> {
+    let mut rows = counts.iter().collect::<Vec<_>>();
+    rows.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+
+    let width = rows
+        .iter()
+        .map(|(name, _)| name.len())
+        .chain([title.len()])
+        .max()
+        .unwrap_or_default();
+
+    writeln!(output, "{:<width$} {:>10}", title, "Count")?;
+
+    for (name, count) in rows {
+        writeln!(output, "{:<width$} {:>10}", name, count)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,63 @@
+use std::io::Write;
+
+use clap::ArgMatches;
+use webaves::warc::validate::{Problem, Severity, Validator};
+
+use crate::argtypes::{MultiInput, OutputStream};
+
+pub fn handle_validate_command(
+    global_matches: &ArgMatches,
+    sub_matches: &ArgMatches,
+) -> anyhow::Result<()> {
+    let is_json = sub_matches.get_one::<bool>("json").cloned().unwrap();
+    let mut multi_input = MultiInput::from_args(global_matches, sub_matches)?;
+    let mut output = OutputStream::from_args(sub_matches)?;
+    let mut validator = Validator::new();
+    let mut problem_count = 0;
+
+    while let Some((path, file)) = multi_input.next_file()? {
+        let filename = path.to_string_lossy();
+        validator.validate_file(&filename, file)?;
+
+        for problem in &validator.problems()[problem_count..] {
+            write_problem(&mut output, problem, is_json)?;
+        }
+        problem_count = validator.problems().len();
+
+        multi_input.inc_progress_bar(&path);
+    }
+
+    let problems = validator.finish();
+
+    for problem in &problems[problem_count..] {
+        write_problem(&mut output, problem, is_json)?;
+    }
+
+    multi_input.progress_bar.finish_and_clear();
+
+    let error_count = problems
+        .iter()
+        .filter(|problem| problem.severity == Severity::Error)
+        .count();
+
+    if error_count > 0 {
+        anyhow::bail!("validation failed with {} errors", error_count);
+    }
+
+    Ok(())
+}
+
+fn write_problem(
+    output: &mut OutputStream,
+    problem: &Problem,
+    is_json: bool,
+) -> anyhow::Result<()> {
+    if is_json {
+        output.write_all(serde_json::to_string(problem)?.as_bytes())?;
+        output.write_all(b"\n")?;
+    } else {
+        writeln!(output, "{}", problem)?;
+    }
+
+    Ok(())
+}
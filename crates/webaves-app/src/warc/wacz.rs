@@ -0,0 +1,105 @@
+use std::{fs::File, io::Write, path::Path};
+
+use clap::ArgMatches;
+use webaves::wacz::{WACZError, WACZReader, WACZWriter};
+
+use crate::argtypes::{InputStream, MultiInput, OutputStream};
+
+pub fn handle_wacz_create_command(
+    global_matches: &ArgMatches,
+    sub_matches: &ArgMatches,
+) -> anyhow::Result<()> {
+    let title = sub_matches.get_one::<String>("title").cloned();
+    let mut multi_input = MultiInput::from_args(global_matches, sub_matches)?;
+    let output = match OutputStream::from_args(sub_matches)? {
+        OutputStream::File(file) => file,
+        OutputStream::Stdout(_) => anyhow::bail!("output must be a file"),
+    };
+
+    let mut writer = WACZWriter::new(output);
+    writer.set_title(title);
+
+    while let Some((path, file)) = multi_input.next_file()? {
+        let file = get_file(file)?;
+        let name = path
+            .file_name()
+            .unwrap_or(path.as_os_str())
+            .to_string_lossy()
+            .into_owned();
+
+        writer.add_warc(&name, file)?;
+        multi_input.inc_progress_bar(&path);
+    }
+
+    writer.finish()?;
+    multi_input.progress_bar.finish_and_clear();
+
+    Ok(())
+}
+
+pub fn handle_wacz_verify_command(
+    global_matches: &ArgMatches,
+    sub_matches: &ArgMatches,
+) -> anyhow::Result<()> {
+    let mut multi_input = MultiInput::from_args(global_matches, sub_matches)?;
+    let mut output = OutputStream::from_args(sub_matches)?;
+    let mut failure_count = 0;
+
+    while let Some((path, file)) = multi_input.next_file()? {
+        let file = get_file(file)?;
+        let mut reader = WACZReader::new(file)?;
+
+        let status = match reader.verify_data_package()? {
+            Some(true) => "ok",
+            Some(false) => "fail",
+            None => "skip",
+        };
+        write_status(&mut output, &path, "datapackage.json", status)?;
+
+        if status == "fail" {
+            failure_count += 1;
+        }
+
+        for resource in reader.data_package().resources.clone() {
+            let status = match reader.verify_resource(&resource) {
+                Ok(true) => "ok",
+                Ok(false) => "fail",
+                Err(WACZError::MissingFile { .. }) => "missing",
+                Err(error) => return Err(error.into()),
+            };
+            write_status(&mut output, &path, &resource.path, status)?;
+
+            if status != "ok" {
+                failure_count += 1;
+            }
+        }
+
+        multi_input.inc_progress_bar(&path);
+    }
+
+    multi_input.progress_bar.finish_and_clear();
+
+    if failure_count > 0 {
+        anyhow::bail!("verification failed with {} errors", failure_count);
+    }
+
+    Ok(())
+}
+
+fn get_file(file: InputStream) -> anyhow::Result<File> {
+    match file {
+        InputStream::File(file) => Ok(file),
+        InputStream::Stdin(_) => anyhow::bail!("input must be a file"),
+    }
+}
+
+fn write_status(
+    output: &mut OutputStream,
+    path: &Path,
+    resource_path: &str,
+    status: &str,
+) -> anyhow::Result<()> {
+    writeln!(output, "{}:{} {}", path.display(), resource_path, status)?;
+
+    Ok(())
+}
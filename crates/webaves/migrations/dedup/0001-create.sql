@@ -0,0 +1,20 @@
+PRAGMA application_id = -826887662;
+PRAGMA journal_mode = WAL;
+
+BEGIN TRANSACTION;
+
+PRAGMA user_version = 1;
+
+CREATE TABLE
+    payload_digests
+    (
+        payload_digest TEXT NOT NULL,
+        target_uri TEXT NOT NULL,
+        record_id TEXT NOT NULL,
+        date TEXT NOT NULL,
+
+        PRIMARY KEY (payload_digest, target_uri)
+    )
+    WITHOUT ROWID;
+
+COMMIT;
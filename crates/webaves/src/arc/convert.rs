@@ -0,0 +1,196 @@
+use std::io::{Read, Write};
+
+use chrono::{DateTime, Utc};
+
+use crate::{
+    header::HeaderMap,
+    warc::{
+        format_date, HeaderMapExt, MetadataRecordBuilder, RecordBuilder, RecordKind,
+        ResourceRecordBuilder, ResponseRecordBuilder, WARCError, WARCRecord, WARCWriter,
+        WarcinfoRecordBuilder,
+    },
+};
+
+use super::{ARCError, ARCReader, ARCRecordMetadata, ARCVersionBlock};
+
+const HTTP_RESPONSE_CONTENT_TYPE: &str = "application/http;msgtype=response";
+const UNKNOWN_CONTENT_TYPES: [&str; 2] = ["no-type", "-"];
+
+/// Converts ARC records to WARC records.
+///
+/// The version block is converted to a `warcinfo` record describing the
+/// new file and a `metadata` record describing the original ARC file.
+/// Each ARC record is converted to a `response` record for HTTP URLs or a
+/// `resource` record otherwise.
+#[derive(Debug, Clone, Default)]
+pub struct ARCConverter {
+    warcinfo_id: Option<String>,
+    date: Option<DateTime<Utc>>,
+}
+
+impl ARCConverter {
+    /// Creates an `ARCConverter`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the record ID of the last `warcinfo` record created.
+    pub fn warcinfo_id(&self) -> Option<&str> {
+        self.warcinfo_id.as_deref()
+    }
+
+    /// Returns the `warcinfo` and `metadata` records for the version block.
+    ///
+    /// The given filename is the name of the new WARC file, if any.
+    pub fn convert_version_block(
+        &mut self,
+        version_block: &ARCVersionBlock,
+        warc_filename: Option<&str>,
+    ) -> Result<(WARCRecord, WARCRecord), WARCError> {
+        self.date = version_block.date_time();
+
+        let mut info_fields = HeaderMap::new();
+        info_fields.insert("software", format!("webaves/{}", env!("CARGO_PKG_VERSION")));
+        info_fields.insert("format", "WARC File Format 1.1");
+        info_fields.insert(
+            "conformsTo",
+            "http://iipc.github.io/warc-specifications/specifications/warc-format/warc-1.1/",
+        );
+        info_fields.insert(
+            "description",
+            format!("Converted from ARC file {}", version_block.filename),
+        );
+
+        if !version_block.origin_code.is_empty() {
+            info_fields.insert("operator", version_block.origin_code.as_str());
+        }
+
+        let mut builder = WarcinfoRecordBuilder::new().info_fields(&info_fields)?;
+
+        if let Some(date) = &self.date {
+            builder = builder.date(date);
+        }
+        if let Some(filename) = warc_filename {
+            builder = builder.filename(filename);
+        }
+
+        let warcinfo = builder.build()?;
+        let warcinfo_id = warcinfo.header.get_required("WARC-Record-ID")?.to_string();
+
+        let mut provenance_fields = HeaderMap::new();
+        provenance_fields.insert("arc-filename", version_block.filename.as_str());
+        provenance_fields.insert(
+            "arc-version",
+            format!(
+                "{}.{}",
+                version_block.major_version, version_block.minor_version
+            ),
+        );
+        provenance_fields.insert("arc-date", version_block.date.as_str());
+        provenance_fields.insert("arc-ip-address", version_block.ip_address.as_str());
+        provenance_fields.insert("arc-origin-code", version_block.origin_code.as_str());
+        provenance_fields.insert("arc-field-names", version_block.field_names.join(" "));
+        provenance_fields.insert("conversion-date", format_date(&Utc::now()));
+
+        let provenance = MetadataRecordBuilder::new()
+            .target_uri(&format!("filedesc://{}", version_block.filename))
+            .warcinfo_id(&warcinfo_id)
+            .metadata_fields(&provenance_fields)?
+            .build()?;
+
+        self.warcinfo_id = Some(warcinfo_id);
+
+        Ok((warcinfo, provenance))
+    }
+
+    /// Returns the header of the WARC record for the given ARC record.
+    ///
+    /// The header does not include digests. The block of the WARC record
+    /// is the unchanged block of the ARC record.
+    pub fn convert_header(&self, metadata: &ARCRecordMetadata) -> Result<HeaderMap, WARCError> {
+        let url = metadata.url();
+
+        if url.starts_with("http://") || url.starts_with("https://") {
+            let builder = ResponseRecordBuilder::new(url).content_type(HTTP_RESPONSE_CONTENT_TYPE);
+            self.build_header(builder, metadata)
+        } else if UNKNOWN_CONTENT_TYPES.contains(&metadata.content_type()) {
+            self.build_header(ResourceRecordBuilder::new(url), metadata)
+        } else {
+            let builder = ResourceRecordBuilder::new(url).content_type(metadata.content_type());
+            self.build_header(builder, metadata)
+        }
+    }
+
+    fn build_header<K: RecordKind>(
+        &self,
+        mut builder: RecordBuilder<K>,
+        metadata: &ARCRecordMetadata,
+    ) -> Result<HeaderMap, WARCError> {
+        match metadata.date_time().or(self.date) {
+            Some(date) => builder = builder.date(&date),
+            None => tracing::warn!(date = metadata.date(), "unparsable ARC date"),
+        }
+
+        let ip_address = metadata.ip_address();
+
+        if ip_address != "0.0.0.0" && ip_address.parse::<std::net::IpAddr>().is_ok() {
+            builder = builder.ip_address(ip_address);
+        }
+
+        if let Some(warcinfo_id) = &self.warcinfo_id {
+            builder = builder.warcinfo_id(warcinfo_id);
+        }
+
+        Ok(builder
+            .content_length(metadata.block_length())
+            .build()?
+            .header)
+    }
+
+    /// Converts all records from the reader and writes them to the writer.
+    ///
+    /// The writer should have a digest algorithm set so that digests are
+    /// computed for the converted records.
+    ///
+    /// Returns the number of ARC records converted.
+    pub fn convert<R: Read, W: Write>(
+        &mut self,
+        reader: &mut ARCReader<'_, R>,
+        writer: &mut WARCWriter<'_, W>,
+        warc_filename: Option<&str>,
+    ) -> Result<u64, ARCError> {
+        let mut count = 0;
+        let mut has_version_block = false;
+
+        loop {
+            let metadata = reader.begin_record()?;
+
+            if !has_version_block {
+                if let Some(version_block) = reader.version_block() {
+                    let (warcinfo, provenance) =
+                        self.convert_version_block(version_block, warc_filename)?;
+                    writer.write_record(&warcinfo)?;
+                    writer.write_record(&provenance)?;
+                    has_version_block = true;
+                }
+            }
+
+            let metadata = match metadata {
+                Some(metadata) => metadata,
+                None => break,
+            };
+
+            tracing::debug!(url = metadata.url(), "convert record");
+
+            let header = self.convert_header(&metadata)?;
+            writer.begin_record(&header)?;
+            std::io::copy(&mut reader.read_block(), &mut writer.write_block())?;
+            writer.end_record()?;
+            reader.end_record()?;
+
+            count += 1;
+        }
+
+        Ok(count)
+    }
+}
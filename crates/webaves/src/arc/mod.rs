@@ -0,0 +1,48 @@
+//! ARC file processing.
+//!
+//! ARC is the file format used by the Internet Archive before WARC.
+//! Both version 1 and version 2 of the format are supported.
+mod convert;
+mod reader;
+
+pub use convert::*;
+pub use reader::*;
+
+use thiserror::Error;
+
+/// Errors during parsing of ARC files.
+#[derive(Error, Debug)]
+pub enum ARCError {
+    /// Not a recognized ARC file.
+    #[error("unknown format")]
+    UnknownFormat,
+
+    /// URL record line couldn't be parsed.
+    #[error("malformed header (at offset {offset})")]
+    MalformedHeader {
+        /// Number of bytes read from the (uncompressed) input stream.
+        offset: u64,
+    },
+
+    /// Version block couldn't be parsed.
+    #[error("malformed version block (at offset {offset})")]
+    MalformedVersionBlock {
+        /// Number of bytes read from the (uncompressed) input stream.
+        offset: u64,
+    },
+
+    /// The length of the record body does not correspond with the value in the header.
+    #[error("wrong block length (at offset {offset})")]
+    WrongBlockLength {
+        /// Number of bytes read from the (uncompressed) input stream.
+        offset: u64,
+    },
+
+    /// Error writing converted WARC records.
+    #[error(transparent)]
+    Warc(#[from] crate::warc::WARCError),
+
+    /// IO error.
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
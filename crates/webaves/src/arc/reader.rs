@@ -0,0 +1,472 @@
+use std::io::{BufRead, BufReader, Read, Take};
+
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+
+use crate::{
+    compress::{CompressionFormat, MultiStreamDecompressor},
+    io::{BufReadMoreExt, SourceCountRead},
+};
+
+use super::ARCError;
+
+const VERSION_BLOCK_URL_PREFIX: &str = "filedesc://";
+const V1_FIELD_COUNT: usize = 5;
+
+/// Reads an ARC file.
+///
+/// Decompression is handled automatically by [MultiStreamDecompressor].
+///
+/// The version block at the start of the file is read automatically by the
+/// first call to [Self::begin_record] and is available from
+/// [Self::version_block].
+pub struct ARCReader<'a, S: Read> {
+    stream: MultiStreamDecompressor<'a, S>,
+    header_limit: u64,
+
+    state: ReaderState,
+
+    file_offset: u64,
+    line_buffer: Vec<u8>,
+    version_block: Option<ARCVersionBlock>,
+
+    block_file_offset: u64,
+    block_length: u64,
+    block_bytes_read: u64,
+}
+
+impl<'a, S: Read> ARCReader<'a, S> {
+    /// Creates an `ARCReader` with the given input buffered stream.
+    pub fn new(stream: S) -> Result<Self, ARCError> {
+        Ok(Self {
+            stream: MultiStreamDecompressor::new(stream)?,
+            header_limit: 16_777_216,
+            state: ReaderState::StartOfHeader,
+            file_offset: 0,
+            line_buffer: Vec::new(),
+            version_block: None,
+            block_file_offset: 0,
+            block_length: 0,
+            block_bytes_read: 0,
+        })
+    }
+
+    /// Creates an `ARCReader` with the given input stream.
+    pub fn new_read<R: Read>(reader: R) -> Result<ARCReader<'a, BufReader<R>>, ARCError> {
+        ARCReader::new(BufReader::new(reader))
+    }
+
+    /// Returns the wrapped stream.
+    pub fn into_inner(self) -> S {
+        self.stream.into_inner()
+    }
+
+    /// Returns the detected compression format of the stream.
+    pub fn compression_format(&self) -> CompressionFormat {
+        self.stream.format()
+    }
+
+    /// Returns the version block of the file.
+    ///
+    /// Returns `None` if [Self::begin_record] has not been called yet.
+    pub fn version_block(&self) -> Option<&ARCVersionBlock> {
+        self.version_block.as_ref()
+    }
+
+    /// Returns the position in the (compressed) stream of the boundary
+    /// between records.
+    ///
+    /// See [crate::warc::WARCReader::raw_file_offset] for details.
+    pub fn raw_file_offset(&mut self) -> Result<u64, ARCError> {
+        match self.stream.source_position()? {
+            Some(offset) => Ok(offset),
+            None => Ok(self.stream.member_offset()),
+        }
+    }
+
+    /// Starts reading a record and returns the URL record line.
+    ///
+    /// The caller must call [Self::read_block] next to advance the stream.
+    ///
+    /// Panics when called out of sequence.
+    ///
+    /// Returns `None` when there are no more records in the stream.
+    pub fn begin_record(&mut self) -> Result<Option<ARCRecordMetadata>, ARCError> {
+        assert!(self.state == ReaderState::StartOfHeader);
+
+        loop {
+            self.skip_separator_lines()?;
+
+            let start_file_offset = self.file_offset;
+            let raw_file_offset = self.raw_file_offset()?;
+
+            tracing::debug!(
+                file_offset = self.file_offset,
+                raw_file_offset,
+                "begin_record"
+            );
+
+            if !self.read_header_line()? {
+                return Ok(None);
+            }
+
+            let is_version_block = self
+                .line_buffer
+                .starts_with(VERSION_BLOCK_URL_PREFIX.as_bytes());
+
+            if self.version_block.is_none() && !is_version_block {
+                return Err(ARCError::UnknownFormat);
+            }
+
+            let field_count = match &self.version_block {
+                Some(version_block) if !is_version_block => version_block.field_names.len(),
+                _ => V1_FIELD_COUNT,
+            };
+            let fields = parse_header_line(&self.line_buffer, field_count, self.file_offset)?;
+            let block_length = parse_block_length(&fields, self.file_offset)?;
+
+            if is_version_block {
+                self.read_version_block(fields, block_length)?;
+                continue;
+            }
+
+            self.block_file_offset = self.file_offset;
+            self.block_length = block_length;
+            self.block_bytes_read = 0;
+            self.state = ReaderState::EndOfHeader;
+
+            return Ok(Some(ARCRecordMetadata {
+                fields,
+                block_length,
+                file_offset: start_file_offset,
+                raw_file_offset,
+            }));
+        }
+    }
+
+    fn skip_separator_lines(&mut self) -> Result<(), ARCError> {
+        loop {
+            let buf = self.stream.fill_buf()?;
+            let amount = buf
+                .iter()
+                .take_while(|&&byte| byte == b'\n' || byte == b'\r')
+                .count();
+
+            if amount == 0 {
+                return Ok(());
+            }
+
+            self.stream.consume(amount);
+            self.file_offset += amount as u64;
+        }
+    }
+
+    fn read_header_line(&mut self) -> Result<bool, ARCError> {
+        tracing::debug!("read_header_line");
+
+        self.line_buffer.clear();
+        let amount =
+            self.stream
+                .read_limit_until(b'\n', &mut self.line_buffer, self.header_limit)?;
+        self.file_offset += amount as u64;
+
+        Ok(amount > 0)
+    }
+
+    fn read_version_block(
+        &mut self,
+        fields: Vec<String>,
+        block_length: u64,
+    ) -> Result<(), ARCError> {
+        tracing::debug!(block_length, "read_version_block");
+
+        if block_length > self.header_limit {
+            return Err(ARCError::MalformedVersionBlock {
+                offset: self.file_offset,
+            });
+        }
+
+        let mut block = Vec::new();
+        let amount = self
+            .stream
+            .by_ref()
+            .take(block_length)
+            .read_to_end(&mut block)?;
+        self.file_offset += amount as u64;
+
+        if amount as u64 != block_length {
+            return Err(ARCError::WrongBlockLength {
+                offset: self.file_offset,
+            });
+        }
+
+        self.version_block = Some(parse_version_block(fields, &block, self.file_offset)?);
+
+        Ok(())
+    }
+
+    /// Starts reading a record body.
+    ///
+    /// The caller must read until the block stream is empty and then
+    /// call [Self::end_record].
+    ///
+    /// Panics when called out of sequence.
+    pub fn read_block(&mut self) -> ARCBlockReader<'_, 'a, S> {
+        assert!(self.state == ReaderState::EndOfHeader);
+        tracing::debug!("read_block");
+
+        let stream = self.stream.by_ref().take(self.block_length);
+        self.state = ReaderState::InBlock;
+
+        ARCBlockReader {
+            stream,
+            num_bytes_read: &mut self.block_bytes_read,
+        }
+    }
+
+    /// Finish reading a record.
+    ///
+    /// Panics when called out of sequence.
+    pub fn end_record(&mut self) -> Result<(), ARCError> {
+        assert!(self.state == ReaderState::InBlock);
+        tracing::debug!("end_record");
+
+        self.file_offset += self.block_bytes_read;
+
+        if self.file_offset != self.block_file_offset + self.block_length {
+            return Err(ARCError::WrongBlockLength {
+                offset: self.file_offset,
+            });
+        }
+
+        self.state = ReaderState::StartOfHeader;
+
+        Ok(())
+    }
+}
+
+impl<'a, S: Read> SourceCountRead for ARCReader<'a, S> {
+    fn source_read_count(&self) -> u64 {
+        self.stream.source_read_count()
+    }
+}
+
+// Splits the line into the expected number of fields. Extra spaces are
+// assumed to be part of the URL.
+fn parse_header_line(
+    line: &[u8],
+    field_count: usize,
+    file_offset: u64,
+) -> Result<Vec<String>, ARCError> {
+    let line = String::from_utf8_lossy(crate::stringutil::trim_trailing_crlf(line));
+    let mut parts = line.split(' ').filter(|part| !part.is_empty());
+    let parts_count = parts.clone().count();
+
+    if field_count == 0 || parts_count < field_count {
+        return Err(ARCError::MalformedHeader {
+            offset: file_offset,
+        });
+    }
+
+    let url_parts = parts_count - field_count + 1;
+    let url = parts
+        .by_ref()
+        .take(url_parts)
+        .collect::<Vec<&str>>()
+        .join("%20");
+
+    let mut fields = vec![url];
+    fields.extend(parts.map(|part| part.to_string()));
+
+    Ok(fields)
+}
+
+fn parse_block_length(fields: &[String], file_offset: u64) -> Result<u64, ARCError> {
+    fields
+        .last()
+        .and_then(|value| value.parse().ok())
+        .ok_or(ARCError::MalformedHeader {
+            offset: file_offset,
+        })
+}
+
+fn parse_version_block(
+    fields: Vec<String>,
+    block: &[u8],
+    file_offset: u64,
+) -> Result<ARCVersionBlock, ARCError> {
+    let error = || ARCError::MalformedVersionBlock {
+        offset: file_offset,
+    };
+
+    let mut lines = block.splitn(3, |&byte| byte == b'\n');
+    let version_line = String::from_utf8_lossy(lines.next().unwrap_or_default());
+    let names_line = String::from_utf8_lossy(lines.next().ok_or_else(error)?);
+    let metadata = lines.next().unwrap_or_default().to_vec();
+
+    let mut version_parts = version_line.trim().splitn(3, ' ');
+    let major_version = version_parts
+        .next()
+        .and_then(|value| value.parse().ok())
+        .ok_or_else(error)?;
+    let minor_version = version_parts
+        .next()
+        .and_then(|value| value.parse().ok())
+        .ok_or_else(error)?;
+    let origin_code = version_parts.next().unwrap_or_default().to_string();
+
+    let field_names = names_line
+        .split_whitespace()
+        .map(|name| name.to_string())
+        .collect::<Vec<String>>();
+
+    if field_names.len() < V1_FIELD_COUNT {
+        return Err(error());
+    }
+
+    let mut fields = fields.into_iter();
+    let url = fields.next().unwrap_or_default();
+
+    Ok(ARCVersionBlock {
+        filename: url
+            .strip_prefix(VERSION_BLOCK_URL_PREFIX)
+            .unwrap_or_default()
+            .to_string(),
+        ip_address: fields.next().unwrap_or_default(),
+        date: fields.next().unwrap_or_default(),
+        content_type: fields.next().unwrap_or_default(),
+        major_version,
+        minor_version,
+        origin_code,
+        field_names,
+        metadata,
+    })
+}
+
+fn parse_date(value: &str) -> Option<DateTime<Utc>> {
+    NaiveDateTime::parse_from_str(value, "%Y%m%d%H%M%S")
+        .ok()
+        .map(|date_time| Utc.from_utc_datetime(&date_time))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ReaderState {
+    StartOfHeader,
+    EndOfHeader,
+    InBlock,
+}
+
+/// Reader stream for a record body.
+pub struct ARCBlockReader<'a, 's, S: Read> {
+    stream: Take<&'a mut MultiStreamDecompressor<'s, S>>,
+    num_bytes_read: &'a mut u64,
+}
+
+impl<'a, 's, S: Read> SourceCountRead for ARCBlockReader<'a, 's, S> {
+    fn source_read_count(&self) -> u64 {
+        self.stream.get_ref().source_read_count()
+    }
+}
+
+impl<'a, 's, S: Read> Read for ARCBlockReader<'a, 's, S> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let size = self.stream.read(buf)?;
+        *self.num_bytes_read += size as u64;
+        Ok(size)
+    }
+}
+
+/// The version block at the start of an ARC file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ARCVersionBlock {
+    /// Filename from the `filedesc://` URL.
+    pub filename: String,
+    /// IP address of the machine that created the file.
+    pub ip_address: String,
+    /// Creation date of the file as `YYYYMMDDhhmmss`.
+    pub date: String,
+    /// Content type of the version block.
+    pub content_type: String,
+    /// Major version number. (1 for both ARC version 1 and 2.)
+    pub major_version: u32,
+    /// Minor version number. (0 for version 1 and 1 for version 2.)
+    pub minor_version: u32,
+    /// Name of the organization that created the file.
+    pub origin_code: String,
+    /// Names of the fields in URL record lines.
+    pub field_names: Vec<String>,
+    /// Any remaining data after the field names, such as XML metadata.
+    pub metadata: Vec<u8>,
+}
+
+impl ARCVersionBlock {
+    /// Returns the parsed creation date.
+    ///
+    /// Returns `None` if the date is not in the expected format.
+    pub fn date_time(&self) -> Option<DateTime<Utc>> {
+        parse_date(&self.date)
+    }
+}
+
+/// A record's URL record line and associated file metadata.
+#[derive(Debug, Clone)]
+pub struct ARCRecordMetadata {
+    fields: Vec<String>,
+    block_length: u64,
+    file_offset: u64,
+    raw_file_offset: u64,
+}
+
+impl ARCRecordMetadata {
+    /// Returns all the values in the URL record line.
+    ///
+    /// The names of the values are given by
+    /// [ARCVersionBlock::field_names]. Spaces in the URL are replaced
+    /// with `%20`.
+    pub fn fields(&self) -> &[String] {
+        &self.fields
+    }
+
+    /// Returns the URL of the document.
+    pub fn url(&self) -> &str {
+        &self.fields[0]
+    }
+
+    /// Returns the IP address of the server.
+    pub fn ip_address(&self) -> &str {
+        &self.fields[1]
+    }
+
+    /// Returns the archive date as `YYYYMMDDhhmmss`.
+    pub fn date(&self) -> &str {
+        &self.fields[2]
+    }
+
+    /// Returns the parsed archive date.
+    ///
+    /// Returns `None` if the date is not in the expected format.
+    pub fn date_time(&self) -> Option<DateTime<Utc>> {
+        parse_date(self.date())
+    }
+
+    /// Returns the content type of the document.
+    pub fn content_type(&self) -> &str {
+        &self.fields[3]
+    }
+
+    /// Returns the length of the body of the record.
+    pub fn block_length(&self) -> u64 {
+        self.block_length
+    }
+
+    /// Number of bytes read in total from the (uncompressed) stream.
+    pub fn file_offset(&self) -> u64 {
+        self.file_offset
+    }
+
+    /// Position of the record in the (compressed) stream.
+    ///
+    /// See [ARCReader::raw_file_offset] for details.
+    pub fn raw_file_offset(&self) -> u64 {
+        self.raw_file_offset
+    }
+}
@@ -1,22 +1,49 @@
 //! Compression and decompression streams.
 
 use std::{
-    io::{ErrorKind, Read, Write},
+    io::{BufRead, ErrorKind, Read, Write},
+    pin::Pin,
     str::FromStr,
+    task::{Context, Poll},
+};
+
+use async_compression::{
+    tokio::{
+        bufread::GzipDecoder as AsyncGzipDecoder,
+        write::{GzipEncoder as AsyncGzipEncoder, ZstdEncoder as AsyncZstdEncoder},
+    },
+    Level as AsyncLevel,
 };
 
 use brotli::enc::writer::CompressorWriter as BrotliEncoder;
 use brotli::Decompressor as BrotliDecoder;
-use flate2::{bufread::MultiGzDecoder, write::GzEncoder};
 use flate2::{
     bufread::{DeflateDecoder, ZlibDecoder},
     write::{DeflateEncoder, ZlibEncoder},
     Compression as GzCompression,
 };
+use flate2::{
+    bufread::{GzDecoder, MultiGzDecoder},
+    write::GzEncoder,
+};
+use zstd::stream::raw::{
+    Decoder as ZstdRawDecoder, InBuffer as ZstdInBuffer, Operation as _, OutBuffer as ZstdOutBuffer,
+};
 use zstd::stream::read::Decoder as ZstdDecoder;
 use zstd::stream::write::Encoder as ZstdEncoder;
 
-use crate::io::{ComboReader, CountRead, PeekRead, SourceCountRead};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, ReadBuf};
+
+use crate::io::{AsyncCountReader, ComboReader, CountRead, PeekRead, SourceCountRead};
+
+/// Magic number of the Zstandard skippable frame that contains a dictionary
+/// at the start of a `.warc.zst` file.
+pub const ZSTD_DICTIONARY_FRAME_MAGIC: u32 = 0x184D2A5D;
+
+/// Default maximum size of a dictionary trained by [train_zstd_dictionary].
+pub const DEFAULT_ZSTD_DICTIONARY_SIZE: usize = 112_640;
+
+const ZSTD_FRAME_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
 
 /// Specifies a compression or decompression format.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -99,14 +126,10 @@ impl<'a, S: Read> Decompressor<'a, S> {
 
         tracing::debug!(?magic_bytes, "decompressor analysis");
 
-        let decoder = match &magic_bytes[0..4] {
-            [0x78, 0x01, _, _] | [0x78, 0x5e, _, _] | [0x78, 0x9c, _, _] | [0x78, 0xda, _, _] => {
-                Decoder::DeflateZlib(ZlibDecoder::new(stream))
-            }
-            [0x1f, 0x8b, _, _] => Decoder::Gzip(MultiGzDecoder::new(stream)),
-            [0x28, 0xb5, 0x2f, 0xfd] | [0x37, 0xa4, 0x30, 0xec] => {
-                Decoder::Zstd(ZstdDecoder::with_buffer(stream)?)
-            }
+        let decoder = match detect_format(&magic_bytes) {
+            Some(CompressionFormat::DeflateZlib) => Decoder::DeflateZlib(ZlibDecoder::new(stream)),
+            Some(CompressionFormat::Gzip) => Decoder::Gzip(MultiGzDecoder::new(stream)),
+            Some(CompressionFormat::Zstd) => Decoder::Zstd(new_zstd_decoder(stream)?),
             _ => {
                 if allow_unknown {
                     Decoder::Raw(stream)
@@ -141,7 +164,7 @@ impl<'a, S: Read> Decompressor<'a, S> {
             CompressionFormat::DeflateZlib => Decoder::DeflateZlib(ZlibDecoder::new(stream)),
             CompressionFormat::Gzip => Decoder::Gzip(MultiGzDecoder::new(stream)),
             CompressionFormat::Brotli => Decoder::Brotli(BrotliDecoder::new(stream, 4096)),
-            CompressionFormat::Zstd => Decoder::Zstd(ZstdDecoder::with_buffer(stream)?),
+            CompressionFormat::Zstd => Decoder::Zstd(new_zstd_decoder(stream)?),
         };
 
         Ok(Self { decoder })
@@ -212,6 +235,918 @@ impl<'a, S: Read> SourceCountRead for Decompressor<'a, S> {
     }
 }
 
+fn detect_format(magic_bytes: &[u8]) -> Option<CompressionFormat> {
+    match magic_bytes {
+        [0x78, 0x01, _, _] | [0x78, 0x5e, _, _] | [0x78, 0x9c, _, _] | [0x78, 0xda, _, _] => {
+            Some(CompressionFormat::DeflateZlib)
+        }
+        [0x1f, 0x8b, _, _] => Some(CompressionFormat::Gzip),
+        [0x28, 0xb5, 0x2f, 0xfd] | [0x37, 0xa4, 0x30, 0xec] | [0x5d, 0x2a, 0x4d, 0x18] => {
+            Some(CompressionFormat::Zstd)
+        }
+        _ => None,
+    }
+}
+
+// Creates a decoder for a whole Zstandard file using the dictionary at the
+// start of the file if present.
+fn new_zstd_decoder<'a, S: Read>(
+    mut stream: ComboReader<S>,
+) -> std::io::Result<ZstdDecoder<'a, ComboReader<S>>> {
+    match read_zstd_dictionary_frame(&mut stream)? {
+        Some(dictionary) => ZstdDecoder::with_dictionary(stream, &dictionary),
+        None => ZstdDecoder::with_buffer(stream),
+    }
+}
+
+/// Trains a Zstandard dictionary from the given samples.
+///
+/// Each sample should be the data of a single compressed frame, such as a
+/// WARC record. The dictionary is at most `max_size` bytes.
+pub fn train_zstd_dictionary<S: AsRef<[u8]>>(
+    samples: &[S],
+    max_size: usize,
+) -> std::io::Result<Vec<u8>> {
+    zstd::dict::from_samples(samples, max_size)
+}
+
+/// Writes a Zstandard dictionary as a skippable frame.
+///
+/// The dictionary is compressed within the frame as described by the
+/// `.warc.zst` format.
+pub fn write_zstd_dictionary_frame<W: Write>(
+    mut stream: W,
+    dictionary: &[u8],
+) -> std::io::Result<()> {
+    let data = zstd::encode_all(dictionary, 0)?;
+    let length = u32::try_from(data.len())
+        .map_err(|_| std::io::Error::new(ErrorKind::InvalidInput, "dictionary too large"))?;
+
+    stream.write_all(&ZSTD_DICTIONARY_FRAME_MAGIC.to_le_bytes())?;
+    stream.write_all(&length.to_le_bytes())?;
+    stream.write_all(&data)?;
+
+    Ok(())
+}
+
+// Reads the dictionary if the stream is positioned at a dictionary frame.
+// Otherwise, nothing is consumed.
+pub(crate) fn read_zstd_dictionary_frame<S: Read>(
+    stream: &mut ComboReader<S>,
+) -> std::io::Result<Option<Vec<u8>>> {
+    let length = match stream.peek_exact(8) {
+        Ok(header) => {
+            if header[0..4] != ZSTD_DICTIONARY_FRAME_MAGIC.to_le_bytes() {
+                return Ok(None);
+            }
+
+            u32::from_le_bytes(header[4..8].try_into().unwrap())
+        }
+        Err(error) if error.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(error) => return Err(error),
+    };
+
+    let mut data = vec![0u8; 8 + length as usize];
+    stream.read_exact(&mut data)?;
+    let data = data.split_off(8);
+
+    tracing::debug!(length, "read zstd dictionary frame");
+
+    Ok(Some(decode_zstd_dictionary_frame_data(data)?))
+}
+
+// Returns the dictionary from the data of a dictionary frame.
+fn decode_zstd_dictionary_frame_data(data: Vec<u8>) -> std::io::Result<Vec<u8>> {
+    if data.starts_with(&ZSTD_FRAME_MAGIC) {
+        zstd::decode_all(data.as_slice())
+    } else {
+        Ok(data)
+    }
+}
+
+// Advances the stream to the next bytes that match the magic bytes of
+// the format.
+fn scan_member_start<S: Read>(
+    stream: &mut ComboReader<S>,
+    format: CompressionFormat,
+    member_offset: u64,
+) -> std::io::Result<bool> {
+    if stream.read_count() == member_offset && !stream.fill_buf()?.is_empty() {
+        stream.consume(1);
+    }
+
+    loop {
+        match stream.peek_exact(4) {
+            Ok(magic_bytes) => {
+                if detect_format(magic_bytes) == Some(format) {
+                    return Ok(true);
+                }
+            }
+            Err(error) if error.kind() == ErrorKind::UnexpectedEof => {
+                let amount = stream.fill_buf()?.len();
+
+                if amount == 0 {
+                    return Ok(false);
+                }
+
+                stream.consume(amount);
+                continue;
+            }
+            Err(error) => return Err(error),
+        }
+
+        let buf = stream.fill_buf()?;
+        let amount = buf[1..]
+            .iter()
+            .position(|byte| [0x1f, 0x28, 0x37, 0x78].contains(byte))
+            .map(|position| position + 1)
+            .unwrap_or(buf.len());
+        stream.consume(amount);
+    }
+}
+
+#[allow(clippy::large_enum_variant)]
+enum MemberDecoder<'a, S: Read> {
+    Idle(ComboReader<S>),
+    Raw(ComboReader<S>),
+    DeflateZlib(ZlibDecoder<ComboReader<S>>),
+    Gzip(GzDecoder<ComboReader<S>>),
+    Zstd(ZstdDecoder<'a, ComboReader<S>>),
+}
+
+impl<'a, S: Read> MemberDecoder<'a, S> {
+    fn get_ref(&self) -> &ComboReader<S> {
+        match self {
+            MemberDecoder::Idle(stream) => stream,
+            MemberDecoder::Raw(stream) => stream,
+            MemberDecoder::DeflateZlib(stream) => stream.get_ref(),
+            MemberDecoder::Gzip(stream) => stream.get_ref(),
+            MemberDecoder::Zstd(stream) => stream.get_ref(),
+        }
+    }
+
+    fn get_mut(&mut self) -> &mut ComboReader<S> {
+        match self {
+            MemberDecoder::Idle(stream) => stream,
+            MemberDecoder::Raw(stream) => stream,
+            MemberDecoder::DeflateZlib(stream) => stream.get_mut(),
+            MemberDecoder::Gzip(stream) => stream.get_mut(),
+            MemberDecoder::Zstd(stream) => stream.get_mut(),
+        }
+    }
+
+    fn into_inner(self) -> ComboReader<S> {
+        match self {
+            MemberDecoder::Idle(stream) => stream,
+            MemberDecoder::Raw(stream) => stream,
+            MemberDecoder::DeflateZlib(stream) => stream.into_inner(),
+            MemberDecoder::Gzip(stream) => stream.into_inner(),
+            MemberDecoder::Zstd(stream) => stream.finish(),
+        }
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            MemberDecoder::Idle(_) => Ok(0),
+            MemberDecoder::Raw(stream) => stream.read(buf),
+            MemberDecoder::DeflateZlib(stream) => stream.read(buf),
+            MemberDecoder::Gzip(stream) => stream.read(buf),
+            MemberDecoder::Zstd(stream) => stream.read(buf),
+        }
+    }
+}
+
+/// Decompression of files consisting of multiple concatenated compressed
+/// members with awareness of member boundaries.
+///
+/// A member is a Gzip member, a Zstd frame, or a Zlib stream. Compressed
+/// data of one member is never mixed with another member in the
+/// internal buffer, so positions of members in the source stream can be
+/// determined.
+///
+/// Uncompressed files are passed through as is.
+pub struct MultiStreamDecompressor<'a, S: Read> {
+    decoder: Option<MemberDecoder<'a, S>>,
+    format: CompressionFormat,
+    buf: Vec<u8>,
+    buf_position: usize,
+    member_offset: u64,
+    member_read_count: u64,
+    zstd_dictionary: Option<Vec<u8>>,
+}
+
+impl<'a, S: Read> MultiStreamDecompressor<'a, S> {
+    /// Open a compressed file or contents unchanged for unsupported or undetectable formats.
+    pub fn new(stream: S) -> std::io::Result<Self> {
+        let mut stream = ComboReader::new(stream);
+        let magic_bytes = match stream.peek_exact(4) {
+            Ok(magic_bytes) => magic_bytes.to_vec(),
+            Err(error) if error.kind() == ErrorKind::UnexpectedEof => Vec::new(),
+            Err(error) => return Err(error),
+        };
+
+        tracing::debug!(?magic_bytes, "multistream decompressor analysis");
+
+        let format = detect_format(&magic_bytes).unwrap_or(CompressionFormat::Raw);
+
+        Self::new_impl(stream, format)
+    }
+
+    /// Open a compressed file with the given format.
+    ///
+    /// Only Raw, Zlib, Gzip, and Zstd are supported.
+    pub fn new_format(stream: S, format: CompressionFormat) -> std::io::Result<Self> {
+        Self::new_impl(ComboReader::new(stream), format)
+    }
+
+    fn new_impl(stream: ComboReader<S>, format: CompressionFormat) -> std::io::Result<Self> {
+        let decoder = match format {
+            CompressionFormat::Raw => MemberDecoder::Raw(stream),
+            CompressionFormat::DeflateZlib | CompressionFormat::Gzip | CompressionFormat::Zstd => {
+                MemberDecoder::Idle(stream)
+            }
+            CompressionFormat::DeflateRaw | CompressionFormat::Brotli => {
+                return Err(ErrorKind::Unsupported.into())
+            }
+        };
+
+        tracing::debug!(?format, "multistream decoder select");
+
+        Ok(Self {
+            decoder: Some(decoder),
+            format,
+            buf: Vec::new(),
+            buf_position: 0,
+            member_offset: 0,
+            member_read_count: 0,
+            zstd_dictionary: None,
+        })
+    }
+
+    /// Returns the detected or given compression format.
+    pub fn format(&self) -> CompressionFormat {
+        self.format
+    }
+
+    /// Returns a reference to the wrapped stream.
+    pub fn get_ref(&self) -> &S {
+        self.decoder.as_ref().unwrap().get_ref().get_ref()
+    }
+
+    /// Returns a mutable reference to the wrapped stream.
+    pub fn get_mut(&mut self) -> &mut S {
+        self.decoder.as_mut().unwrap().get_mut().get_mut()
+    }
+
+    /// Returns the wrapped stream.
+    pub fn into_inner(self) -> S {
+        self.decoder.unwrap().into_inner().into_inner()
+    }
+
+    /// Returns the position in the source stream where the current member begins.
+    pub fn member_offset(&self) -> u64 {
+        self.member_offset
+    }
+
+    /// Returns the number of uncompressed bytes consumed in the current member.
+    pub fn member_read_count(&self) -> u64 {
+        self.member_read_count
+    }
+
+    /// Returns the Zstandard dictionary used to decode frames.
+    pub fn zstd_dictionary(&self) -> Option<&[u8]> {
+        self.zstd_dictionary.as_deref()
+    }
+
+    /// Sets the Zstandard dictionary used to decode frames.
+    ///
+    /// Dictionary frames in the stream are loaded automatically and replace
+    /// this value. This function is intended for when the stream is not
+    /// read from the start of the file.
+    pub fn set_zstd_dictionary(&mut self, dictionary: Option<Vec<u8>>) {
+        self.zstd_dictionary = dictionary;
+    }
+
+    /// Returns the position in the source stream that corresponds to
+    /// the current uncompressed position.
+    ///
+    /// If the current member has been completely consumed, the decompressor
+    /// is advanced to the next member.
+    ///
+    /// For compressed files, the position is only known when it lies on a
+    /// member boundary. Otherwise, `None` is returned.
+    pub fn source_position(&mut self) -> std::io::Result<Option<u64>> {
+        self.fill_buf()?;
+
+        if self.format == CompressionFormat::Raw {
+            let remaining = self.buf.len() - self.buf_position;
+            Ok(Some(self.source_read_count() - remaining as u64))
+        } else if self.member_read_count == 0 {
+            Ok(Some(self.member_offset))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Discards the rest of the current member and advances to the next
+    /// position in the source stream that appears to be the start of a
+    /// member.
+    ///
+    /// This is intended for recovering from corrupt data. If no data of
+    /// the current member has been consumed from the source stream, at
+    /// least one byte is skipped.
+    ///
+    /// Returns `false` when the end of the stream is reached or the stream
+    /// is uncompressed.
+    pub fn skip_to_next_member(&mut self) -> std::io::Result<bool> {
+        if let Some(MemberDecoder::Raw(_)) = &self.decoder {
+            return Ok(false);
+        }
+
+        let mut stream = self.decoder.take().unwrap().into_inner();
+        self.buf.clear();
+        self.buf_position = 0;
+
+        let result = scan_member_start(&mut stream, self.format, self.member_offset);
+        self.decoder = Some(MemberDecoder::Idle(stream));
+
+        tracing::debug!(
+            member_offset = self.member_offset,
+            source_position = self.decoder.as_ref().unwrap().get_ref().read_count(),
+            "skip_to_next_member"
+        );
+
+        if result? {
+            self.next_member()
+        } else {
+            Ok(false)
+        }
+    }
+
+    fn fill_buf_impl(&mut self) -> std::io::Result<()> {
+        self.buf.resize(8192, 0);
+        self.buf_position = 0;
+
+        loop {
+            match self.decoder.as_mut().unwrap().read(&mut self.buf) {
+                Ok(0) => {}
+                Ok(amount) => {
+                    self.buf.truncate(amount);
+                    return Ok(());
+                }
+                Err(error) => {
+                    self.buf.clear();
+                    return Err(error);
+                }
+            }
+
+            if !self.next_member()? {
+                self.buf.clear();
+                return Ok(());
+            }
+        }
+    }
+
+    fn next_member(&mut self) -> std::io::Result<bool> {
+        if let Some(MemberDecoder::Raw(_)) = &self.decoder {
+            return Ok(false);
+        }
+
+        let mut stream = self.decoder.take().unwrap().into_inner();
+
+        if self.format == CompressionFormat::Zstd {
+            match read_zstd_dictionary_frame(&mut stream) {
+                Ok(Some(dictionary)) => self.zstd_dictionary = Some(dictionary),
+                Ok(None) => {}
+                Err(error) => {
+                    self.decoder = Some(MemberDecoder::Idle(stream));
+                    return Err(error);
+                }
+            }
+        }
+
+        self.member_offset = stream.read_count();
+        self.member_read_count = 0;
+
+        let has_more = match stream.fill_buf() {
+            Ok(buf) => !buf.is_empty(),
+            Err(error) => {
+                self.decoder = Some(MemberDecoder::Idle(stream));
+                return Err(error);
+            }
+        };
+
+        if !has_more {
+            self.decoder = Some(MemberDecoder::Idle(stream));
+            return Ok(false);
+        }
+
+        tracing::trace!(member_offset = self.member_offset, "next member");
+
+        self.decoder = Some(match self.format {
+            CompressionFormat::DeflateZlib => MemberDecoder::DeflateZlib(ZlibDecoder::new(stream)),
+            CompressionFormat::Gzip => MemberDecoder::Gzip(GzDecoder::new(stream)),
+            CompressionFormat::Zstd => match &self.zstd_dictionary {
+                Some(dictionary) => MemberDecoder::Zstd(
+                    ZstdDecoder::with_dictionary(stream, dictionary)?.single_frame(),
+                ),
+                None => MemberDecoder::Zstd(ZstdDecoder::with_buffer(stream)?.single_frame()),
+            },
+            _ => unreachable!(),
+        });
+
+        Ok(true)
+    }
+}
+
+impl<'a, S: Read> Read for MultiStreamDecompressor<'a, S> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let data = self.fill_buf()?;
+        let amount = data.len().min(buf.len());
+
+        buf[0..amount].copy_from_slice(&data[0..amount]);
+        self.consume(amount);
+
+        Ok(amount)
+    }
+}
+
+impl<'a, S: Read> BufRead for MultiStreamDecompressor<'a, S> {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        if self.buf_position >= self.buf.len() {
+            self.fill_buf_impl()?;
+        }
+
+        Ok(&self.buf[self.buf_position..])
+    }
+
+    fn consume(&mut self, amount: usize) {
+        let amount = amount.min(self.buf.len() - self.buf_position);
+
+        self.buf_position += amount;
+        self.member_read_count += amount as u64;
+    }
+}
+
+impl<'a, S: Read> SourceCountRead for MultiStreamDecompressor<'a, S> {
+    fn source_read_count(&self) -> u64 {
+        self.decoder.as_ref().unwrap().get_ref().read_count()
+    }
+}
+
+enum AsyncMemberDecoder<S: AsyncBufRead + Unpin> {
+    Idle(AsyncCountReader<S>),
+    Raw(AsyncCountReader<S>),
+    Gzip(AsyncGzipDecoder<AsyncCountReader<S>>),
+    Zstd(AsyncZstdMemberDecoder<AsyncCountReader<S>>),
+}
+
+impl<S: AsyncBufRead + Unpin> AsyncMemberDecoder<S> {
+    fn get_ref(&self) -> &AsyncCountReader<S> {
+        match self {
+            AsyncMemberDecoder::Idle(stream) => stream,
+            AsyncMemberDecoder::Raw(stream) => stream,
+            AsyncMemberDecoder::Gzip(stream) => stream.get_ref(),
+            AsyncMemberDecoder::Zstd(stream) => stream.get_ref(),
+        }
+    }
+
+    fn get_mut(&mut self) -> &mut AsyncCountReader<S> {
+        match self {
+            AsyncMemberDecoder::Idle(stream) => stream,
+            AsyncMemberDecoder::Raw(stream) => stream,
+            AsyncMemberDecoder::Gzip(stream) => stream.get_mut(),
+            AsyncMemberDecoder::Zstd(stream) => stream.get_mut(),
+        }
+    }
+
+    fn into_inner(self) -> AsyncCountReader<S> {
+        match self {
+            AsyncMemberDecoder::Idle(stream) => stream,
+            AsyncMemberDecoder::Raw(stream) => stream,
+            AsyncMemberDecoder::Gzip(stream) => stream.into_inner(),
+            AsyncMemberDecoder::Zstd(stream) => stream.into_inner(),
+        }
+    }
+
+    fn poll_read(
+        &mut self,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        match self {
+            AsyncMemberDecoder::Idle(_) => Poll::Ready(Ok(())),
+            AsyncMemberDecoder::Raw(stream) => Pin::new(stream).poll_read(cx, buf),
+            AsyncMemberDecoder::Gzip(stream) => Pin::new(stream).poll_read(cx, buf),
+            AsyncMemberDecoder::Zstd(stream) => Pin::new(stream).poll_read(cx, buf),
+        }
+    }
+}
+
+/// Async version of [MultiStreamDecompressor].
+///
+/// Only Raw, Gzip, and Zstd are supported.
+pub struct AsyncMultiStreamDecompressor<S: AsyncBufRead + Unpin> {
+    decoder: Option<AsyncMemberDecoder<S>>,
+    format: CompressionFormat,
+    buf: Vec<u8>,
+    buf_position: usize,
+    buf_end: usize,
+    member_offset: u64,
+    member_read_count: u64,
+    zstd_dictionary: Option<Vec<u8>>,
+}
+
+impl<S: AsyncBufRead + Unpin> AsyncMultiStreamDecompressor<S> {
+    /// Open a compressed file or contents unchanged for unsupported or undetectable formats.
+    pub async fn new(stream: S) -> std::io::Result<Self> {
+        let mut stream = AsyncCountReader::new(stream);
+        let magic_bytes = stream.fill_buf().await?;
+        let magic_bytes = &magic_bytes[0..magic_bytes.len().min(4)];
+
+        tracing::debug!(?magic_bytes, "async multistream decompressor analysis");
+
+        let format = match detect_format(magic_bytes) {
+            Some(CompressionFormat::Gzip) => CompressionFormat::Gzip,
+            Some(CompressionFormat::Zstd) => CompressionFormat::Zstd,
+            _ => CompressionFormat::Raw,
+        };
+
+        Self::new_impl(stream, format)
+    }
+
+    /// Open a compressed file with the given format.
+    ///
+    /// Only Raw, Gzip, and Zstd are supported.
+    pub fn new_format(stream: S, format: CompressionFormat) -> std::io::Result<Self> {
+        Self::new_impl(AsyncCountReader::new(stream), format)
+    }
+
+    fn new_impl(stream: AsyncCountReader<S>, format: CompressionFormat) -> std::io::Result<Self> {
+        let decoder = match format {
+            CompressionFormat::Raw => AsyncMemberDecoder::Raw(stream),
+            CompressionFormat::Gzip | CompressionFormat::Zstd => AsyncMemberDecoder::Idle(stream),
+            CompressionFormat::DeflateRaw
+            | CompressionFormat::DeflateZlib
+            | CompressionFormat::Brotli => return Err(ErrorKind::Unsupported.into()),
+        };
+
+        tracing::debug!(?format, "async multistream decoder select");
+
+        Ok(Self {
+            decoder: Some(decoder),
+            format,
+            buf: vec![0; 8192],
+            buf_position: 0,
+            buf_end: 0,
+            member_offset: 0,
+            member_read_count: 0,
+            zstd_dictionary: None,
+        })
+    }
+
+    /// Returns the detected or given compression format.
+    pub fn format(&self) -> CompressionFormat {
+        self.format
+    }
+
+    /// Returns a reference to the wrapped stream.
+    pub fn get_ref(&self) -> &S {
+        self.decoder.as_ref().unwrap().get_ref().get_ref()
+    }
+
+    /// Returns a mutable reference to the wrapped stream.
+    pub fn get_mut(&mut self) -> &mut S {
+        self.decoder.as_mut().unwrap().get_mut().get_mut()
+    }
+
+    /// Returns the wrapped stream.
+    pub fn into_inner(self) -> S {
+        self.decoder.unwrap().into_inner().into_inner()
+    }
+
+    /// Returns the position in the source stream where the current member begins.
+    pub fn member_offset(&self) -> u64 {
+        self.member_offset
+    }
+
+    /// Returns the number of uncompressed bytes consumed in the current member.
+    pub fn member_read_count(&self) -> u64 {
+        self.member_read_count
+    }
+
+    /// Returns the Zstandard dictionary used to decode frames.
+    ///
+    /// See [MultiStreamDecompressor::zstd_dictionary] for details.
+    pub fn zstd_dictionary(&self) -> Option<&[u8]> {
+        self.zstd_dictionary.as_deref()
+    }
+
+    /// Returns the position in the source stream that corresponds to
+    /// the current uncompressed position.
+    ///
+    /// See [MultiStreamDecompressor::source_position] for details.
+    pub async fn source_position(&mut self) -> std::io::Result<Option<u64>> {
+        self.fill_buf().await?;
+
+        if self.format == CompressionFormat::Raw {
+            let remaining = self.buf_end - self.buf_position;
+            Ok(Some(self.source_read_count() - remaining as u64))
+        } else if self.member_read_count == 0 {
+            Ok(Some(self.member_offset))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn poll_fill_buf_impl(&mut self, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        loop {
+            let decoder = self.decoder.as_mut().unwrap();
+
+            if let AsyncMemberDecoder::Idle(stream) = decoder {
+                let has_more = match Pin::new(stream).poll_fill_buf(cx) {
+                    Poll::Ready(Ok(buf)) => !buf.is_empty(),
+                    Poll::Ready(Err(error)) => return Poll::Ready(Err(error)),
+                    Poll::Pending => return Poll::Pending,
+                };
+
+                if !has_more {
+                    return Poll::Ready(Ok(()));
+                }
+
+                self.next_member()?;
+                continue;
+            }
+
+            let mut read_buf = ReadBuf::new(&mut self.buf);
+
+            match decoder.poll_read(cx, &mut read_buf) {
+                Poll::Ready(Ok(())) => {}
+                Poll::Ready(Err(error)) => return Poll::Ready(Err(error)),
+                Poll::Pending => return Poll::Pending,
+            }
+
+            let amount = read_buf.filled().len();
+
+            if amount > 0 {
+                self.buf_position = 0;
+                self.buf_end = amount;
+                return Poll::Ready(Ok(()));
+            }
+
+            if let AsyncMemberDecoder::Raw(_) = decoder {
+                return Poll::Ready(Ok(()));
+            }
+
+            if let AsyncMemberDecoder::Zstd(decoder) = decoder {
+                if let Some(dictionary) = decoder.take_dictionary() {
+                    self.zstd_dictionary = Some(dictionary);
+                }
+            }
+
+            let stream = self.decoder.take().unwrap().into_inner();
+            self.member_offset = stream.read_count();
+            self.member_read_count = 0;
+            self.decoder = Some(AsyncMemberDecoder::Idle(stream));
+        }
+    }
+
+    fn next_member(&mut self) -> std::io::Result<()> {
+        let zstd_decoder = match self.format {
+            CompressionFormat::Zstd => Some(ZstdRawDecoder::with_dictionary(
+                self.zstd_dictionary.as_deref().unwrap_or_default(),
+            )?),
+            _ => None,
+        };
+        let stream = self.decoder.take().unwrap().into_inner();
+
+        tracing::trace!(member_offset = self.member_offset, "async next member");
+
+        self.decoder = Some(match self.format {
+            CompressionFormat::Gzip => AsyncMemberDecoder::Gzip(AsyncGzipDecoder::new(stream)),
+            CompressionFormat::Zstd => {
+                AsyncMemberDecoder::Zstd(AsyncZstdMemberDecoder::new(stream, zstd_decoder.unwrap()))
+            }
+            _ => unreachable!(),
+        });
+
+        Ok(())
+    }
+}
+
+// Decodes a single Zstandard frame.
+//
+// The member may instead be a dictionary frame as read by
+// `read_zstd_dictionary_frame`. It produces no data, and the dictionary is
+// available from `take_dictionary` afterwards.
+struct AsyncZstdMemberDecoder<S: AsyncBufRead + Unpin> {
+    stream: S,
+    decoder: ZstdRawDecoder<'static>,
+    state: AsyncZstdMemberState,
+    dictionary: Option<Vec<u8>>,
+}
+
+enum AsyncZstdMemberState {
+    // Bytes read that may be the magic number of a dictionary frame.
+    Magic(Vec<u8>),
+    // Bytes read of a dictionary frame including the header.
+    DictionaryFrame(Vec<u8>),
+    // Bytes read that have not been given to the decoder yet.
+    Frame(Vec<u8>),
+    Done,
+}
+
+impl<S: AsyncBufRead + Unpin> AsyncZstdMemberDecoder<S> {
+    fn new(stream: S, decoder: ZstdRawDecoder<'static>) -> Self {
+        Self {
+            stream,
+            decoder,
+            state: AsyncZstdMemberState::Magic(Vec::new()),
+            dictionary: None,
+        }
+    }
+
+    fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
+    fn into_inner(self) -> S {
+        self.stream
+    }
+
+    fn take_dictionary(&mut self) -> Option<Vec<u8>> {
+        self.dictionary.take()
+    }
+}
+
+// Returns the length of a dictionary frame including the header, or the
+// length of the header if it has not been read completely.
+fn zstd_dictionary_frame_length(data: &[u8]) -> usize {
+    match data.get(4..8) {
+        Some(header) => 8 + u32::from_le_bytes(header.try_into().unwrap()) as usize,
+        None => 8,
+    }
+}
+
+// Decodes the input into the buffer.
+//
+// Returns the number of bytes consumed from the input and whether the end of
+// the frame was reached.
+fn decode_zstd(
+    decoder: &mut ZstdRawDecoder<'static>,
+    input: &[u8],
+    buf: &mut ReadBuf<'_>,
+) -> std::io::Result<(usize, bool)> {
+    let mut in_buffer = ZstdInBuffer::around(input);
+    let mut out_buffer = ZstdOutBuffer::around(buf.initialize_unfilled());
+    let remaining = decoder.run(&mut in_buffer, &mut out_buffer)?;
+    let amount = out_buffer.pos();
+
+    buf.advance(amount);
+
+    Ok((in_buffer.pos(), remaining == 0))
+}
+
+impl<S: AsyncBufRead + Unpin> AsyncRead for AsyncZstdMemberDecoder<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        let filled = buf.filled().len();
+
+        loop {
+            if matches!(this.state, AsyncZstdMemberState::Done)
+                || buf.filled().len() > filled
+                || buf.remaining() == 0
+            {
+                return Poll::Ready(Ok(()));
+            }
+
+            if let AsyncZstdMemberState::Frame(pending) = &mut this.state {
+                if !pending.is_empty() {
+                    let (amount, is_end) = decode_zstd(&mut this.decoder, pending, buf)?;
+                    pending.drain(0..amount);
+
+                    if is_end {
+                        this.state = AsyncZstdMemberState::Done;
+                    }
+
+                    continue;
+                }
+            }
+
+            let input = match Pin::new(&mut this.stream).poll_fill_buf(cx) {
+                Poll::Ready(Ok(input)) => input,
+                Poll::Ready(Err(error)) => return Poll::Ready(Err(error)),
+                Poll::Pending => return Poll::Pending,
+            };
+
+            if input.is_empty() {
+                return Poll::Ready(Err(ErrorKind::UnexpectedEof.into()));
+            }
+
+            let amount = match &mut this.state {
+                AsyncZstdMemberState::Magic(data) => {
+                    let magic = ZSTD_DICTIONARY_FRAME_MAGIC.to_le_bytes();
+                    let amount = (magic.len() - data.len()).min(input.len());
+                    data.extend_from_slice(&input[0..amount]);
+
+                    if !magic.starts_with(data) {
+                        this.state = AsyncZstdMemberState::Frame(std::mem::take(data));
+                    } else if data.len() == magic.len() {
+                        this.state = AsyncZstdMemberState::DictionaryFrame(std::mem::take(data));
+                    }
+
+                    amount
+                }
+                AsyncZstdMemberState::DictionaryFrame(data) => {
+                    let amount = (zstd_dictionary_frame_length(data) - data.len()).min(input.len());
+                    data.extend_from_slice(&input[0..amount]);
+
+                    if data.len() == zstd_dictionary_frame_length(data) {
+                        let data = std::mem::take(data).split_off(8);
+
+                        tracing::debug!(length = data.len(), "async read zstd dictionary frame");
+
+                        this.dictionary = Some(decode_zstd_dictionary_frame_data(data)?);
+                        this.state = AsyncZstdMemberState::Done;
+                    }
+
+                    amount
+                }
+                AsyncZstdMemberState::Frame(_) => {
+                    let (amount, is_end) = decode_zstd(&mut this.decoder, input, buf)?;
+
+                    if is_end {
+                        this.state = AsyncZstdMemberState::Done;
+                    }
+
+                    amount
+                }
+                AsyncZstdMemberState::Done => unreachable!(),
+            };
+
+            Pin::new(&mut this.stream).consume(amount);
+        }
+    }
+}
+
+impl<S: AsyncBufRead + Unpin> AsyncRead for AsyncMultiStreamDecompressor<S> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let data = match self.as_mut().poll_fill_buf(cx) {
+            Poll::Ready(Ok(data)) => data,
+            Poll::Ready(Err(error)) => return Poll::Ready(Err(error)),
+            Poll::Pending => return Poll::Pending,
+        };
+        let amount = data.len().min(buf.remaining());
+
+        buf.put_slice(&data[0..amount]);
+        self.consume(amount);
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<S: AsyncBufRead + Unpin> AsyncBufRead for AsyncMultiStreamDecompressor<S> {
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<&[u8]>> {
+        let this = self.get_mut();
+
+        if this.buf_position >= this.buf_end {
+            match this.poll_fill_buf_impl(cx) {
+                Poll::Ready(Ok(())) => {}
+                Poll::Ready(Err(error)) => return Poll::Ready(Err(error)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+
+        Poll::Ready(Ok(&this.buf[this.buf_position..this.buf_end]))
+    }
+
+    fn consume(self: Pin<&mut Self>, amount: usize) {
+        let this = self.get_mut();
+        let amount = amount.min(this.buf_end - this.buf_position);
+
+        this.buf_position += amount;
+        this.member_read_count += amount as u64;
+    }
+}
+
+impl<S: AsyncBufRead + Unpin> SourceCountRead for AsyncMultiStreamDecompressor<S> {
+    fn source_read_count(&self) -> u64 {
+        self.decoder.as_ref().unwrap().get_ref().read_count()
+    }
+}
+
 enum Encoder<'a, S: Write> {
     Raw(S),
     DeflateRaw(DeflateEncoder<S>),
@@ -312,6 +1247,20 @@ impl<'a, S: Write> Compressor<'a, S> {
         Ok(Self { encoder })
     }
 
+    /// Create a Zstandard compressor with the given stream, level, and
+    /// dictionary.
+    pub fn new_zstd_with_dictionary(
+        stream: S,
+        level: CompressionLevel,
+        dictionary: &[u8],
+    ) -> std::io::Result<Self> {
+        let level = level.get_int_for_format(CompressionFormat::Zstd);
+
+        Ok(Self {
+            encoder: Encoder::Zstd(ZstdEncoder::with_dictionary(stream, level, dictionary)?),
+        })
+    }
+
     /// Returns a reference to the wrapped stream.
     pub fn get_ref(&self) -> &S {
         match &self.encoder {
@@ -375,3 +1324,126 @@ impl<'a, S: Write> Write for Compressor<'a, S> {
         }
     }
 }
+
+// Prevents the encoders from shutting down the wrapped stream when finishing.
+struct NoShutdown<S: AsyncWrite + Unpin>(S);
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for NoShutdown<S> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.0).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.0).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.0).poll_flush(cx)
+    }
+}
+
+enum AsyncEncoder<S: AsyncWrite + Unpin> {
+    Raw(S),
+    Gzip(AsyncGzipEncoder<NoShutdown<S>>),
+    Zstd(AsyncZstdEncoder<NoShutdown<S>>),
+}
+
+/// Async compression of Gzip and Zstd files.
+pub struct AsyncCompressor<S: AsyncWrite + Unpin> {
+    encoder: AsyncEncoder<S>,
+}
+
+impl<S: AsyncWrite + Unpin> AsyncCompressor<S> {
+    /// Create a compressor with the given stream and codec options.
+    ///
+    /// Only Raw, Gzip, and Zstd are supported.
+    pub fn new(
+        stream: S,
+        format: CompressionFormat,
+        level: CompressionLevel,
+    ) -> std::io::Result<Self> {
+        let quality = AsyncLevel::Precise(level.get_int_for_format(format) as u32);
+        let encoder = match format {
+            CompressionFormat::Raw => AsyncEncoder::Raw(stream),
+            CompressionFormat::Gzip => {
+                AsyncEncoder::Gzip(AsyncGzipEncoder::with_quality(NoShutdown(stream), quality))
+            }
+            CompressionFormat::Zstd => {
+                AsyncEncoder::Zstd(AsyncZstdEncoder::with_quality(NoShutdown(stream), quality))
+            }
+            CompressionFormat::DeflateRaw
+            | CompressionFormat::DeflateZlib
+            | CompressionFormat::Brotli => return Err(ErrorKind::Unsupported.into()),
+        };
+        Ok(Self { encoder })
+    }
+
+    /// Returns a reference to the wrapped stream.
+    pub fn get_ref(&self) -> &S {
+        match &self.encoder {
+            AsyncEncoder::Raw(stream) => stream,
+            AsyncEncoder::Gzip(stream) => &stream.get_ref().0,
+            AsyncEncoder::Zstd(stream) => &stream.get_ref().0,
+        }
+    }
+
+    /// Returns a mutable reference to the wrapped stream.
+    pub fn get_mut(&mut self) -> &mut S {
+        match &mut self.encoder {
+            AsyncEncoder::Raw(stream) => stream,
+            AsyncEncoder::Gzip(stream) => &mut stream.get_mut().0,
+            AsyncEncoder::Zstd(stream) => &mut stream.get_mut().0,
+        }
+    }
+
+    /// Completes a compression file and returns the wrapped stream.
+    ///
+    /// The wrapped stream is flushed but not shut down.
+    pub async fn finish(self) -> std::io::Result<S> {
+        match self.encoder {
+            AsyncEncoder::Raw(stream) => Ok(stream),
+            AsyncEncoder::Gzip(mut stream) => {
+                stream.shutdown().await?;
+                Ok(stream.into_inner().0)
+            }
+            AsyncEncoder::Zstd(mut stream) => {
+                stream.shutdown().await?;
+                Ok(stream.into_inner().0)
+            }
+        }
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for AsyncCompressor<S> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        match &mut self.encoder {
+            AsyncEncoder::Raw(stream) => Pin::new(stream).poll_write(cx, buf),
+            AsyncEncoder::Gzip(stream) => Pin::new(stream).poll_write(cx, buf),
+            AsyncEncoder::Zstd(stream) => Pin::new(stream).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        match &mut self.encoder {
+            AsyncEncoder::Raw(stream) => Pin::new(stream).poll_flush(cx),
+            AsyncEncoder::Gzip(stream) => Pin::new(stream).poll_flush(cx),
+            AsyncEncoder::Zstd(stream) => Pin::new(stream).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        match &mut self.encoder {
+            AsyncEncoder::Raw(stream) => Pin::new(stream).poll_shutdown(cx),
+            AsyncEncoder::Gzip(stream) => Pin::new(stream).poll_shutdown(cx),
+            AsyncEncoder::Zstd(stream) => Pin::new(stream).poll_shutdown(cx),
+        }
+    }
+}
@@ -30,7 +30,9 @@ pub fn normalize_hash_name<S: Into<String>>(name: S) -> String {
 }
 
 /// Returns a hash function from the given name.
-pub fn get_hash_function_by_name<S: Into<String>>(name: S) -> Option<Box<dyn digest::DynDigest>> {
+pub fn get_hash_function_by_name<S: Into<String>>(
+    name: S,
+) -> Option<Box<dyn digest::DynDigest + Send>> {
     let name = normalize_hash_name(name);
 
     match name.as_str() {
@@ -132,6 +132,27 @@ pub fn remove_path_conflict<P: AsRef<Path>>(path: P) -> PathBuf {
     new_path
 }
 
+/// Moves a file to the given path, numbering the path if it conflicts with
+/// existing files, and creates any missing parent directories.
+///
+/// Returns the path the file was moved to. Callers moving files concurrently
+/// to the same directory tree must serialize calls to this function.
+pub fn move_without_conflict<S, D>(source: S, destination: D) -> std::io::Result<PathBuf>
+where
+    S: AsRef<Path>,
+    D: AsRef<Path>,
+{
+    let path = remove_path_conflict(destination);
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    std::fs::rename(source, &path)?;
+
+    Ok(path)
+}
+
 #[cfg(test)]
 mod tests {
     use tempdir::TempDir;
@@ -214,4 +235,22 @@ mod tests {
         test_remove_path_conflict_impl("a.txt", "a.txt_1", &[], &["a.txt"]);
         test_remove_path_conflict_impl("a.txt/b.txt", "a.txt_1/b.txt", &[], &["a.txt"]);
     }
+
+    #[test]
+    fn test_move_without_conflict() {
+        let temp_dir = TempDir::new("webaves-test-").unwrap();
+        let source = temp_dir.path().join("source");
+
+        std::fs::write(&source, b"a").unwrap();
+        let path = move_without_conflict(&source, temp_dir.path().join("a")).unwrap();
+        assert_eq!(path, temp_dir.path().join("a"));
+
+        std::fs::write(&source, b"b").unwrap();
+        let path = move_without_conflict(&source, temp_dir.path().join("a/b")).unwrap();
+        assert_eq!(path, temp_dir.path().join("a_1/b"));
+
+        assert!(!source.exists());
+        assert_eq!(std::fs::read(temp_dir.path().join("a")).unwrap(), b"a");
+        assert_eq!(std::fs::read(temp_dir.path().join("a_1/b")).unwrap(), b"b");
+    }
 }
@@ -0,0 +1,322 @@
+use std::io::Write;
+
+use chrono::{DateTime, Duration, Utc};
+use url::Url;
+
+use crate::{
+    header::HeaderMap,
+    http::{MessageWriter, RequestHeader, ResponseHeader, Version},
+    warc::{
+        format_date, HeaderMapExt, MetadataRecordBuilder, RequestRecordBuilder,
+        ResponseRecordBuilder, WARCError, WARCRecord, WARCWriter, WarcinfoRecordBuilder,
+    },
+};
+
+use super::{HAREntry, HARError, HARLog, HARNameValue, HARPostParam, HAR};
+
+// Fields describing the original encoding of the body which no longer
+// applies because HAR stores decoded bodies.
+const BODY_ENCODING_FIELDS: [&str; 3] = ["Content-Encoding", "Transfer-Encoding", "Content-Length"];
+
+/// Converts HAR entries to WARC records.
+///
+/// Each HAR file is converted to a `warcinfo` record describing the new
+/// file and a `metadata` record describing the original HAR file and
+/// the browser that created it. Each entry is converted to a `request`
+/// and a `response` record containing HTTP/1.1 messages reconstructed from
+/// the headers and content.
+///
+/// Because HAR stores decoded bodies, the content coding and transfer
+/// coding fields are removed and the `Content-Length` field is replaced.
+/// Entries without a response, such as blocked requests, are skipped.
+#[derive(Debug, Clone, Default)]
+pub struct HARConverter {
+    warcinfo_id: Option<String>,
+}
+
+impl HARConverter {
+    /// Creates a `HARConverter`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the record ID of the last `warcinfo` record created.
+    pub fn warcinfo_id(&self) -> Option<&str> {
+        self.warcinfo_id.as_deref()
+    }
+
+    /// Returns the `warcinfo` and `metadata` records for the log.
+    ///
+    /// The given filenames are the name of the HAR file and the name of the
+    /// new WARC file, if any.
+    pub fn convert_log(
+        &mut self,
+        log: &HARLog,
+        har_filename: Option<&str>,
+        warc_filename: Option<&str>,
+    ) -> Result<(WARCRecord, WARCRecord), WARCError> {
+        let mut info_fields = HeaderMap::new();
+        info_fields.insert("software", format!("webaves/{}", env!("CARGO_PKG_VERSION")));
+        info_fields.insert("format", "WARC File Format 1.1");
+        info_fields.insert(
+            "conformsTo",
+            "http://iipc.github.io/warc-specifications/specifications/warc-format/warc-1.1/",
+        );
+        info_fields.insert(
+            "description",
+            format!("Converted from HAR file {}", har_filename.unwrap_or("-")),
+        );
+
+        let mut builder = WarcinfoRecordBuilder::new().info_fields(&info_fields)?;
+
+        if let Some(filename) = warc_filename {
+            builder = builder.filename(filename);
+        }
+
+        let warcinfo = builder.build()?;
+        let warcinfo_id = warcinfo.header.get_required("WARC-Record-ID")?.to_string();
+
+        let mut provenance_fields = HeaderMap::new();
+
+        if let Some(filename) = har_filename {
+            provenance_fields.insert("har-filename", filename);
+        }
+
+        provenance_fields.insert("har-version", log.version.as_str());
+        provenance_fields.insert(
+            "har-creator",
+            format!("{}/{}", log.creator.name, log.creator.version),
+        );
+
+        if let Some(browser) = &log.browser {
+            provenance_fields.insert(
+                "har-browser",
+                format!("{}/{}", browser.name, browser.version),
+            );
+        }
+
+        provenance_fields.insert("conversion-date", format_date(&Utc::now()));
+
+        let mut builder = MetadataRecordBuilder::new()
+            .warcinfo_id(&warcinfo_id)
+            .metadata_fields(&provenance_fields)?;
+
+        if let Some(filename) = har_filename {
+            builder = builder.target_uri(&format!("filedesc://{}", filename));
+        }
+
+        let provenance = builder.build()?;
+
+        self.warcinfo_id = Some(warcinfo_id);
+
+        Ok((warcinfo, provenance))
+    }
+
+    /// Returns the `request` and `response` records for the entry.
+    ///
+    /// The records do not include digests.
+    pub fn convert_entry(&self, entry: &HAREntry) -> Result<(WARCRecord, WARCRecord), HARError> {
+        let started_date = parse_date(entry)?;
+        let response_date = started_date + Duration::milliseconds(entry.time.max(0.0) as i64);
+
+        let mut builder = ResponseRecordBuilder::new(&entry.request.url)
+            .date(&response_date)
+            .payload(format_response(entry)?);
+
+        if let Some(address) = &entry.server_ip_address {
+            let address = address.trim_start_matches('[').trim_end_matches(']');
+
+            if address.parse::<std::net::IpAddr>().is_ok() {
+                builder = builder.ip_address(address);
+            }
+        }
+        if let Some(warcinfo_id) = &self.warcinfo_id {
+            builder = builder.warcinfo_id(warcinfo_id);
+        }
+
+        let response = builder.build()?;
+        let response_id = response.header.get_required("WARC-Record-ID")?;
+
+        let mut builder = RequestRecordBuilder::new(&entry.request.url)
+            .date(&started_date)
+            .concurrent_to(response_id)
+            .payload(format_request(entry)?);
+
+        if let Some(warcinfo_id) = &self.warcinfo_id {
+            builder = builder.warcinfo_id(warcinfo_id);
+        }
+
+        let request = builder.build()?;
+
+        Ok((request, response))
+    }
+
+    /// Converts all entries and writes the records to the writer.
+    ///
+    /// The writer should have a digest algorithm set so that digests are
+    /// computed for the converted records.
+    ///
+    /// Returns the number of entries converted.
+    pub fn convert<W: Write>(
+        &mut self,
+        har: &HAR,
+        writer: &mut WARCWriter<'_, W>,
+        har_filename: Option<&str>,
+        warc_filename: Option<&str>,
+    ) -> Result<u64, HARError> {
+        let (warcinfo, provenance) = self.convert_log(&har.log, har_filename, warc_filename)?;
+        writer.write_record(&warcinfo)?;
+        writer.write_record(&provenance)?;
+
+        let mut count = 0;
+
+        for entry in &har.log.entries {
+            if entry.response.status == 0 {
+                tracing::debug!(
+                    url = entry.request.url.as_str(),
+                    "skip entry without response"
+                );
+                continue;
+            }
+
+            tracing::debug!(url = entry.request.url.as_str(), "convert entry");
+
+            let (request, response) = self.convert_entry(entry)?;
+            writer.write_record(&request)?;
+            writer.write_record(&response)?;
+
+            count += 1;
+        }
+
+        Ok(count)
+    }
+}
+
+fn parse_date(entry: &HAREntry) -> Result<DateTime<Utc>, HARError> {
+    DateTime::parse_from_rfc3339(&entry.started_date_time)
+        .map(|date| date.with_timezone(&Utc))
+        .map_err(|_| invalid_value("startedDateTime", entry))
+}
+
+fn invalid_value(name: &str, entry: &HAREntry) -> HARError {
+    HARError::InvalidValue {
+        name: name.to_string(),
+        url: entry.request.url.clone(),
+    }
+}
+
+fn format_request(entry: &HAREntry) -> Result<Vec<u8>, HARError> {
+    let url = Url::parse(&entry.request.url).map_err(|_| invalid_value("url", entry))?;
+    let target = match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_string(),
+    };
+    let body = match &entry.request.post_data {
+        Some(post_data) if post_data.text.is_empty() && !post_data.params.is_empty() => {
+            format_params(&post_data.params)
+        }
+        Some(post_data) => decode_text(&post_data.text, post_data.encoding.as_deref())
+            .ok_or_else(|| invalid_value("postData", entry))?,
+        None => Vec::new(),
+    };
+
+    let mut header = RequestHeader::new(entry.request.method.as_str(), target);
+    header.request_line.version = parse_version(&entry.request.http_version);
+    header.fields = convert_fields(&entry.request.headers);
+
+    if !body.is_empty() {
+        header
+            .fields
+            .insert("Content-Length", body.len().to_string());
+    }
+
+    if !header.fields.contains_key("Host") {
+        if let Some(host) = url.host_str() {
+            let host = match url.port() {
+                Some(port) => format!("{}:{}", host, port),
+                None => host.to_string(),
+            };
+            header.fields.insert("Host", host);
+            header.fields.reorder_front("Host");
+        }
+    }
+
+    let mut writer = MessageWriter::new(Vec::new());
+    writer.begin_request(&header)?;
+    writer.write_body().write_all(&body)?;
+    writer.end_message()?;
+
+    Ok(writer.into_inner())
+}
+
+fn format_response(entry: &HAREntry) -> Result<Vec<u8>, HARError> {
+    let content = &entry.response.content;
+    let body = match &content.text {
+        Some(text) => decode_text(text, content.encoding.as_deref())
+            .ok_or_else(|| invalid_value("content", entry))?,
+        None => Vec::new(),
+    };
+
+    let mut header = ResponseHeader::new(entry.response.status);
+    header.status_line.version = parse_version(&entry.response.http_version);
+    header.status_line.reason_phrase = entry.response.status_text.clone();
+    header.fields = convert_fields(&entry.response.headers);
+    header
+        .fields
+        .insert("Content-Length", body.len().to_string());
+
+    let mut writer = MessageWriter::new(Vec::new());
+    writer.begin_response(&header)?;
+    writer.write_body().write_all(&body)?;
+    writer.end_message()?;
+
+    Ok(writer.into_inner())
+}
+
+// HTTP/2 and HTTP/3 messages are written as HTTP/1.1.
+fn parse_version(value: &str) -> Version {
+    if value.eq_ignore_ascii_case("HTTP/1.0") {
+        (1, 0)
+    } else {
+        (1, 1)
+    }
+}
+
+fn convert_fields(headers: &[HARNameValue]) -> HeaderMap {
+    let mut fields = HeaderMap::new();
+
+    for header in headers {
+        // Skip HTTP/2 pseudo-header fields such as ":authority".
+        if header.name.starts_with(':') {
+            continue;
+        }
+
+        fields.append(header.name.as_str(), header.value.as_str());
+    }
+
+    for name in BODY_ENCODING_FIELDS {
+        fields.remove(name);
+    }
+
+    fields
+}
+
+// HAR files may contain the parameters instead of the text of a form body.
+// The body is rebuilt as `application/x-www-form-urlencoded`; the contents of
+// uploaded files are not available.
+fn format_params(params: &[HARPostParam]) -> Vec<u8> {
+    let mut serializer = url::form_urlencoded::Serializer::new(String::new());
+
+    for param in params {
+        serializer.append_pair(&param.name, param.value.as_deref().unwrap_or_default());
+    }
+
+    serializer.finish().into_bytes()
+}
+
+fn decode_text(text: &str, encoding: Option<&str>) -> Option<Vec<u8>> {
+    match encoding {
+        Some("base64") => data_encoding::BASE64.decode(text.as_bytes()).ok(),
+        _ => Some(text.as_bytes().to_vec()),
+    }
+}
@@ -0,0 +1,440 @@
+use std::{collections::HashMap, io::Read};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use url::Url;
+
+use crate::{
+    header::HeaderMap,
+    http::{HTTPError, MessageReader, RequestHeader, ResponseHeader, Version},
+    io::ComboReader,
+    warc::{
+        index::{get_media_type, trim_angle_brackets},
+        HeaderMapExt, HeaderMetadata, WARCReader,
+    },
+};
+
+use super::{
+    HARContent, HARCookie, HARCreator, HAREntry, HARError, HARLog, HARNameValue, HARPostData,
+    HARRequest, HARResponse, HARTimings, HAR, HAR_VERSION,
+};
+
+const TEXT_SUBTYPES: [&str; 6] = [
+    "json",
+    "xml",
+    "javascript",
+    "x-javascript",
+    "ecmascript",
+    "x-www-form-urlencoded",
+];
+
+/// Converts WARC request and response records to HAR entries.
+///
+/// Requests and responses are paired using the `WARC-Concurrent-To` field
+/// which may be on either record. A response without a request is given a
+/// `GET` request for the target URI. Requests without a response are
+/// dropped.
+///
+/// Timings are estimated from the `WARC-Date` of the records. Bodies that
+/// are not text are encoded with Base64.
+#[derive(Debug, Default)]
+pub struct HARExporter {
+    pending: HashMap<String, PendingRecord>,
+    links: HashMap<String, String>,
+    entries: Vec<HAREntry>,
+}
+
+impl HARExporter {
+    /// Creates an empty `HARExporter`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of entries created so far.
+    pub fn entry_count(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Reads all the records from the reader and adds them.
+    pub fn add_reader<R: Read>(&mut self, reader: &mut WARCReader<'_, R>) -> Result<(), HARError> {
+        while let Some(metadata) = reader.begin_record()? {
+            let mut block = reader.read_block();
+            self.add_record(&metadata, &mut block)?;
+            std::io::copy(&mut block, &mut std::io::sink())?;
+            reader.end_record()?;
+        }
+
+        Ok(())
+    }
+
+    /// Adds a record with the given block.
+    ///
+    /// Records that are not HTTP requests or responses are ignored. HTTP
+    /// messages that cannot be parsed are logged and ignored.
+    pub fn add_record<R: Read>(
+        &mut self,
+        metadata: &HeaderMetadata,
+        block: R,
+    ) -> Result<(), HARError> {
+        let fields = metadata.fields();
+        let warc_type = fields.get_str("WARC-Type").unwrap_or_default();
+
+        if !is_http_message(fields) {
+            return Ok(());
+        }
+
+        let record_id = fields.get_str("WARC-Record-ID").unwrap_or_default();
+        let url =
+            trim_angle_brackets(fields.get_str("WARC-Target-URI").unwrap_or_default()).to_string();
+        let date = fields
+            .get_str("WARC-Date")
+            .and_then(|value| DateTime::parse_from_rfc3339(value).ok())
+            .map(|value| value.with_timezone(&Utc))
+            .unwrap_or_else(|| DateTime::from(std::time::UNIX_EPOCH));
+
+        let message = match warc_type {
+            "request" => read_request(block).map(|(header, body)| Message::Request(header, body)),
+            "response" => {
+                read_response(block).map(|(header, body)| Message::Response(header, body))
+            }
+            _ => return Ok(()),
+        };
+        let message = match message {
+            Ok(message) => message,
+            Err(error) => {
+                tracing::warn!(?error, record_id, "har http message");
+                return Ok(());
+            }
+        };
+
+        let record = PendingRecord {
+            url,
+            date,
+            ip_address: fields
+                .get_str("WARC-IP-Address")
+                .map(|value| value.to_string()),
+            message,
+        };
+        let concurrent_ids = fields
+            .get_all("WARC-Concurrent-To")
+            .map(|value| value.text.clone())
+            .collect::<Vec<_>>();
+
+        self.add_pending(record_id.to_string(), concurrent_ids, record);
+
+        Ok(())
+    }
+
+    fn add_pending(
+        &mut self,
+        record_id: String,
+        concurrent_ids: Vec<String>,
+        record: PendingRecord,
+    ) {
+        let is_partner = |id: &String| {
+            self.pending
+                .get(id)
+                .map(|other| other.message.is_request() != record.message.is_request())
+                .unwrap_or(false)
+        };
+        let partner_id = concurrent_ids
+            .iter()
+            .find(|id| is_partner(id))
+            .or_else(|| self.links.get(&record_id).filter(|id| is_partner(id)))
+            .cloned();
+
+        match partner_id {
+            Some(partner_id) => {
+                self.links.remove(&record_id);
+                let partner = self.pending.remove(&partner_id).unwrap();
+
+                let (request, response) = if record.message.is_request() {
+                    (Some(record), partner)
+                } else {
+                    (Some(partner), record)
+                };
+
+                self.entries.push(make_entry(request, response));
+            }
+            None => {
+                for id in concurrent_ids {
+                    self.links.insert(id, record_id.clone());
+                }
+
+                self.pending.insert(record_id, record);
+            }
+        }
+    }
+
+    /// Returns the HAR with the entries sorted by start date.
+    ///
+    /// Responses still waiting for a request are included.
+    pub fn finish(mut self) -> HAR {
+        for (_id, record) in self.pending {
+            if !record.message.is_request() {
+                self.entries.push(make_entry(None, record));
+            }
+        }
+
+        self.entries
+            .sort_by(|a, b| a.started_date_time.cmp(&b.started_date_time));
+
+        HAR {
+            log: HARLog {
+                version: HAR_VERSION.to_string(),
+                creator: HARCreator {
+                    name: "webaves".to_string(),
+                    version: env!("CARGO_PKG_VERSION").to_string(),
+                },
+                browser: None,
+                entries: self.entries,
+            },
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Message {
+    Request(RequestHeader, Vec<u8>),
+    Response(ResponseHeader, Vec<u8>),
+}
+
+impl Message {
+    fn is_request(&self) -> bool {
+        matches!(self, Self::Request(..))
+    }
+}
+
+#[derive(Debug)]
+struct PendingRecord {
+    url: String,
+    date: DateTime<Utc>,
+    ip_address: Option<String>,
+    message: Message,
+}
+
+fn is_http_message(fields: &HeaderMap) -> bool {
+    match fields.get_parsed::<crate::http::field::MediaType>("Content-Type") {
+        Ok(Some(media_type)) => media_type.type_ == "application" && media_type.subtype == "http",
+        _ => false,
+    }
+}
+
+fn read_request<R: Read>(block: R) -> Result<(RequestHeader, Vec<u8>), HTTPError> {
+    let mut reader = MessageReader::new(ComboReader::new(block));
+    let header = reader.begin_request()?;
+    let mut body = Vec::new();
+    reader.read_body().read_to_end(&mut body)?;
+    reader.end_message()?;
+
+    Ok((header, body))
+}
+
+fn read_response<R: Read>(block: R) -> Result<(ResponseHeader, Vec<u8>), HTTPError> {
+    let mut reader = MessageReader::new(ComboReader::new(block));
+    let header = reader.begin_response(None)?;
+    let mut body = Vec::new();
+    reader.read_body().read_to_end(&mut body)?;
+    reader.end_message()?;
+
+    Ok((header, body))
+}
+
+fn make_entry(request: Option<PendingRecord>, response: PendingRecord) -> HAREntry {
+    let started_date = request
+        .as_ref()
+        .map(|request| request.date)
+        .unwrap_or(response.date);
+    let time = (response.date - started_date).num_milliseconds().max(0) as f64;
+
+    let har_request = match &request {
+        Some(PendingRecord {
+            url,
+            message: Message::Request(header, body),
+            ..
+        }) => convert_request(url, header, body),
+        _ => convert_request(&response.url, &RequestHeader::new("GET", ""), b""),
+    };
+    let har_response = match &response.message {
+        Message::Response(header, body) => convert_response(header, body),
+        Message::Request(..) => unreachable!(),
+    };
+
+    HAREntry {
+        started_date_time: started_date.to_rfc3339_opts(SecondsFormat::Millis, true),
+        time,
+        request: har_request,
+        response: har_response,
+        cache: Default::default(),
+        timings: HARTimings {
+            blocked: Some(-1.0),
+            dns: Some(-1.0),
+            connect: Some(-1.0),
+            send: 0.0,
+            wait: time,
+            receive: 0.0,
+            ssl: None,
+        },
+        server_ip_address: response.ip_address,
+    }
+}
+
+fn convert_request(url: &str, header: &RequestHeader, body: &[u8]) -> HARRequest {
+    let query_string = Url::parse(url)
+        .map(|url| {
+            url.query_pairs()
+                .map(|(name, value)| HARNameValue {
+                    name: name.to_string(),
+                    value: value.to_string(),
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let post_data = if body.is_empty() {
+        None
+    } else {
+        let mime_type = header
+            .fields
+            .get_str("Content-Type")
+            .unwrap_or_default()
+            .to_string();
+        let (text, encoding) = encode_body(&mime_type, body);
+
+        Some(HARPostData {
+            mime_type,
+            text,
+            encoding,
+            params: Vec::new(),
+        })
+    };
+
+    HARRequest {
+        method: header.request_line.method.clone(),
+        url: url.to_string(),
+        http_version: format_version(header.request_line.version),
+        cookies: header
+            .fields
+            .get_all("Cookie")
+            .flat_map(|value| parse_cookie(&value.to_text_lossy()))
+            .collect(),
+        headers: convert_fields(&header.fields),
+        query_string,
+        post_data,
+        headers_size: -1,
+        body_size: body.len() as i64,
+    }
+}
+
+fn convert_response(header: &ResponseHeader, body: &[u8]) -> HARResponse {
+    let mime_type = header
+        .fields
+        .get_str("Content-Type")
+        .unwrap_or_default()
+        .to_string();
+    let (text, encoding) = encode_body(&mime_type, body);
+    let is_encoded = header.fields.contains_key("Content-Encoding")
+        || header.fields.contains_key("Transfer-Encoding");
+
+    HARResponse {
+        status: header.status_line.status_code,
+        status_text: header.status_line.reason_phrase.clone(),
+        http_version: format_version(header.status_line.version),
+        cookies: header
+            .fields
+            .get_all("Set-Cookie")
+            .filter_map(|value| parse_set_cookie(&value.to_text_lossy()))
+            .collect(),
+        headers: convert_fields(&header.fields),
+        content: HARContent {
+            size: body.len() as i64,
+            mime_type,
+            text: Some(text),
+            encoding,
+        },
+        redirect_url: header
+            .fields
+            .get_str("Location")
+            .unwrap_or_default()
+            .to_string(),
+        headers_size: -1,
+        body_size: if is_encoded { -1 } else { body.len() as i64 },
+    }
+}
+
+fn convert_fields(fields: &HeaderMap) -> Vec<HARNameValue> {
+    fields
+        .iter()
+        .map(|pair| HARNameValue {
+            name: pair.name.to_text_lossy(),
+            value: pair.value.to_text_lossy(),
+        })
+        .collect()
+}
+
+fn format_version(version: Version) -> String {
+    format!("HTTP/{}.{}", version.0, version.1)
+}
+
+// Returns the body as text if it is a text media type, otherwise as Base64.
+fn encode_body(mime_type: &str, body: &[u8]) -> (String, Option<String>) {
+    let is_text = get_media_type(Some(mime_type))
+        .map(|media_type| {
+            let (type_, subtype) = media_type.split_once('/').unwrap_or_default();
+
+            type_ == "text"
+                || TEXT_SUBTYPES.contains(&subtype)
+                || subtype.ends_with("+json")
+                || subtype.ends_with("+xml")
+        })
+        .unwrap_or(false);
+
+    match std::str::from_utf8(body) {
+        Ok(text) if is_text || body.is_empty() => (text.to_string(), None),
+        _ => (
+            data_encoding::BASE64.encode(body),
+            Some("base64".to_string()),
+        ),
+    }
+}
+
+fn parse_cookie(value: &str) -> Vec<HARCookie> {
+    value
+        .split(';')
+        .filter_map(|item| item.split_once('='))
+        .map(|(name, value)| new_cookie(name.trim(), value.trim()))
+        .collect()
+}
+
+fn parse_set_cookie(value: &str) -> Option<HARCookie> {
+    let mut items = value.split(';');
+    let (name, value) = items.next()?.split_once('=')?;
+    let mut cookie = new_cookie(name.trim(), value.trim());
+
+    for item in items {
+        let (name, value) = item.split_once('=').unwrap_or((item, ""));
+        let value = Some(value.trim().to_string());
+
+        match name.trim().to_ascii_lowercase().as_str() {
+            "path" => cookie.path = value,
+            "domain" => cookie.domain = value,
+            "expires" => cookie.expires = value,
+            "httponly" => cookie.http_only = Some(true),
+            "secure" => cookie.secure = Some(true),
+            _ => {}
+        }
+    }
+
+    Some(cookie)
+}
+
+fn new_cookie(name: &str, value: &str) -> HARCookie {
+    HARCookie {
+        name: name.to_string(),
+        value: value.to_string(),
+        path: None,
+        domain: None,
+        expires: None,
+        http_only: None,
+        secure: None,
+    }
+}
@@ -0,0 +1,264 @@
+//! HAR file processing.
+//!
+//! HAR (HTTP Archive) is a JSON format for HTTP transactions used by web
+//! browser developer tools. Version 1.2 of the format is implemented.
+mod convert;
+mod export;
+
+pub use convert::*;
+pub use export::*;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Version of the HAR format implemented.
+pub const HAR_VERSION: &str = "1.2";
+
+/// Errors during processing of HAR files.
+#[derive(Error, Debug)]
+pub enum HARError {
+    /// A value in an entry couldn't be parsed.
+    #[error("invalid value (with name {name}, at URL {url})")]
+    InvalidValue {
+        /// Name of the value.
+        name: String,
+        /// URL of the request.
+        url: String,
+    },
+
+    /// HTTP error.
+    #[error(transparent)]
+    Http(#[from] crate::http::HTTPError),
+
+    /// WARC error.
+    #[error(transparent)]
+    Warc(#[from] crate::warc::WARCError),
+
+    /// IO error.
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// Root object of a HAR file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HAR {
+    /// The log.
+    pub log: HARLog,
+}
+
+/// Exported HTTP transactions.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HARLog {
+    /// Version of the format.
+    pub version: String,
+    /// Software that created the log.
+    pub creator: HARCreator,
+    /// Web browser that created the log.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub browser: Option<HARCreator>,
+    /// HTTP transactions sorted by start date.
+    pub entries: Vec<HAREntry>,
+}
+
+/// Name and version of the software or browser that created the log.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HARCreator {
+    /// Name of the software.
+    pub name: String,
+    /// Version of the software.
+    pub version: String,
+}
+
+/// A request and its response.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HAREntry {
+    /// Date the request was started in ISO 8601 format.
+    pub started_date_time: String,
+    /// Total time of the request in milliseconds.
+    pub time: f64,
+    /// The request.
+    pub request: HARRequest,
+    /// The response.
+    pub response: HARResponse,
+    /// Cache usage. Always empty.
+    #[serde(default)]
+    pub cache: HARCache,
+    /// Time spent in each phase of the request.
+    pub timings: HARTimings,
+    /// IP address of the server.
+    #[serde(
+        rename = "serverIPAddress",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub server_ip_address: Option<String>,
+}
+
+/// An HTTP request.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HARRequest {
+    /// Request method.
+    pub method: String,
+    /// Absolute URL of the request.
+    pub url: String,
+    /// HTTP version such as `HTTP/1.1`.
+    pub http_version: String,
+    /// Cookies sent in the request.
+    pub cookies: Vec<HARCookie>,
+    /// Header fields.
+    pub headers: Vec<HARNameValue>,
+    /// Query string parameters of the URL.
+    pub query_string: Vec<HARNameValue>,
+    /// Request body.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_data: Option<HARPostData>,
+    /// Size of the header in bytes, or -1 if unknown.
+    pub headers_size: i64,
+    /// Size of the body in bytes, or -1 if unknown.
+    pub body_size: i64,
+}
+
+/// An HTTP response.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HARResponse {
+    /// Status code.
+    pub status: u16,
+    /// Reason phrase.
+    pub status_text: String,
+    /// HTTP version such as `HTTP/1.1`.
+    pub http_version: String,
+    /// Cookies set by the response.
+    pub cookies: Vec<HARCookie>,
+    /// Header fields.
+    pub headers: Vec<HARNameValue>,
+    /// Response body.
+    pub content: HARContent,
+    /// Value of the `Location` field, or empty.
+    #[serde(rename = "redirectURL")]
+    pub redirect_url: String,
+    /// Size of the header in bytes, or -1 if unknown.
+    pub headers_size: i64,
+    /// Size of the body as transferred in bytes, or -1 if unknown.
+    pub body_size: i64,
+}
+
+/// A cookie.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HARCookie {
+    /// Name of the cookie.
+    pub name: String,
+    /// Value of the cookie.
+    pub value: String,
+    /// Path attribute.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Domain attribute.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub domain: Option<String>,
+    /// Expires attribute.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires: Option<String>,
+    /// Whether the HttpOnly attribute is present.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub http_only: Option<bool>,
+    /// Whether the Secure attribute is present.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secure: Option<bool>,
+}
+
+/// A header field or query string parameter.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HARNameValue {
+    /// Name of the field or parameter.
+    pub name: String,
+    /// Value of the field or parameter.
+    pub value: String,
+}
+
+/// Body of a request.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HARPostData {
+    /// Media type of the body.
+    pub mime_type: String,
+    /// The body as text.
+    ///
+    /// Empty if the body is given as [Self::params] instead.
+    #[serde(default)]
+    pub text: String,
+    /// `base64` if the text is encoded.
+    ///
+    /// This is not part of the specification for request bodies but is
+    /// used in the same way as [HARContent::encoding].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+    /// The body as form parameters.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub params: Vec<HARPostParam>,
+}
+
+/// A parameter of a form request body.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HARPostParam {
+    /// Name of the parameter.
+    pub name: String,
+    /// Value of the parameter, or the contents of the uploaded file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    /// Name of the uploaded file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_name: Option<String>,
+    /// Media type of the uploaded file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+}
+
+/// Body of a response.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HARContent {
+    /// Size of the decoded body in bytes.
+    pub size: i64,
+    /// Media type of the body.
+    pub mime_type: String,
+    /// The decoded body as text.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// `base64` if the text is encoded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+}
+
+/// Cache usage of a request.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HARCache {}
+
+/// Time spent in each phase of a request in milliseconds.
+///
+/// A value of -1 means the phase does not apply.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HARTimings {
+    /// Time waiting for a network connection.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blocked: Option<f64>,
+    /// Time resolving the host name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dns: Option<f64>,
+    /// Time creating the connection.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connect: Option<f64>,
+    /// Time sending the request.
+    pub send: f64,
+    /// Time waiting for the response.
+    pub wait: f64,
+    /// Time reading the response.
+    pub receive: f64,
+    /// Time negotiating TLS.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssl: Option<f64>,
+}
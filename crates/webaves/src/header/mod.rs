@@ -119,6 +119,14 @@ impl HeaderMap {
         self.pairs.push(FieldPair::new(name, value.into()));
     }
 
+    /// Remove all fields with the given name.
+    pub fn remove<N: Into<String>>(&mut self, name: N) {
+        let mut name = name.into();
+        name.make_ascii_lowercase();
+
+        self.pairs.retain(|pair| pair.name.normalized != name);
+    }
+
     /// Moves the fields to the front of the list order.
     ///
     /// Some servers may sensitive to fields such as "Host" or "Date" being
@@ -497,6 +505,21 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_header_map_remove() {
+        let mut map = HeaderMap::new();
+
+        map.append("k1", "v1");
+        map.append("k2", "v2");
+        map.append("K1", "v3");
+
+        map.remove("k1");
+
+        assert_eq!(map.len(), 1);
+        assert!(!map.contains_key("k1"));
+        assert_eq!(map.get_str("k2"), Some("v2"));
+    }
+
     #[test]
     fn test_header_iter() {
         let mut map = HeaderMap::new();
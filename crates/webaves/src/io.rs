@@ -1,8 +1,12 @@
 //! IO helpers.
 
-use std::io::{BufRead, Error, ErrorKind, Read, Result};
+use std::{
+    io::{BufRead, Error, ErrorKind, Read, Result},
+    pin::Pin,
+    task::{Context, Poll},
+};
 
-use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, ReadBuf};
 
 /// Extension trait for [std::io::BufRead].
 pub trait BufReadMoreExt {
@@ -310,6 +314,69 @@ impl<R: Read> Capture for ComboReader<R> {
     }
 }
 
+/// Async buffered reader that counts the number of bytes read.
+pub struct AsyncCountReader<R: AsyncBufRead + Unpin> {
+    stream: R,
+    read_count: u64,
+}
+
+impl<R: AsyncBufRead + Unpin> AsyncCountReader<R> {
+    /// Creates a reader with the given stream.
+    pub fn new(reader: R) -> Self {
+        Self {
+            stream: reader,
+            read_count: 0,
+        }
+    }
+
+    /// Returns a reference to the wrapped stream.
+    pub fn get_ref(&self) -> &R {
+        &self.stream
+    }
+
+    /// Returns a mutable reference to the wrapped stream.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.stream
+    }
+
+    /// Returns the wrapped stream.
+    pub fn into_inner(self) -> R {
+        self.stream
+    }
+}
+
+impl<R: AsyncBufRead + Unpin> AsyncRead for AsyncCountReader<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<Result<()>> {
+        let filled_before = buf.filled().len();
+        let result = Pin::new(&mut self.stream).poll_read(cx, buf);
+
+        self.read_count += (buf.filled().len() - filled_before) as u64;
+
+        result
+    }
+}
+
+impl<R: AsyncBufRead + Unpin> AsyncBufRead for AsyncCountReader<R> {
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<&[u8]>> {
+        Pin::new(&mut self.get_mut().stream).poll_fill_buf(cx)
+    }
+
+    fn consume(mut self: Pin<&mut Self>, amount: usize) {
+        Pin::new(&mut self.stream).consume(amount);
+        self.read_count += amount as u64;
+    }
+}
+
+impl<R: AsyncBufRead + Unpin> CountRead for AsyncCountReader<R> {
+    fn read_count(&self) -> u64 {
+        self.read_count
+    }
+}
+
 #[cfg(test)]
 mod tests_sync {
     use crate::io::{BufReadMoreExt, Capture, CountRead, SourceCountRead};
@@ -484,8 +551,27 @@ mod tests_sync {
 
 #[cfg(test)]
 mod tests_async {
-    use crate::io::AsyncBufReadMoreExt;
+    use crate::io::{AsyncBufReadMoreExt, AsyncCountReader, CountRead};
     use std::io::Cursor;
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt};
+
+    #[tokio::test]
+    async fn test_async_count_reader() {
+        let mut reader = AsyncCountReader::new(Cursor::new(b"abcdefghij"));
+        let mut buf = [0u8; 4];
+
+        reader.read_exact(&mut buf).await.unwrap();
+        assert_eq!(reader.read_count(), 4);
+
+        let data = reader.fill_buf().await.unwrap();
+        assert_eq!(data, b"efghij");
+        reader.consume(2);
+        assert_eq!(reader.read_count(), 6);
+
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf).await.unwrap();
+        assert_eq!(reader.read_count(), 10);
+    }
 
     #[tokio::test]
     async fn test_read_limit_until() {
@@ -1,12 +1,14 @@
 //! Webaves web archive software suite.
 
 #![warn(missing_docs)]
+pub mod arc;
 pub mod compress;
 pub mod crypto;
 pub mod dns;
 pub mod download;
 pub mod error;
 pub mod fetch;
+pub mod har;
 pub mod header;
 pub mod http;
 pub mod io;
@@ -19,4 +21,5 @@ pub mod stringesc;
 pub mod stringutil;
 pub mod tracker;
 pub mod uuid;
+pub mod wacz;
 pub mod warc;
@@ -0,0 +1,170 @@
+//! WACZ file processing.
+//!
+//! WACZ is a ZIP file containing WARC files, an index of the records, a
+//! list of pages, and a `datapackage.json` file describing the contents.
+//! It is used by browser-based replay software.
+mod reader;
+mod writer;
+
+pub use reader::*;
+pub use writer::*;
+
+use std::io::Write;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+use crate::warc::WARCError;
+
+/// Version of the WACZ specification implemented.
+pub const WACZ_VERSION: &str = "1.1.1";
+
+/// Path of the data package file.
+pub const DATA_PACKAGE_PATH: &str = "datapackage.json";
+
+/// Path of the data package digest file.
+pub const DATA_PACKAGE_DIGEST_PATH: &str = "datapackage-digest.json";
+
+/// Directory containing the WARC files.
+pub const ARCHIVE_DIRECTORY: &str = "archive/";
+
+/// Path of the CDXJ index file.
+pub const INDEX_PATH: &str = "indexes/index.cdx";
+
+/// Path of the page list file.
+pub const PAGES_PATH: &str = "pages/pages.jsonl";
+
+/// Errors during processing of WACZ files.
+#[derive(Error, Debug)]
+pub enum WACZError {
+    /// The file is not a WACZ file.
+    #[error("not a WACZ file")]
+    InvalidFormat,
+
+    /// A file is missing from the ZIP file.
+    #[error("missing file (with path {path})")]
+    MissingFile {
+        /// Path of the file.
+        path: String,
+    },
+
+    /// A compressed WARC file does not have each record compressed
+    /// individually.
+    #[error("records are not compressed individually (in file {name})")]
+    NotMultistream {
+        /// Filename of the WARC file.
+        name: String,
+    },
+
+    /// ZIP error.
+    #[error(transparent)]
+    Zip(#[from] zip::result::ZipError),
+
+    /// JSON error.
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+
+    /// WARC error.
+    #[error(transparent)]
+    Warc(#[from] WARCError),
+
+    /// IO error.
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// Contents of the `datapackage.json` file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DataPackage {
+    /// Frictionless data package profile.
+    pub profile: String,
+    /// Version of the WACZ specification.
+    pub wacz_version: String,
+    /// Title of the collection.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Date the file was created in RFC 3339 format.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created: Option<String>,
+    /// Name of the software that created the file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub software: Option<String>,
+    /// Files in the ZIP file.
+    pub resources: Vec<DataPackageResource>,
+}
+
+/// A file listed in the `datapackage.json` file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DataPackageResource {
+    /// Name of the file.
+    pub name: String,
+    /// Path of the file in the ZIP file.
+    pub path: String,
+    /// Digest of the file as `sha256:` followed by a hex string.
+    pub hash: String,
+    /// Size of the file.
+    pub bytes: u64,
+}
+
+/// Contents of the `datapackage-digest.json` file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DataPackageDigest {
+    /// Path of the data package file.
+    pub path: String,
+    /// Digest of the data package file.
+    pub hash: String,
+}
+
+/// An entry in the `pages/pages.jsonl` file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Page {
+    /// URL of the page.
+    pub url: String,
+    /// Date of the capture in RFC 3339 format.
+    pub ts: String,
+    /// Title of the page.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+}
+
+// Computes the SHA-256 digest and size of the data written through it.
+struct HashWriter<W: Write> {
+    stream: W,
+    hasher: Sha256,
+    bytes: u64,
+}
+
+impl<W: Write> HashWriter<W> {
+    fn new(stream: W) -> Self {
+        Self {
+            stream,
+            hasher: Sha256::default(),
+            bytes: 0,
+        }
+    }
+
+    fn finish(self) -> (W, String, u64) {
+        (
+            self.stream,
+            format!(
+                "sha256:{}",
+                data_encoding::HEXLOWER.encode(&self.hasher.finalize())
+            ),
+            self.bytes,
+        )
+    }
+}
+
+impl<W: Write> Write for HashWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let amount = self.stream.write(buf)?;
+        self.hasher.update(&buf[..amount]);
+        self.bytes += amount as u64;
+        Ok(amount)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.stream.flush()
+    }
+}
@@ -0,0 +1,136 @@
+use std::io::{BufRead, BufReader, Read, Seek};
+
+use zip::{read::ZipFile, ZipArchive};
+
+use crate::warc::WARCReader;
+
+use super::{
+    DataPackage, DataPackageDigest, DataPackageResource, HashWriter, Page, WACZError,
+    ARCHIVE_DIRECTORY, DATA_PACKAGE_DIGEST_PATH, DATA_PACKAGE_PATH, PAGES_PATH,
+};
+
+/// Reads a WACZ file.
+pub struct WACZReader<R: Read + Seek> {
+    archive: ZipArchive<R>,
+    data_package: DataPackage,
+}
+
+impl<R: Read + Seek> WACZReader<R> {
+    /// Opens a WACZ file and reads the data package.
+    pub fn new(stream: R) -> Result<Self, WACZError> {
+        let mut archive = match ZipArchive::new(stream) {
+            Ok(archive) => archive,
+            Err(zip::result::ZipError::InvalidArchive(_)) => return Err(WACZError::InvalidFormat),
+            Err(error) => return Err(error.into()),
+        };
+
+        let data_package = serde_json::from_reader(by_name(&mut archive, DATA_PACKAGE_PATH)?)?;
+
+        Ok(Self {
+            archive,
+            data_package,
+        })
+    }
+
+    /// Returns the wrapped stream.
+    pub fn into_inner(self) -> R {
+        self.archive.into_inner()
+    }
+
+    /// Returns the contents of the `datapackage.json` file.
+    pub fn data_package(&self) -> &DataPackage {
+        &self.data_package
+    }
+
+    /// Returns the sorted names of the WARC files in the archive directory.
+    pub fn warc_names(&self) -> Vec<String> {
+        let mut names = self
+            .archive
+            .file_names()
+            .filter_map(|path| path.strip_prefix(ARCHIVE_DIRECTORY))
+            .filter(|name| !name.is_empty())
+            .map(|name| name.to_string())
+            .collect::<Vec<String>>();
+        names.sort_unstable();
+
+        names
+    }
+
+    /// Returns a reader for the WARC file with the given name.
+    pub fn open_warc(
+        &mut self,
+        name: &str,
+    ) -> Result<WARCReader<'_, BufReader<ZipFile<'_>>>, WACZError> {
+        let path = format!("{}{}", ARCHIVE_DIRECTORY, name);
+        let file = by_name(&mut self.archive, &path)?;
+
+        Ok(WARCReader::new(BufReader::new(file))?)
+    }
+
+    /// Returns the entries in the page list.
+    pub fn pages(&mut self) -> Result<Vec<Page>, WACZError> {
+        let file = BufReader::new(by_name(&mut self.archive, PAGES_PATH)?);
+        let mut pages = Vec::new();
+
+        for (index, line) in file.lines().enumerate() {
+            let line = line?;
+
+            // The first line is a header describing the list.
+            if index == 0 || line.trim().is_empty() {
+                continue;
+            }
+
+            pages.push(serde_json::from_str(&line)?);
+        }
+
+        Ok(pages)
+    }
+
+    /// Checks whether the resource matches the size and digest listed in
+    /// the data package.
+    ///
+    /// Returns [WACZError::MissingFile] if the file does not exist.
+    pub fn verify_resource(&mut self, resource: &DataPackageResource) -> Result<bool, WACZError> {
+        let (hash, bytes) = self.hash_file(&resource.path)?;
+
+        Ok(hash == resource.hash && bytes == resource.bytes)
+    }
+
+    /// Checks whether the `datapackage.json` file matches the digest in the
+    /// `datapackage-digest.json` file.
+    ///
+    /// Returns `None` if the digest file does not exist.
+    pub fn verify_data_package(&mut self) -> Result<Option<bool>, WACZError> {
+        let digest: DataPackageDigest = match by_name(&mut self.archive, DATA_PACKAGE_DIGEST_PATH) {
+            Ok(file) => serde_json::from_reader(file)?,
+            Err(WACZError::MissingFile { .. }) => return Ok(None),
+            Err(error) => return Err(error),
+        };
+
+        let (hash, _) = self.hash_file(DATA_PACKAGE_PATH)?;
+
+        Ok(Some(hash == digest.hash))
+    }
+
+    fn hash_file(&mut self, path: &str) -> Result<(String, u64), WACZError> {
+        let mut file = by_name(&mut self.archive, path)?;
+        let mut writer = HashWriter::new(std::io::sink());
+        std::io::copy(&mut file, &mut writer)?;
+        let (_, hash, bytes) = writer.finish();
+
+        Ok((hash, bytes))
+    }
+}
+
+fn by_name<'a, R: Read + Seek>(
+    archive: &'a mut ZipArchive<R>,
+    path: &str,
+) -> Result<ZipFile<'a>, WACZError> {
+    match archive.by_name(path) {
+        Ok(file) => Ok(file),
+        Err(zip::result::ZipError::FileNotFound) => Err(WACZError::MissingFile {
+            path: path.to_string(),
+        }),
+        Err(error) => Err(error.into()),
+    }
+}
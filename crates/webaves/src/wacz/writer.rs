@@ -0,0 +1,250 @@
+use std::{
+    collections::HashSet,
+    io::{BufReader, Read, Seek, SeekFrom, Write},
+};
+
+use chrono::{NaiveDateTime, Utc};
+use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+use crate::{
+    compress::CompressionFormat,
+    warc::{
+        index::{sort_entries, CDXJWriter, IndexEntry, Indexer},
+        WARCReader,
+    },
+};
+
+use super::{
+    DataPackage, DataPackageDigest, DataPackageResource, HashWriter, Page, WACZError,
+    ARCHIVE_DIRECTORY, DATA_PACKAGE_DIGEST_PATH, DATA_PACKAGE_PATH, INDEX_PATH, PAGES_PATH,
+    WACZ_VERSION,
+};
+
+const PAGES_HEADER: &str = r#"{"format": "json-pages-1.0", "id": "pages", "title": "All Pages"}"#;
+
+/// Creates a WACZ file from WARC files.
+///
+/// WARC files are stored without additional compression so that the
+/// offsets in the index are valid. For compressed WARC files, each record
+/// must be compressed individually.
+///
+/// The index and page list are generated from the records. Pages are
+/// HTML documents from successful HTTP responses.
+pub struct WACZWriter<W: Write + Seek> {
+    zip: ZipWriter<W>,
+    title: Option<String>,
+    resources: Vec<DataPackageResource>,
+    index_entries: Vec<IndexEntry>,
+    pages: Vec<Page>,
+    page_urls: HashSet<String>,
+}
+
+impl<W: Write + Seek> WACZWriter<W> {
+    /// Creates a `WACZWriter` with the given output stream.
+    pub fn new(stream: W) -> Self {
+        Self {
+            zip: ZipWriter::new(stream),
+            title: None,
+            resources: Vec::new(),
+            index_entries: Vec::new(),
+            pages: Vec::new(),
+            page_urls: HashSet::new(),
+        }
+    }
+
+    /// Returns the title of the collection.
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    /// Sets the title of the collection.
+    pub fn set_title(&mut self, title: Option<String>) {
+        self.title = title;
+    }
+
+    /// Returns the pages found so far.
+    pub fn pages(&self) -> &[Page] {
+        &self.pages
+    }
+
+    /// Adds a WARC file with the given filename.
+    ///
+    /// The file is copied into the archive and then read again to index
+    /// the records.
+    ///
+    /// Returns [WACZError::NotMultistream] if the file is compressed but
+    /// its records are not compressed individually.
+    pub fn add_warc<R: Read + Seek>(&mut self, name: &str, mut stream: R) -> Result<(), WACZError> {
+        tracing::debug!(name, "add_warc");
+
+        let path = format!("{}{}", ARCHIVE_DIRECTORY, name);
+        let start_position = stream.stream_position()?;
+        let length = stream.seek(SeekFrom::End(0))? - start_position;
+        stream.seek(SeekFrom::Start(start_position))?;
+
+        check_members(name, &mut stream)?;
+        stream.seek(SeekFrom::Start(start_position))?;
+
+        self.zip.start_file(
+            &path,
+            FileOptions::default()
+                .compression_method(CompressionMethod::Stored)
+                .large_file(length > u32::MAX as u64),
+        )?;
+        self.copy_resource(name, &path, &mut stream)?;
+
+        stream.seek(SeekFrom::Start(start_position))?;
+
+        let reader = WARCReader::new(BufReader::new(stream))?;
+        let mut indexer = Indexer::new(reader, name);
+
+        while let Some(entry) = indexer.next_entry()? {
+            self.add_page(&entry);
+            self.index_entries.push(entry);
+        }
+
+        Ok(())
+    }
+
+    fn copy_resource<R: Read>(
+        &mut self,
+        name: &str,
+        path: &str,
+        stream: &mut R,
+    ) -> Result<(), WACZError> {
+        let mut writer = HashWriter::new(&mut self.zip);
+        std::io::copy(stream, &mut writer)?;
+        let (_, hash, bytes) = writer.finish();
+
+        self.resources.push(DataPackageResource {
+            name: name.to_string(),
+            path: path.to_string(),
+            hash,
+            bytes,
+        });
+
+        Ok(())
+    }
+
+    fn add_page(&mut self, entry: &IndexEntry) {
+        let is_html = entry.mime.as_deref() == Some("text/html");
+        let is_ok = entry.status == Some(200);
+
+        if !is_html || !is_ok || self.page_urls.contains(&entry.url) {
+            return;
+        }
+
+        let ts = match NaiveDateTime::parse_from_str(&entry.timestamp, "%Y%m%d%H%M%S") {
+            Ok(date) => date.format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+            Err(_) => return,
+        };
+
+        self.page_urls.insert(entry.url.clone());
+        self.pages.push(Page {
+            url: entry.url.clone(),
+            ts,
+            title: None,
+        });
+    }
+
+    /// Writes the index, page list, and data package files and returns
+    /// the wrapped stream.
+    pub fn finish(mut self) -> Result<W, WACZError> {
+        self.write_index()?;
+        self.write_pages()?;
+        let data_package = self.write_data_package()?;
+        self.write_data_package_digest(&data_package)?;
+
+        Ok(self.zip.finish()?)
+    }
+
+    fn write_index(&mut self) -> Result<(), WACZError> {
+        sort_entries(&mut self.index_entries);
+
+        let mut data = Vec::new();
+        let mut writer = CDXJWriter::new(&mut data);
+
+        for entry in &self.index_entries {
+            writer.write_entry(entry)?;
+        }
+
+        self.write_file(INDEX_PATH, &data)
+    }
+
+    fn write_pages(&mut self) -> Result<(), WACZError> {
+        let mut data = Vec::new();
+        writeln!(&mut data, "{}", PAGES_HEADER)?;
+
+        for page in &self.pages {
+            serde_json::to_writer(&mut data, page)?;
+            data.push(b'\n');
+        }
+
+        self.write_file(PAGES_PATH, &data)
+    }
+
+    fn write_data_package(&mut self) -> Result<Vec<u8>, WACZError> {
+        let data_package = DataPackage {
+            profile: "data-package".to_string(),
+            wacz_version: WACZ_VERSION.to_string(),
+            title: self.title.clone(),
+            created: Some(Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string()),
+            software: Some(format!("webaves/{}", env!("CARGO_PKG_VERSION"))),
+            resources: self.resources.clone(),
+        };
+        let data = serde_json::to_vec_pretty(&data_package)?;
+
+        self.zip
+            .start_file(DATA_PACKAGE_PATH, FileOptions::default())?;
+        self.zip.write_all(&data)?;
+
+        Ok(data)
+    }
+
+    fn write_data_package_digest(&mut self, data_package: &[u8]) -> Result<(), WACZError> {
+        let mut writer = HashWriter::new(std::io::sink());
+        writer.write_all(data_package)?;
+        let (_, hash, _) = writer.finish();
+
+        let digest = DataPackageDigest {
+            path: DATA_PACKAGE_PATH.to_string(),
+            hash,
+        };
+
+        self.zip
+            .start_file(DATA_PACKAGE_DIGEST_PATH, FileOptions::default())?;
+        serde_json::to_writer_pretty(&mut self.zip, &digest)?;
+
+        Ok(())
+    }
+
+    fn write_file(&mut self, path: &str, data: &[u8]) -> Result<(), WACZError> {
+        self.zip.start_file(path, FileOptions::default())?;
+
+        let name = path.rsplit('/').next().unwrap_or(path);
+        self.copy_resource(name, path, &mut &data[..])
+    }
+}
+
+// Checks that each record ends a compression member so that the offsets in
+// the index point to the start of a member.
+fn check_members<R: Read>(name: &str, stream: R) -> Result<(), WACZError> {
+    let mut reader = WARCReader::new(BufReader::new(stream))?;
+
+    if reader.compression_format() == CompressionFormat::Raw {
+        return Ok(());
+    }
+
+    while reader.begin_record()?.is_some() {
+        std::io::copy(&mut reader.read_block(), &mut std::io::sink())?;
+        reader.end_record()?;
+
+        if !reader.is_member_boundary()? {
+            return Err(WACZError::NotMultistream {
+                name: name.to_string(),
+            });
+        }
+    }
+
+    Ok(())
+}
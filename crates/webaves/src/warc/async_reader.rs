@@ -0,0 +1,277 @@
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use tokio::io::{AsyncBufRead, AsyncRead, AsyncReadExt, ReadBuf, Take};
+
+use crate::{
+    compress::AsyncMultiStreamDecompressor,
+    header::HeaderMap,
+    io::{AsyncBufReadMoreExt, SourceCountRead},
+};
+
+use super::header::HeaderMapExt;
+use super::reader::{is_end_of_record_line, is_magic_bytes, parse_header, ReaderState};
+use super::{HeaderMetadata, WARCError};
+
+/// Reads a WARC file asynchronously.
+///
+/// This is the async version of [WARCReader](super::WARCReader).
+/// Decompression of Gzip and Zstd files is handled automatically by
+/// [AsyncMultiStreamDecompressor].
+pub struct AsyncWARCReader<S: AsyncBufRead + Unpin + Send> {
+    stream: AsyncMultiStreamDecompressor<S>,
+    header_limit: u64,
+
+    state: ReaderState,
+
+    file_offset: u64,
+
+    magic_bytes_buffer: Vec<u8>,
+    header_buffer: Vec<u8>,
+
+    record_id: String,
+    block_file_offset: u64,
+    block_length: u64,
+    block_bytes_read: u64,
+}
+
+impl<S: AsyncBufRead + Unpin + Send> AsyncWARCReader<S> {
+    /// Creates a `AsyncWARCReader` with the given input buffered stream.
+    pub async fn new(stream: S) -> Result<Self, WARCError> {
+        Ok(Self {
+            stream: AsyncMultiStreamDecompressor::new(stream).await?,
+            header_limit: 16_777_216,
+            state: ReaderState::StartOfHeader,
+            magic_bytes_buffer: Vec::new(),
+            header_buffer: Vec::new(),
+            file_offset: 0,
+            record_id: String::new(),
+            block_file_offset: 0,
+            block_length: 0,
+            block_bytes_read: 0,
+        })
+    }
+
+    /// Returns the wrapped stream.
+    pub fn into_inner(self) -> S {
+        self.stream.into_inner()
+    }
+
+    /// Starts reading a record and returns the header.
+    ///
+    /// The caller must call [Self::read_block] next to advance the stream.
+    ///
+    /// Panics when called out of sequence.
+    ///
+    /// Returns `None` when there are no more records in the stream.
+    pub async fn begin_record(&mut self) -> Result<Option<HeaderMetadata>, WARCError> {
+        assert!(self.state == ReaderState::StartOfHeader);
+
+        let start_file_offset = self.file_offset;
+        let raw_file_offset = self.raw_file_offset().await?;
+
+        tracing::debug!(
+            file_offset = self.file_offset,
+            raw_file_offset,
+            "begin_record"
+        );
+
+        if !self.read_magic_bytes().await? {
+            return Ok(None);
+        }
+        self.read_header_lines().await?;
+        let header_map = parse_header(&self.header_buffer, self.file_offset)?;
+        self.prepare_for_block_read(&header_map)?;
+
+        self.state = ReaderState::EndOfHeader;
+
+        Ok(Some(HeaderMetadata::new(
+            String::from_utf8_lossy(&self.magic_bytes_buffer)
+                .trim()
+                .to_string(),
+            header_map,
+            self.block_length,
+            start_file_offset,
+            raw_file_offset,
+        )))
+    }
+
+    /// Returns the position in the (compressed) stream of the boundary
+    /// between records.
+    ///
+    /// See [WARCReader::raw_file_offset](super::WARCReader::raw_file_offset)
+    /// for details.
+    pub async fn raw_file_offset(&mut self) -> Result<u64, WARCError> {
+        match self.stream.source_position().await? {
+            Some(offset) => Ok(offset),
+            None => Ok(self.stream.member_offset()),
+        }
+    }
+
+    async fn read_magic_bytes(&mut self) -> Result<bool, WARCError> {
+        tracing::debug!("read_magic_bytes");
+
+        self.magic_bytes_buffer.clear();
+        self.stream
+            .read_limit_until(b'\n', &mut self.magic_bytes_buffer, self.header_limit)
+            .await?;
+
+        self.file_offset += self.magic_bytes_buffer.len() as u64;
+
+        tracing::trace!(magic_bytes_buffer = ?self.magic_bytes_buffer, "read_magic_bytes");
+
+        if self.magic_bytes_buffer.is_empty() {
+            return Ok(false);
+        }
+
+        if !is_magic_bytes(&self.magic_bytes_buffer) {
+            return Err(WARCError::UnknownFormat);
+        }
+
+        Ok(true)
+    }
+
+    async fn read_header_lines(&mut self) -> Result<(), WARCError> {
+        tracing::debug!("read_header_lines");
+
+        self.header_buffer.clear();
+
+        let amount = crate::header::read_async_until_boundary(
+            &mut self.stream,
+            &mut self.header_buffer,
+            self.header_limit,
+        )
+        .await?;
+        self.file_offset += amount;
+
+        Ok(())
+    }
+
+    fn prepare_for_block_read(&mut self, header_map: &HeaderMap) -> Result<(), WARCError> {
+        self.record_id = header_map
+            .get_str("WARC-Record-ID")
+            .unwrap_or_default()
+            .to_string();
+        self.block_file_offset = self.file_offset;
+        self.block_length = header_map.get_parsed_required("Content-Length")?;
+        self.block_bytes_read = 0;
+
+        tracing::debug!(
+            block_file_offset = self.block_file_offset,
+            block_length = self.block_length,
+            "prepare_for_block_read"
+        );
+
+        Ok(())
+    }
+
+    /// Starts reading a record body.
+    ///
+    /// The caller must read until the block stream is empty and then
+    /// call [Self::end_record].
+    ///
+    /// Panics when called out of sequence.
+    pub fn read_block(&mut self) -> AsyncBlockReader<'_, S> {
+        assert!(self.state == ReaderState::EndOfHeader);
+        tracing::debug!("read_block");
+
+        let stream = (&mut self.stream).take(self.block_length);
+        self.state = ReaderState::InBlock;
+
+        AsyncBlockReader {
+            stream,
+            num_bytes_read: &mut self.block_bytes_read,
+        }
+    }
+
+    /// Finish reading a record.
+    ///
+    /// Panics when called out of sequence.
+    pub async fn end_record(&mut self) -> Result<(), WARCError> {
+        assert!(self.state == ReaderState::InBlock);
+        tracing::debug!("end_record");
+
+        self.file_offset += self.block_bytes_read;
+
+        self.check_block_length()?;
+        self.read_end_of_record_lines().await?;
+
+        self.state = ReaderState::StartOfHeader;
+
+        Ok(())
+    }
+
+    fn check_block_length(&self) -> Result<(), WARCError> {
+        let current_offset = self.file_offset;
+        let expected_offset = self.block_file_offset + self.block_length;
+
+        tracing::debug!(current_offset, expected_offset, "check_block_length");
+
+        if current_offset != expected_offset {
+            return Err(WARCError::WrongBlockLength {
+                record_id: self.record_id.clone(),
+            });
+        }
+
+        Ok(())
+    }
+
+    async fn read_end_of_record_lines(&mut self) -> Result<(), WARCError> {
+        tracing::debug!("read_end_of_record_lines");
+
+        self.header_buffer.clear();
+
+        for _ in 0..2 {
+            let buf_position = self.header_buffer.len();
+            let amount = self
+                .stream
+                .read_limit_until(b'\n', &mut self.header_buffer, self.header_limit)
+                .await?;
+            self.file_offset += amount as u64;
+            let line = &self.header_buffer[buf_position..];
+
+            if !is_end_of_record_line(line) {
+                return Err(WARCError::MalformedFooter {
+                    offset: self.file_offset,
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<S: AsyncBufRead + Unpin + Send> SourceCountRead for AsyncWARCReader<S> {
+    fn source_read_count(&self) -> u64 {
+        self.stream.source_read_count()
+    }
+}
+
+/// Async reader stream for a record body.
+pub struct AsyncBlockReader<'a, S: AsyncBufRead + Unpin> {
+    stream: Take<&'a mut AsyncMultiStreamDecompressor<S>>,
+    num_bytes_read: &'a mut u64,
+}
+
+impl<'a, S: AsyncBufRead + Unpin> SourceCountRead for AsyncBlockReader<'a, S> {
+    fn source_read_count(&self) -> u64 {
+        self.stream.get_ref().source_read_count()
+    }
+}
+
+impl<'a, S: AsyncBufRead + Unpin> AsyncRead for AsyncBlockReader<'a, S> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let filled_before = buf.filled().len();
+        let result = Pin::new(&mut self.stream).poll_read(cx, buf);
+
+        *self.num_bytes_read += (buf.filled().len() - filled_before) as u64;
+
+        result
+    }
+}
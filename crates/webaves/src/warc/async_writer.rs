@@ -0,0 +1,250 @@
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+use crate::{
+    compress::{AsyncCompressor, CompressionFormat, CompressionLevel},
+    header::{HeaderFormatter, HeaderMap},
+    warc::HeaderMapExt,
+};
+
+use super::writer::WriterState;
+use super::{WARCError, DEFAULT_VERSION};
+
+/// Writes a WARC file asynchronously.
+///
+/// This is the async version of [WARCWriter](super::WARCWriter).
+/// Only Gzip and Zstd compression is supported.
+pub struct AsyncWARCWriter<S: AsyncWrite + Unpin> {
+    stream: Option<S>,
+
+    state: WriterState,
+
+    compressed_stream: Option<AsyncCompressor<S>>,
+    compression_format: CompressionFormat,
+    compression_level: CompressionLevel,
+
+    version: String,
+    header_formatter: HeaderFormatter,
+    header_buffer: Vec<u8>,
+
+    record_id: String,
+    block_length: u64,
+    block_amount_written: u64,
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWARCWriter<S> {
+    /// Creates a writer with the given stream without compression.
+    pub fn new(stream: S) -> Self {
+        Self::new_compressed(stream, CompressionFormat::Raw, Default::default())
+    }
+
+    /// Creates a writer with the given stream and compression configuration.
+    pub fn new_compressed(
+        stream: S,
+        compression_format: CompressionFormat,
+        compression_level: CompressionLevel,
+    ) -> Self {
+        Self {
+            stream: Some(stream),
+            state: WriterState::StartOfHeader,
+            compressed_stream: None,
+            compression_format,
+            compression_level,
+            version: DEFAULT_VERSION.to_string(),
+            header_formatter: HeaderFormatter::new(),
+            header_buffer: Vec::new(),
+            record_id: String::new(),
+            block_length: 0,
+            block_amount_written: 0,
+        }
+    }
+
+    /// Returns the formatter for headers.
+    pub fn header_formatter(&self) -> &HeaderFormatter {
+        &self.header_formatter
+    }
+
+    /// Sets the formatter for headers.
+    pub fn set_header_formatter(&mut self, header_formatter: HeaderFormatter) {
+        self.header_formatter = header_formatter;
+    }
+
+    /// Returns the WARC version string used when writing headers.
+    ///
+    /// Default: [DEFAULT_VERSION]
+    pub fn version(&self) -> &str {
+        self.version.as_ref()
+    }
+
+    /// Sets the WARC version string used when writing headers.
+    pub fn set_version(&mut self, version: String) {
+        self.version = version;
+    }
+
+    /// Returns the wrapped stream.
+    ///
+    /// Panics if the writer is in the middle of writing a record.
+    pub fn into_inner(self) -> S {
+        self.stream.unwrap()
+    }
+
+    /// Begins a record by writing the header.
+    ///
+    /// Writes the WARC version, the header as name-value fields, and
+    /// the ending newline.
+    ///
+    /// The caller must call [Self::write_block] next to advance the stream.
+    ///
+    /// Panics when called out of sequence.
+    pub async fn begin_record(&mut self, header: &HeaderMap) -> Result<(), WARCError> {
+        assert!(self.state == WriterState::StartOfHeader);
+        assert!(self.stream.is_some());
+        assert!(self.compressed_stream.is_none());
+
+        tracing::debug!("begin_record");
+
+        self.create_compressor()?;
+        self.write_header(header).await?;
+        self.prepare_for_block_write(header)?;
+
+        self.state = WriterState::EndOfHeader;
+
+        Ok(())
+    }
+
+    fn create_compressor(&mut self) -> Result<(), WARCError> {
+        tracing::debug!("create_compressor");
+
+        let stream = self.stream.take().unwrap();
+        let stream = AsyncCompressor::new(stream, self.compression_format, self.compression_level)?;
+        self.compressed_stream = Some(stream);
+
+        Ok(())
+    }
+
+    async fn write_header(&mut self, header: &HeaderMap) -> Result<(), WARCError> {
+        tracing::debug!("write_header");
+
+        self.header_buffer.clear();
+        self.header_buffer
+            .extend_from_slice(self.version.as_bytes());
+        self.header_buffer.extend_from_slice(b"\r\n");
+        if let Err(error) = self
+            .header_formatter
+            .format_header(header, &mut self.header_buffer)
+        {
+            return Err(WARCError::MalformedHeader {
+                offset: 0,
+                source: Some(Box::new(error)),
+            });
+        }
+        self.header_buffer.extend_from_slice(b"\r\n");
+
+        let stream = self.compressed_stream.as_mut().unwrap();
+        stream.write_all(&self.header_buffer).await?;
+
+        Ok(())
+    }
+
+    fn prepare_for_block_write(&mut self, header: &HeaderMap) -> Result<(), WARCError> {
+        self.record_id = header
+            .get_str("WARC-Record-Id")
+            .unwrap_or_default()
+            .to_string();
+        self.block_length = header.get_parsed_required("Content-Length")?;
+        self.block_amount_written = 0;
+
+        tracing::debug!(block_length = self.block_length, "prepare_for_block_write");
+
+        Ok(())
+    }
+
+    /// Starts writing a record body.
+    ///
+    /// The caller must write all the block contents and then call [Self::end_record].
+    /// The amount of bytes written must match `Content-Length` in the name-value fields.
+    ///
+    /// Panics when called out of sequence.
+    pub fn write_block(&mut self) -> AsyncBlockWriter<'_, S> {
+        assert!(self.state == WriterState::EndOfHeader);
+        tracing::debug!("write_block");
+
+        self.state = WriterState::InBlock;
+
+        AsyncBlockWriter {
+            stream: self.compressed_stream.as_mut().unwrap(),
+            num_bytes_written: &mut self.block_amount_written,
+        }
+    }
+
+    /// Finish writing a record.
+    ///
+    /// Panics when called out of sequence.
+    pub async fn end_record(&mut self) -> Result<(), WARCError> {
+        assert!(self.state == WriterState::InBlock);
+        tracing::debug!("end_record");
+        assert!(self.stream.is_none());
+        assert!(self.compressed_stream.is_some());
+
+        self.check_block_length()?;
+
+        let mut stream = self.compressed_stream.take().unwrap();
+        stream.write_all(b"\r\n\r\n").await?;
+        let mut stream = stream.finish().await?;
+        stream.flush().await?;
+        self.stream = Some(stream);
+        self.state = WriterState::StartOfHeader;
+
+        Ok(())
+    }
+
+    fn check_block_length(&self) -> Result<(), WARCError> {
+        tracing::debug!(
+            bytes_written = self.block_amount_written,
+            block_length = self.block_length,
+            "check_block_length"
+        );
+
+        if self.block_amount_written != self.block_length {
+            return Err(WARCError::WrongBlockLength {
+                record_id: self.record_id.clone(),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// Async writer stream for a record body.
+pub struct AsyncBlockWriter<'a, S: AsyncWrite + Unpin> {
+    stream: &'a mut AsyncCompressor<S>,
+    num_bytes_written: &'a mut u64,
+}
+
+impl<'a, S: AsyncWrite + Unpin> AsyncWrite for AsyncBlockWriter<'a, S> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let result = Pin::new(&mut *self.stream).poll_write(cx, buf);
+
+        if let Poll::Ready(Ok(amount)) = result {
+            *self.num_bytes_written += amount as u64;
+        }
+
+        result
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut *self.stream).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut *self.stream).poll_flush(cx)
+    }
+}
@@ -0,0 +1,307 @@
+//! Deduplication of records with identical payloads.
+//!
+//! Payload digests of written records are kept in a [DigestStore]. When a
+//! record with the same payload digest and target URI is written again, a
+//! [Deduplicator] replaces it with a `revisit` record that refers to the
+//! original record.
+use std::{io::Write, path::Path};
+
+use rusqlite::{Connection, OptionalExtension};
+
+use super::{
+    HeaderMapExt, RevisitRecordBuilder, WARCError, WARCRecord, WARCRecordType, WARCWriter,
+    IDENTICAL_PAYLOAD_DIGEST_PROFILE,
+};
+
+const APP_ID: i64 = -826887662;
+
+/// Errors during deduplication.
+#[derive(thiserror::Error, Debug)]
+pub enum DedupError {
+    /// The file is not a digest store.
+    #[error("not a digest store database")]
+    InvalidDatabase,
+
+    /// Database error.
+    #[error(transparent)]
+    Database(#[from] rusqlite::Error),
+
+    /// WARC error.
+    #[error(transparent)]
+    Warc(#[from] WARCError),
+}
+
+/// Information about a previously written record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DigestEntry {
+    /// Value of `WARC-Record-ID`.
+    pub record_id: String,
+    /// Value of `WARC-Target-URI`.
+    pub target_uri: String,
+    /// Value of `WARC-Date`.
+    pub date: String,
+}
+
+/// Persistent store of payload digests.
+///
+/// Entries are keyed by the payload digest and target URI.
+pub struct DigestStore {
+    db: Connection,
+}
+
+impl DigestStore {
+    /// Opens or creates a database file at the given path.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, DedupError> {
+        let exists = path.as_ref().exists();
+
+        let connection = Connection::open(path)?;
+
+        let store = Self { db: connection };
+
+        if exists {
+            store.check_app_id()?;
+        }
+
+        store.migrate_db()?;
+
+        Ok(store)
+    }
+
+    /// Creates a temporary database in memory.
+    pub fn open_in_memory() -> Result<Self, DedupError> {
+        let store = Self {
+            db: Connection::open_in_memory()?,
+        };
+        store.migrate_db()?;
+
+        Ok(store)
+    }
+
+    fn check_app_id(&self) -> Result<(), DedupError> {
+        let mut app_id = 0;
+        self.db.pragma_query(None, "application_id", |row| {
+            app_id = row.get::<_, i64>(0)?;
+            Ok(())
+        })?;
+
+        if app_id == APP_ID {
+            Ok(())
+        } else {
+            Err(DedupError::InvalidDatabase)
+        }
+    }
+
+    fn migrate_db(&self) -> Result<(), DedupError> {
+        let mut version: i64 = 0;
+        self.db.pragma_query(None, "user_version", |row| {
+            version = row.get(0)?;
+            Ok(())
+        })?;
+
+        tracing::debug!(version, "migrate_db");
+
+        static DIR: include_dir::Dir =
+            include_dir::include_dir!("$CARGO_MANIFEST_DIR/migrations/dedup/");
+        let mut file_versions = Vec::new();
+
+        for file in DIR.files() {
+            let filename = file.path().file_name().unwrap().to_str().unwrap();
+            let (file_version, _) = filename.split_once('-').unwrap();
+            let file_version: i64 = file_version.parse().unwrap();
+
+            file_versions.push((file_version, file));
+        }
+
+        file_versions.sort_unstable_by_key(|item| item.0);
+
+        for (file_version, file) in file_versions {
+            if file_version > version {
+                tracing::info!(version = file_version, "migrate database");
+                self.db.execute_batch(file.contents_utf8().unwrap())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the record with the given payload digest and target URI.
+    pub fn get(
+        &self,
+        payload_digest: &str,
+        target_uri: &str,
+    ) -> Result<Option<DigestEntry>, DedupError> {
+        let entry = self
+            .db
+            .query_row(
+                "SELECT record_id, date FROM payload_digests
+                WHERE payload_digest = ?1 AND target_uri = ?2",
+                [payload_digest, target_uri],
+                |row| {
+                    Ok(DigestEntry {
+                        record_id: row.get(0)?,
+                        target_uri: target_uri.to_string(),
+                        date: row.get(1)?,
+                    })
+                },
+            )
+            .optional()?;
+
+        Ok(entry)
+    }
+
+    /// Adds a record with the given payload digest.
+    ///
+    /// If an entry already exists, it is not replaced.
+    pub fn insert(&self, payload_digest: &str, entry: &DigestEntry) -> Result<(), DedupError> {
+        self.db.execute(
+            "INSERT OR IGNORE INTO payload_digests
+            (payload_digest, target_uri, record_id, date)
+            VALUES (?1, ?2, ?3, ?4)",
+            [
+                payload_digest,
+                &entry.target_uri,
+                &entry.record_id,
+                &entry.date,
+            ],
+        )?;
+
+        Ok(())
+    }
+}
+
+/// Replaces records having duplicate payloads with revisit records.
+///
+/// Only `response` records with the `WARC-Payload-Digest` and
+/// `WARC-Target-URI` fields are considered. The payload digest must be
+/// present in the record given to the deduplicator; it is not computed
+/// when the record is written.
+pub struct Deduplicator {
+    store: DigestStore,
+}
+
+impl Deduplicator {
+    /// Creates a `Deduplicator` with the given digest store.
+    pub fn new(store: DigestStore) -> Self {
+        Self { store }
+    }
+
+    /// Returns a reference to the digest store.
+    pub fn store(&self) -> &DigestStore {
+        &self.store
+    }
+
+    /// Returns the digest store.
+    pub fn into_inner(self) -> DigestStore {
+        self.store
+    }
+
+    /// Checks whether the given record is a duplicate.
+    ///
+    /// If the payload was seen before, a revisit record with the
+    /// [IDENTICAL_PAYLOAD_DIGEST_PROFILE] is returned. The revisit record
+    /// keeps the record ID, date, and HTTP header of the given record.
+    /// Otherwise, the record is added to the store and `None` is returned.
+    pub fn deduplicate(&self, record: &WARCRecord) -> Result<Option<WARCRecord>, DedupError> {
+        let header = &record.header;
+
+        let record_type = header
+            .get_str("WARC-Type")
+            .and_then(|value| value.parse::<WARCRecordType>().ok());
+
+        if record_type != Some(WARCRecordType::Response) {
+            return Ok(None);
+        }
+
+        let (payload_digest, target_uri) = match (
+            header.get_str("WARC-Payload-Digest"),
+            header.get_str("WARC-Target-URI"),
+        ) {
+            (Some(payload_digest), Some(target_uri)) => (payload_digest, target_uri),
+            _ => return Ok(None),
+        };
+
+        match self.store.get(payload_digest, target_uri)? {
+            Some(entry) => {
+                tracing::debug!(
+                    target_uri,
+                    payload_digest,
+                    refers_to = %entry.record_id,
+                    "duplicate payload"
+                );
+
+                Ok(Some(make_revisit_record(record, &entry)?))
+            }
+            None => {
+                self.store.insert(
+                    payload_digest,
+                    &DigestEntry {
+                        record_id: header.get_required("WARC-Record-ID")?.to_string(),
+                        target_uri: target_uri.to_string(),
+                        date: header.get_required("WARC-Date")?.to_string(),
+                    },
+                )?;
+
+                Ok(None)
+            }
+        }
+    }
+
+    /// Writes the given record or a revisit record if it is a duplicate.
+    ///
+    /// Returns whether a revisit record was written.
+    pub fn write_record<S: Write>(
+        &self,
+        writer: &mut WARCWriter<'_, S>,
+        record: &WARCRecord,
+    ) -> Result<bool, DedupError> {
+        match self.deduplicate(record)? {
+            Some(revisit) => {
+                writer.write_record(&revisit)?;
+                Ok(true)
+            }
+            None => {
+                writer.write_record(record)?;
+                Ok(false)
+            }
+        }
+    }
+}
+
+fn make_revisit_record(record: &WARCRecord, entry: &DigestEntry) -> Result<WARCRecord, WARCError> {
+    let header = &record.header;
+    let target_uri = header.get_required("WARC-Target-URI")?;
+
+    let mut builder = RevisitRecordBuilder::new(target_uri, IDENTICAL_PAYLOAD_DIGEST_PROFILE)
+        .record_id(header.get_required("WARC-Record-ID")?)
+        .field("WARC-Date", header.get_required("WARC-Date")?)
+        .refers_to(&entry.record_id)
+        .refers_to_target_uri(&entry.target_uri)
+        .refers_to_date(&entry.date);
+
+    for name in [
+        "WARC-Warcinfo-ID",
+        "WARC-Concurrent-To",
+        "WARC-IP-Address",
+        "WARC-Payload-Digest",
+        "Content-Type",
+    ] {
+        for value in header.get_all(name) {
+            builder = builder.append_field(name, &value.text);
+        }
+    }
+
+    builder.payload(http_header_slice(&record.block)).build()
+}
+
+// Returns the HTTP header portion of a HTTP message.
+fn http_header_slice(block: &[u8]) -> &[u8] {
+    for (index, window) in block.windows(4).enumerate() {
+        if window == b"\r\n\r\n" {
+            return &block[0..index + 4];
+        } else if window.starts_with(b"\n\n") {
+            return &block[0..index + 2];
+        }
+    }
+
+    block
+}
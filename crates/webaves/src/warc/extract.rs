@@ -2,12 +2,15 @@
 
 use std::io::Read;
 
+use regex::Regex;
+use url::Url;
+
 use crate::{
-    http::{field::MediaType, MessageReader},
+    http::{field::MediaType, MessageReader, ResponseHeader},
     io::ComboReader,
 };
 
-use super::{HeaderMapExt, HeaderMetadata};
+use super::{index::get_media_type, HeaderMapExt, HeaderMetadata};
 
 /// Determine whether an extractor can handle a record.
 pub trait Classifier {
@@ -29,6 +32,13 @@ pub trait Extractor<S: Read>: Read {
     fn finish(self) -> Result<S, crate::error::Error>;
     /// Checks for any errors and returns the wrapped stream.
     fn finish_box(self: Box<Self>) -> Result<S, crate::error::Error>;
+
+    /// Returns the media type of the document without parameters.
+    ///
+    /// The default implementation returns the media type of the record.
+    fn media_type(&self, metadata: &HeaderMetadata) -> Option<String> {
+        get_media_type(metadata.fields().get_str("Content-Type"))
+    }
 }
 
 /// Creates an extractor.
@@ -40,6 +50,7 @@ pub struct ExtractorDispatcher<'a, S: Read> {
     source: Option<S>,
     extractor: Option<Box<dyn 'a + Extractor<S>>>,
     extractors: Vec<(Box<dyn 'a + Classifier>, ExtractorFactory<'a, S>)>,
+    filter: Option<&'a ExtractionFilter>,
 }
 
 impl<'a, S: 'a + Read> ExtractorDispatcher<'a, S> {
@@ -49,6 +60,7 @@ impl<'a, S: 'a + Read> ExtractorDispatcher<'a, S> {
             source: Some(source),
             extractor: None,
             extractors: Vec::new(),
+            filter: None,
         }
     }
 
@@ -97,6 +109,11 @@ impl<'a, S: 'a + Read> ExtractorDispatcher<'a, S> {
         );
     }
 
+    /// Sets the filter that selects the documents to extract.
+    pub fn set_filter(&mut self, filter: &'a ExtractionFilter) {
+        self.filter = Some(filter);
+    }
+
     /// Returns whether any contained extractors can extract a document with the given header.
     pub fn can_accept_any(&self, metadata: &HeaderMetadata) -> bool {
         self.extractors
@@ -107,14 +124,30 @@ impl<'a, S: 'a + Read> ExtractorDispatcher<'a, S> {
     /// Sets up this object to extract a document with the given header.
     ///
     /// This function must be called before calling [Self::read].
-    pub fn begin(&mut self, metadata: &HeaderMetadata) -> Result<(), crate::error::Error> {
+    ///
+    /// Returns false if the document is rejected by the filter. In that
+    /// case, the document should not be read and the wrapped stream can be
+    /// obtained with [Self::into_inner].
+    pub fn begin(&mut self, metadata: &HeaderMetadata) -> Result<bool, crate::error::Error> {
         for (classifier, factory) in &self.extractors {
             if classifier.can_accept(metadata) {
                 let extractor = factory(self.source.take().unwrap())?;
+                let accepted = match self.filter {
+                    Some(filter) => {
+                        let url = metadata
+                            .fields()
+                            .get_parsed::<Url>("WARC-Target-URI")
+                            .unwrap_or_default();
+                        let media_type = extractor.media_type(metadata);
+
+                        filter.is_accepted(url.as_ref(), media_type.as_deref())
+                    }
+                    None => true,
+                };
 
                 self.extractor = Some(extractor);
 
-                return Ok(());
+                return Ok(accepted);
             }
         }
 
@@ -224,15 +257,21 @@ impl Classifier for HTTPClassifier {
 /// Extracts from WARC "response" records with media type "application/http".
 pub struct HTTPExtractor<'a, S: Read> {
     reader: MessageReader<'a, ComboReader<S>>,
+    header: ResponseHeader,
 }
 
 impl<'a, S: Read> HTTPExtractor<'a, S> {
     /// Creates a new `HTTPExtractor` with the given input stream.
     pub fn new(source: S) -> Result<Self, crate::error::Error> {
         let mut reader = MessageReader::new(ComboReader::new(source));
-        reader.begin_response(None)?;
+        let header = reader.begin_response(None)?;
 
-        Ok(Self { reader })
+        Ok(Self { reader, header })
+    }
+
+    /// Returns the parsed HTTP response header.
+    pub fn response_header(&self) -> &ResponseHeader {
+        &self.header
     }
 }
 
@@ -262,6 +301,10 @@ impl<'a, S: Read> Extractor<S> for HTTPExtractor<'a, S> {
         self.reader.end_message()?;
         Ok(self.reader.into_inner().into_inner())
     }
+
+    fn media_type(&self, _metadata: &HeaderMetadata) -> Option<String> {
+        get_media_type(self.header.fields.get_str("Content-Type"))
+    }
 }
 
 impl<'a, S: Read> Read for HTTPExtractor<'a, S> {
@@ -269,3 +312,186 @@ impl<'a, S: Read> Read for HTTPExtractor<'a, S> {
         self.reader.read_body().read(buf)
     }
 }
+
+/// Selects documents to extract by URL and media type.
+///
+/// The rules are similar to the accept and reject options of Wget. An
+/// accept or reject list is a comma-separated list of:
+///
+/// - media types such as `text/html` or `image/*`, which match the media
+///   type of the document,
+/// - wildcard patterns containing `*`, `?`, or `[`, which match the file
+///   name of the URL,
+/// - otherwise, suffixes such as `html` or `.tar.gz`, which match the end of
+///   the file name of the URL.
+///
+/// The list entries are case-insensitive. The file name of a URL ending with
+/// a slash is `index.html`. Accept and reject patterns are regular
+/// expressions that search the whole URL.
+///
+/// A document is extracted if it matches an entry in the accept lists (or
+/// there are none) and no entry in the reject lists, and its URL matches an
+/// accept pattern (or there are none) and no reject pattern.
+#[derive(Debug, Clone, Default)]
+pub struct ExtractionFilter {
+    accept: Vec<FilterRule>,
+    reject: Vec<FilterRule>,
+    accept_patterns: Vec<Regex>,
+    reject_patterns: Vec<Regex>,
+}
+
+#[derive(Debug, Clone)]
+enum FilterRule {
+    MediaType(String),
+    Wildcard(Regex),
+    Suffix(String),
+}
+
+impl FilterRule {
+    fn parse(value: &str) -> Result<Self, regex::Error> {
+        if value.contains('/') {
+            Ok(Self::MediaType(value.to_ascii_lowercase()))
+        } else if value.contains(['*', '?', '[']) {
+            Ok(Self::Wildcard(Regex::new(&wildcard_to_regex(value))?))
+        } else {
+            Ok(Self::Suffix(value.to_ascii_lowercase()))
+        }
+    }
+
+    fn matches(&self, filename: &str, media_type: Option<&str>) -> bool {
+        match self {
+            Self::MediaType(value) => match (value.strip_suffix("/*"), media_type) {
+                (Some(type_), Some(media_type)) => media_type
+                    .split_once('/')
+                    .map(|(other_type, _)| other_type == type_)
+                    .unwrap_or_default(),
+                (None, Some(media_type)) => media_type == value,
+                (_, None) => false,
+            },
+            Self::Wildcard(regex) => regex.is_match(filename),
+            Self::Suffix(value) => filename.to_ascii_lowercase().ends_with(value.as_str()),
+        }
+    }
+}
+
+impl ExtractionFilter {
+    /// Creates a filter that accepts all documents.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns whether the filter has no rules.
+    pub fn is_empty(&self) -> bool {
+        self.accept.is_empty()
+            && self.reject.is_empty()
+            && self.accept_patterns.is_empty()
+            && self.reject_patterns.is_empty()
+    }
+
+    /// Adds a comma-separated list of entries to the accept list.
+    pub fn add_accept(&mut self, list: &str) -> Result<(), regex::Error> {
+        self.accept.extend(parse_rule_list(list)?);
+        Ok(())
+    }
+
+    /// Adds a comma-separated list of entries to the reject list.
+    pub fn add_reject(&mut self, list: &str) -> Result<(), regex::Error> {
+        self.reject.extend(parse_rule_list(list)?);
+        Ok(())
+    }
+
+    /// Adds a regular expression that URLs must match.
+    pub fn add_accept_pattern(&mut self, pattern: &str) -> Result<(), regex::Error> {
+        self.accept_patterns.push(Regex::new(pattern)?);
+        Ok(())
+    }
+
+    /// Adds a regular expression that URLs must not match.
+    pub fn add_reject_pattern(&mut self, pattern: &str) -> Result<(), regex::Error> {
+        self.reject_patterns.push(Regex::new(pattern)?);
+        Ok(())
+    }
+
+    /// Returns whether a document with the given URL and media type should
+    /// be extracted.
+    ///
+    /// The media type should not include parameters.
+    pub fn is_accepted(&self, url: Option<&Url>, media_type: Option<&str>) -> bool {
+        let filename = url.map(url_filename).unwrap_or_default();
+        let url = url.map(|url| url.as_str()).unwrap_or_default();
+        let media_type = media_type.map(|value| value.to_ascii_lowercase());
+        let media_type = media_type.as_deref();
+
+        (self.accept.is_empty()
+            || self
+                .accept
+                .iter()
+                .any(|rule| rule.matches(&filename, media_type)))
+            && !self
+                .reject
+                .iter()
+                .any(|rule| rule.matches(&filename, media_type))
+            && (self.accept_patterns.is_empty()
+                || self.accept_patterns.iter().any(|regex| regex.is_match(url)))
+            && !self.reject_patterns.iter().any(|regex| regex.is_match(url))
+    }
+}
+
+fn parse_rule_list(list: &str) -> Result<Vec<FilterRule>, regex::Error> {
+    list.split(',')
+        .map(|value| value.trim())
+        .filter(|value| !value.is_empty())
+        .map(FilterRule::parse)
+        .collect()
+}
+
+fn url_filename(url: &Url) -> String {
+    let segment = url
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .unwrap_or_default();
+
+    if segment.is_empty() {
+        return "index.html".to_string();
+    }
+
+    percent_encoding::percent_decode_str(segment)
+        .decode_utf8_lossy()
+        .to_string()
+}
+
+// Wildcards are `*`, `?`, and bracket expressions like `[a-z]` or `[!0-9]`.
+fn wildcard_to_regex(pattern: &str) -> String {
+    let mut output = String::from("(?i)^");
+    let mut chars = pattern.chars().peekable();
+
+    while let Some(char_) = chars.next() {
+        match char_ {
+            '*' => output.push_str(".*"),
+            '?' => output.push('.'),
+            '[' => {
+                output.push('[');
+
+                if chars.next_if_eq(&'!').is_some() {
+                    output.push('^');
+                }
+
+                for char_ in chars.by_ref() {
+                    if char_ == ']' {
+                        break;
+                    } else if char_ == '\\' || char_ == '[' {
+                        output.push('\\');
+                    }
+
+                    output.push(char_);
+                }
+
+                output.push(']');
+            }
+            _ => output.push_str(&regex::escape(char_.encode_utf8(&mut [0; 4]))),
+        }
+    }
+
+    output.push('$');
+    output
+}
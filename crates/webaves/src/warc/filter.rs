@@ -0,0 +1,614 @@
+//! Selection of records using filter expressions.
+//!
+//! A [FilterExpression] is parsed from text such as:
+//!
+//! ```text
+//! WARC-Type == response and http.mime == text/html and http.status >= 200 and http.status < 300
+//! ```
+//!
+//! A condition compares a field with a value using `==`, `!=`, `~` (regular
+//! expression search), `!~`, `<`, `<=`, `>`, or `>=`. The ordering operators
+//! compare numbers. Conditions are combined with `and`, `or`, `not`, and
+//! parentheses. Values containing spaces, parentheses, or operator characters
+//! must be enclosed in double quotes.
+//!
+//! A field is a WARC header field name (case-insensitive) or one of:
+//!
+//! - `http.status`: status code of the HTTP response
+//! - `http.mime`: media type of the HTTP response without parameters
+//! - `url.host`: host of the `WARC-Target-URI`
+//!
+//! A condition on a missing field is false, except for `!=` and `!~` which
+//! are true. If a field occurs multiple times, the condition is true if any
+//! value matches.
+//!
+//! [RecordFilter] copies the records that match an expression to another
+//! file.
+use std::{
+    borrow::Cow,
+    collections::HashSet,
+    io::{Read, Seek, SeekFrom, Write},
+    str::FromStr,
+};
+
+use regex::Regex;
+use url::Url;
+
+use crate::{header::HeaderMap, http::ResponseHeader};
+
+use super::{
+    extract::HTTPExtractor,
+    index::{get_media_type, is_http_response, trim_angle_brackets},
+    pack::{copy_record, is_same_encoding, raw_header_formatter, scan_records, ScannedRecord},
+    HeaderMetadata, WARCError, WARCReader, WARCWriter,
+};
+
+/// Errors during filtering.
+#[derive(thiserror::Error, Debug)]
+pub enum FilterError {
+    /// The filter expression could not be parsed.
+    #[error("invalid filter expression: {message} (at position {position})")]
+    InvalidExpression {
+        /// Position of the character in the expression.
+        position: usize,
+        /// Description of the error.
+        message: String,
+    },
+
+    /// A regular expression in the filter expression is invalid.
+    #[error(transparent)]
+    InvalidRegex(#[from] regex::Error),
+
+    /// WARC error.
+    #[error(transparent)]
+    Warc(#[from] WARCError),
+
+    /// IO error.
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+    Equal,
+    NotEqual,
+    Match,
+    NotMatch,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Word(String),
+    Quoted(String),
+    Operator(Operator),
+    Open,
+    Close,
+}
+
+#[derive(Debug, Clone)]
+struct Condition {
+    field: String,
+    operator: Operator,
+    value: String,
+    regex: Option<Regex>,
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    And(Box<Node>, Box<Node>),
+    Or(Box<Node>, Box<Node>),
+    Not(Box<Node>),
+    Condition(Condition),
+}
+
+/// Parsed filter expression.
+///
+/// See the [module](self) documentation for the syntax.
+#[derive(Debug, Clone)]
+pub struct FilterExpression {
+    root: Node,
+}
+
+impl FilterExpression {
+    /// Returns whether the expression uses fields of the HTTP response.
+    pub fn uses_http(&self) -> bool {
+        uses_http(&self.root)
+    }
+
+    /// Returns whether a record with the given WARC header and HTTP response
+    /// header matches the expression.
+    pub fn matches(&self, fields: &HeaderMap, http_header: Option<&ResponseHeader>) -> bool {
+        evaluate(&self.root, fields, http_header)
+    }
+}
+
+impl FromStr for FilterExpression {
+    type Err = FilterError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let tokens = tokenize(s)?;
+        let mut parser = Parser {
+            tokens,
+            index: 0,
+            length: s.len(),
+        };
+        let root = parser.parse_or()?;
+
+        if parser.index < parser.tokens.len() {
+            return Err(parser.error("unexpected token"));
+        }
+
+        Ok(Self { root })
+    }
+}
+
+fn tokenize(text: &str) -> Result<Vec<(usize, Token)>, FilterError> {
+    let mut tokens = Vec::new();
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((position, c)) = chars.next() {
+        let token = match c {
+            _ if c.is_whitespace() => continue,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '"' => {
+                let mut value = String::new();
+
+                loop {
+                    match chars.next() {
+                        Some((_, '"')) => break,
+                        Some((_, '\\')) => match chars.next_if(|(_, c)| *c == '"' || *c == '\\') {
+                            Some((_, c)) => value.push(c),
+                            None => value.push('\\'),
+                        },
+                        Some((_, c)) => value.push(c),
+                        None => {
+                            return Err(FilterError::InvalidExpression {
+                                position,
+                                message: "unterminated string".to_string(),
+                            })
+                        }
+                    }
+                }
+
+                Token::Quoted(value)
+            }
+            '=' | '!' | '~' | '<' | '>' => {
+                let next = chars.next_if(|(_, c)| *c == '=' || *c == '~');
+                let operator = match (c, next.map(|(_, c)| c)) {
+                    ('=', Some('=')) => Operator::Equal,
+                    ('!', Some('=')) => Operator::NotEqual,
+                    ('~', None) => Operator::Match,
+                    ('!', Some('~')) => Operator::NotMatch,
+                    ('<', None) => Operator::Less,
+                    ('<', Some('=')) => Operator::LessEqual,
+                    ('>', None) => Operator::Greater,
+                    ('>', Some('=')) => Operator::GreaterEqual,
+                    _ => {
+                        return Err(FilterError::InvalidExpression {
+                            position,
+                            message: "unknown operator".to_string(),
+                        })
+                    }
+                };
+
+                Token::Operator(operator)
+            }
+            _ => {
+                let mut value = c.to_string();
+
+                while let Some((_, c)) =
+                    chars.next_if(|(_, c)| !c.is_whitespace() && !"()\"=!~<>".contains(*c))
+                {
+                    value.push(c);
+                }
+
+                Token::Word(value)
+            }
+        };
+
+        tokens.push((position, token));
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    index: usize,
+    length: usize,
+}
+
+impl Parser {
+    fn error(&self, message: &str) -> FilterError {
+        FilterError::InvalidExpression {
+            position: self
+                .tokens
+                .get(self.index)
+                .map(|(position, _)| *position)
+                .unwrap_or(self.length),
+            message: message.to_string(),
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.index).map(|(_, token)| token)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.peek().cloned();
+        self.index += 1;
+        token
+    }
+
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Word(word)) if word.eq_ignore_ascii_case(keyword))
+    }
+
+    fn parse_or(&mut self) -> Result<Node, FilterError> {
+        let mut node = self.parse_and()?;
+
+        while self.peek_keyword("or") {
+            self.index += 1;
+            node = Node::Or(Box::new(node), Box::new(self.parse_and()?));
+        }
+
+        Ok(node)
+    }
+
+    fn parse_and(&mut self) -> Result<Node, FilterError> {
+        let mut node = self.parse_unary()?;
+
+        while self.peek_keyword("and") {
+            self.index += 1;
+            node = Node::And(Box::new(node), Box::new(self.parse_unary()?));
+        }
+
+        Ok(node)
+    }
+
+    fn parse_unary(&mut self) -> Result<Node, FilterError> {
+        if self.peek_keyword("not") {
+            self.index += 1;
+            return Ok(Node::Not(Box::new(self.parse_unary()?)));
+        }
+
+        if self.peek() == Some(&Token::Open) {
+            self.index += 1;
+            let node = self.parse_or()?;
+
+            if self.peek() != Some(&Token::Close) {
+                return Err(self.error("expected closing parenthesis"));
+            }
+
+            self.index += 1;
+            return Ok(node);
+        }
+
+        self.parse_condition()
+    }
+
+    fn parse_condition(&mut self) -> Result<Node, FilterError> {
+        let field = match self.peek() {
+            Some(Token::Word(word)) => word.to_ascii_lowercase(),
+            _ => return Err(self.error("expected field name")),
+        };
+        self.index += 1;
+
+        let operator = match self.peek() {
+            Some(Token::Operator(operator)) => *operator,
+            _ => return Err(self.error("expected operator")),
+        };
+        self.index += 1;
+
+        let value_index = self.index;
+        let value = match self.next() {
+            Some(Token::Word(value)) | Some(Token::Quoted(value)) => value,
+            _ => {
+                self.index = value_index;
+                return Err(self.error("expected value"));
+            }
+        };
+
+        let regex = match operator {
+            Operator::Match | Operator::NotMatch => Some(Regex::new(&value)?),
+            _ => None,
+        };
+
+        if is_numeric_operator(operator) && value.parse::<f64>().is_err() {
+            self.index = value_index;
+            return Err(self.error("expected number"));
+        }
+
+        Ok(Node::Condition(Condition {
+            field,
+            operator,
+            value,
+            regex,
+        }))
+    }
+}
+
+fn is_numeric_operator(operator: Operator) -> bool {
+    matches!(
+        operator,
+        Operator::Less | Operator::LessEqual | Operator::Greater | Operator::GreaterEqual
+    )
+}
+
+fn uses_http(node: &Node) -> bool {
+    match node {
+        Node::And(left, right) | Node::Or(left, right) => uses_http(left) || uses_http(right),
+        Node::Not(node) => uses_http(node),
+        Node::Condition(condition) => condition.field.starts_with("http."),
+    }
+}
+
+fn evaluate(node: &Node, fields: &HeaderMap, http_header: Option<&ResponseHeader>) -> bool {
+    match node {
+        Node::And(left, right) => {
+            evaluate(left, fields, http_header) && evaluate(right, fields, http_header)
+        }
+        Node::Or(left, right) => {
+            evaluate(left, fields, http_header) || evaluate(right, fields, http_header)
+        }
+        Node::Not(node) => !evaluate(node, fields, http_header),
+        Node::Condition(condition) => {
+            let values = field_values(&condition.field, fields, http_header);
+
+            match condition.operator {
+                Operator::NotEqual => !values.iter().any(|value| value == &condition.value),
+                Operator::NotMatch => {
+                    let regex = condition.regex.as_ref().unwrap();
+                    !values.iter().any(|value| regex.is_match(value))
+                }
+                _ => values.iter().any(|value| compare(condition, value)),
+            }
+        }
+    }
+}
+
+fn compare(condition: &Condition, value: &str) -> bool {
+    match condition.operator {
+        Operator::Equal => value == condition.value,
+        Operator::Match => condition.regex.as_ref().unwrap().is_match(value),
+        Operator::NotEqual | Operator::NotMatch => unreachable!(),
+        operator => {
+            let (value, other) = match (value.trim().parse::<f64>(), condition.value.parse::<f64>())
+            {
+                (Ok(value), Ok(other)) => (value, other),
+                _ => return false,
+            };
+
+            match operator {
+                Operator::Less => value < other,
+                Operator::LessEqual => value <= other,
+                Operator::Greater => value > other,
+                Operator::GreaterEqual => value >= other,
+                _ => unreachable!(),
+            }
+        }
+    }
+}
+
+fn field_values<'a>(
+    name: &str,
+    fields: &'a HeaderMap,
+    http_header: Option<&ResponseHeader>,
+) -> Vec<Cow<'a, str>> {
+    match name {
+        "http.status" => http_header
+            .map(|header| Cow::Owned(header.status_line.status_code.to_string()))
+            .into_iter()
+            .collect(),
+        "http.mime" => http_header
+            .and_then(|header| get_media_type(header.fields.get_str("Content-Type")))
+            .map(Cow::Owned)
+            .into_iter()
+            .collect(),
+        "url.host" => fields
+            .get_str("WARC-Target-URI")
+            .and_then(|url| Url::parse(trim_angle_brackets(url)).ok())
+            .and_then(|url| url.host_str().map(|host| Cow::Owned(host.to_string())))
+            .into_iter()
+            .collect(),
+        _ => fields
+            .get_all(name)
+            .map(|value| Cow::Borrowed(value.text.as_str()))
+            .collect(),
+    }
+}
+
+// Match result and relations of a record found in the first pass.
+struct RecordEntry {
+    is_match: bool,
+    record_id: Option<String>,
+    concurrent_to: Vec<String>,
+}
+
+/// Copies records that match a filter expression to a writer.
+///
+/// The input is read twice: first to select the records and then to copy
+/// them. When a record is compressed as a separate member in the same
+/// format as the output, the compressed data is copied as is. Otherwise,
+/// the record is decompressed and written with the unchanged header.
+pub struct RecordFilter {
+    expression: FilterExpression,
+    include_concurrent: bool,
+}
+
+impl RecordFilter {
+    /// Creates a `RecordFilter` with the given expression.
+    pub fn new(expression: FilterExpression) -> Self {
+        Self {
+            expression,
+            include_concurrent: false,
+        }
+    }
+
+    /// Returns the filter expression.
+    pub fn expression(&self) -> &FilterExpression {
+        &self.expression
+    }
+
+    /// Returns whether records related to a matching record by
+    /// `WARC-Concurrent-To` are also copied.
+    ///
+    /// Default: false
+    pub fn include_concurrent(&self) -> bool {
+        self.include_concurrent
+    }
+
+    /// Sets whether records related to a matching record by
+    /// `WARC-Concurrent-To` are also copied.
+    ///
+    /// This includes the records that a matching record refers to, and the
+    /// records that refer to a matching record, such as `request` records
+    /// of a `response` record.
+    pub fn set_include_concurrent(&mut self, value: bool) {
+        self.include_concurrent = value;
+    }
+
+    /// Copies the matching records from the stream to the writer.
+    ///
+    /// The stream is read from the beginning.
+    ///
+    /// Records are written with their raw header values and WARC version,
+    /// so this function leaves a raw header formatter set on the writer and
+    /// changes its version. Set them again before writing other records.
+    ///
+    /// Returns the number of records copied.
+    pub fn filter_stream<S: Read + Seek, W: Write>(
+        &self,
+        mut stream: S,
+        writer: &mut WARCWriter<'_, W>,
+    ) -> Result<u64, FilterError> {
+        stream.seek(SeekFrom::Start(0))?;
+
+        let mut reader = WARCReader::new(&mut stream)?;
+        let entries = scan_records(&mut reader, |metadata, block| {
+            self.scan_record(metadata, block)
+        })?;
+        let is_same_encoding = is_same_encoding(
+            &reader,
+            writer.compression_format(),
+            writer.zstd_dictionary(),
+        );
+        drop(reader);
+
+        let selected = self.select_records(&entries);
+
+        tracing::debug!(
+            record_count = entries.len(),
+            selected_count = selected.len(),
+            "filter_stream"
+        );
+
+        writer.set_header_formatter(raw_header_formatter());
+
+        if selected
+            .iter()
+            .all(|index| entries[*index].is_separate_member)
+        {
+            for index in &selected {
+                let entry = &entries[*index];
+
+                if is_same_encoding {
+                    std::io::copy(&mut entry.raw_reader(&mut stream)?, writer.get_mut())?;
+                } else {
+                    let mut reader = WARCReader::open_at(&mut stream, entry.start)?;
+                    let metadata = reader.begin_record()?.ok_or(WARCError::UnknownFormat)?;
+                    copy_record(&mut reader, &metadata, writer)?;
+                    reader.end_record()?;
+                }
+            }
+        } else {
+            let selected = selected.iter().cloned().collect::<HashSet<usize>>();
+
+            stream.seek(SeekFrom::Start(0))?;
+            let mut reader = WARCReader::new(&mut stream)?;
+            let mut index = 0;
+
+            while let Some(metadata) = reader.begin_record()? {
+                if selected.contains(&index) {
+                    copy_record(&mut reader, &metadata, writer)?;
+                } else {
+                    std::io::copy(&mut reader.read_block(), &mut std::io::sink())?;
+                }
+
+                reader.end_record()?;
+                index += 1;
+            }
+        }
+
+        Ok(selected.len() as u64)
+    }
+
+    fn scan_record<S: Read>(
+        &self,
+        metadata: &HeaderMetadata,
+        block: &mut S,
+    ) -> Result<RecordEntry, FilterError> {
+        let mut http_header = None;
+
+        if self.expression.uses_http() && is_http_response(metadata) {
+            match HTTPExtractor::new(block) {
+                Ok(extractor) => http_header = Some(extractor.response_header().clone()),
+                Err(error) => tracing::debug!(?error, "filter http response"),
+            }
+        }
+
+        let fields = metadata.fields();
+
+        Ok(RecordEntry {
+            is_match: self.expression.matches(fields, http_header.as_ref()),
+            record_id: fields
+                .get_str("WARC-Record-ID")
+                .map(|value| value.to_string()),
+            concurrent_to: fields
+                .get_all("WARC-Concurrent-To")
+                .map(|value| value.text.clone())
+                .collect(),
+        })
+    }
+
+    // Returns the indexes of the records to be copied.
+    fn select_records(&self, entries: &[ScannedRecord<RecordEntry>]) -> Vec<usize> {
+        let mut matched_ids = HashSet::new();
+        let mut related_ids = HashSet::new();
+
+        if self.include_concurrent {
+            for entry in entries.iter().filter(|entry| entry.value.is_match) {
+                if let Some(record_id) = &entry.value.record_id {
+                    matched_ids.insert(record_id.as_str());
+                }
+
+                related_ids.extend(entry.value.concurrent_to.iter().map(|id| id.as_str()));
+            }
+        }
+
+        entries
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| {
+                entry.value.is_match
+                    || entry
+                        .value
+                        .record_id
+                        .as_deref()
+                        .map(|id| related_ids.contains(id))
+                        .unwrap_or_default()
+                    || entry
+                        .value
+                        .concurrent_to
+                        .iter()
+                        .any(|id| matched_ids.contains(id.as_str()))
+            })
+            .map(|(index, _)| index)
+            .collect()
+    }
+}
@@ -0,0 +1,465 @@
+//! Index generation for WARC files.
+//!
+//! Indexes allow replay software to locate records within WARC files
+//! without reading the files sequentially. Each indexed record is
+//! described by an [IndexEntry] which can be formatted as CDXJ lines or
+//! as the legacy space-delimited CDX format.
+use std::io::{Read, Write};
+
+use serde::Serialize;
+use url::{Host, Url};
+
+use crate::{
+    http::{field::MediaType, ResponseHeader},
+    io::SourceCountRead,
+};
+
+use super::{extract::HTTPExtractor, HeaderMapExt, HeaderMetadata, WARCError, WARCReader};
+
+/// WARC record types that are indexed by default.
+pub const DEFAULT_RECORD_TYPES: [&str; 4] = ["response", "revisit", "resource", "metadata"];
+
+/// Information required to locate a record in a WARC file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexEntry {
+    /// Canonicalized URL in SURT form used as the sort key.
+    pub surt: String,
+    /// Date of the record as a 14-digit timestamp (`YYYYMMDDhhmmss`).
+    pub timestamp: String,
+    /// Target URI of the record.
+    pub url: String,
+    /// Media type of the document.
+    pub mime: Option<String>,
+    /// HTTP status code of the response.
+    pub status: Option<u16>,
+    /// Digest of the payload.
+    ///
+    /// SHA-1 digests are stored without the algorithm label.
+    pub digest: Option<String>,
+    /// Target URL of a HTTP redirect.
+    pub redirect: Option<String>,
+    /// Length of the record in the (compressed) file.
+    pub length: u64,
+    /// Position of the record in the (compressed) file.
+    pub offset: u64,
+    /// Name of the WARC file.
+    pub filename: String,
+}
+
+/// Reads records from a WARC file and produces index entries.
+pub struct Indexer<'a, S: Read> {
+    reader: WARCReader<'a, S>,
+    filename: String,
+    record_types: Vec<String>,
+}
+
+impl<'a, S: Read> Indexer<'a, S> {
+    /// Creates an `Indexer` with the given reader and the filename to be
+    /// stored in the entries.
+    pub fn new<F: Into<String>>(reader: WARCReader<'a, S>, filename: F) -> Self {
+        Self {
+            reader,
+            filename: filename.into(),
+            record_types: DEFAULT_RECORD_TYPES
+                .iter()
+                .map(|item| item.to_string())
+                .collect(),
+        }
+    }
+
+    /// Returns the WARC record types that are indexed.
+    ///
+    /// Default: [DEFAULT_RECORD_TYPES]
+    pub fn record_types(&self) -> &[String] {
+        self.record_types.as_ref()
+    }
+
+    /// Sets the WARC record types that are indexed.
+    pub fn set_record_types(&mut self, record_types: Vec<String>) {
+        self.record_types = record_types;
+    }
+
+    /// Returns the wrapped reader.
+    pub fn into_inner(self) -> WARCReader<'a, S> {
+        self.reader
+    }
+
+    /// Reads records until an indexable record is found and returns its entry.
+    ///
+    /// Returns `None` when there are no more records in the stream.
+    pub fn next_entry(&mut self) -> Result<Option<IndexEntry>, WARCError> {
+        loop {
+            let metadata = match self.reader.begin_record()? {
+                Some(metadata) => metadata,
+                None => return Ok(None),
+            };
+
+            let indexable = self.is_indexable(&metadata);
+            let http_header = self.read_block(&metadata, indexable)?;
+
+            self.reader.end_record()?;
+
+            if indexable {
+                let length = self.reader.raw_file_offset()? - metadata.raw_file_offset();
+
+                return Ok(Some(self.make_entry(&metadata, http_header, length)));
+            }
+        }
+    }
+
+    fn is_indexable(&self, metadata: &HeaderMetadata) -> bool {
+        let warc_type = metadata.fields().get_str("WARC-Type").unwrap_or_default();
+
+        metadata.fields().contains_key("WARC-Target-URI")
+            && self.record_types.iter().any(|item| item == warc_type)
+    }
+
+    fn read_block(
+        &mut self,
+        metadata: &HeaderMetadata,
+        indexable: bool,
+    ) -> Result<Option<ResponseHeader>, WARCError> {
+        let mut block = self.reader.read_block();
+        let mut http_header = None;
+
+        if indexable && is_http_response(metadata) {
+            match HTTPExtractor::new(&mut block) {
+                Ok(extractor) => http_header = Some(extractor.response_header().clone()),
+                Err(error) => tracing::debug!(?error, "index http response"),
+            }
+        }
+
+        std::io::copy(&mut block, &mut std::io::sink())?;
+
+        Ok(http_header)
+    }
+
+    fn make_entry(
+        &self,
+        metadata: &HeaderMetadata,
+        http_header: Option<ResponseHeader>,
+        length: u64,
+    ) -> IndexEntry {
+        let fields = metadata.fields();
+        let url = trim_angle_brackets(fields.get_str("WARC-Target-URI").unwrap_or_default());
+        let warc_type = fields.get_str("WARC-Type").unwrap_or_default();
+
+        let mime = if warc_type == "revisit" {
+            Some("warc/revisit".to_string())
+        } else {
+            match &http_header {
+                Some(header) => get_media_type(header.fields.get_str("Content-Type")),
+                None => get_media_type(fields.get_str("Content-Type")),
+            }
+        };
+
+        let redirect = match &http_header {
+            Some(header) if (300..400).contains(&header.status_line.status_code) => header
+                .fields
+                .get_str("Location")
+                .map(|value| value.to_string()),
+            _ => None,
+        };
+
+        IndexEntry {
+            surt: surt(url),
+            timestamp: date_to_timestamp(fields.get_str("WARC-Date").unwrap_or_default()),
+            url: url.to_string(),
+            mime,
+            status: http_header.map(|header| header.status_line.status_code),
+            digest: fields.get_str("WARC-Payload-Digest").map(strip_sha1_label),
+            redirect,
+            length,
+            offset: metadata.raw_file_offset(),
+            filename: self.filename.clone(),
+        }
+    }
+}
+
+impl<'a, S: Read> SourceCountRead for Indexer<'a, S> {
+    fn source_read_count(&self) -> u64 {
+        self.reader.source_read_count()
+    }
+}
+
+/// Sorts entries by SURT, timestamp, filename, and offset.
+pub fn sort_entries(entries: &mut [IndexEntry]) {
+    entries.sort_by(|a, b| {
+        (&a.surt, &a.timestamp, &a.filename, a.offset).cmp(&(
+            &b.surt,
+            &b.timestamp,
+            &b.filename,
+            b.offset,
+        ))
+    });
+}
+
+/// Writes index entries in the CDXJ format.
+///
+/// Each line consists of the SURT, the timestamp, and a JSON object
+/// containing the remaining fields.
+pub struct CDXJWriter<W: Write> {
+    stream: W,
+}
+
+impl<W: Write> CDXJWriter<W> {
+    /// Creates a `CDXJWriter` with the given output stream.
+    pub fn new(stream: W) -> Self {
+        Self { stream }
+    }
+
+    /// Returns the wrapped stream.
+    pub fn into_inner(self) -> W {
+        self.stream
+    }
+
+    /// Writes an entry as a line.
+    pub fn write_entry(&mut self, entry: &IndexEntry) -> std::io::Result<()> {
+        let block = CDXJBlock {
+            url: &entry.url,
+            mime: entry.mime.as_deref(),
+            status: entry.status.map(|status| status.to_string()),
+            digest: entry.digest.as_deref(),
+            length: entry.length.to_string(),
+            offset: entry.offset.to_string(),
+            filename: &entry.filename,
+        };
+        let block = serde_json::to_string(&block)
+            .map_err(|error| std::io::Error::new(std::io::ErrorKind::InvalidData, error))?;
+
+        writeln!(
+            &mut self.stream,
+            "{} {} {}",
+            entry.surt, entry.timestamp, block
+        )
+    }
+}
+
+/// Header line of the CDX format written by [CDXWriter].
+pub const CDX_HEADER: &str = " CDX N b a m s k r M S V g";
+
+/// Writes index entries in the legacy 11-field CDX format.
+///
+/// The fields are the SURT (N), timestamp (b), original URL (a),
+/// media type (m), status code (s), digest (k), redirect (r),
+/// meta tags (M), compressed record length (S), compressed record offset (V),
+/// and filename (g). Unavailable values are written as `-`.
+pub struct CDXWriter<W: Write> {
+    stream: W,
+}
+
+impl<W: Write> CDXWriter<W> {
+    /// Creates a `CDXWriter` with the given output stream.
+    pub fn new(stream: W) -> Self {
+        Self { stream }
+    }
+
+    /// Returns the wrapped stream.
+    pub fn into_inner(self) -> W {
+        self.stream
+    }
+
+    /// Writes the header line describing the fields.
+    ///
+    /// This function should be called once before writing any entries.
+    pub fn write_header(&mut self) -> std::io::Result<()> {
+        writeln!(&mut self.stream, "{}", CDX_HEADER)
+    }
+
+    /// Writes an entry as a line.
+    pub fn write_entry(&mut self, entry: &IndexEntry) -> std::io::Result<()> {
+        writeln!(
+            &mut self.stream,
+            "{} {} {} {} {} {} {} - {} {} {}",
+            cdx_field(&entry.surt),
+            cdx_field(&entry.timestamp),
+            cdx_field(&entry.url),
+            cdx_field(entry.mime.as_deref().unwrap_or_default()),
+            cdx_field(
+                &entry
+                    .status
+                    .map(|status| status.to_string())
+                    .unwrap_or_default()
+            ),
+            cdx_field(entry.digest.as_deref().unwrap_or_default()),
+            cdx_field(entry.redirect.as_deref().unwrap_or_default()),
+            entry.length,
+            entry.offset,
+            cdx_field(&entry.filename),
+        )
+    }
+}
+
+fn cdx_field(value: &str) -> String {
+    if value.is_empty() {
+        "-".to_string()
+    } else {
+        value.replace(' ', "%20")
+    }
+}
+
+#[derive(Serialize)]
+struct CDXJBlock<'a> {
+    url: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mime: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    digest: Option<&'a str>,
+    length: String,
+    offset: String,
+    filename: &'a str,
+}
+
+pub(super) fn is_http_response(metadata: &HeaderMetadata) -> bool {
+    let warc_type = metadata.fields().get_str("WARC-Type").unwrap_or_default();
+
+    match metadata.fields().get_parsed::<MediaType>("Content-Type") {
+        Ok(Some(media_type)) => {
+            (warc_type == "response" || warc_type == "revisit")
+                && media_type.type_ == "application"
+                && media_type.subtype == "http"
+        }
+        _ => false,
+    }
+}
+
+pub(crate) fn get_media_type(value: Option<&str>) -> Option<String> {
+    match value?.parse::<MediaType>() {
+        Ok(media_type) => {
+            Some(format!("{}/{}", media_type.type_, media_type.subtype).to_ascii_lowercase())
+        }
+        Err(_) => None,
+    }
+}
+
+pub(crate) fn trim_angle_brackets(value: &str) -> &str {
+    value
+        .strip_prefix('<')
+        .and_then(|value| value.strip_suffix('>'))
+        .unwrap_or(value)
+}
+
+fn strip_sha1_label(value: &str) -> String {
+    match value.split_once(':') {
+        Some((label, digest)) if crate::crypto::normalize_hash_name(label) == "sha1" => {
+            digest.to_string()
+        }
+        _ => value.to_string(),
+    }
+}
+
+/// Converts a `WARC-Date` value to a 14-digit timestamp.
+///
+/// Dates with less precision are padded with zeros.
+pub fn date_to_timestamp(date: &str) -> String {
+    let mut timestamp = date
+        .chars()
+        .filter(|c| c.is_ascii_digit())
+        .take(14)
+        .collect::<String>();
+
+    while timestamp.len() < 14 {
+        timestamp.push('0');
+    }
+
+    timestamp
+}
+
+/// Canonicalizes a URL into the Sort-friendly URI Reordering Transform form.
+///
+/// The scheme, user info, fragment, and `www` host prefix are removed.
+/// Domain name labels are reversed and separated with commas.
+/// Query parameters are sorted and the result is lowercased.
+///
+/// Example: `http://www.example.com/Index.html?b=1&a=2` becomes
+/// `com,example)/index.html?a=2&b=1`.
+pub fn surt(url: &str) -> String {
+    let parsed_url = match Url::parse(url) {
+        Ok(parsed_url) => parsed_url,
+        Err(_) => return url.to_lowercase(),
+    };
+
+    let host = match parsed_url.host() {
+        Some(Host::Domain(domain)) => {
+            let domain = domain.to_lowercase();
+            let domain = domain.trim_end_matches('.');
+            let domain = strip_www_prefix(domain);
+
+            domain.rsplit('.').collect::<Vec<&str>>().join(",")
+        }
+        Some(Host::Ipv4(address)) => address.to_string(),
+        Some(Host::Ipv6(address)) => format!("[{address}]"),
+        None => return url.to_lowercase(),
+    };
+
+    let mut output = host;
+
+    if let Some(port) = parsed_url.port() {
+        output.push_str(&format!(":{port}"));
+    }
+
+    output.push(')');
+    output.push_str(parsed_url.path());
+
+    if let Some(query) = parsed_url.query() {
+        let mut parameters = query.split('&').collect::<Vec<&str>>();
+        parameters.sort_unstable();
+
+        output.push('?');
+        output.push_str(&parameters.join("&"));
+    }
+
+    output.to_lowercase()
+}
+
+fn strip_www_prefix(domain: &str) -> &str {
+    if let Some(remain) = domain.strip_prefix("www") {
+        let remain = remain.trim_start_matches(|c: char| c.is_ascii_digit());
+
+        if let Some(remain) = remain.strip_prefix('.') {
+            if remain.contains('.') {
+                return remain;
+            }
+        }
+    }
+
+    domain
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_surt() {
+        assert_eq!(surt("http://example.com/"), "com,example)/");
+        assert_eq!(surt("https://www.Example.com"), "com,example)/");
+        assert_eq!(
+            surt("http://www2.example.com:8080/Index.html?b=1&a=2#frag"),
+            "com,example:8080)/index.html?a=2&b=1"
+        );
+        assert_eq!(surt("http://user@sub.example.com/a"), "com,example,sub)/a");
+        assert_eq!(surt("http://www.com/"), "com,www)/");
+        assert_eq!(surt("http://127.0.0.1/a"), "127.0.0.1)/a");
+        assert_eq!(surt("dns:Example.com"), "dns:example.com");
+    }
+
+    #[test]
+    fn test_date_to_timestamp() {
+        assert_eq!(date_to_timestamp("2000-12-30T01:02:03Z"), "20001230010203");
+        assert_eq!(
+            date_to_timestamp("2000-12-30T01:02:03.123456Z"),
+            "20001230010203"
+        );
+        assert_eq!(date_to_timestamp("2000-12"), "20001200000000");
+    }
+
+    #[test]
+    fn test_strip_sha1_label() {
+        assert_eq!(strip_sha1_label("sha1:ABCD"), "ABCD");
+        assert_eq!(strip_sha1_label("SHA-1:ABCD"), "ABCD");
+        assert_eq!(strip_sha1_label("sha256:abcd"), "sha256:abcd");
+    }
+}
@@ -0,0 +1,142 @@
+use std::io::{Read, Seek, SeekFrom};
+
+use super::{
+    pack::{is_same_encoding, raw_header_formatter, scan_records},
+    HeaderMetadata, RecordLocation, RotatingWARCWriter, WARCError, WARCReader, WARCRecordType,
+};
+
+/// Location of a record before and after it was copied by [RecordMerger].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordMapping {
+    /// Position of the record in the (compressed) input file.
+    pub source_offset: u64,
+    /// Location of the record in the output files.
+    pub target: RecordLocation,
+}
+
+/// Copies records from WARC files to a series of files.
+///
+/// This is used for merging many small files into fewer files or splitting
+/// a large file into smaller files with the limits of the
+/// [RotatingWARCWriter]. The writer should have warcinfo records disabled
+/// if the output should contain only the input records.
+///
+/// Files where each record is compressed individually in the output's
+/// format are copied without decompressing them. Other files are
+/// decompressed and each record is written with its unchanged header.
+#[derive(Debug, Default)]
+pub struct RecordMerger {
+    rewrite_warcinfo: bool,
+}
+
+impl RecordMerger {
+    /// Creates a `RecordMerger` with the default settings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns whether the `WARC-Filename` field of warcinfo records is
+    /// changed to the name of the output file.
+    ///
+    /// Default: false
+    pub fn rewrite_warcinfo(&self) -> bool {
+        self.rewrite_warcinfo
+    }
+
+    /// Sets whether the `WARC-Filename` field of warcinfo records is
+    /// changed to the name of the output file.
+    ///
+    /// Rewritten records are written with the unchanged block, so they are
+    /// recompressed but their block digests remain valid.
+    pub fn set_rewrite_warcinfo(&mut self, value: bool) {
+        self.rewrite_warcinfo = value;
+    }
+
+    /// Copies all records from the stream to the writer.
+    ///
+    /// The stream is read from the beginning.
+    ///
+    /// Returns the old and new location of each record.
+    pub fn merge_stream<S: Read + Seek>(
+        &self,
+        mut stream: S,
+        writer: &mut RotatingWARCWriter<'_>,
+    ) -> Result<Vec<RecordMapping>, WARCError> {
+        stream.seek(SeekFrom::Start(0))?;
+
+        let mut reader = WARCReader::new(&mut stream)?;
+        let entries = scan_records(&mut reader, |metadata, _block| {
+            Ok::<_, WARCError>(metadata.record_type() == Some(WARCRecordType::Warcinfo))
+        })?;
+        // Output files never contain a dictionary.
+        let is_same_encoding = is_same_encoding(&reader, writer.compression_format(), None);
+        drop(reader);
+
+        tracing::debug!(
+            record_count = entries.len(),
+            is_same_encoding,
+            "merge_stream"
+        );
+
+        let mut mappings = Vec::with_capacity(entries.len());
+
+        if is_same_encoding && entries.iter().all(|entry| entry.is_separate_member) {
+            for entry in &entries {
+                let target = if self.rewrite_warcinfo && entry.value {
+                    let mut reader = WARCReader::open_at(&mut stream, entry.start)?;
+                    let metadata = reader.begin_record()?.ok_or(WARCError::UnknownFormat)?;
+                    let target = self.copy_record(&mut reader, &metadata, writer)?;
+                    reader.end_record()?;
+                    target
+                } else {
+                    writer.write_raw_record(entry.raw_reader(&mut stream)?)?
+                };
+
+                mappings.push(RecordMapping {
+                    source_offset: entry.start,
+                    target,
+                });
+            }
+        } else {
+            stream.seek(SeekFrom::Start(0))?;
+            let mut reader = WARCReader::new(&mut stream)?;
+
+            for entry in &entries {
+                let metadata = reader.begin_record()?.ok_or(WARCError::UnknownFormat)?;
+                let target = self.copy_record(&mut reader, &metadata, writer)?;
+                reader.end_record()?;
+
+                mappings.push(RecordMapping {
+                    source_offset: entry.start,
+                    target,
+                });
+            }
+        }
+
+        Ok(mappings)
+    }
+
+    // Writes the header and block of the current record of the reader.
+    fn copy_record<R: Read>(
+        &self,
+        reader: &mut WARCReader<'_, R>,
+        metadata: &HeaderMetadata,
+        writer: &mut RotatingWARCWriter<'_>,
+    ) -> Result<RecordLocation, WARCError> {
+        let path = writer.prepare_record()?;
+        let mut fields = metadata.fields().clone();
+
+        if self.rewrite_warcinfo && metadata.record_type() == Some(WARCRecordType::Warcinfo) {
+            let filename = path.file_name().unwrap_or_default().to_string_lossy();
+            fields.insert("WARC-Filename", filename.as_ref());
+        }
+
+        let inner_writer = writer.writer_mut();
+        inner_writer.set_header_formatter(raw_header_formatter());
+        inner_writer.set_version(metadata.version().to_string());
+
+        writer.begin_record(&fields)?;
+        std::io::copy(&mut reader.read_block(), &mut writer.write_block())?;
+        writer.end_record()
+    }
+}
@@ -1,11 +1,32 @@
 //! WARC file processing.
+mod async_reader;
+mod async_writer;
+pub mod dedup;
 pub mod extract;
+pub mod filter;
 mod header;
+pub mod index;
+mod merge;
+mod pack;
 mod reader;
+mod record;
+mod revisit;
+mod rotate;
+mod segment;
+pub mod stats;
+pub mod validate;
 mod writer;
 
+pub use async_reader::*;
+pub use async_writer::*;
 pub use header::*;
+pub use merge::*;
+pub use pack::*;
 pub use reader::*;
+pub use record::*;
+pub use revisit::*;
+pub use rotate::*;
+pub use segment::*;
 pub use writer::*;
 
 use thiserror::Error;
@@ -53,6 +74,13 @@ pub enum WARCError {
         offset: u64,
     },
 
+    /// A segmented record is missing segments.
+    #[error("incomplete segmented record (at record ID {record_id})")]
+    IncompleteSegments {
+        /// ID of the first segment.
+        record_id: String,
+    },
+
     /// IO error.
     #[error(transparent)]
     Io(#[from] std::io::Error),
@@ -0,0 +1,174 @@
+use std::io::{Read, Seek, SeekFrom, Take, Write};
+
+use crate::{compress::CompressionFormat, header::HeaderFormatter};
+
+use super::{BlockReader, HeaderMetadata, WARCError, WARCReader, WARCWriter};
+
+/// Copies all records from the reader to the writer without modifying them.
+///
+/// This function is intended for changing the compression of a WARC file.
+/// The header of each record is written using the raw values and the
+/// original WARC version so that the uncompressed contents of the output
+/// are identical to the input. The writer's header formatter is replaced
+/// for this purpose.
+///
+/// The writer should not have a digest algorithm set, otherwise the
+/// digest fields will be recomputed.
+///
+/// Returns the number of records copied.
+pub fn pack_records<R: Read, W: Write>(
+    reader: &mut WARCReader<'_, R>,
+    writer: &mut WARCWriter<'_, W>,
+) -> Result<u64, WARCError> {
+    writer.set_header_formatter(raw_header_formatter());
+
+    let mut count = 0;
+
+    while let Some(metadata) = reader.begin_record()? {
+        tracing::debug!(raw_file_offset = metadata.raw_file_offset(), "pack record");
+
+        copy_record(reader, &metadata, writer)?;
+        reader.end_record()?;
+
+        count += 1;
+    }
+
+    Ok(count)
+}
+
+pub(super) fn raw_header_formatter() -> HeaderFormatter {
+    let mut header_formatter = HeaderFormatter::new();
+    header_formatter.set_use_raw(true);
+    header_formatter
+}
+
+// Writes the header and block of the current record of the reader.
+pub(super) fn copy_record<R: Read, W: Write>(
+    reader: &mut WARCReader<'_, R>,
+    metadata: &HeaderMetadata,
+    writer: &mut WARCWriter<'_, W>,
+) -> Result<(), WARCError> {
+    writer.set_version(metadata.version().to_string());
+    writer.begin_record(metadata.fields())?;
+    std::io::copy(&mut reader.read_block(), &mut writer.write_block())?;
+    writer.end_record()
+}
+
+// Location of a record in the input found by `scan_records` with a value
+// computed from the record.
+pub(super) struct ScannedRecord<T> {
+    pub start: u64,
+    pub end: u64,
+    pub is_separate_member: bool,
+    pub value: T,
+}
+
+impl<T> ScannedRecord<T> {
+    // Returns the (compressed) data of the record in the stream.
+    pub fn raw_reader<'s, S: Read + Seek>(
+        &self,
+        stream: &'s mut S,
+    ) -> std::io::Result<Take<&'s mut S>> {
+        stream.seek(SeekFrom::Start(self.start))?;
+        Ok(stream.take(self.end - self.start))
+    }
+}
+
+// Reads all records to find their locations for copying them in a second
+// pass. When a record is compressed as a separate member in the same
+// format as the output (see `is_same_encoding`), the compressed data can
+// be copied as is. Otherwise, the record has to be decompressed and
+// written with `copy_record`.
+//
+// The function is called with each record to compute the value stored with
+// its location. Any block data not read by the function is discarded.
+pub(super) fn scan_records<'a, S, T, E, F>(
+    reader: &mut WARCReader<'a, S>,
+    mut inspect: F,
+) -> Result<Vec<ScannedRecord<T>>, E>
+where
+    S: Read,
+    E: From<WARCError> + From<std::io::Error>,
+    F: FnMut(&HeaderMetadata, &mut BlockReader<'_, 'a, S>) -> Result<T, E>,
+{
+    let mut records = Vec::new();
+
+    loop {
+        let start = reader.raw_file_offset()?;
+        let is_start_boundary = reader.is_member_boundary()?;
+
+        let metadata = match reader.begin_record()? {
+            Some(metadata) => metadata,
+            None => break,
+        };
+
+        let mut block = reader.read_block();
+        let value = inspect(&metadata, &mut block)?;
+        std::io::copy(&mut block, &mut std::io::sink())?;
+        reader.end_record()?;
+
+        records.push(ScannedRecord {
+            start,
+            end: reader.raw_file_offset()?,
+            is_separate_member: is_start_boundary && reader.is_member_boundary()?,
+            value,
+        });
+    }
+
+    Ok(records)
+}
+
+// Returns whether compressed records from the reader can be copied as is to
+// output with the given compression format and Zstandard dictionary.
+pub(super) fn is_same_encoding<S: Read>(
+    reader: &WARCReader<'_, S>,
+    compression_format: CompressionFormat,
+    zstd_dictionary: Option<&[u8]>,
+) -> bool {
+    reader.compression_format() == compression_format && reader.zstd_dictionary() == zstd_dictionary
+}
+
+/// Maximum number of bytes of a record used as a sample by
+/// [collect_dictionary_samples].
+pub const DICTIONARY_SAMPLE_LENGTH: usize = 65536;
+
+/// Reads records and returns them as samples for training a Zstandard
+/// dictionary with [crate::compress::train_zstd_dictionary].
+///
+/// Each sample is a record as written to a file, truncated to
+/// [DICTIONARY_SAMPLE_LENGTH] bytes. At most `max_count` records are read.
+pub fn collect_dictionary_samples<R: Read>(
+    reader: &mut WARCReader<'_, R>,
+    max_count: usize,
+) -> Result<Vec<Vec<u8>>, WARCError> {
+    let header_formatter = raw_header_formatter();
+    let mut samples = Vec::new();
+
+    while samples.len() < max_count {
+        let metadata = match reader.begin_record()? {
+            Some(metadata) => metadata,
+            None => break,
+        };
+
+        let mut sample = Vec::new();
+        sample.extend_from_slice(metadata.version().as_bytes());
+        sample.extend_from_slice(b"\r\n");
+        header_formatter
+            .format_header(metadata.fields(), &mut sample)
+            .map_err(|error| WARCError::MalformedHeader {
+                offset: metadata.file_offset(),
+                source: Some(Box::new(error)),
+            })?;
+        sample.extend_from_slice(b"\r\n");
+
+        let limit = DICTIONARY_SAMPLE_LENGTH.saturating_sub(sample.len());
+        let mut block = reader.read_block();
+        (&mut block).take(limit as u64).read_to_end(&mut sample)?;
+        std::io::copy(&mut block, &mut std::io::sink())?;
+        reader.end_record()?;
+
+        samples.push(sample);
+    }
+
+    Ok(samples)
+}
@@ -1,24 +1,40 @@
-use std::io::{BufReader, Read, Take};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Take};
 
 use crate::{
-    compress::Decompressor,
+    compress::{read_zstd_dictionary_frame, CompressionFormat, MultiStreamDecompressor},
     header::{HeaderMap, HeaderParser},
-    io::{BufReadMoreExt, SourceCountRead},
+    io::{BufReadMoreExt, ComboReader, SourceCountRead},
 };
 
 use super::header::HeaderMapExt;
-use super::WARCError;
+use super::{WARCError, WARCRecordType};
 
 /// Reads a WARC file.
 ///
-/// Decompression is handled automatically by [Decompressor].
+/// Decompression is handled automatically by [MultiStreamDecompressor].
+/// A Zstandard dictionary at the start of the file is loaded and used to
+/// decode the records.
+///
+/// When recovery is enabled with [Self::set_recovery], malformed records
+/// and corrupt compressed data are skipped instead of returning an error.
+/// The reader scans forward to the next compression member or `WARC/`
+/// version line and continues reading. Skipped ranges are logged and
+/// available from [Self::take_skipped_ranges].
 pub struct WARCReader<'a, S: Read> {
-    stream: BufReader<Decompressor<'a, S>>,
+    stream: MultiStreamDecompressor<'a, S>,
     header_limit: u64,
 
     state: ReaderState,
 
+    recovery: bool,
+    record_raw_file_offset: u64,
+    pending_error: Option<WARCError>,
+    pending_magic_bytes_offset: Option<u64>,
+    block_error: Option<std::io::Error>,
+    skipped_ranges: Vec<SkippedRange>,
+
     file_offset: u64,
+    raw_file_offset_base: u64,
 
     magic_bytes_buffer: Vec<u8>,
     header_buffer: Vec<u8>,
@@ -33,12 +49,19 @@ impl<'a, S: Read> WARCReader<'a, S> {
     /// Creates a `WARCReader` with the given input buffered stream.
     pub fn new(stream: S) -> Result<Self, WARCError> {
         Ok(Self {
-            stream: BufReader::new(Decompressor::new_allow_unknown(stream)?),
+            stream: MultiStreamDecompressor::new(stream)?,
             header_limit: 16_777_216,
             state: ReaderState::StartOfHeader,
+            recovery: false,
+            record_raw_file_offset: 0,
+            pending_error: None,
+            pending_magic_bytes_offset: None,
+            block_error: None,
+            skipped_ranges: Vec::new(),
             magic_bytes_buffer: Vec::new(),
             header_buffer: Vec::new(),
             file_offset: 0,
+            raw_file_offset_base: 0,
             record_id: String::new(),
             block_file_offset: 0,
             block_length: 0,
@@ -48,7 +71,7 @@ impl<'a, S: Read> WARCReader<'a, S> {
 
     /// Returns the wrapped stream.
     pub fn into_inner(self) -> S {
-        self.stream.into_inner().into_inner()
+        self.stream.into_inner()
     }
 
     /// Creates a `WARCReader` with the given input stream.
@@ -56,6 +79,24 @@ impl<'a, S: Read> WARCReader<'a, S> {
         WARCReader::new(BufReader::new(reader))
     }
 
+    /// Returns whether recovery from errors is enabled.
+    ///
+    /// Default: false
+    pub fn recovery(&self) -> bool {
+        self.recovery
+    }
+
+    /// Sets whether to skip malformed records and corrupt data instead of
+    /// returning an error.
+    pub fn set_recovery(&mut self, value: bool) {
+        self.recovery = value;
+    }
+
+    /// Returns the ranges skipped during recovery since the last call.
+    pub fn take_skipped_ranges(&mut self) -> Vec<SkippedRange> {
+        std::mem::take(&mut self.skipped_ranges)
+    }
+
     /// Starts reading a record and returns the header.
     ///
     /// The caller must call [Self::read_block] next to advance the stream.
@@ -66,19 +107,53 @@ impl<'a, S: Read> WARCReader<'a, S> {
     pub fn begin_record(&mut self) -> Result<Option<HeaderMetadata>, WARCError> {
         assert!(self.state == ReaderState::StartOfHeader);
 
-        let decompressor_stream = self.stream.get_ref();
-        let start_file_offset = self.file_offset;
-        let raw_file_offset = decompressor_stream.source_read_count();
+        if !self.recovery {
+            return self.begin_record_impl();
+        }
+
+        loop {
+            if let Some(error) = self.pending_error.take() {
+                self.recover(error)?;
+            }
+
+            match self.begin_record_impl() {
+                Ok(metadata) => return Ok(metadata),
+                Err(error) => self.pending_error = Some(error),
+            }
+        }
+    }
+
+    fn begin_record_impl(&mut self) -> Result<Option<HeaderMetadata>, WARCError> {
+        let raw_file_offset = match self.pending_magic_bytes_offset.take() {
+            Some(raw_file_offset) => raw_file_offset,
+            None => {
+                let raw_file_offset = match self.raw_file_offset() {
+                    Ok(raw_file_offset) => raw_file_offset,
+                    Err(error) => {
+                        // Position of the member that could not be
+                        // decompressed for recovery.
+                        self.record_raw_file_offset = self.member_raw_file_offset();
+                        return Err(error);
+                    }
+                };
+                self.record_raw_file_offset = raw_file_offset;
+
+                if !self.read_magic_bytes()? {
+                    return Ok(None);
+                }
+
+                raw_file_offset
+            }
+        };
+        let start_file_offset = self.file_offset - self.magic_bytes_buffer.len() as u64;
+        self.record_raw_file_offset = raw_file_offset;
 
         tracing::debug!(
-            file_offset = self.file_offset,
+            file_offset = start_file_offset,
             raw_file_offset,
             "begin_record"
         );
 
-        if !self.read_magic_bytes()? {
-            return Ok(None);
-        }
         self.read_header_lines()?;
         let header_map = self.parse_header_lines()?;
         self.prepare_for_block_read(&header_map)?;
@@ -96,6 +171,57 @@ impl<'a, S: Read> WARCReader<'a, S> {
         }))
     }
 
+    /// Returns the position in the (compressed) stream of the boundary
+    /// between records.
+    ///
+    /// This function is intended to be called before [Self::begin_record] or
+    /// after [Self::end_record]. After the last record, the value is the
+    /// length of the stream.
+    ///
+    /// For compressed files, the position is exact only when each record
+    /// is compressed as a separate member. Otherwise, the position of the
+    /// member containing the boundary is returned.
+    pub fn raw_file_offset(&mut self) -> Result<u64, WARCError> {
+        match self.stream.source_position()? {
+            Some(offset) => Ok(self.raw_file_offset_base + offset),
+            None => Ok(self.raw_file_offset_base + self.stream.member_offset()),
+        }
+    }
+
+    /// Returns the number of bytes read from the (uncompressed) stream.
+    ///
+    /// This function is intended to be called before [Self::begin_record] or
+    /// after [Self::end_record].
+    pub fn file_offset(&self) -> u64 {
+        self.file_offset
+    }
+
+    /// Returns the detected compression format of the stream.
+    pub fn compression_format(&self) -> CompressionFormat {
+        self.stream.format()
+    }
+
+    /// Returns the Zstandard dictionary loaded from the stream.
+    pub fn zstd_dictionary(&self) -> Option<&[u8]> {
+        self.stream.zstd_dictionary()
+    }
+
+    /// Returns whether the current position in the stream is the start of
+    /// a compression member.
+    ///
+    /// This function is intended to be called before [Self::begin_record] or
+    /// after [Self::end_record]. For uncompressed files, this is always true.
+    pub fn is_member_boundary(&mut self) -> Result<bool, WARCError> {
+        Ok(self.stream.source_position()?.is_some())
+    }
+
+    // Position in the (compressed) stream of the current member. Unlike
+    // `raw_file_offset`, this is available after a decompression error. If
+    // the previous member ended cleanly, it is the member that failed.
+    pub(super) fn member_raw_file_offset(&self) -> u64 {
+        self.raw_file_offset_base + self.stream.member_offset()
+    }
+
     fn read_magic_bytes(&mut self) -> Result<bool, WARCError> {
         tracing::debug!("read_magic_bytes");
 
@@ -111,15 +237,93 @@ impl<'a, S: Read> WARCReader<'a, S> {
             return Ok(false);
         }
 
-        if !(self.magic_bytes_buffer.starts_with(b"WARC/0.")
-            || self.magic_bytes_buffer.starts_with(b"WARC/1."))
-        {
+        if !is_magic_bytes(&self.magic_bytes_buffer) {
             return Err(WARCError::UnknownFormat);
         }
 
         Ok(true)
     }
 
+    fn recover(&mut self, error: WARCError) -> Result<(), WARCError> {
+        let start = self.record_raw_file_offset;
+
+        tracing::debug!(start, %error, "recover");
+
+        if matches!(error, WARCError::Io(_)) {
+            self.stream.skip_to_next_member()?;
+        }
+
+        loop {
+            match self.scan_magic_bytes() {
+                Ok(()) => break,
+                Err(WARCError::Io(_)) if self.stream.format() != CompressionFormat::Raw => {
+                    self.stream.skip_to_next_member()?;
+                }
+                Err(error) => return Err(error),
+            }
+        }
+
+        let end = match self.pending_magic_bytes_offset {
+            Some(offset) => offset,
+            None => self.raw_file_offset()?,
+        };
+
+        tracing::warn!(start, end, %error, "skipped malformed data");
+
+        self.skipped_ranges.push(SkippedRange { start, end, error });
+
+        Ok(())
+    }
+
+    // Consumes the stream until a version line is found or EOF.
+    fn scan_magic_bytes(&mut self) -> Result<(), WARCError> {
+        self.magic_bytes_buffer.clear();
+        let mut candidate_offset = 0;
+
+        loop {
+            let byte = match self.stream.fill_buf()?.first() {
+                Some(byte) => *byte,
+                None => return Ok(()),
+            };
+
+            let matched = match self.magic_bytes_buffer.len() {
+                0..=4 => byte == b"WARC/"[self.magic_bytes_buffer.len()],
+                5 => byte == b'0' || byte == b'1',
+                _ => byte == b'.',
+            };
+
+            if !matched {
+                if self.magic_bytes_buffer.is_empty() {
+                    self.stream.consume(1);
+                    self.file_offset += 1;
+                } else {
+                    self.magic_bytes_buffer.clear();
+                }
+
+                continue;
+            }
+
+            if self.magic_bytes_buffer.is_empty() {
+                candidate_offset = self.raw_file_offset()?;
+            }
+
+            self.magic_bytes_buffer.push(byte);
+            self.stream.consume(1);
+            self.file_offset += 1;
+
+            if self.magic_bytes_buffer.len() == 7 {
+                self.file_offset += self.stream.read_limit_until(
+                    b'\n',
+                    &mut self.magic_bytes_buffer,
+                    self.header_limit,
+                )? as u64;
+                self.pending_magic_bytes_offset = Some(candidate_offset);
+
+                return Ok(());
+            }
+        }
+    }
+
     fn read_header_lines(&mut self) -> Result<(), WARCError> {
         tracing::debug!("read_header_lines");
 
@@ -138,15 +342,7 @@ impl<'a, S: Read> WARCReader<'a, S> {
     fn parse_header_lines(&mut self) -> Result<HeaderMap, WARCError> {
         tracing::debug!("parse_header_lines");
 
-        match HeaderParser::new()
-            .parse_header(crate::stringutil::trim_trailing_crlf(&self.header_buffer))
-        {
-            Ok(header_map) => Ok(header_map),
-            Err(error) => Err(WARCError::MalformedHeader {
-                offset: self.file_offset,
-                source: Some(Box::new(error)),
-            }),
-        }
+        parse_header(&self.header_buffer, self.file_offset)
     }
 
     fn prepare_for_block_read(&mut self, header_map: &HeaderMap) -> Result<(), WARCError> {
@@ -183,9 +379,24 @@ impl<'a, S: Read> WARCReader<'a, S> {
         BlockReader {
             stream,
             num_bytes_read: &mut self.block_bytes_read,
+            error: self.recovery.then_some(&mut self.block_error),
         }
     }
 
+    // Reads from the record body without borrowing the reader for the
+    // duration of the block.
+    pub(super) fn read_block_partial(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        assert!(self.state == ReaderState::EndOfHeader || self.state == ReaderState::InBlock);
+
+        self.state = ReaderState::InBlock;
+
+        let remaining = self.block_length - self.block_bytes_read;
+        let size = self.stream.by_ref().take(remaining).read(buf)?;
+        self.block_bytes_read += size as u64;
+
+        Ok(size)
+    }
+
     /// Finish reading a record.
     ///
     /// Panics when called out of sequence.
@@ -193,8 +404,26 @@ impl<'a, S: Read> WARCReader<'a, S> {
         assert!(self.state == ReaderState::InBlock);
         tracing::debug!("end_record");
 
+        let result = self.end_record_impl();
+
+        if self.recovery {
+            if let Err(error) = result {
+                self.state = ReaderState::StartOfHeader;
+                self.pending_error = Some(error);
+                return Ok(());
+            }
+        }
+
+        result
+    }
+
+    fn end_record_impl(&mut self) -> Result<(), WARCError> {
         self.file_offset += self.block_bytes_read;
 
+        if let Some(error) = self.block_error.take() {
+            return Err(error.into());
+        }
+
         self.check_block_length()?;
         self.read_end_of_record_lines()?;
 
@@ -232,7 +461,7 @@ impl<'a, S: Read> WARCReader<'a, S> {
             self.file_offset += amount as u64;
             let line = &self.header_buffer[buf_position..];
 
-            if line.is_empty() || !b"\r\n".contains(&line[0]) {
+            if !is_end_of_record_line(line) {
                 return Err(WARCError::MalformedFooter {
                     offset: self.file_offset,
                 });
@@ -243,39 +472,120 @@ impl<'a, S: Read> WARCReader<'a, S> {
     }
 }
 
+impl<'a, S: Read + Seek> WARCReader<'a, S> {
+    /// Creates a `WARCReader` positioned at the record at the given offset.
+    ///
+    /// The offset is the position of the record in the (compressed) stream,
+    /// such as one obtained from [Self::raw_file_offset] or an index.
+    /// For compressed files, the offset must be the start of a
+    /// compression member.
+    ///
+    /// Offsets reported by [HeaderMetadata::raw_file_offset] are positions in
+    /// the whole stream, but [HeaderMetadata::file_offset] is counted from
+    /// the given offset.
+    ///
+    /// Returns [WARCError::UnknownFormat] if the offset is not the start of
+    /// a record.
+    pub fn open_at(mut stream: S, offset: u64) -> Result<Self, WARCError> {
+        tracing::debug!(offset, "open_at");
+
+        // The dictionary of a Zstandard file is at the start of the file.
+        stream.seek(SeekFrom::Start(0))?;
+        let zstd_dictionary = read_zstd_dictionary_frame(&mut ComboReader::new(&mut stream))?;
+
+        stream.seek(SeekFrom::Start(offset))?;
+
+        let mut reader = Self::new(stream)?;
+        reader.raw_file_offset_base = offset;
+        reader.stream.set_zstd_dictionary(zstd_dictionary);
+
+        if !reader.stream.fill_buf()?.starts_with(b"WARC/") {
+            return Err(WARCError::UnknownFormat);
+        }
+
+        Ok(reader)
+    }
+}
+
 impl<'a, S: Read> SourceCountRead for WARCReader<'a, S> {
     fn source_read_count(&self) -> u64 {
-        self.stream.get_ref().source_read_count()
+        self.stream.source_read_count()
     }
 }
 
+pub(super) fn is_magic_bytes(line: &[u8]) -> bool {
+    line.starts_with(b"WARC/0.") || line.starts_with(b"WARC/1.")
+}
+
+pub(super) fn parse_header(buf: &[u8], file_offset: u64) -> Result<HeaderMap, WARCError> {
+    match HeaderParser::new().parse_header(crate::stringutil::trim_trailing_crlf(buf)) {
+        Ok(header_map) => Ok(header_map),
+        Err(error) => Err(WARCError::MalformedHeader {
+            offset: file_offset,
+            source: Some(Box::new(error)),
+        }),
+    }
+}
+
+pub(super) fn is_end_of_record_line(line: &[u8]) -> bool {
+    !line.is_empty() && b"\r\n".contains(&line[0])
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum ReaderState {
+pub(super) enum ReaderState {
     StartOfHeader,
     EndOfHeader,
     InBlock,
 }
 
 /// Reader stream for a record body.
+///
+/// When recovery is enabled, errors are deferred to
+/// [WARCReader::end_record] and the stream ends early instead.
 pub struct BlockReader<'a, 's, S: Read> {
-    stream: Take<&'a mut BufReader<Decompressor<'s, S>>>,
+    stream: Take<&'a mut MultiStreamDecompressor<'s, S>>,
     num_bytes_read: &'a mut u64,
+    error: Option<&'a mut Option<std::io::Error>>,
 }
 
 impl<'a, 's, S: Read> SourceCountRead for BlockReader<'a, 's, S> {
     fn source_read_count(&self) -> u64 {
-        self.stream.get_ref().get_ref().source_read_count()
+        self.stream.get_ref().source_read_count()
     }
 }
 
 impl<'a, 's, S: Read> Read for BlockReader<'a, 's, S> {
     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
-        let size = self.stream.read(buf)?;
-        *self.num_bytes_read += size as u64;
-        Ok(size)
+        match (self.stream.read(buf), &mut self.error) {
+            (Ok(size), _) => {
+                *self.num_bytes_read += size as u64;
+                Ok(size)
+            }
+            (Err(error), Some(error_slot)) => {
+                tracing::debug!(%error, "block read deferred error");
+                **error_slot = Some(error);
+                Ok(0)
+            }
+            (Err(error), None) => Err(error),
+        }
     }
 }
 
+/// A range of the stream skipped during recovery from an error.
+#[derive(Debug)]
+pub struct SkippedRange {
+    /// Position in the (compressed) stream of the record containing the
+    /// error.
+    ///
+    /// For compressed files, this is the position of the member
+    /// containing the record if the record is not at a member boundary.
+    pub start: u64,
+    /// Position in the (compressed) stream where reading resumed.
+    pub end: u64,
+    /// The error that caused the range to be skipped.
+    pub error: WARCError,
+}
+
 /// A record's header and associated file metadata.
 #[derive(Debug, Clone)]
 pub struct HeaderMetadata {
@@ -287,6 +597,22 @@ pub struct HeaderMetadata {
 }
 
 impl HeaderMetadata {
+    pub(super) fn new(
+        version: String,
+        fields: HeaderMap,
+        block_length: u64,
+        file_offset: u64,
+        raw_file_offset: u64,
+    ) -> Self {
+        Self {
+            version,
+            fields,
+            block_length,
+            file_offset,
+            raw_file_offset,
+        }
+    }
+
     /// Returns the WARC record version.
     pub fn version(&self) -> &str {
         self.version.as_ref()
@@ -297,6 +623,13 @@ impl HeaderMetadata {
         &self.fields
     }
 
+    /// Returns the record type from the `WARC-Type` field.
+    ///
+    /// Returns `None` if the field is missing or the type is unknown.
+    pub fn record_type(&self) -> Option<WARCRecordType> {
+        self.fields.get_str("WARC-Type")?.parse().ok()
+    }
+
     /// Returns the length of the body of the record.
     pub fn block_length(&self) -> u64 {
         self.block_length
@@ -307,7 +640,9 @@ impl HeaderMetadata {
         self.file_offset
     }
 
-    /// Number of bytes read in total from the (compressed) stream.
+    /// Position of the record in the (compressed) stream.
+    ///
+    /// See [WARCReader::raw_file_offset] for details.
     pub fn raw_file_offset(&self) -> u64 {
         self.raw_file_offset
     }
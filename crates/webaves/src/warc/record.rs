@@ -0,0 +1,631 @@
+use std::{fmt::Display, marker::PhantomData, str::FromStr};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+
+use crate::header::{HeaderFormatter, HeaderMap};
+
+use super::{HeaderMapExt, WARCError};
+
+/// Revisit profile for records with an identical payload digest.
+pub const IDENTICAL_PAYLOAD_DIGEST_PROFILE: &str =
+    "http://netpreserve.org/warc/1.1/revisit/identical-payload-digest";
+
+/// Revisit profile for records where the server responded "not modified".
+pub const SERVER_NOT_MODIFIED_PROFILE: &str =
+    "http://netpreserve.org/warc/1.1/revisit/server-not-modified";
+
+/// Type of a WARC record as given in the `WARC-Type` field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WARCRecordType {
+    /// Describes the records that follow it.
+    Warcinfo,
+    /// Complete scheme-specific response.
+    Response,
+    /// Resource without full protocol response information.
+    Resource,
+    /// Complete scheme-specific request.
+    Request,
+    /// Content describing or annotating another record.
+    Metadata,
+    /// Revisitation of content already archived.
+    Revisit,
+    /// Alternative version of another record's content.
+    Conversion,
+    /// Remaining segments of a segmented record.
+    Continuation,
+}
+
+impl WARCRecordType {
+    /// Returns the value used in the `WARC-Type` field.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WARCRecordType::Warcinfo => "warcinfo",
+            WARCRecordType::Response => "response",
+            WARCRecordType::Resource => "resource",
+            WARCRecordType::Request => "request",
+            WARCRecordType::Metadata => "metadata",
+            WARCRecordType::Revisit => "revisit",
+            WARCRecordType::Conversion => "conversion",
+            WARCRecordType::Continuation => "continuation",
+        }
+    }
+
+    /// Returns the names of fields that are mandatory for this record type
+    /// according to WARC 1.1.
+    pub fn mandatory_fields(&self) -> &'static [&'static str] {
+        match self {
+            WARCRecordType::Warcinfo | WARCRecordType::Metadata => {
+                &["WARC-Record-ID", "Content-Length", "WARC-Date", "WARC-Type"]
+            }
+            WARCRecordType::Response
+            | WARCRecordType::Resource
+            | WARCRecordType::Request
+            | WARCRecordType::Conversion => &[
+                "WARC-Record-ID",
+                "Content-Length",
+                "WARC-Date",
+                "WARC-Type",
+                "WARC-Target-URI",
+            ],
+            WARCRecordType::Revisit => &[
+                "WARC-Record-ID",
+                "Content-Length",
+                "WARC-Date",
+                "WARC-Type",
+                "WARC-Target-URI",
+                "WARC-Profile",
+            ],
+            WARCRecordType::Continuation => &[
+                "WARC-Record-ID",
+                "Content-Length",
+                "WARC-Date",
+                "WARC-Type",
+                "WARC-Target-URI",
+                "WARC-Segment-Origin-ID",
+                "WARC-Segment-Number",
+            ],
+        }
+    }
+
+    /// Returns whether the `WARC-Concurrent-To` field may be used.
+    pub fn allows_concurrent_to(&self) -> bool {
+        !matches!(
+            self,
+            WARCRecordType::Warcinfo | WARCRecordType::Conversion | WARCRecordType::Continuation
+        )
+    }
+}
+
+impl FromStr for WARCRecordType {
+    type Err = crate::error::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "warcinfo" => Ok(WARCRecordType::Warcinfo),
+            "response" => Ok(WARCRecordType::Response),
+            "resource" => Ok(WARCRecordType::Resource),
+            "request" => Ok(WARCRecordType::Request),
+            "metadata" => Ok(WARCRecordType::Metadata),
+            "revisit" => Ok(WARCRecordType::Revisit),
+            "conversion" => Ok(WARCRecordType::Conversion),
+            "continuation" => Ok(WARCRecordType::Continuation),
+            _ => Err(crate::error::Error::Parse("unknown record type".into())),
+        }
+    }
+}
+
+impl Display for WARCRecordType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Returns a new record ID using a UUID version 7.
+///
+/// The value is formatted as `<urn:uuid:...>`.
+pub fn new_record_id() -> String {
+    format!("<urn:uuid:{}>", crate::uuid::new_v7().as_hyphenated())
+}
+
+/// Formats a date for the `WARC-Date` field.
+pub fn format_date(date: &DateTime<Utc>) -> String {
+    date.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+/// Checks that the header contains the fields required by WARC 1.1.
+///
+/// The record type is determined from the `WARC-Type` field. Unknown
+/// record types are only checked for the fields common to all records.
+pub fn check_mandatory_fields(header: &HeaderMap) -> Result<(), WARCError> {
+    let record_type = header.get_required("WARC-Type")?;
+    let fields = match WARCRecordType::from_str(record_type) {
+        Ok(record_type) => record_type.mandatory_fields(),
+        Err(_) => WARCRecordType::Warcinfo.mandatory_fields(),
+    };
+
+    for name in fields {
+        header.get_required(name)?;
+    }
+
+    Ok(())
+}
+
+/// A record header and block held in memory.
+#[derive(Debug, Clone)]
+pub struct WARCRecord {
+    /// Name-value fields of the record.
+    pub header: HeaderMap,
+    /// Contents of the record.
+    pub block: Vec<u8>,
+}
+
+/// Marker for the record type of a [RecordBuilder].
+pub trait RecordKind {
+    /// Type of the record.
+    const RECORD_TYPE: WARCRecordType;
+}
+
+macro_rules! record_kind {
+    ($name:ident, $record_type:expr, $doc:literal) => {
+        #[doc = $doc]
+        #[derive(Debug, Clone, Copy)]
+        pub struct $name;
+
+        impl RecordKind for $name {
+            const RECORD_TYPE: WARCRecordType = $record_type;
+        }
+    };
+}
+
+record_kind!(
+    WarcinfoKind,
+    WARCRecordType::Warcinfo,
+    "Marker for warcinfo records."
+);
+record_kind!(
+    ResponseKind,
+    WARCRecordType::Response,
+    "Marker for response records."
+);
+record_kind!(
+    ResourceKind,
+    WARCRecordType::Resource,
+    "Marker for resource records."
+);
+record_kind!(
+    RequestKind,
+    WARCRecordType::Request,
+    "Marker for request records."
+);
+record_kind!(
+    MetadataKind,
+    WARCRecordType::Metadata,
+    "Marker for metadata records."
+);
+record_kind!(
+    RevisitKind,
+    WARCRecordType::Revisit,
+    "Marker for revisit records."
+);
+record_kind!(
+    ConversionKind,
+    WARCRecordType::Conversion,
+    "Marker for conversion records."
+);
+record_kind!(
+    ContinuationKind,
+    WARCRecordType::Continuation,
+    "Marker for continuation records."
+);
+
+/// Builder for warcinfo records.
+pub type WarcinfoRecordBuilder = RecordBuilder<WarcinfoKind>;
+/// Builder for response records.
+pub type ResponseRecordBuilder = RecordBuilder<ResponseKind>;
+/// Builder for resource records.
+pub type ResourceRecordBuilder = RecordBuilder<ResourceKind>;
+/// Builder for request records.
+pub type RequestRecordBuilder = RecordBuilder<RequestKind>;
+/// Builder for metadata records.
+pub type MetadataRecordBuilder = RecordBuilder<MetadataKind>;
+/// Builder for revisit records.
+pub type RevisitRecordBuilder = RecordBuilder<RevisitKind>;
+/// Builder for conversion records.
+pub type ConversionRecordBuilder = RecordBuilder<ConversionKind>;
+/// Builder for continuation records.
+pub type ContinuationRecordBuilder = RecordBuilder<ContinuationKind>;
+
+/// Builds a record of a given type.
+///
+/// The `WARC-Type`, `WARC-Record-ID`, and `WARC-Date` fields are generated
+/// automatically. Mandatory fields are checked when the record is built.
+///
+/// Use the type aliases such as [ResponseRecordBuilder] to create builders.
+#[derive(Debug, Clone)]
+pub struct RecordBuilder<K: RecordKind> {
+    header: HeaderMap,
+    block: Option<Vec<u8>>,
+    kind: PhantomData<K>,
+}
+
+impl<K: RecordKind> RecordBuilder<K> {
+    fn new_impl() -> Self {
+        let mut header = HeaderMap::new();
+        header.insert("WARC-Type", K::RECORD_TYPE.as_str());
+        header.insert("WARC-Record-ID", new_record_id());
+        header.insert("WARC-Date", format_date(&Utc::now()));
+
+        Self {
+            header,
+            block: None,
+            kind: PhantomData,
+        }
+    }
+
+    fn new_with_target_uri(target_uri: &str) -> Self {
+        Self::new_impl().field("WARC-Target-URI", target_uri)
+    }
+
+    /// Returns the type of the record.
+    pub fn record_type(&self) -> WARCRecordType {
+        K::RECORD_TYPE
+    }
+
+    /// Returns the header built so far.
+    pub fn header(&self) -> &HeaderMap {
+        &self.header
+    }
+
+    /// Sets a field, replacing any existing values.
+    pub fn field(mut self, name: &str, value: &str) -> Self {
+        self.header.insert(name, value);
+        self
+    }
+
+    /// Adds a field, keeping any existing values.
+    pub fn append_field(mut self, name: &str, value: &str) -> Self {
+        self.header.append(name, value);
+        self
+    }
+
+    /// Sets the record ID instead of the generated ID.
+    pub fn record_id(self, record_id: &str) -> Self {
+        self.field("WARC-Record-ID", record_id)
+    }
+
+    /// Sets the date instead of the current time.
+    pub fn date(self, date: &DateTime<Utc>) -> Self {
+        self.field("WARC-Date", &format_date(date))
+    }
+
+    /// Sets the media type of the block.
+    pub fn content_type(self, content_type: &str) -> Self {
+        self.field("Content-Type", content_type)
+    }
+
+    /// Sets the length of the block.
+    ///
+    /// This is not needed when [Self::payload] is used.
+    pub fn content_length(self, content_length: u64) -> Self {
+        self.field("Content-Length", &content_length.to_string())
+    }
+
+    /// Sets the ID of the warcinfo record associated with this record.
+    pub fn warcinfo_id(self, record_id: &str) -> Self {
+        self.field("WARC-Warcinfo-ID", record_id)
+    }
+
+    /// Adds the ID of a record created as part of the same capture event.
+    pub fn concurrent_to(self, record_id: &str) -> Self {
+        self.append_field("WARC-Concurrent-To", record_id)
+    }
+
+    /// Sets the IP address of the server.
+    pub fn ip_address(self, address: &str) -> Self {
+        self.field("WARC-IP-Address", address)
+    }
+
+    /// Sets the digest of the block.
+    pub fn block_digest(self, digest: &str) -> Self {
+        self.field("WARC-Block-Digest", digest)
+    }
+
+    /// Sets the digest of the payload.
+    pub fn payload_digest(self, digest: &str) -> Self {
+        self.field("WARC-Payload-Digest", digest)
+    }
+
+    /// Sets the reason the block was truncated.
+    pub fn truncated(self, reason: &str) -> Self {
+        self.field("WARC-Truncated", reason)
+    }
+
+    /// Sets the contents of the block and the `Content-Length` field.
+    pub fn payload<P: Into<Vec<u8>>>(mut self, payload: P) -> Self {
+        let payload = payload.into();
+        self = self.content_length(payload.len() as u64);
+        self.block = Some(payload);
+        self
+    }
+
+    /// Checks the fields and returns the record.
+    ///
+    /// If [Self::payload] was not used, the block is empty and the
+    /// contents are expected to be written separately.
+    pub fn build(self) -> Result<WARCRecord, WARCError> {
+        check_mandatory_fields(&self.header)?;
+
+        self.header.get_parsed_required::<u64>("Content-Length")?;
+
+        let date = self.header.get_required("WARC-Date")?;
+        if let Err(error) = DateTime::parse_from_rfc3339(date) {
+            return Err(self.make_error("WARC-Date", Box::new(error)));
+        }
+
+        for name in ["WARC-Record-ID", "WARC-Warcinfo-ID", "WARC-Refers-To"] {
+            for value in self.header.get_all(name) {
+                if !(value.text.starts_with('<') && value.text.ends_with('>')) {
+                    return Err(self.make_error(name, "not enclosed in angle brackets".into()));
+                }
+            }
+        }
+
+        if !K::RECORD_TYPE.allows_concurrent_to() && self.header.contains_key("WARC-Concurrent-To")
+        {
+            return Err(self.make_error(
+                "WARC-Concurrent-To",
+                "not allowed for this record type".into(),
+            ));
+        }
+
+        Ok(WARCRecord {
+            header: self.header,
+            block: self.block.unwrap_or_default(),
+        })
+    }
+
+    fn warc_fields(self, fields: &HeaderMap) -> Result<Self, WARCError> {
+        let mut block = Vec::new();
+
+        if let Err(error) = HeaderFormatter::new().format_header(fields, &mut block) {
+            return Err(self.make_error("Content-Type", Box::new(error)));
+        }
+
+        Ok(self.content_type("application/warc-fields").payload(block))
+    }
+
+    fn make_error(
+        &self,
+        name: &str,
+        source: Box<dyn std::error::Error + Send + Sync>,
+    ) -> WARCError {
+        WARCError::InvalidFieldValue {
+            name: name.to_string(),
+            record_id: self
+                .header
+                .get_str("WARC-Record-ID")
+                .unwrap_or_default()
+                .to_string(),
+            source: Some(source),
+        }
+    }
+}
+
+impl RecordBuilder<WarcinfoKind> {
+    /// Creates a builder for a warcinfo record.
+    pub fn new() -> Self {
+        Self::new_impl()
+    }
+
+    /// Sets the name of the WARC file containing the record.
+    pub fn filename(self, filename: &str) -> Self {
+        self.field("WARC-Filename", filename)
+    }
+
+    /// Sets the block to the given name-value fields.
+    pub fn info_fields(self, fields: &HeaderMap) -> Result<Self, WARCError> {
+        self.warc_fields(fields)
+    }
+}
+
+impl Default for RecordBuilder<WarcinfoKind> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RecordBuilder<ResponseKind> {
+    /// Creates a builder for a response record.
+    ///
+    /// The content type defaults to a HTTP response.
+    pub fn new(target_uri: &str) -> Self {
+        Self::new_with_target_uri(target_uri).content_type("application/http;msgtype=response")
+    }
+}
+
+impl RecordBuilder<ResourceKind> {
+    /// Creates a builder for a resource record.
+    pub fn new(target_uri: &str) -> Self {
+        Self::new_with_target_uri(target_uri)
+    }
+}
+
+impl RecordBuilder<RequestKind> {
+    /// Creates a builder for a request record.
+    ///
+    /// The content type defaults to a HTTP request.
+    pub fn new(target_uri: &str) -> Self {
+        Self::new_with_target_uri(target_uri).content_type("application/http;msgtype=request")
+    }
+}
+
+impl RecordBuilder<MetadataKind> {
+    /// Creates a builder for a metadata record.
+    pub fn new() -> Self {
+        Self::new_impl()
+    }
+
+    /// Sets the URI of the resource described by the record.
+    pub fn target_uri(self, target_uri: &str) -> Self {
+        self.field("WARC-Target-URI", target_uri)
+    }
+
+    /// Sets the ID of the record described by this record.
+    pub fn refers_to(self, record_id: &str) -> Self {
+        self.field("WARC-Refers-To", record_id)
+    }
+
+    /// Sets the block to the given name-value fields.
+    pub fn metadata_fields(self, fields: &HeaderMap) -> Result<Self, WARCError> {
+        self.warc_fields(fields)
+    }
+}
+
+impl Default for RecordBuilder<MetadataKind> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RecordBuilder<RevisitKind> {
+    /// Creates a builder for a revisit record with the given profile.
+    ///
+    /// See [IDENTICAL_PAYLOAD_DIGEST_PROFILE] and [SERVER_NOT_MODIFIED_PROFILE].
+    pub fn new(target_uri: &str, profile: &str) -> Self {
+        Self::new_with_target_uri(target_uri).field("WARC-Profile", profile)
+    }
+
+    /// Sets the ID of the record that contains the revisited content.
+    pub fn refers_to(self, record_id: &str) -> Self {
+        self.field("WARC-Refers-To", record_id)
+    }
+
+    /// Sets the target URI of the record that contains the revisited content.
+    pub fn refers_to_target_uri(self, target_uri: &str) -> Self {
+        self.field("WARC-Refers-To-Target-URI", target_uri)
+    }
+
+    /// Sets the date of the record that contains the revisited content.
+    pub fn refers_to_date(self, date: &str) -> Self {
+        self.field("WARC-Refers-To-Date", date)
+    }
+}
+
+impl RecordBuilder<ConversionKind> {
+    /// Creates a builder for a conversion record.
+    pub fn new(target_uri: &str) -> Self {
+        Self::new_with_target_uri(target_uri)
+    }
+
+    /// Sets the ID of the record that was converted.
+    pub fn refers_to(self, record_id: &str) -> Self {
+        self.field("WARC-Refers-To", record_id)
+    }
+}
+
+impl RecordBuilder<ContinuationKind> {
+    /// Creates a builder for a continuation record.
+    ///
+    /// The segment number is the position of the segment starting from 1 for
+    /// the first record, so it must be at least 2.
+    pub fn new(target_uri: &str, segment_origin_id: &str, segment_number: u64) -> Self {
+        Self::new_with_target_uri(target_uri)
+            .field("WARC-Segment-Origin-ID", segment_origin_id)
+            .field("WARC-Segment-Number", &segment_number.to_string())
+    }
+
+    /// Sets the total length of all segments for the last segment.
+    pub fn segment_total_length(self, length: u64) -> Self {
+        self.field("WARC-Segment-Total-Length", &length.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_type_parse() {
+        assert_eq!(
+            WARCRecordType::from_str("response").unwrap(),
+            WARCRecordType::Response
+        );
+        assert_eq!(
+            WARCRecordType::from_str("Continuation").unwrap(),
+            WARCRecordType::Continuation
+        );
+        assert!(WARCRecordType::from_str("abc").is_err());
+        assert_eq!(WARCRecordType::Warcinfo.to_string(), "warcinfo");
+    }
+
+    #[test]
+    fn test_response_builder() {
+        let record = ResponseRecordBuilder::new("http://example.com/")
+            .concurrent_to("<urn:uuid:00000001-0002-0003-0004-000000000005>")
+            .payload(b"HTTP/1.1 204 No Content\r\n\r\n".as_slice())
+            .build()
+            .unwrap();
+
+        assert_eq!(record.header.get_str("WARC-Type"), Some("response"));
+        assert_eq!(
+            record.header.get_str("WARC-Target-URI"),
+            Some("http://example.com/")
+        );
+        assert_eq!(record.header.get_str("Content-Length"), Some("27"));
+        assert!(record
+            .header
+            .get_str("WARC-Record-ID")
+            .unwrap()
+            .starts_with("<urn:uuid:"));
+        assert!(record.header.get_str("WARC-Date").unwrap().ends_with('Z'));
+        assert_eq!(record.block.len(), 27);
+    }
+
+    #[test]
+    fn test_builder_validation() {
+        assert!(ResourceRecordBuilder::new("http://example.com/")
+            .build()
+            .is_err());
+        assert!(ResourceRecordBuilder::new("http://example.com/")
+            .content_length(0)
+            .date(&Utc::now())
+            .build()
+            .is_ok());
+        assert!(ResourceRecordBuilder::new("http://example.com/")
+            .content_length(0)
+            .record_id("urn:uuid:00000001-0002-0003-0004-000000000005")
+            .build()
+            .is_err());
+        assert!(ResourceRecordBuilder::new("http://example.com/")
+            .content_length(0)
+            .field("WARC-Date", "yesterday")
+            .build()
+            .is_err());
+        assert!(
+            ContinuationRecordBuilder::new("http://example.com/", "<urn:test:1>", 2)
+                .concurrent_to("<urn:test:2>")
+                .payload(b"abc".as_slice())
+                .build()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_warcinfo_builder() {
+        let mut fields = HeaderMap::new();
+        fields.insert("software", "webaves");
+
+        let record = WarcinfoRecordBuilder::new()
+            .filename("example.warc.gz")
+            .info_fields(&fields)
+            .unwrap()
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            record.header.get_str("Content-Type"),
+            Some("application/warc-fields")
+        );
+        assert_eq!(record.block, b"software: webaves\r\n");
+    }
+}
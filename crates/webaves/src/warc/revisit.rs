@@ -0,0 +1,314 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufReader, Cursor, Read},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    http::{HTTPError, ResponseHeader},
+    io::ComboReader,
+};
+
+use super::{
+    index::{is_http_response, trim_angle_brackets},
+    HeaderMetadata, WARCError, WARCReader, WARCRecordType,
+};
+
+// Fields that describe how the payload is framed in the message. They are
+// taken from the original response because its payload is used unchanged.
+const PAYLOAD_FRAMING_FIELDS: [&str; 3] =
+    ["Content-Length", "Transfer-Encoding", "Content-Encoding"];
+
+/// Location of a record that may be referenced by revisit records.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OriginalLocation {
+    /// Path of the file containing the record.
+    pub path: PathBuf,
+    /// Position of the record in the (compressed) file.
+    pub offset: u64,
+    /// ID of the record.
+    pub record_id: String,
+}
+
+/// Index of HTTP responses referenced by revisit records in multiple WARC
+/// files.
+///
+/// `response` records are indexed by `WARC-Record-ID` and by
+/// `WARC-Payload-Digest` with `WARC-Target-URI`. A revisit record is
+/// resolved using `WARC-Refers-To` if present and indexed, otherwise by its
+/// payload digest and `WARC-Refers-To-Target-URI` (or `WARC-Target-URI`). Once
+/// found, the revisit can be read as a complete response with [Self::open].
+#[derive(Debug, Default)]
+pub struct RevisitIndex {
+    records: HashMap<String, OriginalLocation>,
+    digests: HashMap<(String, String), String>,
+}
+
+impl RevisitIndex {
+    /// Creates an empty `RevisitIndex`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads the given WARC file and adds all HTTP responses found.
+    pub fn add_file<P: AsRef<Path>>(&mut self, path: P) -> Result<(), WARCError> {
+        let path = path.as_ref();
+
+        tracing::debug!(?path, "add_file");
+
+        let file = File::open(path)?;
+        let mut reader = WARCReader::new(BufReader::new(file))?;
+
+        while let Some(metadata) = reader.begin_record()? {
+            self.add_record(path, &metadata);
+            std::io::copy(&mut reader.read_block(), &mut std::io::sink())?;
+            reader.end_record()?;
+        }
+
+        Ok(())
+    }
+
+    /// Adds the record if it is an HTTP response.
+    ///
+    /// The record's [HeaderMetadata::raw_file_offset] must be usable with
+    /// [WARCReader::open_at].
+    ///
+    /// Returns whether the record was added.
+    pub fn add_record(&mut self, path: &Path, metadata: &HeaderMetadata) -> bool {
+        let fields = metadata.fields();
+
+        if metadata.record_type() != Some(WARCRecordType::Response) || !is_http_response(metadata) {
+            return false;
+        }
+
+        let record_id = match fields.get_str("WARC-Record-ID") {
+            Some(value) => value.to_string(),
+            None => return false,
+        };
+
+        if let (Some(digest), Some(url)) = (
+            fields.get_str("WARC-Payload-Digest"),
+            fields.get_str("WARC-Target-URI"),
+        ) {
+            self.digests
+                .entry(digest_key(digest, url))
+                .or_insert_with(|| record_id.clone());
+        }
+
+        self.records.insert(
+            record_id.clone(),
+            OriginalLocation {
+                path: path.to_path_buf(),
+                offset: metadata.raw_file_offset(),
+                record_id,
+            },
+        );
+
+        true
+    }
+
+    /// Returns the number of records in the index.
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Returns whether the index is empty.
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Returns the location of the original record of the given revisit
+    /// record.
+    pub fn find(&self, metadata: &HeaderMetadata) -> Option<&OriginalLocation> {
+        let fields = metadata.fields();
+
+        if metadata.record_type() != Some(WARCRecordType::Revisit) {
+            return None;
+        }
+
+        if let Some(location) = fields
+            .get_str("WARC-Refers-To")
+            .and_then(|record_id| self.records.get(record_id))
+        {
+            return Some(location);
+        }
+
+        let digest = fields.get_str("WARC-Payload-Digest")?;
+        let url = fields
+            .get_str("WARC-Refers-To-Target-URI")
+            .or_else(|| fields.get_str("WARC-Target-URI"))?;
+        let record_id = self.digests.get(&digest_key(digest, url))?;
+
+        self.records.get(record_id)
+    }
+
+    /// Opens the given revisit record for reading as a `response` record.
+    ///
+    /// The HTTP header of the revisit record is combined with the payload
+    /// of the original record. The fields describing the length and
+    /// encoding of the payload are taken from the original header. If the
+    /// revisit block does not contain an HTTP header, or contains a
+    /// "304 Not Modified" header, the original status line is used.
+    ///
+    /// Returns the header of the combined record, which can be used like a
+    /// `response` record such as with
+    /// [ExtractorDispatcher](super::extract::ExtractorDispatcher), and a
+    /// reader for its block.
+    ///
+    /// Returns [WARCError::InvalidFieldValue] if the original record is not
+    /// in the index.
+    pub fn open<R: Read>(
+        &self,
+        metadata: &HeaderMetadata,
+        mut revisit_block: R,
+    ) -> Result<(HeaderMetadata, RevisitBlockReader), WARCError> {
+        let location = self
+            .find(metadata)
+            .ok_or_else(|| WARCError::InvalidFieldValue {
+                name: "WARC-Refers-To".to_string(),
+                record_id: metadata
+                    .fields()
+                    .get_str("WARC-Record-ID")
+                    .unwrap_or_default()
+                    .to_string(),
+                source: None,
+            })?;
+
+        tracing::debug!(
+            path = ?location.path,
+            offset = location.offset,
+            record_id = location.record_id.as_str(),
+            "open original"
+        );
+
+        let mut revisit_data = Vec::new();
+        revisit_block.read_to_end(&mut revisit_data)?;
+
+        let (original_metadata, original_block) = open_original(location)?;
+        let mut original_block = ComboReader::new(original_block);
+        let mut original_header_data = Vec::new();
+        crate::header::read_until_boundary(
+            &mut original_block,
+            &mut original_header_data,
+            original_metadata.block_length(),
+        )?;
+        let original_header = parse_response_header(&original_header_data)?;
+
+        let mut header = if revisit_data.is_empty() {
+            original_header.clone()
+        } else {
+            let mut revisit_header_data = Vec::new();
+            crate::header::read_until_boundary(
+                revisit_data.as_slice(),
+                &mut revisit_header_data,
+                revisit_data.len() as u64,
+            )?;
+            parse_response_header(&revisit_header_data)?
+        };
+
+        if header.status_line.status_code == 304 {
+            header.status_line = original_header.status_line.clone();
+        }
+
+        for name in PAYLOAD_FRAMING_FIELDS {
+            header.fields.remove(name);
+
+            for value in original_header.fields.get_all(name) {
+                header.fields.append(name, value.clone());
+            }
+        }
+
+        let mut header_data = Vec::new();
+        header.format(&mut header_data).map_err(into_warc_error)?;
+        header_data.extend_from_slice(b"\r\n");
+
+        let block_length = header_data.len() as u64 + original_metadata.block_length()
+            - original_header_data.len() as u64;
+
+        let mut fields = metadata.fields().clone();
+        fields.insert("WARC-Type", "response");
+        fields.insert("Content-Length", block_length.to_string());
+        fields.remove("WARC-Block-Digest");
+        fields.remove("WARC-Profile");
+        fields.remove("WARC-Truncated");
+
+        if let Some(content_type) = original_metadata.fields().get_str("Content-Type") {
+            fields.insert("Content-Type", content_type);
+        }
+
+        let combined_metadata = HeaderMetadata::new(
+            metadata.version().to_string(),
+            fields,
+            block_length,
+            metadata.file_offset(),
+            metadata.raw_file_offset(),
+        );
+        let reader = RevisitBlockReader {
+            inner: Cursor::new(header_data).chain(original_block),
+        };
+
+        Ok((combined_metadata, reader))
+    }
+}
+
+/// Reader stream for the block of a revisit record combined with the
+/// payload of the original record.
+pub struct RevisitBlockReader {
+    inner: std::io::Chain<Cursor<Vec<u8>>, ComboReader<OriginalBlockReader>>,
+}
+
+impl Read for RevisitBlockReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.inner.read(buf)
+    }
+}
+
+struct OriginalBlockReader {
+    reader: WARCReader<'static, BufReader<File>>,
+}
+
+impl Read for OriginalBlockReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.reader.read_block_partial(buf)
+    }
+}
+
+// Opens the file at the offset and skips to the record. The offset is
+// the start of the compression member which may contain other records
+// if the records are not compressed individually.
+fn open_original(
+    location: &OriginalLocation,
+) -> Result<(HeaderMetadata, OriginalBlockReader), WARCError> {
+    let file = File::open(&location.path)?;
+    let mut reader = WARCReader::open_at(BufReader::new(file), location.offset)?;
+
+    loop {
+        let metadata = reader.begin_record()?.ok_or(WARCError::UnknownFormat)?;
+
+        if metadata.fields().get_str("WARC-Record-ID") == Some(location.record_id.as_str()) {
+            return Ok((metadata, OriginalBlockReader { reader }));
+        }
+
+        std::io::copy(&mut reader.read_block(), &mut std::io::sink())?;
+        reader.end_record()?;
+    }
+}
+
+fn parse_response_header(data: &[u8]) -> Result<ResponseHeader, WARCError> {
+    ResponseHeader::parse_from(crate::stringutil::trim_trailing_crlf(data)).map_err(into_warc_error)
+}
+
+fn into_warc_error(error: HTTPError) -> WARCError {
+    match error {
+        HTTPError::Io(error) => WARCError::Io(error),
+        error => WARCError::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, error)),
+    }
+}
+
+fn digest_key(digest: &str, url: &str) -> (String, String) {
+    (
+        digest.trim().to_string(),
+        trim_angle_brackets(url.trim()).to_string(),
+    )
+}
@@ -0,0 +1,587 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Read, Seek},
+    path::{Path, PathBuf},
+};
+
+use chrono::Utc;
+
+use crate::{
+    compress::{CompressionFormat, CompressionLevel},
+    header::HeaderMap,
+};
+
+use super::{
+    BlockWriter, ContinuationRecordBuilder, HeaderMapExt, WARCError, WARCRecord, WARCWriter,
+    WarcinfoRecordBuilder,
+};
+
+/// Default maximum size of a file written by [RotatingWARCWriter].
+pub const DEFAULT_MAX_FILE_SIZE: u64 = 1_000_000_000;
+
+/// Suffix added to filenames while files are being written.
+pub const OPEN_FILE_SUFFIX: &str = ".open";
+
+/// Smallest block size of a segment written by
+/// [RotatingWARCWriter::write_segmented_record].
+pub const MIN_SEGMENT_SIZE: u64 = 65536;
+
+/// Location of a record written to a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordLocation {
+    /// Path of the file after it is closed.
+    pub path: PathBuf,
+    /// Position of the record in the (compressed) file.
+    pub offset: u64,
+    /// Length of the record in the (compressed) file.
+    pub length: u64,
+}
+
+impl RecordLocation {
+    /// Returns the filename portion of the path.
+    pub fn filename(&self) -> &str {
+        self.path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default()
+    }
+}
+
+/// Writes records to a series of WARC files.
+///
+/// Files are named `PREFIX-YYYYMMDDhhmmss-SERIAL-HOST.warc.gz` (the
+/// extension depends on the compression format) and are created in the
+/// given directory. While a file is being written, the [OPEN_FILE_SUFFIX]
+/// is appended to the filename. When a file is closed, it is renamed to
+/// its final name.
+///
+/// A new file is started when the file size or the number of records
+/// reaches the configured limits. Each file begins with a warcinfo record
+/// unless disabled with [Self::set_warcinfo_enabled].
+///
+/// [Self::close] should be called to finish the last file and check for
+/// errors. If the writer is dropped instead, the last file is finished and
+/// renamed on a best-effort basis. A file with an unfinished record keeps
+/// the [OPEN_FILE_SUFFIX].
+pub struct RotatingWARCWriter<'a> {
+    directory: PathBuf,
+    prefix: String,
+    hostname: String,
+    serial: u64,
+
+    compression_format: CompressionFormat,
+    compression_level: CompressionLevel,
+    digest_algorithm: Option<String>,
+    max_file_size: Option<u64>,
+    max_record_count: Option<u64>,
+    warcinfo_enabled: bool,
+    warcinfo_fields: HeaderMap,
+
+    writer: Option<WARCWriter<'a, BufWriter<File>>>,
+    path: PathBuf,
+    warcinfo_id: String,
+    record_count: u64,
+    record_offset: u64,
+    record_in_progress: bool,
+}
+
+impl<'a> RotatingWARCWriter<'a> {
+    /// Creates a `RotatingWARCWriter` with the given output directory and
+    /// filename prefix.
+    ///
+    /// Files are Gzip compressed by default.
+    pub fn new<P: Into<PathBuf>, S: Into<String>>(directory: P, prefix: S) -> Self {
+        let hostname = whoami::hostname();
+
+        let mut warcinfo_fields = HeaderMap::new();
+        warcinfo_fields.insert("software", format!("webaves/{}", env!("CARGO_PKG_VERSION")));
+        warcinfo_fields.insert("hostname", hostname.as_str());
+        warcinfo_fields.insert("format", "WARC File Format 1.1");
+        warcinfo_fields.insert(
+            "conformsTo",
+            "http://iipc.github.io/warc-specifications/specifications/warc-format/warc-1.1/",
+        );
+
+        Self {
+            directory: directory.into(),
+            prefix: prefix.into(),
+            hostname,
+            serial: 0,
+            compression_format: CompressionFormat::Gzip,
+            compression_level: CompressionLevel::default(),
+            digest_algorithm: None,
+            max_file_size: Some(DEFAULT_MAX_FILE_SIZE),
+            max_record_count: None,
+            warcinfo_enabled: true,
+            warcinfo_fields,
+            writer: None,
+            path: PathBuf::new(),
+            warcinfo_id: String::new(),
+            record_count: 0,
+            record_offset: 0,
+            record_in_progress: false,
+        }
+    }
+
+    /// Sets the compression format and level of new files.
+    pub fn set_compression(
+        &mut self,
+        compression_format: CompressionFormat,
+        compression_level: CompressionLevel,
+    ) {
+        self.compression_format = compression_format;
+        self.compression_level = compression_level;
+    }
+
+    /// Returns the compression format of new files.
+    ///
+    /// Default: [CompressionFormat::Gzip]
+    pub fn compression_format(&self) -> CompressionFormat {
+        self.compression_format
+    }
+
+    /// Sets the hash algorithm name used for computing digests.
+    ///
+    /// See [WARCWriter::set_digest_algorithm].
+    pub fn set_digest_algorithm(&mut self, algorithm: Option<&str>) -> Result<(), WARCError> {
+        let mut writer = WARCWriter::new(std::io::sink());
+        writer.set_digest_algorithm(algorithm)?;
+        self.digest_algorithm = writer.digest_algorithm().map(|value| value.to_string());
+
+        Ok(())
+    }
+
+    /// Returns the maximum size of a file.
+    ///
+    /// The size may be exceeded by the last record in the file.
+    ///
+    /// Default: [DEFAULT_MAX_FILE_SIZE]
+    pub fn max_file_size(&self) -> Option<u64> {
+        self.max_file_size
+    }
+
+    /// Sets the maximum size of a file.
+    pub fn set_max_file_size(&mut self, max_file_size: Option<u64>) {
+        self.max_file_size = max_file_size;
+    }
+
+    /// Returns the maximum number of records in a file excluding the
+    /// warcinfo record.
+    ///
+    /// Default: `None`
+    pub fn max_record_count(&self) -> Option<u64> {
+        self.max_record_count
+    }
+
+    /// Sets the maximum number of records in a file excluding the
+    /// warcinfo record.
+    pub fn set_max_record_count(&mut self, max_record_count: Option<u64>) {
+        self.max_record_count = max_record_count;
+    }
+
+    /// Returns the hostname used in filenames.
+    ///
+    /// Default: the hostname of the system
+    pub fn hostname(&self) -> &str {
+        self.hostname.as_ref()
+    }
+
+    /// Sets the hostname used in filenames.
+    pub fn set_hostname<S: Into<String>>(&mut self, hostname: S) {
+        self.hostname = hostname.into();
+    }
+
+    /// Returns the serial number of the next file.
+    pub fn serial(&self) -> u64 {
+        self.serial
+    }
+
+    /// Sets the serial number of the next file.
+    pub fn set_serial(&mut self, serial: u64) {
+        self.serial = serial;
+    }
+
+    /// Returns whether each file begins with a warcinfo record.
+    ///
+    /// Default: true
+    pub fn warcinfo_enabled(&self) -> bool {
+        self.warcinfo_enabled
+    }
+
+    /// Sets whether each file begins with a warcinfo record.
+    pub fn set_warcinfo_enabled(&mut self, value: bool) {
+        self.warcinfo_enabled = value;
+    }
+
+    /// Returns the name-value fields written in the warcinfo records.
+    pub fn warcinfo_fields(&self) -> &HeaderMap {
+        &self.warcinfo_fields
+    }
+
+    /// Returns a mutable reference to the name-value fields written in
+    /// the warcinfo records.
+    pub fn warcinfo_fields_mut(&mut self) -> &mut HeaderMap {
+        &mut self.warcinfo_fields
+    }
+
+    /// Returns the path of the current file after it is closed.
+    ///
+    /// Returns `None` if no file is open.
+    pub fn current_path(&self) -> Option<&Path> {
+        if self.writer.is_some() {
+            Some(&self.path)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the ID of the warcinfo record of the current file.
+    ///
+    /// Returns `None` if no file is open or warcinfo records are disabled.
+    pub fn warcinfo_id(&self) -> Option<&str> {
+        if self.writer.is_some() && !self.warcinfo_id.is_empty() {
+            Some(&self.warcinfo_id)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the position in the current file where the next record
+    /// will be written.
+    ///
+    /// Returns `None` if no file is open.
+    pub fn current_offset(&mut self) -> Result<Option<u64>, WARCError> {
+        match &mut self.writer {
+            Some(writer) => Ok(Some(stream_position(writer.get_mut())?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Starts a new file if needed and returns the path of the file that
+    /// the next record will be written to.
+    ///
+    /// This is useful when the header of the next record depends on the
+    /// filename.
+    pub fn prepare_record(&mut self) -> Result<&Path, WARCError> {
+        self.prepare_file()?;
+        Ok(&self.path)
+    }
+
+    /// Begins a record by writing the header.
+    ///
+    /// A new file is started if needed.
+    ///
+    /// See [WARCWriter::begin_record].
+    pub fn begin_record(&mut self, header: &HeaderMap) -> Result<(), WARCError> {
+        self.prepare_file()?;
+        self.begin_record_in_file(header)
+    }
+
+    fn begin_record_in_file(&mut self, header: &HeaderMap) -> Result<(), WARCError> {
+        let writer = self.writer.as_mut().unwrap();
+        self.record_offset = stream_position(writer.get_mut())?;
+        self.record_in_progress = true;
+        writer.begin_record(header)
+    }
+
+    /// Starts writing a record body.
+    ///
+    /// See [WARCWriter::write_block].
+    pub fn write_block(&mut self) -> BlockWriter<'a, '_, BufWriter<File>> {
+        self.writer.as_mut().unwrap().write_block()
+    }
+
+    /// Finish writing a record and returns its location.
+    ///
+    /// See [WARCWriter::end_record].
+    pub fn end_record(&mut self) -> Result<RecordLocation, WARCError> {
+        let writer = self.writer.as_mut().unwrap();
+        writer.end_record()?;
+        self.record_count += 1;
+        self.record_in_progress = false;
+
+        let end_offset = stream_position(writer.get_mut())?;
+
+        Ok(RecordLocation {
+            path: self.path.clone(),
+            offset: self.record_offset,
+            length: end_offset - self.record_offset,
+        })
+    }
+
+    /// Writes a complete record held in memory and returns its location.
+    pub fn write_record(&mut self, record: &WARCRecord) -> Result<RecordLocation, WARCError> {
+        self.begin_record(&record.header)?;
+        std::io::Write::write_all(&mut self.write_block(), &record.block)?;
+        self.end_record()
+    }
+
+    /// Writes a record that is already formatted and returns its location.
+    ///
+    /// The data is copied to the file as is, so it must be a complete
+    /// record encoded in the compression format of the files. For
+    /// compressed files, the record must be a separate compression member.
+    pub fn write_raw_record<R: Read>(&mut self, mut data: R) -> Result<RecordLocation, WARCError> {
+        self.prepare_file()?;
+
+        let file = self.writer.as_mut().unwrap().get_mut();
+        self.record_offset = stream_position(file)?;
+        self.record_in_progress = true;
+        std::io::copy(&mut data, file)?;
+        self.record_count += 1;
+        self.record_in_progress = false;
+
+        let end_offset = stream_position(file)?;
+
+        Ok(RecordLocation {
+            path: self.path.clone(),
+            offset: self.record_offset,
+            length: end_offset - self.record_offset,
+        })
+    }
+
+    // Returns the writer of the current file. A file must be open.
+    pub(super) fn writer_mut(&mut self) -> &mut WARCWriter<'a, BufWriter<File>> {
+        self.writer.as_mut().unwrap()
+    }
+
+    /// Writes a record, splitting the block into segments across files if
+    /// the block does not fit within the maximum file size.
+    ///
+    /// The header must contain the `WARC-Record-ID`, `WARC-Date`, and
+    /// `Content-Length` fields. The given block must provide exactly
+    /// `Content-Length` bytes.
+    ///
+    /// If the block is split, the first segment keeps the given header with
+    /// the `WARC-Segment-Number` field added. Any `WARC-Block-Digest` field is
+    /// removed because it does not apply to the segment. The rest of the
+    /// block is written as `continuation` records in new files. Segments are
+    /// at least [MIN_SEGMENT_SIZE] bytes so the maximum file size can be
+    /// exceeded by a small amount.
+    ///
+    /// Returns the location of each segment.
+    pub fn write_segmented_record<R: Read>(
+        &mut self,
+        header: &HeaderMap,
+        mut block: R,
+    ) -> Result<Vec<RecordLocation>, WARCError> {
+        let total_length = header.get_parsed_required::<u64>("Content-Length")?;
+        let origin_id = header.get_required("WARC-Record-ID")?.to_string();
+        let date = header.get_required("WARC-Date")?.to_string();
+        let target_uri = header
+            .get_str("WARC-Target-URI")
+            .unwrap_or_default()
+            .to_string();
+
+        let mut locations = Vec::new();
+        let mut remaining = total_length;
+        let mut segment_number = 1;
+
+        loop {
+            self.prepare_file()?;
+            let mut available = self.available_space()?;
+
+            if remaining > available && available < MIN_SEGMENT_SIZE && self.record_count > 0 {
+                self.close()?;
+                self.prepare_file()?;
+                available = self.available_space()?;
+            }
+
+            let length = if remaining <= available {
+                remaining
+            } else {
+                available.max(MIN_SEGMENT_SIZE).min(remaining)
+            };
+
+            tracing::debug!(segment_number, length, remaining, "write segment");
+
+            let segment_header = if segment_number == 1 && length == total_length {
+                header.clone()
+            } else if segment_number == 1 {
+                let mut segment_header = header.clone();
+                segment_header.remove("WARC-Block-Digest");
+                segment_header.insert("WARC-Segment-Number", "1");
+                segment_header.insert("Content-Length", length.to_string());
+                segment_header
+            } else {
+                let mut builder =
+                    ContinuationRecordBuilder::new(&target_uri, &origin_id, segment_number)
+                        .field("WARC-Date", &date)
+                        .content_length(length);
+
+                if !self.warcinfo_id.is_empty() {
+                    builder = builder.warcinfo_id(&self.warcinfo_id);
+                }
+
+                if length == remaining {
+                    builder = builder.segment_total_length(total_length);
+                }
+
+                builder.build()?.header
+            };
+
+            self.begin_record_in_file(&segment_header)?;
+            let amount = std::io::copy(&mut block.by_ref().take(length), &mut self.write_block())?;
+
+            if amount != length {
+                return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+            }
+
+            locations.push(self.end_record()?);
+            remaining -= length;
+
+            if remaining == 0 {
+                break;
+            }
+
+            self.close()?;
+            segment_number += 1;
+        }
+
+        Ok(locations)
+    }
+
+    /// Finishes the current file and renames it to its final name.
+    ///
+    /// The next record will be written to a new file.
+    pub fn close(&mut self) -> Result<(), WARCError> {
+        if let Some(writer) = self.writer.take() {
+            let file = writer
+                .into_inner()
+                .into_inner()
+                .map_err(|error| error.into_error())?;
+            file.sync_all()?;
+            drop(file);
+
+            tracing::debug!(path = ?self.path, "close file");
+
+            std::fs::rename(open_path(&self.path), &self.path)?;
+        }
+
+        Ok(())
+    }
+
+    fn prepare_file(&mut self) -> Result<(), WARCError> {
+        if self.needs_new_file()? {
+            self.close()?;
+        }
+
+        if self.writer.is_none() {
+            self.open_file()?;
+        }
+
+        Ok(())
+    }
+
+    fn available_space(&mut self) -> Result<u64, WARCError> {
+        match self.max_file_size {
+            Some(max_file_size) => {
+                let offset = stream_position(self.writer.as_mut().unwrap().get_mut())?;
+                Ok(max_file_size.saturating_sub(offset))
+            }
+            None => Ok(u64::MAX),
+        }
+    }
+
+    fn needs_new_file(&mut self) -> Result<bool, WARCError> {
+        let writer = match &mut self.writer {
+            Some(writer) => writer,
+            None => return Ok(false),
+        };
+
+        if let Some(max_record_count) = self.max_record_count {
+            if self.record_count >= max_record_count {
+                return Ok(true);
+            }
+        }
+
+        if let Some(max_file_size) = self.max_file_size {
+            if stream_position(writer.get_mut())? >= max_file_size {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+
+    fn open_file(&mut self) -> Result<(), WARCError> {
+        let filename = self.make_filename();
+        self.path = self.directory.join(&filename);
+        self.serial += 1;
+        self.record_count = 0;
+
+        tracing::debug!(path = ?self.path, "open file");
+
+        let file = std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(open_path(&self.path))?;
+        let mut writer = WARCWriter::new_compressed(
+            BufWriter::new(file),
+            self.compression_format,
+            self.compression_level,
+        );
+        writer.set_digest_algorithm(self.digest_algorithm.as_deref())?;
+
+        self.warcinfo_id.clear();
+
+        if self.warcinfo_enabled {
+            let warcinfo = WarcinfoRecordBuilder::new()
+                .filename(&filename)
+                .info_fields(&self.warcinfo_fields)?
+                .build()?;
+            writer.write_record(&warcinfo)?;
+
+            self.warcinfo_id = warcinfo
+                .header
+                .get_str("WARC-Record-ID")
+                .unwrap_or_default()
+                .to_string();
+        }
+
+        self.writer = Some(writer);
+
+        Ok(())
+    }
+
+    fn make_filename(&self) -> String {
+        let extension = match self.compression_format {
+            CompressionFormat::Gzip => ".warc.gz",
+            CompressionFormat::Zstd => ".warc.zst",
+            _ => ".warc",
+        };
+
+        format!(
+            "{}-{}-{:05}-{}{}",
+            self.prefix,
+            Utc::now().format("%Y%m%d%H%M%S"),
+            self.serial,
+            self.hostname,
+            extension
+        )
+    }
+}
+
+impl<'a> Drop for RotatingWARCWriter<'a> {
+    fn drop(&mut self) {
+        if self.record_in_progress {
+            return;
+        }
+
+        if let Err(error) = self.close() {
+            tracing::warn!(path = ?self.path, %error, "close file on drop failed");
+        }
+    }
+}
+
+// Position in the file including data not yet flushed from the buffer.
+fn stream_position(file: &mut BufWriter<File>) -> std::io::Result<u64> {
+    let buffered = file.buffer().len() as u64;
+    Ok(file.get_mut().stream_position()? + buffered)
+}
+
+fn open_path(path: &Path) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(OPEN_FILE_SUFFIX);
+    PathBuf::from(path)
+}
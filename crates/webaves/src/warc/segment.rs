@@ -0,0 +1,278 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fs::File,
+    io::{BufReader, Read},
+    path::{Path, PathBuf},
+};
+
+use super::{HeaderMapExt, HeaderMetadata, WARCError, WARCReader, WARCRecordType};
+
+/// Location of a segment of a segmented record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SegmentLocation {
+    /// Path of the file containing the segment.
+    pub path: PathBuf,
+    /// Position of the record in the (compressed) file.
+    pub offset: u64,
+    /// Position of the segment starting from 1.
+    pub segment_number: u64,
+    /// Length of the segment's block.
+    pub block_length: u64,
+}
+
+#[derive(Debug, Default)]
+struct SegmentedRecord {
+    first: Option<HeaderMetadata>,
+    segments: Vec<SegmentLocation>,
+    total_length: Option<u64>,
+}
+
+/// Index of segmented records in multiple WARC files.
+///
+/// Records split with `WARC-Segment-Number` and `continuation` records are
+/// collected by the ID of the first segment. Once all the segments have been
+/// found, the record can be read as a single block with [Self::open].
+#[derive(Debug, Default)]
+pub struct SegmentIndex {
+    records: HashMap<String, SegmentedRecord>,
+}
+
+impl SegmentIndex {
+    /// Creates an empty `SegmentIndex`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads the given WARC file and adds all segments found.
+    pub fn add_file<P: AsRef<Path>>(&mut self, path: P) -> Result<(), WARCError> {
+        let path = path.as_ref();
+
+        tracing::debug!(?path, "add_file");
+
+        let file = File::open(path)?;
+        let mut reader = WARCReader::new(BufReader::new(file))?;
+
+        while let Some(metadata) = reader.begin_record()? {
+            self.add_record(path, &metadata)?;
+            std::io::copy(&mut reader.read_block(), &mut std::io::sink())?;
+            reader.end_record()?;
+        }
+
+        Ok(())
+    }
+
+    /// Adds the record if it is a segment.
+    ///
+    /// The record's [HeaderMetadata::raw_file_offset] must be usable with
+    /// [WARCReader::open_at].
+    ///
+    /// Returns whether the record is a segment.
+    pub fn add_record(
+        &mut self,
+        path: &Path,
+        metadata: &HeaderMetadata,
+    ) -> Result<bool, WARCError> {
+        let fields = metadata.fields();
+        let segment_number = match fields.get_parsed::<u64>("WARC-Segment-Number")? {
+            Some(value) => value,
+            None => return Ok(false),
+        };
+
+        let location = SegmentLocation {
+            path: path.to_path_buf(),
+            offset: metadata.raw_file_offset(),
+            segment_number,
+            block_length: metadata.block_length(),
+        };
+
+        if metadata.record_type() == Some(WARCRecordType::Continuation) {
+            let origin_id = fields.get_required("WARC-Segment-Origin-ID")?;
+            let total_length = fields.get_parsed::<u64>("WARC-Segment-Total-Length")?;
+
+            tracing::debug!(origin_id, segment_number, "add_record continuation");
+
+            let record = self.records.entry(origin_id.to_string()).or_default();
+            record.segments.push(location);
+
+            if total_length.is_some() {
+                record.total_length = total_length;
+            }
+        } else {
+            let record_id = fields.get_required("WARC-Record-ID")?;
+
+            tracing::debug!(record_id, segment_number, "add_record first segment");
+
+            let record = self.records.entry(record_id.to_string()).or_default();
+            record.first = Some(metadata.clone());
+            record.segments.push(location);
+        }
+
+        Ok(true)
+    }
+
+    /// Returns the IDs of the first segments of all the records found.
+    pub fn record_ids(&self) -> Vec<&str> {
+        let mut ids = self
+            .records
+            .keys()
+            .map(|key| key.as_str())
+            .collect::<Vec<&str>>();
+        ids.sort_unstable();
+        ids
+    }
+
+    /// Returns the locations of the segments found for the given record
+    /// ordered by segment number.
+    pub fn segments(&self, record_id: &str) -> Vec<SegmentLocation> {
+        let mut segments = match self.records.get(record_id) {
+            Some(record) => record.segments.clone(),
+            None => Vec::new(),
+        };
+        segments.sort_by_key(|segment| segment.segment_number);
+        segments
+    }
+
+    /// Returns whether all the segments of the given record have been found.
+    pub fn is_complete(&self, record_id: &str) -> bool {
+        let record = match self.records.get(record_id) {
+            Some(record) => record,
+            None => return false,
+        };
+
+        if record.first.is_none() {
+            return false;
+        }
+
+        let total_length = match record.total_length {
+            Some(value) => value,
+            None => return false,
+        };
+
+        let segments = self.segments(record_id);
+
+        segments
+            .iter()
+            .enumerate()
+            .all(|(index, segment)| segment.segment_number == index as u64 + 1)
+            && segments
+                .iter()
+                .map(|segment| segment.block_length)
+                .sum::<u64>()
+                == total_length
+    }
+
+    /// Opens the given record for reading the block of all segments.
+    ///
+    /// Returns the header of the first segment and a reader for the
+    /// reassembled block. In the returned header, `Content-Length` is the
+    /// total length and `WARC-Segment-Number` is removed so it can be used
+    /// like an unsegmented record, such as with
+    /// [ExtractorDispatcher](super::extract::ExtractorDispatcher).
+    ///
+    /// Returns [WARCError::IncompleteSegments] if segments are missing.
+    pub fn open(
+        &self,
+        record_id: &str,
+    ) -> Result<(HeaderMetadata, SegmentedBlockReader), WARCError> {
+        if !self.is_complete(record_id) {
+            return Err(WARCError::IncompleteSegments {
+                record_id: record_id.to_string(),
+            });
+        }
+
+        let record = &self.records[record_id];
+        let first = record.first.as_ref().unwrap();
+        let total_length = record.total_length.unwrap();
+
+        let mut fields = first.fields().clone();
+        fields.remove("WARC-Segment-Number");
+        fields.insert("Content-Length", total_length.to_string());
+
+        let metadata = HeaderMetadata::new(
+            first.version().to_string(),
+            fields,
+            total_length,
+            first.file_offset(),
+            first.raw_file_offset(),
+        );
+        let reader = SegmentedBlockReader {
+            segments: self.segments(record_id).into(),
+            current: None,
+        };
+
+        Ok((metadata, reader))
+    }
+}
+
+/// Reader stream for the block of a segmented record.
+///
+/// Each segment is opened and read in order.
+pub struct SegmentedBlockReader {
+    segments: VecDeque<SegmentLocation>,
+    current: Option<WARCReader<'static, BufReader<File>>>,
+}
+
+impl SegmentedBlockReader {
+    fn open_segment(
+        segment: &SegmentLocation,
+    ) -> Result<WARCReader<'static, BufReader<File>>, WARCError> {
+        tracing::debug!(
+            path = ?segment.path,
+            offset = segment.offset,
+            segment_number = segment.segment_number,
+            "open_segment"
+        );
+
+        let file = File::open(&segment.path)?;
+        let mut reader = WARCReader::open_at(BufReader::new(file), segment.offset)?;
+        let metadata = reader.begin_record()?.ok_or(WARCError::UnknownFormat)?;
+
+        if metadata.block_length() != segment.block_length {
+            return Err(WARCError::WrongBlockLength {
+                record_id: metadata
+                    .fields()
+                    .get_str("WARC-Record-ID")
+                    .unwrap_or_default()
+                    .to_string(),
+            });
+        }
+
+        Ok(reader)
+    }
+}
+
+impl Read for SegmentedBlockReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        loop {
+            if self.current.is_none() {
+                match self.segments.pop_front() {
+                    Some(segment) => {
+                        self.current = Some(Self::open_segment(&segment).map_err(into_io_error)?)
+                    }
+                    None => return Ok(0),
+                }
+            }
+
+            let reader = self.current.as_mut().unwrap();
+            let amount = reader.read_block_partial(buf)?;
+
+            if amount > 0 {
+                return Ok(amount);
+            }
+
+            reader.end_record().map_err(into_io_error)?;
+            self.current = None;
+        }
+    }
+}
+
+fn into_io_error(error: WARCError) -> std::io::Error {
+    match error {
+        WARCError::Io(error) => error,
+        error => std::io::Error::other(error),
+    }
+}
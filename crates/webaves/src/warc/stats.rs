@@ -0,0 +1,200 @@
+//! Summary statistics of WARC files.
+//!
+//! [Statistics] counts records by type, document media type, HTTP status
+//! class, and host. It also keeps the total file sizes, the range of record
+//! dates, and the digest algorithms used.
+use std::{collections::BTreeMap, io::Read};
+
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Serializer};
+use url::Url;
+
+use crate::http::ResponseHeader;
+
+use super::{
+    extract::HTTPExtractor,
+    format_date,
+    index::{get_media_type, is_http_response, trim_angle_brackets},
+    HeaderMetadata, WARCError, WARCReader,
+};
+
+/// Record types that contain documents.
+///
+/// Media types and hosts are counted only for these records.
+pub const DOCUMENT_RECORD_TYPES: [&str; 3] = ["response", "resource", "revisit"];
+
+/// Counts of records and sizes of WARC files.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Statistics {
+    /// Number of records.
+    pub record_count: u64,
+    /// Number of records for each `WARC-Type`.
+    pub record_types: BTreeMap<String, u64>,
+    /// Number of documents for each media type.
+    ///
+    /// The type is taken from the HTTP response if available. Revisit
+    /// records are counted as `warc/revisit`.
+    pub mime_types: BTreeMap<String, u64>,
+    /// Number of HTTP responses for each status class such as `2xx`.
+    pub status_classes: BTreeMap<String, u64>,
+    /// Number of documents for each host of the target URI.
+    pub hosts: BTreeMap<String, u64>,
+    /// Number of digest fields for each algorithm name.
+    pub digest_algorithms: BTreeMap<String, u64>,
+    /// Number of revisit records.
+    pub revisit_count: u64,
+    /// Total size of the files as stored.
+    pub compressed_bytes: u64,
+    /// Total size of the records after decompression.
+    pub uncompressed_bytes: u64,
+    /// Earliest `WARC-Date` value.
+    #[serde(serialize_with = "serialize_date")]
+    pub first_date: Option<DateTime<Utc>>,
+    /// Latest `WARC-Date` value.
+    #[serde(serialize_with = "serialize_date")]
+    pub last_date: Option<DateTime<Utc>>,
+}
+
+impl Statistics {
+    /// Creates an empty `Statistics`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a record to the counts.
+    ///
+    /// The HTTP header should be provided for HTTP response records.
+    pub fn add_record(&mut self, metadata: &HeaderMetadata, http_header: Option<&ResponseHeader>) {
+        let fields = metadata.fields();
+        let warc_type = fields.get_str("WARC-Type").unwrap_or_default();
+
+        self.record_count += 1;
+        increment(&mut self.record_types, warc_type);
+
+        if warc_type == "revisit" {
+            self.revisit_count += 1;
+        }
+
+        if DOCUMENT_RECORD_TYPES.contains(&warc_type) {
+            let mime = if warc_type == "revisit" {
+                Some("warc/revisit".to_string())
+            } else {
+                match http_header {
+                    Some(header) => get_media_type(header.fields.get_str("Content-Type")),
+                    None => get_media_type(fields.get_str("Content-Type")),
+                }
+            };
+            increment(&mut self.mime_types, mime.as_deref().unwrap_or("unknown"));
+
+            let url = trim_angle_brackets(fields.get_str("WARC-Target-URI").unwrap_or_default());
+
+            if let Some(host) = Url::parse(url)
+                .ok()
+                .and_then(|url| url.host_str().map(|host| host.to_ascii_lowercase()))
+            {
+                increment(&mut self.hosts, &host);
+            }
+        }
+
+        if let Some(header) = http_header {
+            let status_class = format!("{}xx", header.status_line.status_code / 100);
+            increment(&mut self.status_classes, &status_class);
+        }
+
+        for name in ["WARC-Block-Digest", "WARC-Payload-Digest"] {
+            for value in fields.get_all(name) {
+                if let Some((algorithm, _)) = value.text.split_once(':') {
+                    increment(
+                        &mut self.digest_algorithms,
+                        &algorithm.trim().to_ascii_lowercase(),
+                    );
+                }
+            }
+        }
+
+        if let Some(date) = fields
+            .get_str("WARC-Date")
+            .and_then(|value| DateTime::parse_from_rfc3339(value).ok())
+            .map(|value| value.with_timezone(&Utc))
+        {
+            self.first_date = Some(self.first_date.map_or(date, |value| value.min(date)));
+            self.last_date = Some(self.last_date.map_or(date, |value| value.max(date)));
+        }
+    }
+
+    /// Adds to the total sizes.
+    pub fn add_bytes(&mut self, compressed_bytes: u64, uncompressed_bytes: u64) {
+        self.compressed_bytes += compressed_bytes;
+        self.uncompressed_bytes += uncompressed_bytes;
+    }
+
+    /// Reads all the records from the reader and adds them.
+    ///
+    /// The reader should be created at the start of the file. The sizes
+    /// are counted from the start of the stream so that data read when
+    /// creating the reader, such as a Zstandard dictionary, is included.
+    pub fn add_reader<R: Read>(&mut self, reader: &mut WARCReader<'_, R>) -> Result<(), WARCError> {
+        while let Some(metadata) = reader.begin_record()? {
+            let mut block = reader.read_block();
+            let mut http_header = None;
+
+            if is_http_response(&metadata) {
+                match HTTPExtractor::new(&mut block) {
+                    Ok(extractor) => http_header = Some(extractor.response_header().clone()),
+                    Err(error) => tracing::debug!(?error, "stats http response"),
+                }
+            }
+
+            std::io::copy(&mut block, &mut std::io::sink())?;
+            reader.end_record()?;
+
+            self.add_record(&metadata, http_header.as_ref());
+        }
+
+        self.add_bytes(reader.raw_file_offset()?, reader.file_offset());
+
+        Ok(())
+    }
+
+    /// Adds the counts and sizes of another `Statistics`.
+    pub fn merge(&mut self, other: &Statistics) {
+        self.record_count += other.record_count;
+        self.revisit_count += other.revisit_count;
+        self.add_bytes(other.compressed_bytes, other.uncompressed_bytes);
+
+        for (map, other_map) in [
+            (&mut self.record_types, &other.record_types),
+            (&mut self.mime_types, &other.mime_types),
+            (&mut self.status_classes, &other.status_classes),
+            (&mut self.hosts, &other.hosts),
+            (&mut self.digest_algorithms, &other.digest_algorithms),
+        ] {
+            for (key, count) in other_map {
+                *map.entry(key.clone()).or_default() += count;
+            }
+        }
+
+        self.first_date = match (self.first_date, other.first_date) {
+            (Some(date), Some(other_date)) => Some(date.min(other_date)),
+            (date, other_date) => date.or(other_date),
+        };
+        self.last_date = match (self.last_date, other.last_date) {
+            (Some(date), Some(other_date)) => Some(date.max(other_date)),
+            (date, other_date) => date.or(other_date),
+        };
+    }
+}
+
+fn increment(map: &mut BTreeMap<String, u64>, key: &str) {
+    *map.entry(key.to_string()).or_default() += 1;
+}
+
+fn serialize_date<S: Serializer>(
+    value: &Option<DateTime<Utc>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match value {
+        Some(date) => serializer.serialize_some(&format_date(date)),
+        None => serializer.serialize_none(),
+    }
+}
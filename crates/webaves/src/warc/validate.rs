@@ -0,0 +1,771 @@
+//! Checking WARC files for conformance to the specification.
+//!
+//! [Validator] reads records and reports [Problem]s found such as missing
+//! mandatory fields, malformed values, and mismatched digests.
+use std::{collections::HashSet, fmt::Display, io::Read, net::IpAddr};
+
+use chrono::DateTime;
+use digest::DynDigest;
+use serde::Serialize;
+use url::Url;
+
+use crate::{compress::CompressionFormat, header::HeaderMap};
+
+use super::{
+    writer::hash_http_payload, HeaderMapExt, HeaderMetadata, LabelledDigest, WARCError, WARCReader,
+    WARCRecordType,
+};
+
+/// How serious a problem is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// The file may not be read correctly by other software.
+    Warning,
+    /// The file violates the specification.
+    Error,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// Category of a problem.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProblemKind {
+    /// The file does not begin with a WARC record.
+    NotWarc,
+    /// The record could not be parsed.
+    MalformedRecord,
+    /// Data that is not a record follows the last record.
+    TrailingData,
+    /// The WARC version is not 1.0 or 1.1.
+    UnsupportedVersion,
+    /// The `WARC-Type` is not defined by the specification.
+    UnknownRecordType,
+    /// A mandatory field is missing.
+    MissingField,
+    /// The `WARC-Date` is not a valid timestamp.
+    InvalidDate,
+    /// A record ID is not a URI enclosed in angle brackets.
+    InvalidRecordId,
+    /// The record ID was used by a previous record.
+    DuplicateRecordId,
+    /// A field value is invalid.
+    InvalidField,
+    /// `Content-Length` does not match the length of the block.
+    WrongContentLength,
+    /// `WARC-Block-Digest` does not match the block.
+    BlockDigestMismatch,
+    /// `WARC-Payload-Digest` does not match the payload.
+    PayloadDigestMismatch,
+    /// The digest algorithm is not supported.
+    UnsupportedDigest,
+    /// A referenced record ID does not exist in the files.
+    DanglingReference,
+    /// The record is not compressed as a separate member.
+    CompressionMember,
+}
+
+/// A violation found in a file.
+#[derive(Debug, Clone, Serialize)]
+pub struct Problem {
+    /// Name of the file.
+    pub filename: String,
+    /// Position of the record in the (compressed) file.
+    pub offset: u64,
+    /// ID of the record if available.
+    pub record_id: Option<String>,
+    /// How serious the problem is.
+    pub severity: Severity,
+    /// Category of the problem.
+    pub kind: ProblemKind,
+    /// Human readable description.
+    pub message: String,
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}: {}: ", self.filename, self.offset, self.severity)?;
+
+        if let Some(record_id) = &self.record_id {
+            write!(f, "{}: ", record_id)?;
+        }
+
+        write!(f, "{}", self.message)
+    }
+}
+
+struct Reference {
+    record_id: String,
+    field_name: &'static str,
+    filename: String,
+    offset: u64,
+    source_record_id: String,
+}
+
+// Location and ID of the record being checked.
+struct RecordContext<'a> {
+    filename: &'a str,
+    offset: u64,
+    record_id: Option<String>,
+}
+
+/// Checks WARC files for conformance to the specification.
+///
+/// Call [Self::validate_file] for each file and then [Self::finish] to
+/// check references between records.
+///
+/// References with `WARC-Concurrent-To`, `WARC-Refers-To`, and
+/// `WARC-Warcinfo-ID` to records not in the given files are reported as
+/// warnings since the records may be stored in other files.
+#[derive(Default)]
+pub struct Validator {
+    record_ids: HashSet<String>,
+    references: Vec<Reference>,
+    problems: Vec<Problem>,
+}
+
+impl Validator {
+    /// Creates a `Validator`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the problems found so far.
+    pub fn problems(&self) -> &[Problem] {
+        &self.problems
+    }
+
+    /// Returns whether any problems with [Severity::Error] were found.
+    pub fn has_errors(&self) -> bool {
+        self.problems
+            .iter()
+            .any(|problem| problem.severity == Severity::Error)
+    }
+
+    /// Reads all the records in the given stream and checks them.
+    ///
+    /// The filename is used in reporting problems. If a record cannot be
+    /// parsed, the problem is reported and the rest of the stream is not
+    /// checked.
+    ///
+    /// Returns the number of problems found in the file.
+    pub fn validate_file<S: Read>(
+        &mut self,
+        filename: &str,
+        stream: S,
+    ) -> Result<usize, WARCError> {
+        tracing::debug!(filename, "validate_file");
+
+        let problem_count = self.problems.len();
+
+        let mut reader = match WARCReader::new(stream) {
+            Ok(reader) => reader,
+            Err(error) => {
+                self.add_read_error(filename, 0, None, error, true);
+                return Ok(self.problems.len() - problem_count);
+            }
+        };
+        let is_compressed = reader.compression_format() != CompressionFormat::Raw;
+        let mut is_first = true;
+        let mut offset = 0;
+
+        loop {
+            let at_boundary = match get_position(&mut reader) {
+                Ok((position, at_boundary)) => {
+                    offset = position;
+                    at_boundary
+                }
+                Err(error) => {
+                    self.add_read_error(filename, offset, None, error, is_first);
+                    break;
+                }
+            };
+
+            let metadata = match reader.begin_record() {
+                Ok(Some(metadata)) => metadata,
+                Ok(None) => break,
+                Err(error) => {
+                    self.add_read_error(filename, offset, None, error, is_first);
+                    break;
+                }
+            };
+            is_first = false;
+
+            let context = RecordContext {
+                filename,
+                offset,
+                record_id: metadata
+                    .fields()
+                    .get_str("WARC-Record-ID")
+                    .map(|value| value.to_string()),
+            };
+
+            if is_compressed && !at_boundary {
+                self.add(
+                    &context,
+                    Severity::Warning,
+                    ProblemKind::CompressionMember,
+                    "record does not begin a new compression member".to_string(),
+                );
+            }
+
+            self.check_header(&context, &metadata);
+
+            if let Err(error) = self.check_block(&context, &metadata, &mut reader) {
+                self.add_read_error(filename, offset, context.record_id, error, false);
+                break;
+            }
+
+            if let Err(error) = reader.end_record() {
+                self.add_read_error(filename, offset, context.record_id, error, false);
+                break;
+            }
+
+            let at_boundary = match reader.is_member_boundary() {
+                Ok(value) => value,
+                Err(error) => {
+                    let member_offset = reader.member_raw_file_offset();
+
+                    if is_compressed && member_offset > offset {
+                        // The record's member ended but the data after it
+                        // is not a member.
+                        self.add(
+                            &RecordContext {
+                                filename,
+                                offset: member_offset,
+                                record_id: None,
+                            },
+                            Severity::Error,
+                            ProblemKind::TrailingData,
+                            "data that is not a record".to_string(),
+                        );
+                    } else {
+                        self.add_read_error(filename, offset, context.record_id, error, false);
+                    }
+                    break;
+                }
+            };
+
+            if is_compressed && !at_boundary {
+                self.add(
+                    &context,
+                    Severity::Warning,
+                    ProblemKind::CompressionMember,
+                    "compression member continues after the end of the record".to_string(),
+                );
+            }
+        }
+
+        Ok(self.problems.len() - problem_count)
+    }
+
+    /// Checks references between records and returns all the problems found.
+    pub fn finish(mut self) -> Vec<Problem> {
+        for reference in std::mem::take(&mut self.references) {
+            if !self.record_ids.contains(&reference.record_id) {
+                self.problems.push(Problem {
+                    filename: reference.filename,
+                    offset: reference.offset,
+                    record_id: Some(reference.source_record_id),
+                    severity: Severity::Warning,
+                    kind: ProblemKind::DanglingReference,
+                    message: format!(
+                        "{} refers to record {} which was not found",
+                        reference.field_name, reference.record_id
+                    ),
+                });
+            }
+        }
+
+        self.problems
+    }
+
+    fn add(
+        &mut self,
+        context: &RecordContext,
+        severity: Severity,
+        kind: ProblemKind,
+        message: String,
+    ) {
+        tracing::debug!(offset = context.offset, ?kind, %message, "problem");
+
+        self.problems.push(Problem {
+            filename: context.filename.to_string(),
+            offset: context.offset,
+            record_id: context.record_id.clone(),
+            severity,
+            kind,
+            message,
+        });
+    }
+
+    fn add_read_error(
+        &mut self,
+        filename: &str,
+        offset: u64,
+        record_id: Option<String>,
+        error: WARCError,
+        is_first: bool,
+    ) {
+        let kind = match &error {
+            WARCError::UnknownFormat if is_first => ProblemKind::NotWarc,
+            WARCError::UnknownFormat => ProblemKind::TrailingData,
+            WARCError::WrongBlockLength { .. } | WARCError::MalformedFooter { .. } => {
+                ProblemKind::WrongContentLength
+            }
+            WARCError::InvalidFieldValue { .. } => ProblemKind::InvalidField,
+            _ => ProblemKind::MalformedRecord,
+        };
+        let message = match kind {
+            ProblemKind::NotWarc => "not a WARC file".to_string(),
+            ProblemKind::TrailingData => "data that is not a record".to_string(),
+            ProblemKind::WrongContentLength => {
+                format!("Content-Length does not match the block: {}", error)
+            }
+            _ => error.to_string(),
+        };
+        let context = RecordContext {
+            filename,
+            offset,
+            record_id,
+        };
+
+        self.add(&context, Severity::Error, kind, message);
+    }
+
+    fn check_header(&mut self, context: &RecordContext, metadata: &HeaderMetadata) {
+        let fields = metadata.fields();
+
+        if metadata.version() != "WARC/1.0" && metadata.version() != "WARC/1.1" {
+            self.add(
+                context,
+                Severity::Warning,
+                ProblemKind::UnsupportedVersion,
+                format!("unsupported version {}", metadata.version()),
+            );
+        }
+
+        let record_type = match fields.get_str("WARC-Type") {
+            Some(value) => match value.parse::<WARCRecordType>() {
+                Ok(record_type) => Some(record_type),
+                Err(_) => {
+                    self.add(
+                        context,
+                        Severity::Warning,
+                        ProblemKind::UnknownRecordType,
+                        format!("unknown record type {}", value),
+                    );
+                    None
+                }
+            },
+            None => None,
+        };
+
+        let mandatory_fields = match record_type {
+            Some(record_type) => record_type.mandatory_fields(),
+            None => &["WARC-Record-ID", "Content-Length", "WARC-Date", "WARC-Type"],
+        };
+
+        for name in mandatory_fields {
+            if !fields.contains_key(*name) {
+                self.add(
+                    context,
+                    Severity::Error,
+                    ProblemKind::MissingField,
+                    format!("missing mandatory field {}", name),
+                );
+            }
+        }
+
+        self.check_record_id(context, fields);
+        self.check_date(context, metadata);
+        self.check_references(context, fields);
+        self.check_field_values(context, fields);
+
+        if let Some(record_type) = record_type {
+            self.check_type_fields(context, record_type, metadata);
+        }
+    }
+
+    fn check_record_id(&mut self, context: &RecordContext, fields: &HeaderMap) {
+        if let Some(record_id) = fields.get_str("WARC-Record-ID") {
+            if !is_valid_id(record_id) {
+                self.add(
+                    context,
+                    Severity::Error,
+                    ProblemKind::InvalidRecordId,
+                    format!("malformed WARC-Record-ID {}", record_id),
+                );
+            }
+
+            if !self.record_ids.insert(record_id.to_string()) {
+                self.add(
+                    context,
+                    Severity::Error,
+                    ProblemKind::DuplicateRecordId,
+                    "record ID is not unique".to_string(),
+                );
+            }
+        }
+    }
+
+    fn check_date(&mut self, context: &RecordContext, metadata: &HeaderMetadata) {
+        let date = match metadata.fields().get_str("WARC-Date") {
+            Some(date) => date,
+            None => return,
+        };
+
+        // WARC 1.0 requires second precision. WARC 1.1 allows fractions.
+        let is_valid = date.len() >= 20
+            && date.ends_with('Z')
+            && DateTime::parse_from_rfc3339(date).is_ok()
+            && (metadata.version() != "WARC/1.0" || date.len() == 20);
+
+        if !is_valid {
+            self.add(
+                context,
+                Severity::Error,
+                ProblemKind::InvalidDate,
+                format!("malformed WARC-Date {}", date),
+            );
+        }
+    }
+
+    fn check_references(&mut self, context: &RecordContext, fields: &HeaderMap) {
+        for field_name in [
+            "WARC-Concurrent-To",
+            "WARC-Refers-To",
+            "WARC-Warcinfo-ID",
+            "WARC-Segment-Origin-ID",
+        ] {
+            for value in fields.get_all(field_name) {
+                if !is_valid_id(&value.text) {
+                    self.add(
+                        context,
+                        Severity::Error,
+                        ProblemKind::InvalidField,
+                        format!("malformed record ID in {}: {}", field_name, value.text),
+                    );
+                    continue;
+                }
+
+                self.references.push(Reference {
+                    record_id: value.text.clone(),
+                    field_name,
+                    filename: context.filename.to_string(),
+                    offset: context.offset,
+                    source_record_id: context.record_id.clone().unwrap_or_default(),
+                });
+            }
+        }
+    }
+
+    fn check_field_values(&mut self, context: &RecordContext, fields: &HeaderMap) {
+        for name in [
+            "Content-Length",
+            "WARC-Segment-Number",
+            "WARC-Segment-Total-Length",
+        ] {
+            if let Err(error) = fields.get_parsed::<u64>(name) {
+                self.add_invalid_field(context, name, &error);
+            }
+        }
+
+        for name in [
+            "WARC-Target-URI",
+            "WARC-Refers-To-Target-URI",
+            "WARC-Profile",
+        ] {
+            if let Some(value) = fields.get_str(name) {
+                if let Err(error) = Url::parse(value) {
+                    self.add_invalid_field(context, name, &error);
+                }
+            }
+        }
+
+        if let Err(error) = fields.get_parsed::<IpAddr>("WARC-IP-Address") {
+            self.add_invalid_field(context, "WARC-IP-Address", &error);
+        }
+
+        if let Some(value) = fields.get_str("WARC-Refers-To-Date") {
+            if DateTime::parse_from_rfc3339(value).is_err() {
+                self.add(
+                    context,
+                    Severity::Error,
+                    ProblemKind::InvalidField,
+                    format!("malformed WARC-Refers-To-Date {}", value),
+                );
+            }
+        }
+
+        for name in ["WARC-Block-Digest", "WARC-Payload-Digest"] {
+            match fields.get_parsed::<LabelledDigest>(name) {
+                Ok(Some(digest)) => {
+                    if crate::crypto::get_hash_function_by_name(&digest.algorithm).is_none() {
+                        self.add(
+                            context,
+                            Severity::Warning,
+                            ProblemKind::UnsupportedDigest,
+                            format!("unsupported algorithm {} in {}", digest.algorithm, name),
+                        );
+                    }
+                }
+                Ok(None) => {}
+                Err(error) => self.add_invalid_field(context, name, &error),
+            }
+        }
+    }
+
+    fn add_invalid_field(
+        &mut self,
+        context: &RecordContext,
+        name: &str,
+        error: &dyn std::error::Error,
+    ) {
+        let message = match error.source() {
+            Some(source) => format!("invalid value in {}: {}", name, source),
+            None => format!("invalid value in {}: {}", name, error),
+        };
+        self.add(context, Severity::Error, ProblemKind::InvalidField, message);
+    }
+
+    fn check_type_fields(
+        &mut self,
+        context: &RecordContext,
+        record_type: WARCRecordType,
+        metadata: &HeaderMetadata,
+    ) {
+        let fields = metadata.fields();
+
+        if !record_type.allows_concurrent_to() && fields.contains_key("WARC-Concurrent-To") {
+            self.add(
+                context,
+                Severity::Error,
+                ProblemKind::InvalidField,
+                format!(
+                    "WARC-Concurrent-To is not allowed in {} records",
+                    record_type
+                ),
+            );
+        }
+
+        if record_type == WARCRecordType::Warcinfo && fields.contains_key("WARC-Target-URI") {
+            self.add(
+                context,
+                Severity::Error,
+                ProblemKind::InvalidField,
+                "WARC-Target-URI is not allowed in warcinfo records".to_string(),
+            );
+        }
+
+        if record_type != WARCRecordType::Warcinfo && fields.contains_key("WARC-Filename") {
+            self.add(
+                context,
+                Severity::Error,
+                ProblemKind::InvalidField,
+                format!("WARC-Filename is not allowed in {} records", record_type),
+            );
+        }
+
+        if record_type == WARCRecordType::Revisit {
+            // Profiles are defined for both WARC 1.0 and 1.1
+            let profile = fields.get_str("WARC-Profile").unwrap_or_default();
+
+            if !profile.is_empty()
+                && !profile.ends_with("/revisit/identical-payload-digest")
+                && !profile.ends_with("/revisit/server-not-modified")
+            {
+                self.add(
+                    context,
+                    Severity::Warning,
+                    ProblemKind::InvalidField,
+                    format!("unknown revisit profile {}", profile),
+                );
+            }
+
+            if profile.ends_with("/revisit/identical-payload-digest")
+                && !fields.contains_key("WARC-Payload-Digest")
+            {
+                self.add(
+                    context,
+                    Severity::Error,
+                    ProblemKind::MissingField,
+                    "missing WARC-Payload-Digest for identical payload digest profile".to_string(),
+                );
+            }
+        }
+
+        if record_type == WARCRecordType::Continuation {
+            if let Ok(Some(number)) = fields.get_parsed::<u64>("WARC-Segment-Number") {
+                if number < 2 {
+                    self.add(
+                        context,
+                        Severity::Error,
+                        ProblemKind::InvalidField,
+                        "WARC-Segment-Number must be at least 2 in continuation records"
+                            .to_string(),
+                    );
+                }
+            }
+        } else if let Ok(Some(number)) = fields.get_parsed::<u64>("WARC-Segment-Number") {
+            if number != 1 {
+                self.add(
+                    context,
+                    Severity::Error,
+                    ProblemKind::InvalidField,
+                    format!("WARC-Segment-Number must be 1 in {} records", record_type),
+                );
+            }
+        }
+
+        if metadata.block_length() > 0
+            && !fields.contains_key("Content-Type")
+            && record_type != WARCRecordType::Continuation
+        {
+            self.add(
+                context,
+                Severity::Warning,
+                ProblemKind::MissingField,
+                "missing Content-Type for record with a block".to_string(),
+            );
+        }
+    }
+
+    fn check_block<S: Read>(
+        &mut self,
+        context: &RecordContext,
+        metadata: &HeaderMetadata,
+        reader: &mut WARCReader<S>,
+    ) -> Result<(), WARCError> {
+        let fields = metadata.fields();
+        let block_digest = get_digest(fields, "WARC-Block-Digest");
+        let payload_digest = get_digest(fields, "WARC-Payload-Digest");
+        let record_type = metadata.record_type();
+        let content_type = fields
+            .get_str("Content-Type")
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+
+        let is_http = content_type.starts_with("application/http")
+            && matches!(
+                record_type,
+                Some(WARCRecordType::Response) | Some(WARCRecordType::Request)
+            );
+        let check_payload = payload_digest.is_some()
+            && !fields.contains_key("WARC-Segment-Number")
+            && !fields.contains_key("WARC-Truncated")
+            && matches!(
+                record_type,
+                Some(WARCRecordType::Response)
+                    | Some(WARCRecordType::Request)
+                    | Some(WARCRecordType::Resource)
+            );
+
+        let block_hasher = block_digest.as_ref().map(|(hasher, _)| hasher.box_clone());
+        let mut stream = DigestReader::new(reader.read_block(), block_hasher);
+
+        let payload_result = if check_payload {
+            let (hasher, expected) = payload_digest.as_ref().unwrap();
+
+            if is_http {
+                let (result, inner) = hash_http_payload(stream, hasher.box_clone(), &content_type)?;
+                stream = inner;
+                result.map(|actual| (actual, expected))
+            } else {
+                let mut payload_stream = DigestReader::new(stream, Some(hasher.box_clone()));
+                std::io::copy(&mut payload_stream, &mut std::io::sink())?;
+                let actual = payload_stream.finalize();
+                stream = payload_stream.into_inner();
+                actual.map(|actual| (actual, expected))
+            }
+        } else {
+            None
+        };
+
+        std::io::copy(&mut stream, &mut std::io::sink())?;
+
+        if let (Some(actual), Some((_, expected))) = (stream.finalize(), &block_digest) {
+            if &actual != expected {
+                self.add(
+                    context,
+                    Severity::Error,
+                    ProblemKind::BlockDigestMismatch,
+                    "WARC-Block-Digest does not match the block".to_string(),
+                );
+            }
+        }
+
+        if let Some((actual, expected)) = payload_result {
+            if &actual != expected {
+                self.add(
+                    context,
+                    Severity::Error,
+                    ProblemKind::PayloadDigestMismatch,
+                    "WARC-Payload-Digest does not match the payload".to_string(),
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn get_position<S: Read>(reader: &mut WARCReader<S>) -> Result<(u64, bool), WARCError> {
+    Ok((reader.raw_file_offset()?, reader.is_member_boundary()?))
+}
+
+fn is_valid_id(value: &str) -> bool {
+    value.starts_with('<')
+        && value.ends_with('>')
+        && value.len() > 2
+        && Url::parse(&value[1..value.len() - 1]).is_ok()
+}
+
+fn get_digest(fields: &HeaderMap, name: &str) -> Option<(Box<dyn DynDigest + Send>, Vec<u8>)> {
+    let digest = fields.get_parsed::<LabelledDigest>(name).ok()??;
+    let hasher = crate::crypto::get_hash_function_by_name(&digest.algorithm)?;
+
+    Some((hasher, digest.value))
+}
+
+// Computes a digest of the data read through it.
+struct DigestReader<R: Read> {
+    inner: R,
+    hasher: Option<Box<dyn DynDigest>>,
+}
+
+impl<R: Read> DigestReader<R> {
+    fn new(inner: R, hasher: Option<Box<dyn DynDigest>>) -> Self {
+        Self { inner, hasher }
+    }
+
+    fn finalize(&mut self) -> Option<Vec<u8>> {
+        self.hasher
+            .as_mut()
+            .map(|hasher| hasher.finalize_reset().to_vec())
+    }
+
+    fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for DigestReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let amount = self.inner.read(buf)?;
+
+        if let Some(hasher) = &mut self.hasher {
+            hasher.update(&buf[0..amount]);
+        }
+
+        Ok(amount)
+    }
+}
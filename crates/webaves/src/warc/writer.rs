@@ -1,22 +1,38 @@
-use std::io::Write;
+use std::{
+    fs::File,
+    io::{Cursor, Read, Seek, SeekFrom, Write},
+};
+
+use digest::DynDigest;
 
 use crate::{
     compress::{CompressionFormat, CompressionLevel, Compressor},
     header::{HeaderFormatter, HeaderMap},
+    http::{CompressionOption, MessageReader},
+    io::ComboReader,
     warc::HeaderMapExt,
 };
 
-use super::WARCError;
+use super::{LabelledDigest, WARCError, WARCRecord};
 
 /// Default WARC version string.
 pub const DEFAULT_VERSION: &str = "WARC/1.1";
 
+/// Default size of a record block held in memory before it is spooled to a
+/// temporary file.
+pub const DEFAULT_SPOOL_MEMORY_LIMIT: u64 = 8_388_608;
+
 /// Writes a WARC file.
 ///
 /// The writer handles compression automatically as multi-streams for rapid
 /// access to records by consuming software.
 /// If a stream with compression is given to this writer (although allowed
 /// by the WARC format), the output WARC file will not indexable and seekable.
+///
+/// When a digest algorithm is set with [Self::set_digest_algorithm], the
+/// writer computes the `WARC-Block-Digest`, `WARC-Payload-Digest`, and
+/// `Content-Length` fields. The block is held until [Self::end_record] is
+/// called.
 pub struct WARCWriter<'a, S: Write> {
     stream: Option<S>,
 
@@ -25,6 +41,7 @@ pub struct WARCWriter<'a, S: Write> {
     compressed_stream: Option<Compressor<'a, S>>,
     compression_format: CompressionFormat,
     compression_level: CompressionLevel,
+    zstd_dictionary: Option<Vec<u8>>,
 
     version: String,
     header_formatter: HeaderFormatter,
@@ -32,6 +49,11 @@ pub struct WARCWriter<'a, S: Write> {
     record_id: String,
     block_length: u64,
     block_amount_written: u64,
+
+    digest_algorithm: Option<String>,
+    spool_memory_limit: u64,
+    pending_header: Option<HeaderMap>,
+    spool: Option<BlockSpool>,
 }
 
 impl<'a, S: Write> WARCWriter<'a, S> {
@@ -52,11 +74,16 @@ impl<'a, S: Write> WARCWriter<'a, S> {
             compressed_stream: None,
             compression_format,
             compression_level,
+            zstd_dictionary: None,
             version: DEFAULT_VERSION.to_string(),
             header_formatter: HeaderFormatter::new(),
             record_id: String::new(),
             block_length: 0,
             block_amount_written: 0,
+            digest_algorithm: None,
+            spool_memory_limit: DEFAULT_SPOOL_MEMORY_LIMIT,
+            pending_header: None,
+            spool: None,
         }
     }
 
@@ -64,6 +91,7 @@ impl<'a, S: Write> WARCWriter<'a, S> {
     pub fn header_formatter(&self) -> &HeaderFormatter {
         &self.header_formatter
     }
+
     /// Sets the formatter for headers.
     pub fn set_header_formatter(&mut self, header_formatter: HeaderFormatter) {
         self.header_formatter = header_formatter;
@@ -81,6 +109,102 @@ impl<'a, S: Write> WARCWriter<'a, S> {
         self.version = version;
     }
 
+    /// Returns the hash algorithm name used for computing digests.
+    ///
+    /// Default: `None` (digests are not computed)
+    pub fn digest_algorithm(&self) -> Option<&str> {
+        self.digest_algorithm.as_deref()
+    }
+
+    /// Sets the hash algorithm name used for computing digests.
+    ///
+    /// The name must be supported by [crate::crypto::get_hash_function_by_name].
+    /// When set, the `WARC-Block-Digest`, `WARC-Payload-Digest`, and
+    /// `Content-Length` fields of records are replaced by computed values.
+    /// The payload digest is computed only for `application/http` records
+    /// that are not segmented or revisit records. If the HTTP message of
+    /// such a record cannot be parsed, the field is removed.
+    ///
+    /// Returns an error if the algorithm is not supported.
+    pub fn set_digest_algorithm(&mut self, algorithm: Option<&str>) -> Result<(), WARCError> {
+        if let Some(algorithm) = algorithm {
+            if crate::crypto::get_hash_function_by_name(algorithm).is_none() {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!("unsupported hash algorithm {}", algorithm),
+                )
+                .into());
+            }
+        }
+
+        self.digest_algorithm = algorithm.map(crate::crypto::normalize_hash_name);
+
+        Ok(())
+    }
+
+    /// Returns the size of a block held in memory before it is written to a
+    /// temporary file when computing digests.
+    ///
+    /// Default: [DEFAULT_SPOOL_MEMORY_LIMIT]
+    pub fn spool_memory_limit(&self) -> u64 {
+        self.spool_memory_limit
+    }
+
+    /// Sets the size of a block held in memory before it is written to a
+    /// temporary file when computing digests.
+    pub fn set_spool_memory_limit(&mut self, spool_memory_limit: u64) {
+        self.spool_memory_limit = spool_memory_limit;
+    }
+
+    /// Returns the compression format of records.
+    pub fn compression_format(&self) -> CompressionFormat {
+        self.compression_format
+    }
+
+    /// Returns the Zstandard dictionary used to compress records.
+    pub fn zstd_dictionary(&self) -> Option<&[u8]> {
+        self.zstd_dictionary.as_deref()
+    }
+
+    /// Writes a Zstandard dictionary and uses it to compress the records
+    /// that follow.
+    ///
+    /// The dictionary is written as a skippable frame as described by the
+    /// `.warc.zst` format. [crate::compress::train_zstd_dictionary] can be
+    /// used to create a dictionary. This function should be called before
+    /// the first record is written so that the dictionary is at the start of
+    /// the file.
+    ///
+    /// Panics if the compression format is not Zstd or when called out of
+    /// sequence.
+    pub fn write_zstd_dictionary(&mut self, dictionary: Vec<u8>) -> Result<(), WARCError> {
+        assert!(self.state == WriterState::StartOfHeader);
+        assert!(self.compression_format == CompressionFormat::Zstd);
+
+        tracing::debug!(length = dictionary.len(), "write_zstd_dictionary");
+
+        crate::compress::write_zstd_dictionary_frame(self.stream.as_mut().unwrap(), &dictionary)?;
+        self.zstd_dictionary = Some(dictionary);
+
+        Ok(())
+    }
+
+    /// Returns a reference to the wrapped stream.
+    pub fn get_ref(&self) -> &S {
+        match &self.stream {
+            Some(stream) => stream,
+            None => self.compressed_stream.as_ref().unwrap().get_ref(),
+        }
+    }
+
+    /// Returns a mutable reference to the wrapped stream.
+    pub fn get_mut(&mut self) -> &mut S {
+        match &mut self.stream {
+            Some(stream) => stream,
+            None => self.compressed_stream.as_mut().unwrap().get_mut(),
+        }
+    }
+
     /// Returns the wrapped stream.
     ///
     /// Panics if the writer is in the middle of writing a record.
@@ -103,20 +227,43 @@ impl<'a, S: Write> WARCWriter<'a, S> {
 
         tracing::debug!("begin_record");
 
-        self.create_compressor()?;
-        self.write_header(header)?;
-        self.prepare_for_block_write(header)?;
+        if let Some(algorithm) = &self.digest_algorithm {
+            self.record_id = header
+                .get_str("WARC-Record-ID")
+                .unwrap_or_default()
+                .to_string();
+            self.pending_header = Some(header.clone());
+            self.spool = Some(BlockSpool::new(algorithm, self.spool_memory_limit));
+        } else {
+            self.create_compressor()?;
+            self.write_header(header)?;
+            self.prepare_for_block_write(header)?;
+        }
 
         self.state = WriterState::EndOfHeader;
 
         Ok(())
     }
 
+    /// Writes a complete record held in memory.
+    ///
+    /// This calls [Self::begin_record], [Self::write_block], and [Self::end_record].
+    pub fn write_record(&mut self, record: &WARCRecord) -> Result<(), WARCError> {
+        self.begin_record(&record.header)?;
+        self.write_block().write_all(&record.block)?;
+        self.end_record()
+    }
+
     fn create_compressor(&mut self) -> Result<(), WARCError> {
         tracing::debug!("create_compressor");
 
         let stream = self.stream.take().unwrap();
-        let stream = Compressor::new(stream, self.compression_format, self.compression_level)?;
+        let stream = match &self.zstd_dictionary {
+            Some(dictionary) => {
+                Compressor::new_zstd_with_dictionary(stream, self.compression_level, dictionary)?
+            }
+            None => Compressor::new(stream, self.compression_format, self.compression_level)?,
+        };
         self.compressed_stream = Some(stream);
 
         Ok(())
@@ -165,8 +312,13 @@ impl<'a, S: Write> WARCWriter<'a, S> {
 
         self.state = WriterState::InBlock;
 
+        let target = match self.spool.as_mut() {
+            Some(spool) => BlockTarget::Spool(spool),
+            None => BlockTarget::Stream(self.compressed_stream.as_mut().unwrap()),
+        };
+
         BlockWriter {
-            stream: self.compressed_stream.as_mut().unwrap(),
+            target,
             num_bytes_written: &mut self.block_amount_written,
         }
     }
@@ -177,6 +329,11 @@ impl<'a, S: Write> WARCWriter<'a, S> {
     pub fn end_record(&mut self) -> Result<(), WARCError> {
         assert!(self.state == WriterState::InBlock);
         tracing::debug!("end_record");
+
+        if self.spool.is_some() {
+            self.write_spooled_record()?;
+        }
+
         assert!(self.stream.is_none());
         assert!(self.compressed_stream.is_some());
 
@@ -192,6 +349,39 @@ impl<'a, S: Write> WARCWriter<'a, S> {
         Ok(())
     }
 
+    fn write_spooled_record(&mut self) -> Result<(), WARCError> {
+        let mut spool = self.spool.take().unwrap();
+        let mut header = self.pending_header.take().unwrap();
+
+        let block_digest = spool.finalize_digest();
+        header.insert("Content-Length", spool.length.to_string());
+        header.insert("WARC-Block-Digest", block_digest.to_string());
+
+        if has_http_payload(&header) {
+            match compute_payload_digest(&header, &mut spool)? {
+                Some(payload_digest) => {
+                    header.insert("WARC-Payload-Digest", payload_digest.to_string());
+                }
+                // A digest given by the caller may not match the block, so it
+                // is not kept next to the recomputed block digest.
+                None => {
+                    header.remove("WARC-Payload-Digest");
+                }
+            }
+        }
+
+        tracing::debug!(length = spool.length, %block_digest, "write_spooled_record");
+
+        self.create_compressor()?;
+        self.write_header(&header)?;
+        self.prepare_for_block_write(&header)?;
+
+        let stream = self.compressed_stream.as_mut().unwrap();
+        self.block_amount_written = std::io::copy(&mut spool.reader()?, stream)?;
+
+        Ok(())
+    }
+
     fn check_block_length(&self) -> Result<(), WARCError> {
         tracing::debug!(
             bytes_written = self.block_amount_written,
@@ -209,26 +399,190 @@ impl<'a, S: Write> WARCWriter<'a, S> {
     }
 }
 
+fn has_http_payload(header: &HeaderMap) -> bool {
+    let content_type = header
+        .get_str("Content-Type")
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+
+    if !content_type.starts_with("application/http") {
+        return false;
+    }
+
+    // The payload of a segmented record spans multiple records.
+    if header.contains_key("WARC-Segment-Number") {
+        return false;
+    }
+
+    // The payload digest of a revisit record is the digest of the
+    // revisited record.
+    header.get_str("WARC-Type") != Some("revisit")
+}
+
+// Returns `None` if the block is not a valid HTTP message.
+fn compute_payload_digest(
+    header: &HeaderMap,
+    spool: &mut BlockSpool,
+) -> Result<Option<LabelledDigest>, WARCError> {
+    let content_type = header
+        .get_str("Content-Type")
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    let algorithm = spool.algorithm.clone();
+    let hasher = crate::crypto::get_hash_function_by_name(&algorithm).unwrap();
+    let (digest, _) = hash_http_payload(spool.reader()?, hasher, &content_type)?;
+
+    Ok(digest.map(|digest| LabelledDigest::new(algorithm, digest)))
+}
+
+// Parses the HTTP message and hashes the body.
+//
+// Returns the digest if the message could be parsed and the given stream.
+pub(super) fn hash_http_payload<R: Read>(
+    stream: R,
+    mut hasher: Box<dyn DynDigest>,
+    content_type: &str,
+) -> std::io::Result<(Option<Vec<u8>>, R)> {
+    let mut reader = MessageReader::new(ComboReader::new(stream));
+    reader.set_compression(CompressionOption::None);
+
+    let result = if content_type.contains("msgtype=request") {
+        reader.begin_request().map(|_| ())
+    } else {
+        reader.begin_response(None).map(|_| ())
+    };
+
+    if let Err(error) = result {
+        tracing::debug!(?error, "payload digest http parse");
+        return Ok((None, reader.into_inner().into_inner()));
+    }
+
+    let mut buf = vec![0u8; 16384];
+
+    loop {
+        let amount = match reader.read_body().read(&mut buf) {
+            Ok(amount) => amount,
+            Err(error) => {
+                tracing::debug!(?error, "payload digest http body");
+                return Ok((None, reader.into_inner().into_inner()));
+            }
+        };
+
+        if amount == 0 {
+            break;
+        }
+
+        hasher.update(&buf[0..amount]);
+    }
+
+    Ok((
+        Some(hasher.finalize().to_vec()),
+        reader.into_inner().into_inner(),
+    ))
+}
+
+// Holds a record block in memory or a temporary file while computing
+// the block digest.
+struct BlockSpool {
+    algorithm: String,
+    hasher: Box<dyn DynDigest + Send>,
+    memory: Vec<u8>,
+    file: Option<File>,
+    memory_limit: u64,
+    length: u64,
+}
+
+impl BlockSpool {
+    fn new(algorithm: &str, memory_limit: u64) -> Self {
+        Self {
+            algorithm: algorithm.to_string(),
+            hasher: crate::crypto::get_hash_function_by_name(algorithm).unwrap(),
+            memory: Vec::new(),
+            file: None,
+            memory_limit,
+            length: 0,
+        }
+    }
+
+    fn finalize_digest(&mut self) -> LabelledDigest {
+        LabelledDigest::new(self.algorithm.clone(), self.hasher.finalize_reset())
+    }
+
+    fn reader(&mut self) -> std::io::Result<Box<dyn Read + '_>> {
+        match &mut self.file {
+            Some(file) => {
+                file.seek(SeekFrom::Start(0))?;
+                Ok(Box::new(file))
+            }
+            None => Ok(Box::new(Cursor::new(&self.memory))),
+        }
+    }
+}
+
+impl Write for BlockSpool {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.file.is_none() && self.memory.len() as u64 + buf.len() as u64 > self.memory_limit {
+            tracing::debug!("spooling block to temporary file");
+
+            let mut file = tempfile::tempfile()?;
+            file.write_all(&self.memory)?;
+            self.memory = Vec::new();
+            self.file = Some(file);
+        }
+
+        let amount = match &mut self.file {
+            Some(file) => file.write(buf)?,
+            None => {
+                self.memory.extend_from_slice(buf);
+                buf.len()
+            }
+        };
+
+        self.hasher.update(&buf[0..amount]);
+        self.length += amount as u64;
+
+        Ok(amount)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match &mut self.file {
+            Some(file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+enum BlockTarget<'a, 'b, S: Write> {
+    Stream(&'b mut Compressor<'a, S>),
+    Spool(&'b mut BlockSpool),
+}
+
 /// Writer stream for a record body.
 pub struct BlockWriter<'a, 'b, S: Write> {
-    stream: &'b mut Compressor<'a, S>,
+    target: BlockTarget<'a, 'b, S>,
     num_bytes_written: &'b mut u64,
 }
 
 impl<'a, 'b, S: Write> Write for BlockWriter<'a, 'b, S> {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
-        let amount = self.stream.write(buf)?;
+        let amount = match &mut self.target {
+            BlockTarget::Stream(stream) => stream.write(buf)?,
+            BlockTarget::Spool(spool) => spool.write(buf)?,
+        };
         *self.num_bytes_written += amount as u64;
         Ok(amount)
     }
 
     fn flush(&mut self) -> std::io::Result<()> {
-        self.stream.flush()
+        match &mut self.target {
+            BlockTarget::Stream(stream) => stream.flush(),
+            BlockTarget::Spool(spool) => spool.flush(),
+        }
     }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum WriterState {
+pub(super) enum WriterState {
     StartOfHeader,
     EndOfHeader,
     InBlock,
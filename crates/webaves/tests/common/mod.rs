@@ -0,0 +1,58 @@
+// Helpers shared by the integration tests. Each test crate uses only some
+// of them.
+#![allow(dead_code)]
+
+use webaves::{
+    compress::CompressionFormat,
+    header::HeaderMap,
+    warc::{ResponseRecordBuilder, WARCRecord, WARCWriter},
+};
+
+/// HTTP header of a successful response with the payload of
+/// [make_response].
+pub const HTTP_OK_HEADER: &[u8] = b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\n";
+
+/// Digest of the payload of [make_response].
+pub const PAYLOAD_DIGEST: &str = "sha1:2JKSMEQ7TLWTNRV7TNE5J2ELBYP4WHGX";
+
+/// Makes the header of a `resource` record with a record ID and URL
+/// numbered by `index`.
+pub fn make_header(index: usize, length: usize) -> HeaderMap {
+    let mut header = HeaderMap::new();
+    header.insert("WARC-Type", "resource");
+    header.insert("WARC-Date", "2000-12-30T01:02:03Z");
+    header.insert(
+        "WARC-Record-ID",
+        format!("<urn:uuid:00000001-0002-0003-0004-00000000000{}>", index),
+    );
+    header.insert("WARC-Target-URI", format!("http://example.com/{}", index));
+    header.insert("Content-Length", length.to_string());
+    header
+}
+
+/// Makes a `response` record of an HTTP message with the given header and
+/// the payload "Hello".
+pub fn make_response(url: &str, record_id: &str, date: &str, http_header: &[u8]) -> WARCRecord {
+    let mut block = http_header.to_vec();
+    block.extend_from_slice(b"Hello");
+
+    ResponseRecordBuilder::new(url)
+        .record_id(record_id)
+        .field("WARC-Date", date)
+        .payload_digest(PAYLOAD_DIGEST)
+        .payload(block)
+        .build()
+        .unwrap()
+}
+
+/// Writes the records to a WARC file with computed SHA-1 digests.
+pub fn write_records(records: &[WARCRecord], format: CompressionFormat) -> Vec<u8> {
+    let mut writer = WARCWriter::new_compressed(Vec::new(), format, Default::default());
+    writer.set_digest_algorithm(Some("sha1")).unwrap();
+
+    for record in records {
+        writer.write_record(record).unwrap();
+    }
+
+    writer.into_inner()
+}
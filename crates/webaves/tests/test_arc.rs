@@ -0,0 +1,238 @@
+use std::io::{Read, Write};
+
+use webaves::{
+    arc::{ARCConverter, ARCError, ARCReader},
+    compress::{CompressionFormat, Compressor},
+    warc::{validate::Validator, WARCReader, WARCRecordType, WARCWriter},
+};
+
+const VERSION_1_BLOCK: &[u8] = b"1 0 Alexa Internet\n\
+URL IP-address Archive-date Content-type Archive-length\n";
+
+const VERSION_2_BLOCK: &[u8] = b"1 1 InternetArchive\n\
+URL IP-address Archive-date Content-type Result-code Checksum Location Offset Filename Archive-length\n\
+<arcmetadata/>\n";
+
+const HTTP_RESPONSE: &[u8] = b"HTTP/1.0 200 OK\r\nContent-Type: text/html\r\n\r\nHello";
+
+fn make_arc(version_block: &[u8], record_lines: &[&str], compress: bool) -> Vec<u8> {
+    let mut members = Vec::new();
+    let mut member = format!(
+        "filedesc://example.arc 0.0.0.0 19960923142103 text/plain {}\n",
+        version_block.len()
+    )
+    .into_bytes();
+    member.extend_from_slice(version_block);
+    member.push(b'\n');
+    members.push(member);
+
+    for line in record_lines {
+        let mut member = format!("{} {}\n", line, HTTP_RESPONSE.len()).into_bytes();
+        member.extend_from_slice(HTTP_RESPONSE);
+        member.push(b'\n');
+        members.push(member);
+    }
+
+    let mut output = Vec::new();
+
+    for member in members {
+        if compress {
+            let mut compressor =
+                Compressor::new(Vec::new(), CompressionFormat::Gzip, Default::default()).unwrap();
+            compressor.write_all(&member).unwrap();
+            output.extend(compressor.finish().unwrap());
+        } else {
+            output.extend(member);
+        }
+    }
+
+    output
+}
+
+fn read_block<S: Read>(reader: &mut ARCReader<'_, S>) -> Vec<u8> {
+    let mut block = Vec::new();
+    reader.read_block().read_to_end(&mut block).unwrap();
+    reader.end_record().unwrap();
+    block
+}
+
+#[test_log::test]
+fn read_arc_v1() {
+    let data = make_arc(
+        VERSION_1_BLOCK,
+        &[
+            "http://example.com/ 192.0.2.1 19960923142104 text/html",
+            "http://example.com/a b.html 192.0.2.1 19960923142105 text/html",
+        ],
+        false,
+    );
+    let mut reader = ARCReader::new(data.as_slice()).unwrap();
+
+    let metadata = reader.begin_record().unwrap().unwrap();
+    let version_block = reader.version_block().unwrap();
+    assert_eq!(version_block.filename, "example.arc");
+    assert_eq!(version_block.major_version, 1);
+    assert_eq!(version_block.minor_version, 0);
+    assert_eq!(version_block.origin_code, "Alexa Internet");
+    assert_eq!(version_block.field_names.len(), 5);
+
+    assert_eq!(metadata.url(), "http://example.com/");
+    assert_eq!(metadata.ip_address(), "192.0.2.1");
+    assert_eq!(metadata.date(), "19960923142104");
+    assert_eq!(
+        metadata.date_time().unwrap().to_rfc3339(),
+        "1996-09-23T14:21:04+00:00"
+    );
+    assert_eq!(metadata.content_type(), "text/html");
+    assert_eq!(metadata.block_length(), HTTP_RESPONSE.len() as u64);
+    assert_eq!(read_block(&mut reader), HTTP_RESPONSE);
+
+    let metadata = reader.begin_record().unwrap().unwrap();
+    assert_eq!(metadata.url(), "http://example.com/a%20b.html");
+    assert_eq!(metadata.date(), "19960923142105");
+    assert_eq!(read_block(&mut reader), HTTP_RESPONSE);
+
+    assert!(reader.begin_record().unwrap().is_none());
+}
+
+#[test_log::test]
+fn read_arc_v2_gzip() {
+    let data = make_arc(
+        VERSION_2_BLOCK,
+        &[
+            "http://example.com/ 192.0.2.1 20000101000000 text/html 200 - - 0 example.arc",
+            "http://example.net/ 192.0.2.2 20000101000001 text/html 200 - - 0 example.arc",
+        ],
+        true,
+    );
+    let mut reader = ARCReader::new(data.as_slice()).unwrap();
+    let mut offsets = Vec::new();
+
+    while let Some(metadata) = reader.begin_record().unwrap() {
+        assert_eq!(metadata.fields().len(), 10);
+        assert_eq!(metadata.fields()[4], "200");
+        assert_eq!(read_block(&mut reader), HTTP_RESPONSE);
+        offsets.push(metadata.raw_file_offset());
+    }
+
+    assert_eq!(reader.compression_format(), CompressionFormat::Gzip);
+
+    let version_block = reader.version_block().unwrap();
+    assert_eq!(version_block.minor_version, 1);
+    assert_eq!(version_block.metadata, b"<arcmetadata/>\n");
+
+    assert_eq!(offsets.len(), 2);
+    assert!(offsets[0] > 0);
+
+    let mut reader = ARCReader::new(&data[offsets[1] as usize..]).unwrap();
+    let result = reader.begin_record();
+    assert!(matches!(result, Err(ARCError::UnknownFormat)));
+}
+
+#[test_log::test]
+fn read_arc_truncated() {
+    let mut data = make_arc(
+        VERSION_1_BLOCK,
+        &["http://example.com/ 192.0.2.1 19960923142104 text/html"],
+        false,
+    );
+    data.truncate(data.len() - 3);
+    let mut reader = ARCReader::new(data.as_slice()).unwrap();
+
+    reader.begin_record().unwrap().unwrap();
+    std::io::copy(&mut reader.read_block(), &mut std::io::sink()).unwrap();
+    assert!(matches!(
+        reader.end_record(),
+        Err(ARCError::WrongBlockLength { .. })
+    ));
+}
+
+#[test_log::test]
+fn read_warc_as_arc() {
+    let mut reader = ARCReader::new(b"WARC/1.1\r\n\r\n".as_slice()).unwrap();
+    assert!(matches!(
+        reader.begin_record(),
+        Err(ARCError::UnknownFormat)
+    ));
+}
+
+#[test_log::test]
+fn convert_arc_to_warc() {
+    let data = make_arc(
+        VERSION_1_BLOCK,
+        &[
+            "http://example.com/ 192.0.2.1 19960923142104 text/html",
+            "dns:example.com 192.0.2.53 19960923142105 text/dns",
+        ],
+        true,
+    );
+    let mut reader = ARCReader::new(data.as_slice()).unwrap();
+    let mut writer =
+        WARCWriter::new_compressed(Vec::new(), CompressionFormat::Gzip, Default::default());
+    writer.set_digest_algorithm(Some("sha1")).unwrap();
+
+    let mut converter = ARCConverter::new();
+    let count = converter
+        .convert(&mut reader, &mut writer, Some("example.warc.gz"))
+        .unwrap();
+    assert_eq!(count, 2);
+
+    let output = writer.into_inner();
+
+    let mut validator = Validator::new();
+    validator
+        .validate_file("example.warc.gz", output.as_slice())
+        .unwrap();
+    let problems = validator.finish();
+    assert!(problems.is_empty(), "{:?}", problems);
+
+    let mut reader = WARCReader::new(output.as_slice()).unwrap();
+    let mut headers = Vec::new();
+
+    while let Some(metadata) = reader.begin_record().unwrap() {
+        let mut block = Vec::new();
+        reader.read_block().read_to_end(&mut block).unwrap();
+        reader.end_record().unwrap();
+        headers.push((metadata, block));
+    }
+
+    assert_eq!(headers.len(), 4);
+
+    let (warcinfo, _) = &headers[0];
+    let warcinfo_id = warcinfo.fields().get_str("WARC-Record-ID").unwrap();
+    assert_eq!(warcinfo.record_type(), Some(WARCRecordType::Warcinfo));
+    assert_eq!(
+        warcinfo.fields().get_str("WARC-Filename"),
+        Some("example.warc.gz")
+    );
+    assert_eq!(
+        warcinfo.fields().get_str("WARC-Date"),
+        Some("1996-09-23T14:21:03Z")
+    );
+
+    let (provenance, block) = &headers[1];
+    assert_eq!(provenance.record_type(), Some(WARCRecordType::Metadata));
+    assert_eq!(
+        provenance.fields().get_str("WARC-Target-URI"),
+        Some("filedesc://example.arc")
+    );
+    assert!(String::from_utf8_lossy(block).contains("arc-origin-code: Alexa Internet"));
+
+    let (response, block) = &headers[2];
+    let fields = response.fields();
+    assert_eq!(response.record_type(), Some(WARCRecordType::Response));
+    assert_eq!(
+        fields.get_str("WARC-Target-URI"),
+        Some("http://example.com/")
+    );
+    assert_eq!(fields.get_str("WARC-Date"), Some("1996-09-23T14:21:04Z"));
+    assert_eq!(fields.get_str("WARC-IP-Address"), Some("192.0.2.1"));
+    assert_eq!(fields.get_str("WARC-Warcinfo-ID"), Some(warcinfo_id));
+    assert!(fields.contains_key("WARC-Block-Digest"));
+    assert!(fields.contains_key("WARC-Payload-Digest"));
+    assert_eq!(block, HTTP_RESPONSE);
+
+    let (resource, _) = &headers[3];
+    assert_eq!(resource.record_type(), Some(WARCRecordType::Resource));
+    assert_eq!(resource.fields().get_str("Content-Type"), Some("text/dns"));
+}
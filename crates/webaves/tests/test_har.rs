@@ -0,0 +1,363 @@
+mod common;
+
+use std::io::Read;
+
+use webaves::{
+    compress::CompressionFormat,
+    har::{HARConverter, HARExporter, HAR},
+    warc::{
+        RequestRecordBuilder, ResponseRecordBuilder, WARCReader, WARCRecord, WARCWriter,
+        WarcinfoRecordBuilder,
+    },
+};
+
+fn make_records() -> Vec<WARCRecord> {
+    let mut records = Vec::new();
+
+    let warcinfo = WarcinfoRecordBuilder::new()
+        .payload(b"software: test\r\n".as_slice())
+        .build()
+        .unwrap();
+    records.push(warcinfo);
+
+    // Response written first with the request pointing to it.
+    let response = ResponseRecordBuilder::new("http://example.com/api?q=a+b&page=2")
+        .record_id("<urn:uuid:00000000-0000-0000-0000-000000000001>")
+        .field("WARC-Date", "2022-01-01T00:00:01.500Z")
+        .field("WARC-IP-Address", "192.0.2.1")
+        .payload(
+            b"HTTP/1.1 200 OK\r\n\
+            Content-Type: application/json\r\n\
+            Set-Cookie: session=abc; Path=/; HttpOnly\r\n\
+            Content-Length: 11\r\n\r\n\
+            {\"a\": true}"
+                .as_slice(),
+        )
+        .build()
+        .unwrap();
+    let request = RequestRecordBuilder::new("http://example.com/api?q=a+b&page=2")
+        .field("WARC-Date", "2022-01-01T00:00:01Z")
+        .concurrent_to("<urn:uuid:00000000-0000-0000-0000-000000000001>")
+        .payload(b"GET /api?q=a+b&page=2 HTTP/1.1\r\nCookie: a=1; b=2\r\n\r\n".as_slice())
+        .build()
+        .unwrap();
+    records.push(response);
+    records.push(request);
+
+    // Request written first with the response pointing to it.
+    let request = RequestRecordBuilder::new("http://example.com/image.png")
+        .record_id("<urn:uuid:00000000-0000-0000-0000-000000000002>")
+        .field("WARC-Date", "2022-01-01T00:00:00Z")
+        .payload(b"GET /image.png HTTP/1.1\r\n\r\n".as_slice())
+        .build()
+        .unwrap();
+    let response = ResponseRecordBuilder::new("http://example.com/image.png")
+        .field("WARC-Date", "2022-01-01T00:00:00Z")
+        .concurrent_to("<urn:uuid:00000000-0000-0000-0000-000000000002>")
+        .payload(
+            b"HTTP/1.1 200 OK\r\nContent-Type: image/png\r\nContent-Length: 4\r\n\r\n\x89PNG"
+                .as_slice(),
+        )
+        .build()
+        .unwrap();
+    records.push(request);
+    records.push(response);
+
+    // Response without a request.
+    let response = ResponseRecordBuilder::new("http://example.com/missing")
+        .field("WARC-Date", "2022-01-02T00:00:00Z")
+        .payload(
+            b"HTTP/1.1 301 Moved Permanently\r\n\
+            Location: http://example.com/\r\n\
+            Content-Length: 0\r\n\r\n"
+                .as_slice(),
+        )
+        .build()
+        .unwrap();
+    records.push(response);
+
+    records
+}
+
+#[test_log::test]
+fn test_har_export() {
+    let data = common::write_records(&make_records(), CompressionFormat::Raw);
+    let mut exporter = HARExporter::new();
+    exporter
+        .add_reader(&mut WARCReader::new(data.as_slice()).unwrap())
+        .unwrap();
+    assert_eq!(exporter.entry_count(), 2);
+
+    let har = exporter.finish();
+    assert_eq!(har.log.version, "1.2");
+    assert_eq!(har.log.entries.len(), 3);
+
+    let entry = &har.log.entries[0];
+    assert_eq!(entry.started_date_time, "2022-01-01T00:00:00.000Z");
+    assert_eq!(entry.request.url, "http://example.com/image.png");
+    assert_eq!(entry.response.content.mime_type, "image/png");
+    assert_eq!(entry.response.content.text.as_deref(), Some("iVBORw=="));
+    assert_eq!(entry.response.content.encoding.as_deref(), Some("base64"));
+
+    let entry = &har.log.entries[1];
+    assert_eq!(entry.started_date_time, "2022-01-01T00:00:01.000Z");
+    assert_eq!(entry.time, 500.0);
+    assert_eq!(entry.timings.wait, 500.0);
+    assert_eq!(entry.server_ip_address.as_deref(), Some("192.0.2.1"));
+    assert_eq!(entry.request.method, "GET");
+    assert_eq!(entry.request.http_version, "HTTP/1.1");
+    assert_eq!(entry.request.query_string.len(), 2);
+    assert_eq!(entry.request.query_string[0].name, "q");
+    assert_eq!(entry.request.query_string[0].value, "a b");
+    assert_eq!(entry.request.cookies.len(), 2);
+    assert_eq!(entry.request.cookies[1].name, "b");
+    assert_eq!(entry.request.cookies[1].value, "2");
+    assert_eq!(entry.response.status, 200);
+    assert_eq!(entry.response.status_text, "OK");
+    assert_eq!(entry.response.cookies.len(), 1);
+    assert_eq!(entry.response.cookies[0].name, "session");
+    assert_eq!(entry.response.cookies[0].path.as_deref(), Some("/"));
+    assert_eq!(entry.response.cookies[0].http_only, Some(true));
+    assert_eq!(entry.response.content.size, 11);
+    assert_eq!(
+        entry.response.content.text.as_deref(),
+        Some("{\"a\": true}")
+    );
+    assert_eq!(entry.response.content.encoding, None);
+
+    let entry = &har.log.entries[2];
+    assert_eq!(entry.request.method, "GET");
+    assert_eq!(entry.request.url, "http://example.com/missing");
+    assert_eq!(entry.response.status, 301);
+    assert_eq!(entry.response.redirect_url, "http://example.com/");
+
+    let json = serde_json::to_value(&har).unwrap();
+    assert!(json["log"]["entries"][1]["request"]["queryString"].is_array());
+    assert_eq!(json["log"]["entries"][1]["serverIPAddress"], "192.0.2.1");
+    assert_eq!(
+        json["log"]["entries"][2]["response"]["redirectURL"],
+        "http://example.com/"
+    );
+}
+
+const HAR_DATA: &str = r#"{
+  "log": {
+    "version": "1.2",
+    "creator": { "name": "WebInspector", "version": "537.36" },
+    "browser": { "name": "Chrome", "version": "100.0" },
+    "pages": [],
+    "entries": [
+      {
+        "startedDateTime": "2022-01-01T01:00:00.000+01:00",
+        "time": 250.5,
+        "request": {
+          "method": "POST",
+          "url": "https://example.com:8443/form?a=1",
+          "httpVersion": "http/2.0",
+          "headers": [
+            { "name": ":authority", "value": "example.com:8443" },
+            { "name": "content-type", "value": "application/octet-stream" }
+          ],
+          "queryString": [{ "name": "a", "value": "1" }],
+          "cookies": [],
+          "headersSize": -1,
+          "bodySize": 3,
+          "postData": { "mimeType": "application/octet-stream", "text": "AQID", "encoding": "base64" }
+        },
+        "response": {
+          "status": 200,
+          "statusText": "",
+          "httpVersion": "http/2.0",
+          "headers": [
+            { "name": ":status", "value": "200" },
+            { "name": "content-type", "value": "text/plain" },
+            { "name": "content-encoding", "value": "gzip" },
+            { "name": "content-length", "value": "100" }
+          ],
+          "cookies": [],
+          "content": { "size": 5, "mimeType": "text/plain", "text": "Hello" },
+          "redirectURL": "",
+          "headersSize": -1,
+          "bodySize": -1,
+          "_transferSize": 120
+        },
+        "cache": {},
+        "timings": { "blocked": 1.0, "send": 0.1, "wait": 200.0, "receive": 0.5 },
+        "serverIPAddress": "[2001:db8::1]"
+      },
+      {
+        "startedDateTime": "2022-01-01T00:00:01.000Z",
+        "time": 0,
+        "request": {
+          "method": "GET",
+          "url": "https://ads.example.com/",
+          "httpVersion": "",
+          "headers": [],
+          "queryString": [],
+          "cookies": [],
+          "headersSize": -1,
+          "bodySize": 0
+        },
+        "response": {
+          "status": 0,
+          "statusText": "",
+          "httpVersion": "",
+          "headers": [],
+          "cookies": [],
+          "content": { "size": 0, "mimeType": "x-unknown" },
+          "redirectURL": "",
+          "headersSize": -1,
+          "bodySize": -1,
+          "_error": "net::ERR_BLOCKED_BY_CLIENT"
+        },
+        "cache": {},
+        "timings": { "send": 0, "wait": 0, "receive": 0 }
+      }
+    ]
+  }
+}"#;
+
+#[test_log::test]
+fn test_har_convert() {
+    let har: HAR = serde_json::from_str(HAR_DATA).unwrap();
+    let mut writer = WARCWriter::new(Vec::new());
+    writer.set_digest_algorithm(Some("sha1")).unwrap();
+
+    let mut converter = HARConverter::new();
+    let count = converter
+        .convert(&har, &mut writer, Some("capture.har"), None)
+        .unwrap();
+    assert_eq!(count, 1);
+
+    let data = writer.into_inner();
+    let mut reader = WARCReader::new(data.as_slice()).unwrap();
+    let mut records = Vec::new();
+
+    while let Some(metadata) = reader.begin_record().unwrap() {
+        let mut block = Vec::new();
+        reader.read_block().read_to_end(&mut block).unwrap();
+        reader.end_record().unwrap();
+        records.push((metadata, String::from_utf8_lossy(&block).to_string()));
+    }
+
+    assert_eq!(records.len(), 4);
+
+    let (metadata, block) = &records[1];
+    assert_eq!(metadata.fields().get_str("WARC-Type"), Some("metadata"));
+    assert!(block.contains("har-filename: capture.har\r\n"));
+    assert!(block.contains("har-creator: WebInspector/537.36\r\n"));
+    assert!(block.contains("har-browser: Chrome/100.0\r\n"));
+
+    let (request, request_block) = &records[2];
+    let (response, response_block) = &records[3];
+    let response_id = response.fields().get_str("WARC-Record-ID").unwrap();
+    assert_eq!(request.fields().get_str("WARC-Type"), Some("request"));
+    assert_eq!(
+        request.fields().get_str("WARC-Concurrent-To"),
+        Some(response_id)
+    );
+    assert_eq!(
+        request.fields().get_str("WARC-Date"),
+        Some("2022-01-01T00:00:00Z")
+    );
+    assert!(request.fields().contains_key("WARC-Block-Digest"));
+    assert_eq!(
+        request_block.as_bytes(),
+        b"POST /form?a=1 HTTP/1.1\r\n\
+        Host: example.com:8443\r\n\
+        content-type: application/octet-stream\r\n\
+        Content-Length: 3\r\n\r\n\x01\x02\x03"
+    );
+
+    assert_eq!(response.fields().get_str("WARC-Type"), Some("response"));
+    assert_eq!(
+        response.fields().get_str("WARC-Date"),
+        Some("2022-01-01T00:00:00.250Z")
+    );
+    assert_eq!(
+        response.fields().get_str("WARC-IP-Address"),
+        Some("2001:db8::1")
+    );
+    assert!(response.fields().contains_key("WARC-Payload-Digest"));
+    assert_eq!(
+        response_block,
+        "HTTP/1.1 200 \r\ncontent-type: text/plain\r\nContent-Length: 5\r\n\r\nHello"
+    );
+}
+
+#[test_log::test]
+fn test_har_convert_post_params() {
+    let mut har: HAR = serde_json::from_str(HAR_DATA).unwrap();
+    har.log.entries.truncate(1);
+    har.log.entries[0].request.post_data = Some(
+        serde_json::from_str(
+            r#"{
+              "mimeType": "application/x-www-form-urlencoded",
+              "params": [
+                { "name": "q", "value": "a b&c" },
+                { "name": "empty" }
+              ]
+            }"#,
+        )
+        .unwrap(),
+    );
+
+    let mut writer = WARCWriter::new(Vec::new());
+    HARConverter::new()
+        .convert(&har, &mut writer, None, None)
+        .unwrap();
+
+    let data = writer.into_inner();
+    let mut reader = WARCReader::new(data.as_slice()).unwrap();
+    let mut request_block = Vec::new();
+
+    while let Some(metadata) = reader.begin_record().unwrap() {
+        let mut block = Vec::new();
+        reader.read_block().read_to_end(&mut block).unwrap();
+        reader.end_record().unwrap();
+
+        if metadata.fields().get_str("WARC-Type") == Some("request") {
+            request_block = block;
+        }
+    }
+
+    assert_eq!(
+        request_block,
+        b"POST /form?a=1 HTTP/1.1\r\n\
+        Host: example.com:8443\r\n\
+        content-type: application/octet-stream\r\n\
+        Content-Length: 16\r\n\r\nq=a+b%26c&empty="
+    );
+}
+
+#[test]
+fn test_har_round_trip() {
+    let data = common::write_records(&make_records(), CompressionFormat::Raw);
+    let mut exporter = HARExporter::new();
+    exporter
+        .add_reader(&mut WARCReader::new(data.as_slice()).unwrap())
+        .unwrap();
+    let har = exporter.finish();
+
+    let mut writer = WARCWriter::new(Vec::new());
+    HARConverter::new()
+        .convert(&har, &mut writer, None, None)
+        .unwrap();
+
+    let data = writer.into_inner();
+    let mut exporter = HARExporter::new();
+    exporter
+        .add_reader(&mut WARCReader::new(data.as_slice()).unwrap())
+        .unwrap();
+    let round_trip_har = exporter.finish();
+
+    assert_eq!(round_trip_har.log.entries.len(), har.log.entries.len());
+
+    for (entry, round_trip_entry) in har.log.entries.iter().zip(&round_trip_har.log.entries) {
+        assert_eq!(round_trip_entry.started_date_time, entry.started_date_time);
+        assert_eq!(round_trip_entry.time, entry.time);
+        assert_eq!(round_trip_entry.request.url, entry.request.url);
+        assert_eq!(round_trip_entry.response.status, entry.response.status);
+        assert_eq!(round_trip_entry.response.content, entry.response.content);
+    }
+}
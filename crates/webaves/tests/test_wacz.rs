@@ -0,0 +1,133 @@
+mod common;
+
+use std::io::{Cursor, Write};
+
+use webaves::{
+    compress::{CompressionFormat, Compressor},
+    wacz::{WACZError, WACZReader, WACZWriter},
+    warc::{new_record_id, ResourceRecordBuilder, WARCRecord, WARCRecordType},
+};
+
+fn make_response(url: &str, content_type: &str, status: &str) -> WARCRecord {
+    let http_header = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: 5\r\n\r\n",
+        status, content_type
+    );
+
+    common::make_response(
+        url,
+        &new_record_id(),
+        "2000-12-30T01:02:03Z",
+        http_header.as_bytes(),
+    )
+}
+
+fn make_wacz() -> Vec<u8> {
+    let warc_1 = common::write_records(
+        &[
+            make_response("http://example.com/", "text/html", "200 OK"),
+            make_response("http://example.com/style.css", "text/css", "200 OK"),
+            make_response("http://example.com/missing", "text/html", "404 Not Found"),
+        ],
+        CompressionFormat::Gzip,
+    );
+    let warc_2 = common::write_records(
+        &[
+            ResourceRecordBuilder::new("urn:example:note")
+                .content_type("text/plain")
+                .payload(b"Hello".as_slice())
+                .build()
+                .unwrap(),
+            make_response("http://example.net/", "text/html; charset=utf-8", "200 OK"),
+        ],
+        CompressionFormat::Gzip,
+    );
+
+    let mut writer = WACZWriter::new(Cursor::new(Vec::new()));
+    writer.set_title(Some("Example".to_string()));
+    writer.add_warc("1.warc.gz", Cursor::new(warc_1)).unwrap();
+    writer.add_warc("2.warc.gz", Cursor::new(warc_2)).unwrap();
+
+    writer.finish().unwrap().into_inner()
+}
+
+#[test_log::test]
+fn wacz_create_and_read() {
+    let data = make_wacz();
+    let mut reader = WACZReader::new(Cursor::new(data)).unwrap();
+
+    let data_package = reader.data_package().clone();
+    assert_eq!(data_package.title.as_deref(), Some("Example"));
+    assert_eq!(
+        data_package
+            .resources
+            .iter()
+            .map(|resource| resource.path.as_str())
+            .collect::<Vec<&str>>(),
+        vec![
+            "archive/1.warc.gz",
+            "archive/2.warc.gz",
+            "indexes/index.cdx",
+            "pages/pages.jsonl"
+        ]
+    );
+
+    let pages = reader.pages().unwrap();
+    assert_eq!(
+        pages
+            .iter()
+            .map(|page| page.url.as_str())
+            .collect::<Vec<&str>>(),
+        vec!["http://example.com/", "http://example.net/"]
+    );
+    assert_eq!(pages[0].ts, "2000-12-30T01:02:03Z");
+
+    assert_eq!(reader.warc_names(), vec!["1.warc.gz", "2.warc.gz"]);
+
+    {
+        let mut warc_reader = reader.open_warc("2.warc.gz").unwrap();
+        let metadata = warc_reader.begin_record().unwrap().unwrap();
+        assert_eq!(metadata.record_type(), Some(WARCRecordType::Resource));
+    }
+
+    assert_eq!(reader.verify_data_package().unwrap(), Some(true));
+
+    for resource in &data_package.resources {
+        assert!(reader.verify_resource(resource).unwrap());
+    }
+
+    let mut resource = data_package.resources[0].clone();
+    resource.bytes += 1;
+    assert!(!reader.verify_resource(&resource).unwrap());
+
+    resource.path = "archive/3.warc.gz".to_string();
+    assert!(matches!(
+        reader.verify_resource(&resource),
+        Err(WACZError::MissingFile { .. })
+    ));
+}
+
+#[test_log::test]
+fn wacz_invalid_format() {
+    let result = WACZReader::new(Cursor::new(b"WARC/1.1\r\n\r\n".to_vec()));
+    assert!(matches!(result, Err(WACZError::InvalidFormat)));
+}
+
+#[test_log::test]
+fn wacz_reject_single_member() {
+    let data = common::write_records(
+        &[
+            make_response("http://example.com/", "text/html", "200 OK"),
+            make_response("http://example.net/", "text/html", "200 OK"),
+        ],
+        CompressionFormat::Raw,
+    );
+    let mut compressor =
+        Compressor::new(Vec::new(), CompressionFormat::Gzip, Default::default()).unwrap();
+    compressor.write_all(&data).unwrap();
+    let data = compressor.finish().unwrap();
+
+    let mut writer = WACZWriter::new(Cursor::new(Vec::new()));
+    let result = writer.add_warc("1.warc.gz", Cursor::new(data));
+    assert!(matches!(result, Err(WACZError::NotMultistream { .. })));
+}
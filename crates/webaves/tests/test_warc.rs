@@ -1,12 +1,18 @@
 use std::{
     fs::File,
-    io::{Read, Write},
+    io::{Cursor, Read, Write},
     path::PathBuf,
 };
 
 use webaves::{
+    compress::{train_zstd_dictionary, CompressionFormat, Decompressor},
     header::HeaderMap,
-    warc::{WARCReader, WARCWriter},
+    warc::{
+        collect_dictionary_samples, pack_records,
+        validate::{ProblemKind, Validator},
+        RequestRecordBuilder, ResponseRecordBuilder, WARCError, WARCReader, WARCRecordType,
+        WARCWriter, WarcinfoRecordBuilder,
+    },
 };
 
 #[test_log::test]
@@ -109,3 +115,334 @@ fn minimal_warc_write() {
         .join("tests/data/warc_minimal.warc");
     assert_eq!(output_buf, std::fs::read(path).unwrap());
 }
+
+#[test_log::test]
+fn minimal_warc_open_at() {
+    let path = PathBuf::new()
+        .join(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/data/warc_minimal.warc");
+    let file = File::open(path).unwrap();
+    let mut reader = WARCReader::open_at(file, 165).unwrap();
+
+    let metadata = reader.begin_record().unwrap().unwrap();
+    assert_eq!(metadata.raw_file_offset(), 165);
+    assert_eq!(
+        metadata.fields().get_str("WARC-Record-ID").unwrap(),
+        "<urn:uuid:10000001-0002-0003-0004-000000000005>"
+    );
+
+    let path = PathBuf::new()
+        .join(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/data/warc_minimal.warc");
+    let file = File::open(path).unwrap();
+    let result = WARCReader::open_at(file, 10);
+    assert!(matches!(result, Err(WARCError::UnknownFormat)));
+}
+
+#[test_log::test]
+fn compressed_warc_open_at() {
+    let mut output = Vec::new();
+    let mut offsets = Vec::new();
+
+    for index in 0..3 {
+        let mut writer =
+            WARCWriter::new_compressed(Vec::new(), CompressionFormat::Gzip, Default::default());
+        let mut header = HeaderMap::new();
+        header.insert("WARC-Type", "resource");
+        header.insert("WARC-Date", "2000-12-30T01:02:03Z");
+        header.insert(
+            "WARC-Record-ID",
+            format!("<urn:uuid:00000001-0002-0003-0004-00000000000{}>", index),
+        );
+        header.insert("Content-Length", "5");
+        writer.begin_record(&header).unwrap();
+        writer.write_block().write_all(b"Hello").unwrap();
+        writer.end_record().unwrap();
+
+        offsets.push(output.len() as u64);
+        output.extend_from_slice(&writer.into_inner());
+    }
+
+    let mut reader = WARCReader::open_at(Cursor::new(&output), offsets[1]).unwrap();
+
+    for (index, offset) in offsets.iter().enumerate().skip(1) {
+        let metadata = reader.begin_record().unwrap().unwrap();
+        assert_eq!(metadata.raw_file_offset(), *offset);
+        assert_eq!(
+            metadata.fields().get_str("WARC-Record-ID").unwrap(),
+            format!("<urn:uuid:00000001-0002-0003-0004-00000000000{}>", index)
+        );
+        std::io::copy(&mut reader.read_block(), &mut std::io::sink()).unwrap();
+        reader.end_record().unwrap();
+    }
+
+    assert!(reader.begin_record().unwrap().is_none());
+    assert_eq!(reader.raw_file_offset().unwrap(), output.len() as u64);
+
+    let result = WARCReader::open_at(Cursor::new(&output), offsets[1] + 3);
+    assert!(matches!(result, Err(WARCError::UnknownFormat)));
+}
+
+fn read_single_record(data: &[u8]) -> (HeaderMap, Vec<u8>) {
+    let mut reader = WARCReader::new(data).unwrap();
+    let metadata = reader.begin_record().unwrap().unwrap();
+    let mut block = Vec::new();
+    reader.read_block().read_to_end(&mut block).unwrap();
+    reader.end_record().unwrap();
+    assert!(reader.begin_record().unwrap().is_none());
+
+    (metadata.fields().clone(), block)
+}
+
+#[test_log::test]
+fn warc_write_auto_digest() {
+    let mut writer = WARCWriter::new(Vec::new());
+    writer.set_digest_algorithm(Some("SHA-1")).unwrap();
+
+    let mut header = HeaderMap::new();
+    header.insert("WARC-Type", "resource");
+    header.insert("WARC-Date", "2000-12-30T01:02:03Z");
+    header.insert(
+        "WARC-Record-ID",
+        "<urn:uuid:00000001-0002-0003-0004-000000000005>",
+    );
+    header.insert("Content-Type", "text/plain");
+
+    writer.begin_record(&header).unwrap();
+    writer.write_block().write_all(b"Hello").unwrap();
+    writer.end_record().unwrap();
+
+    let (fields, block) = read_single_record(&writer.into_inner());
+
+    assert_eq!(block, b"Hello");
+    assert_eq!(fields.get_str("Content-Length"), Some("5"));
+    assert_eq!(
+        fields.get_str("WARC-Block-Digest"),
+        Some("sha1:677Z5C33WLQJW4ETLJOXQXQMYXM5BK7Q")
+    );
+    assert!(fields.get_str("WARC-Payload-Digest").is_none());
+}
+
+#[test_log::test]
+fn warc_write_auto_digest_http_spooled() {
+    let mut writer =
+        WARCWriter::new_compressed(Vec::new(), CompressionFormat::Gzip, Default::default());
+    writer.set_digest_algorithm(Some("sha1")).unwrap();
+    writer.set_spool_memory_limit(10);
+
+    let mut header = HeaderMap::new();
+    header.insert("WARC-Type", "response");
+    header.insert("WARC-Date", "2000-12-30T01:02:03Z");
+    header.insert(
+        "WARC-Record-ID",
+        "<urn:uuid:00000001-0002-0003-0004-000000000005>",
+    );
+    header.insert("Content-Type", "application/http;msgtype=response");
+    header.insert("Content-Length", "999");
+
+    let http_block = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n\
+        5\r\nHello\r\n6\r\n world\r\n0\r\n\r\n";
+
+    writer.begin_record(&header).unwrap();
+    let mut block_writer = writer.write_block();
+    for chunk in http_block.chunks(7) {
+        block_writer.write_all(chunk).unwrap();
+    }
+    writer.end_record().unwrap();
+
+    let (fields, block) = read_single_record(&writer.into_inner());
+
+    assert_eq!(block, http_block);
+    assert_eq!(fields.get_str("Content-Length"), Some("73"));
+    assert_eq!(
+        fields.get_str("WARC-Block-Digest"),
+        Some("sha1:CXUWI6QPSBKPI64ZSRUS7RRJUYVB44LZ")
+    );
+    assert_eq!(
+        fields.get_str("WARC-Payload-Digest"),
+        Some("sha1:PNICYOQ7JDEGBGXCCLG7WY455Y4WOP26")
+    );
+}
+
+#[test_log::test]
+fn warc_write_auto_digest_http_invalid() {
+    let mut writer = WARCWriter::new(Vec::new());
+    writer.set_digest_algorithm(Some("sha1")).unwrap();
+
+    let mut header = HeaderMap::new();
+    header.insert("WARC-Type", "response");
+    header.insert("WARC-Date", "2000-12-30T01:02:03Z");
+    header.insert(
+        "WARC-Record-ID",
+        "<urn:uuid:00000001-0002-0003-0004-000000000005>",
+    );
+    header.insert("Content-Type", "application/http;msgtype=response");
+    header.insert(
+        "WARC-Payload-Digest",
+        "sha1:PNICYOQ7JDEGBGXCCLG7WY455Y4WOP26",
+    );
+
+    let http_block = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\nHello";
+
+    writer.begin_record(&header).unwrap();
+    writer.write_block().write_all(http_block).unwrap();
+    writer.end_record().unwrap();
+
+    let (fields, block) = read_single_record(&writer.into_inner());
+
+    assert_eq!(block, http_block);
+    assert!(fields.contains_key("WARC-Block-Digest"));
+    assert!(fields.get_str("WARC-Payload-Digest").is_none());
+}
+
+#[test_log::test]
+fn warc_write_auto_digest_unsupported() {
+    let mut writer = WARCWriter::new(Vec::new());
+
+    assert!(writer.set_digest_algorithm(Some("crc32")).is_err());
+    assert!(writer.digest_algorithm().is_none());
+}
+
+#[test_log::test]
+fn warc_write_built_records() {
+    let mut info_fields = HeaderMap::new();
+    info_fields.insert("software", "test");
+    let warcinfo = WarcinfoRecordBuilder::new()
+        .filename("test.warc")
+        .info_fields(&info_fields)
+        .unwrap()
+        .build()
+        .unwrap();
+    let warcinfo_id = warcinfo.header.get_str("WARC-Record-ID").unwrap();
+    let response = ResponseRecordBuilder::new("http://example.com/")
+        .warcinfo_id(warcinfo_id)
+        .payload(b"HTTP/1.1 204 No Content\r\n\r\n".as_slice())
+        .build()
+        .unwrap();
+    let response_id = response.header.get_str("WARC-Record-ID").unwrap();
+    let request = RequestRecordBuilder::new("http://example.com/")
+        .warcinfo_id(warcinfo_id)
+        .concurrent_to(response_id)
+        .payload(b"GET / HTTP/1.1\r\nHost: example.com\r\n\r\n".as_slice())
+        .build()
+        .unwrap();
+
+    let mut writer = WARCWriter::new(Vec::new());
+    writer.write_record(&warcinfo).unwrap();
+    writer.write_record(&response).unwrap();
+    writer.write_record(&request).unwrap();
+    let data = writer.into_inner();
+
+    let mut reader = WARCReader::new(data.as_slice()).unwrap();
+    let mut record_types = Vec::new();
+
+    while let Some(metadata) = reader.begin_record().unwrap() {
+        record_types.push(metadata.record_type().unwrap());
+        std::io::copy(&mut reader.read_block(), &mut std::io::sink()).unwrap();
+        reader.end_record().unwrap();
+    }
+
+    assert_eq!(
+        record_types,
+        [
+            WARCRecordType::Warcinfo,
+            WARCRecordType::Response,
+            WARCRecordType::Request
+        ]
+    );
+}
+
+#[test_log::test]
+fn pack_records_preserves_bytes() {
+    let mut input = Vec::new();
+    input.extend_from_slice(b"WARC/1.0\r\nwarc-type:  resource\r\n");
+    input.extend_from_slice(b"WARC-Date: 2000-12-30T01:02:03Z\r\n");
+    input.extend_from_slice(b"WARC-Record-ID: <urn:uuid:00000001-0002-0003-0004-000000000005>\r\n");
+    input.extend_from_slice(b"Content-Length: 5\r\n\r\nHello\r\n\r\n");
+    let path = PathBuf::new()
+        .join(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/data/warc_minimal.warc");
+    input.extend_from_slice(&std::fs::read(path).unwrap());
+
+    let mut reader = WARCReader::new(input.as_slice()).unwrap();
+    let mut writer =
+        WARCWriter::new_compressed(Vec::new(), CompressionFormat::Zstd, Default::default());
+    let count = pack_records(&mut reader, &mut writer).unwrap();
+    let output = writer.into_inner();
+
+    assert_eq!(count, 3);
+    assert_eq!(zstd::decode_all(output.as_slice()).unwrap(), input);
+
+    let mut validator = Validator::new();
+    validator
+        .validate_file("output", output.as_slice())
+        .unwrap();
+    assert!(!validator
+        .problems()
+        .iter()
+        .any(|problem| problem.kind == ProblemKind::CompressionMember));
+}
+
+#[test_log::test]
+fn zstd_dictionary_write_read() {
+    let mut input = WARCWriter::new(Vec::new());
+
+    for index in 0..200 {
+        let record = ResponseRecordBuilder::new(&format!("http://example.com/{}", index))
+            .payload(
+                format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\n\r\n<p>Page {}</p>",
+                    index
+                )
+                .as_bytes(),
+            )
+            .build()
+            .unwrap();
+        input.write_record(&record).unwrap();
+    }
+
+    let input = input.into_inner();
+    let samples =
+        collect_dictionary_samples(&mut WARCReader::new(input.as_slice()).unwrap(), 100).unwrap();
+    assert_eq!(samples.len(), 100);
+
+    let dictionary = train_zstd_dictionary(&samples, 4096).unwrap();
+    let mut writer =
+        WARCWriter::new_compressed(Vec::new(), CompressionFormat::Zstd, Default::default());
+    writer.write_zstd_dictionary(dictionary.clone()).unwrap();
+    pack_records(&mut WARCReader::new(input.as_slice()).unwrap(), &mut writer).unwrap();
+    let output = writer.into_inner();
+
+    let mut writer =
+        WARCWriter::new_compressed(Vec::new(), CompressionFormat::Zstd, Default::default());
+    pack_records(&mut WARCReader::new(input.as_slice()).unwrap(), &mut writer).unwrap();
+    assert!(output.len() < writer.into_inner().len());
+
+    let mut reader = WARCReader::new(output.as_slice()).unwrap();
+    let mut offsets = Vec::new();
+    let mut count = 0;
+
+    while let Some(metadata) = reader.begin_record().unwrap() {
+        offsets.push(metadata.raw_file_offset());
+        std::io::copy(&mut reader.read_block(), &mut std::io::sink()).unwrap();
+        reader.end_record().unwrap();
+        count += 1;
+    }
+
+    assert_eq!(count, 200);
+    assert!(offsets[0] > 8);
+
+    let mut reader = WARCReader::open_at(Cursor::new(&output), offsets[150]).unwrap();
+    let metadata = reader.begin_record().unwrap().unwrap();
+    assert_eq!(
+        metadata.fields().get_str("WARC-Target-URI"),
+        Some("http://example.com/150")
+    );
+
+    let mut decompressed = Vec::new();
+    Decompressor::new(output.as_slice())
+        .unwrap()
+        .read_to_end(&mut decompressed)
+        .unwrap();
+    assert_eq!(decompressed, input);
+}
@@ -0,0 +1,188 @@
+mod common;
+
+use std::{io::Write, path::PathBuf};
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt, BufReader};
+use webaves::{
+    compress::CompressionFormat,
+    warc::{AsyncWARCReader, AsyncWARCWriter, WARCReader, WARCWriter},
+};
+
+async fn write_async_warc(format: CompressionFormat) -> Vec<u8> {
+    let mut writer = AsyncWARCWriter::new_compressed(Vec::new(), format, Default::default());
+
+    for index in 0..3 {
+        let block = format!("Hello world {}", index);
+        writer
+            .begin_record(&common::make_header(index, block.len()))
+            .await
+            .unwrap();
+        writer
+            .write_block()
+            .write_all(block.as_bytes())
+            .await
+            .unwrap();
+        writer.end_record().await.unwrap();
+    }
+
+    writer.into_inner()
+}
+
+async fn check_async_read(data: &[u8]) -> Vec<u64> {
+    let mut reader = AsyncWARCReader::new(BufReader::new(data)).await.unwrap();
+    let mut offsets = Vec::new();
+
+    for index in 0..3 {
+        let metadata = reader.begin_record().await.unwrap().unwrap();
+        assert_eq!(metadata.version(), "WARC/1.1");
+        assert_eq!(
+            metadata.fields().get_str("WARC-Record-ID").unwrap(),
+            format!("<urn:uuid:00000001-0002-0003-0004-00000000000{}>", index)
+        );
+        offsets.push(metadata.raw_file_offset());
+
+        let mut block = Vec::new();
+        reader.read_block().read_to_end(&mut block).await.unwrap();
+        assert_eq!(block, format!("Hello world {}", index).as_bytes());
+
+        reader.end_record().await.unwrap();
+    }
+
+    assert!(reader.begin_record().await.unwrap().is_none());
+    assert_eq!(reader.raw_file_offset().await.unwrap(), data.len() as u64);
+
+    offsets
+}
+
+fn check_sync_read(data: &[u8], expected_offsets: &[u64]) {
+    let mut reader = WARCReader::new(data).unwrap();
+
+    for offset in expected_offsets {
+        let metadata = reader.begin_record().unwrap().unwrap();
+        assert_eq!(metadata.raw_file_offset(), *offset);
+        std::io::copy(&mut reader.read_block(), &mut std::io::sink()).unwrap();
+        reader.end_record().unwrap();
+    }
+
+    assert!(reader.begin_record().unwrap().is_none());
+}
+
+#[test_log::test(tokio::test)]
+async fn async_warc_minimal_read() {
+    let path = PathBuf::new()
+        .join(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/data/warc_minimal.warc");
+    let file = tokio::fs::File::open(path).await.unwrap();
+    let mut reader = AsyncWARCReader::new(BufReader::new(file)).await.unwrap();
+
+    let metadata = reader.begin_record().await.unwrap().unwrap();
+    assert_eq!(metadata.file_offset(), 0);
+    assert_eq!(metadata.block_length(), 10);
+    let mut block = Vec::new();
+    reader.read_block().read_to_end(&mut block).await.unwrap();
+    assert_eq!(block, b"\xf0\xf1\xf2\xf3\xf4\xf5\xf6\xf7\xf8\xf9");
+    reader.end_record().await.unwrap();
+
+    let metadata = reader.begin_record().await.unwrap().unwrap();
+    assert_eq!(metadata.file_offset(), 165);
+    assert_eq!(metadata.raw_file_offset(), 165);
+    assert_eq!(metadata.block_length(), 16);
+    let mut block = Vec::new();
+    reader.read_block().read_to_end(&mut block).await.unwrap();
+    reader.end_record().await.unwrap();
+
+    assert!(reader.begin_record().await.unwrap().is_none());
+}
+
+#[test_log::test(tokio::test)]
+async fn async_warc_minimal_write() {
+    let mut writer = AsyncWARCWriter::new(Vec::new());
+
+    let mut header = common::make_header(5, 10);
+    header.insert(
+        "WARC-Record-ID",
+        "<urn:uuid:00000001-0002-0003-0004-000000000005>",
+    );
+    writer.begin_record(&header).await.unwrap();
+    writer
+        .write_block()
+        .write_all(b"\xf0\xf1\xf2\xf3\xf4\xf5\xf6\xf7\xf8\xf9")
+        .await
+        .unwrap();
+    writer.end_record().await.unwrap();
+
+    let mut sync_writer = WARCWriter::new(Vec::new());
+    sync_writer.begin_record(&header).unwrap();
+    sync_writer
+        .write_block()
+        .write_all(b"\xf0\xf1\xf2\xf3\xf4\xf5\xf6\xf7\xf8\xf9")
+        .unwrap();
+    sync_writer.end_record().unwrap();
+
+    assert_eq!(writer.into_inner(), sync_writer.into_inner());
+}
+
+#[test_log::test(tokio::test)]
+async fn async_warc_gzip_round_trip() {
+    let data = write_async_warc(CompressionFormat::Gzip).await;
+    assert_eq!(&data[0..2], b"\x1f\x8b");
+
+    let offsets = check_async_read(&data).await;
+    assert_eq!(offsets[0], 0);
+    assert!(offsets[1] > 0);
+    check_sync_read(&data, &offsets);
+}
+
+#[test_log::test(tokio::test)]
+async fn async_warc_zstd_round_trip() {
+    let data = write_async_warc(CompressionFormat::Zstd).await;
+    assert_eq!(&data[0..4], b"\x28\xb5\x2f\xfd");
+
+    let offsets = check_async_read(&data).await;
+    check_sync_read(&data, &offsets);
+}
+
+#[test_log::test(tokio::test)]
+async fn async_warc_zstd_dictionary_read() {
+    let mut writer =
+        WARCWriter::new_compressed(Vec::new(), CompressionFormat::Zstd, Default::default());
+    writer
+        .write_zstd_dictionary(b"Hello world WARC/1.1\r\nWARC-Type: resource\r\n".repeat(16))
+        .unwrap();
+
+    for index in 0..3 {
+        let block = format!("Hello world {}", index);
+        writer
+            .begin_record(&common::make_header(index, block.len()))
+            .unwrap();
+        writer.write_block().write_all(block.as_bytes()).unwrap();
+        writer.end_record().unwrap();
+    }
+
+    let data = writer.into_inner();
+    assert_eq!(&data[0..4], b"\x5d\x2a\x4d\x18");
+
+    let offsets = check_async_read(&data).await;
+    assert!(offsets[0] > 8);
+    check_sync_read(&data, &offsets);
+}
+
+#[test_log::test(tokio::test)]
+async fn async_warc_read_sync_written() {
+    let mut data = Vec::new();
+
+    for index in 0..3 {
+        let block = format!("Hello world {}", index);
+        let mut writer =
+            WARCWriter::new_compressed(Vec::new(), CompressionFormat::Gzip, Default::default());
+        writer
+            .begin_record(&common::make_header(index, block.len()))
+            .unwrap();
+        writer.write_block().write_all(block.as_bytes()).unwrap();
+        writer.end_record().unwrap();
+        data.extend_from_slice(&writer.into_inner());
+    }
+
+    let offsets = check_async_read(&data).await;
+    check_sync_read(&data, &offsets);
+}
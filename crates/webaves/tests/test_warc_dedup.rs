@@ -0,0 +1,108 @@
+mod common;
+
+use common::PAYLOAD_DIGEST;
+use webaves::warc::{
+    dedup::{Deduplicator, DigestEntry, DigestStore},
+    WARCReader, WARCRecordType, WARCWriter, IDENTICAL_PAYLOAD_DIGEST_PROFILE,
+};
+
+#[test_log::test]
+fn digest_store_persistent() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("dedup.db");
+    let entry = DigestEntry {
+        record_id: "<urn:uuid:00000001-0002-0003-0004-000000000001>".to_string(),
+        target_uri: "http://example.com/".to_string(),
+        date: "2000-12-30T01:02:03Z".to_string(),
+    };
+
+    {
+        let store = DigestStore::open(&path).unwrap();
+        assert!(store
+            .get(PAYLOAD_DIGEST, "http://example.com/")
+            .unwrap()
+            .is_none());
+        store.insert(PAYLOAD_DIGEST, &entry).unwrap();
+    }
+
+    let store = DigestStore::open(&path).unwrap();
+    assert_eq!(
+        store.get(PAYLOAD_DIGEST, "http://example.com/").unwrap(),
+        Some(entry)
+    );
+    assert!(store
+        .get(PAYLOAD_DIGEST, "http://example.net/")
+        .unwrap()
+        .is_none());
+}
+
+#[test_log::test]
+fn deduplicator_write_revisit() {
+    let dedup = Deduplicator::new(DigestStore::open_in_memory().unwrap());
+    let mut writer = WARCWriter::new(Vec::new());
+
+    let first = common::make_response(
+        "http://example.com/",
+        "<urn:uuid:00000001-0002-0003-0004-000000000001>",
+        "2000-12-30T01:02:03Z",
+        common::HTTP_OK_HEADER,
+    );
+    let second = common::make_response(
+        "http://example.com/",
+        "<urn:uuid:00000001-0002-0003-0004-000000000002>",
+        "2000-12-31T01:02:03Z",
+        common::HTTP_OK_HEADER,
+    );
+    let other_url = common::make_response(
+        "http://example.net/",
+        "<urn:uuid:00000001-0002-0003-0004-000000000003>",
+        "2000-12-31T01:02:03Z",
+        common::HTTP_OK_HEADER,
+    );
+
+    assert!(!dedup.write_record(&mut writer, &first).unwrap());
+    assert!(dedup.write_record(&mut writer, &second).unwrap());
+    assert!(!dedup.write_record(&mut writer, &other_url).unwrap());
+
+    let data = writer.into_inner();
+    let mut reader = WARCReader::new(data.as_slice()).unwrap();
+
+    let metadata = reader.begin_record().unwrap().unwrap();
+    assert_eq!(metadata.record_type(), Some(WARCRecordType::Response));
+    std::io::copy(&mut reader.read_block(), &mut std::io::sink()).unwrap();
+    reader.end_record().unwrap();
+
+    let metadata = reader.begin_record().unwrap().unwrap();
+    let fields = metadata.fields();
+    assert_eq!(metadata.record_type(), Some(WARCRecordType::Revisit));
+    assert_eq!(
+        fields.get_str("WARC-Record-ID"),
+        Some("<urn:uuid:00000001-0002-0003-0004-000000000002>")
+    );
+    assert_eq!(fields.get_str("WARC-Date"), Some("2000-12-31T01:02:03Z"));
+    assert_eq!(
+        fields.get_str("WARC-Profile"),
+        Some(IDENTICAL_PAYLOAD_DIGEST_PROFILE)
+    );
+    assert_eq!(
+        fields.get_str("WARC-Refers-To"),
+        Some("<urn:uuid:00000001-0002-0003-0004-000000000001>")
+    );
+    assert_eq!(
+        fields.get_str("WARC-Refers-To-Target-URI"),
+        Some("http://example.com/")
+    );
+    assert_eq!(
+        fields.get_str("WARC-Refers-To-Date"),
+        Some("2000-12-30T01:02:03Z")
+    );
+    assert_eq!(fields.get_str("WARC-Payload-Digest"), Some(PAYLOAD_DIGEST));
+
+    let mut block = Vec::new();
+    std::io::copy(&mut reader.read_block(), &mut block).unwrap();
+    assert_eq!(block, b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\n");
+    reader.end_record().unwrap();
+
+    let metadata = reader.begin_record().unwrap().unwrap();
+    assert_eq!(metadata.record_type(), Some(WARCRecordType::Response));
+}
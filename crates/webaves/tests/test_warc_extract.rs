@@ -0,0 +1,144 @@
+use std::io::Read;
+
+use url::Url;
+use webaves::warc::{
+    extract::{ExtractionFilter, ExtractorDispatcher},
+    ResponseRecordBuilder, WARCReader, WARCWriter,
+};
+
+fn is_accepted(filter: &ExtractionFilter, url: &str, media_type: &str) -> bool {
+    filter.is_accepted(Some(&Url::parse(url).unwrap()), Some(media_type))
+}
+
+#[test]
+fn extraction_filter_accept_reject() {
+    let filter = ExtractionFilter::new();
+    assert!(filter.is_empty());
+    assert!(is_accepted(&filter, "http://example.com/", "text/html"));
+    assert!(filter.is_accepted(None, None));
+
+    let mut filter = ExtractionFilter::new();
+    filter.add_accept("html, .PNG").unwrap();
+    filter.add_accept("image/*,application/json").unwrap();
+    filter.add_reject("*.tmp.*,[!a-z]*").unwrap();
+    assert!(!filter.is_empty());
+
+    assert!(is_accepted(
+        &filter,
+        "http://example.com/a.html",
+        "text/plain"
+    ));
+    assert!(is_accepted(
+        &filter,
+        "http://example.com/a.png?x=1",
+        "text/plain"
+    ));
+    assert!(is_accepted(&filter, "http://example.com/", "image/gif"));
+    assert!(is_accepted(
+        &filter,
+        "http://example.com/api",
+        "application/JSON"
+    ));
+    assert!(is_accepted(&filter, "http://example.com/", "text/html"));
+    assert!(is_accepted(
+        &filter,
+        "http://example.com/a.html/",
+        "text/css"
+    ));
+    assert!(!is_accepted(&filter, "http://example.com/api", "text/html"));
+    assert!(!is_accepted(
+        &filter,
+        "http://example.com/a.tmp.html",
+        "text/html"
+    ));
+    assert!(!is_accepted(
+        &filter,
+        "http://example.com/1.html",
+        "text/html"
+    ));
+    assert!(!is_accepted(
+        &filter,
+        "http://example.com/%31.html",
+        "text/html"
+    ));
+    assert!(!filter.is_accepted(None, None));
+
+    let mut filter = ExtractionFilter::new();
+    filter.add_reject("index.html").unwrap();
+    assert!(!is_accepted(
+        &filter,
+        "http://example.com/dir/",
+        "text/html"
+    ));
+    assert!(is_accepted(&filter, "http://example.com/dir", "text/html"));
+
+    let mut filter = ExtractionFilter::new();
+    filter
+        .add_accept_pattern(r"^https?://example\.com/")
+        .unwrap();
+    filter.add_accept_pattern(r"^ftp://").unwrap();
+    filter.add_reject_pattern("private").unwrap();
+
+    assert!(is_accepted(&filter, "http://example.com/a", "text/html"));
+    assert!(is_accepted(&filter, "ftp://example.net/a", "text/html"));
+    assert!(!is_accepted(&filter, "http://example.net/a", "text/html"));
+    assert!(!is_accepted(
+        &filter,
+        "http://example.com/private/a",
+        "text/html"
+    ));
+    assert!(!is_accepted(
+        &filter,
+        "http://example.com/a?private",
+        "text/html"
+    ));
+
+    assert!(ExtractionFilter::new().add_accept_pattern("(").is_err());
+}
+
+#[test_log::test]
+fn extraction_filter_dispatcher() {
+    let mut writer = WARCWriter::new(Vec::new());
+
+    for (url, content_type) in [
+        ("http://example.com/a.html", "text/html; charset=utf-8"),
+        ("http://example.com/b.html", "image/png"),
+    ] {
+        let payload = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: 5\r\n\r\nHello",
+            content_type
+        );
+        let record = ResponseRecordBuilder::new(url)
+            .payload(payload.as_bytes())
+            .build()
+            .unwrap();
+        writer.write_record(&record).unwrap();
+    }
+
+    let data = writer.into_inner();
+    let mut reader = WARCReader::new(data.as_slice()).unwrap();
+    let mut filter = ExtractionFilter::new();
+    filter.add_accept("text/html").unwrap();
+    let mut results = Vec::new();
+
+    while let Some(metadata) = reader.begin_record().unwrap() {
+        let mut extractor = ExtractorDispatcher::new(reader.read_block());
+        extractor.add_default_extractors();
+        extractor.set_filter(&filter);
+        assert!(extractor.can_accept_any(&metadata));
+
+        if extractor.begin(&metadata).unwrap() {
+            let mut payload = Vec::new();
+            extractor.read_to_end(&mut payload).unwrap();
+            extractor.finish().unwrap();
+            results.push(Some(payload));
+        } else {
+            std::io::copy(&mut extractor.into_inner(), &mut std::io::sink()).unwrap();
+            results.push(None);
+        }
+
+        reader.end_record().unwrap();
+    }
+
+    assert_eq!(results, [Some(b"Hello".to_vec()), None]);
+}
@@ -0,0 +1,212 @@
+mod common;
+
+use std::io::{Cursor, Write};
+
+use webaves::{
+    compress::{CompressionFormat, Compressor},
+    header::HeaderMap,
+    http::ResponseHeader,
+    warc::{
+        filter::{FilterError, FilterExpression, RecordFilter},
+        RequestRecordBuilder, ResponseRecordBuilder, WARCReader, WARCRecord, WARCWriter,
+    },
+};
+
+const RESPONSES: [(&str, &str); 3] = [
+    (
+        "http://example.com/",
+        "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\n\r\n<p>Hello</p>",
+    ),
+    (
+        "http://example.com/image.png",
+        "HTTP/1.1 200 OK\r\nContent-Type: image/png\r\n\r\nPNG",
+    ),
+    (
+        "http://example.net/missing",
+        "HTTP/1.1 404 Not Found\r\nContent-Type: text/html\r\n\r\n<p>Not found</p>",
+    ),
+];
+
+fn make_records() -> Vec<WARCRecord> {
+    let mut records = Vec::new();
+
+    for (index, (url, response)) in RESPONSES.iter().enumerate() {
+        let request_id = format!("<urn:uuid:00000000-0000-0000-0000-00000000000{}>", index);
+        let request = RequestRecordBuilder::new(url)
+            .record_id(&request_id)
+            .payload(format!("GET / HTTP/1.1\r\nHost: {}\r\n\r\n", index).as_bytes())
+            .build()
+            .unwrap();
+        let response = ResponseRecordBuilder::new(url)
+            .concurrent_to(&request_id)
+            .payload(response.as_bytes())
+            .build()
+            .unwrap();
+
+        records.push(request);
+        records.push(response);
+    }
+
+    records
+}
+
+fn read_target_uris(data: &[u8]) -> Vec<String> {
+    let mut reader = WARCReader::new(data).unwrap();
+    let mut uris = Vec::new();
+
+    while let Some(metadata) = reader.begin_record().unwrap() {
+        let warc_type = metadata.fields().get_str("WARC-Type").unwrap();
+        let url = metadata.fields().get_str("WARC-Target-URI").unwrap();
+        uris.push(format!("{} {}", warc_type, url));
+        std::io::copy(&mut reader.read_block(), &mut std::io::sink()).unwrap();
+        reader.end_record().unwrap();
+    }
+
+    uris
+}
+
+#[test]
+fn filter_expression_matches() {
+    let mut fields = HeaderMap::new();
+    fields.insert("WARC-Type", "response");
+    fields.insert("WARC-Target-URI", "http://www.example.com/index.html");
+    fields.insert("Content-Length", "100");
+
+    let mut http_header = ResponseHeader::new(200);
+    http_header
+        .fields
+        .insert("Content-Type", "Text/HTML; charset=utf-8");
+
+    let check = |expression: &str| {
+        expression
+            .parse::<FilterExpression>()
+            .unwrap()
+            .matches(&fields, Some(&http_header))
+    };
+
+    assert!(check("WARC-Type == response"));
+    assert!(check("warc-type == \"response\""));
+    assert!(!check("WARC-Type != response"));
+    assert!(check(
+        "http.mime == text/html and http.status >= 200 and http.status<300"
+    ));
+    assert!(check("url.host ~ \"(^|\\.)example\\.com$\""));
+    assert!(check("not WARC-Target-URI ~ \"\\.png$\""));
+    assert!(check(
+        "Content-Length > 99.5 and (http.status == 404 or WARC-Type == response)"
+    ));
+    assert!(!check("WARC-Refers-To ~ ."));
+    assert!(check("WARC-Refers-To !~ ."));
+    assert!(check("WARC-Refers-To != x"));
+
+    let expression = "http.status == 200".parse::<FilterExpression>().unwrap();
+    assert!(expression.uses_http());
+    assert!(!expression.matches(&fields, None));
+    assert!(!"WARC-Type == response"
+        .parse::<FilterExpression>()
+        .unwrap()
+        .uses_http());
+}
+
+#[test]
+fn filter_expression_invalid() {
+    for (expression, position) in [
+        ("", 0),
+        ("WARC-Type", 9),
+        ("WARC-Type ==", 12),
+        ("WARC-Type = response", 10),
+        ("(WARC-Type == response", 22),
+        ("WARC-Type == response WARC-Type", 22),
+        ("Content-Length > abc", 17),
+        ("WARC-Type == \"response", 13),
+    ] {
+        match expression.parse::<FilterExpression>() {
+            Err(FilterError::InvalidExpression {
+                position: actual, ..
+            }) => {
+                assert_eq!(actual, position, "{}", expression)
+            }
+            other => panic!("{}: {:?}", expression, other.map(|_| ())),
+        }
+    }
+
+    assert!(matches!(
+        "WARC-Target-URI ~ \"(\"".parse::<FilterExpression>(),
+        Err(FilterError::InvalidRegex(_))
+    ));
+}
+
+#[test_log::test]
+fn filter_stream_copy_members() {
+    let input = common::write_records(&make_records(), CompressionFormat::Gzip);
+    let expression = "http.mime == text/html and http.status == 200"
+        .parse()
+        .unwrap();
+    let filter = RecordFilter::new(expression);
+    let mut writer =
+        WARCWriter::new_compressed(Vec::new(), CompressionFormat::Gzip, Default::default());
+
+    let count = filter
+        .filter_stream(Cursor::new(&input), &mut writer)
+        .unwrap();
+    let output = writer.into_inner();
+
+    assert_eq!(count, 1);
+    assert_eq!(read_target_uris(&output), ["response http://example.com/"]);
+
+    // The compressed member is copied unchanged.
+    assert!(input
+        .windows(output.len())
+        .any(|window| window == output.as_slice()));
+}
+
+#[test_log::test]
+fn filter_stream_include_concurrent_recompress() {
+    let input = common::write_records(&make_records(), CompressionFormat::Gzip);
+    let expression = "WARC-Type == response and url.host == example.com"
+        .parse()
+        .unwrap();
+    let mut filter = RecordFilter::new(expression);
+    filter.set_include_concurrent(true);
+    let mut writer =
+        WARCWriter::new_compressed(Vec::new(), CompressionFormat::Zstd, Default::default());
+
+    let count = filter
+        .filter_stream(Cursor::new(&input), &mut writer)
+        .unwrap();
+    let output = writer.into_inner();
+
+    assert_eq!(count, 4);
+    assert_eq!(
+        read_target_uris(&output),
+        [
+            "request http://example.com/",
+            "response http://example.com/",
+            "request http://example.com/image.png",
+            "response http://example.com/image.png",
+        ]
+    );
+}
+
+#[test_log::test]
+fn filter_stream_whole_file_compression() {
+    let input = common::write_records(&make_records(), CompressionFormat::Raw);
+    let mut compressor =
+        Compressor::new(Vec::new(), CompressionFormat::Gzip, Default::default()).unwrap();
+    compressor.write_all(&input).unwrap();
+    let input = compressor.finish().unwrap();
+
+    let filter = RecordFilter::new("http.status == 404".parse().unwrap());
+    let mut writer = WARCWriter::new(Vec::new());
+
+    let count = filter
+        .filter_stream(Cursor::new(&input), &mut writer)
+        .unwrap();
+    let output = writer.into_inner();
+
+    assert_eq!(count, 1);
+    assert_eq!(
+        read_target_uris(&output),
+        ["response http://example.net/missing"]
+    );
+}
@@ -0,0 +1,128 @@
+use std::io::Write;
+
+use webaves::{
+    compress::CompressionFormat,
+    header::HeaderMap,
+    warc::{
+        index::{CDXJWriter, CDXWriter, Indexer},
+        WARCReader, WARCWriter,
+    },
+};
+
+fn write_record(output: &mut Vec<u8>, header: &HeaderMap, block: &[u8]) {
+    let mut writer =
+        WARCWriter::new_compressed(Vec::new(), CompressionFormat::Gzip, Default::default());
+    writer.begin_record(header).unwrap();
+    writer.write_block().write_all(block).unwrap();
+    writer.end_record().unwrap();
+    output.extend_from_slice(&writer.into_inner());
+}
+
+fn build_warc() -> (Vec<u8>, Vec<usize>) {
+    let mut output = Vec::new();
+    let mut offsets = Vec::new();
+
+    let mut header = HeaderMap::new();
+    header.insert("WARC-Type", "warcinfo");
+    header.insert("WARC-Date", "2000-12-30T01:02:03Z");
+    header.insert(
+        "WARC-Record-ID",
+        "<urn:uuid:00000001-0002-0003-0004-000000000005>",
+    );
+    header.insert("Content-Type", "application/warc-fields");
+    header.insert("Content-Length", "18");
+    offsets.push(output.len());
+    write_record(&mut output, &header, b"software: test\r\n\r\n");
+
+    let block = b"HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: 5\r\n\r\nHello";
+    let mut header = HeaderMap::new();
+    header.insert("WARC-Type", "response");
+    header.insert("WARC-Date", "2000-12-30T01:02:04Z");
+    header.insert(
+        "WARC-Record-ID",
+        "<urn:uuid:10000001-0002-0003-0004-000000000005>",
+    );
+    header.insert("WARC-Target-URI", "http://www.example.com/Index.html");
+    header.insert(
+        "WARC-Payload-Digest",
+        "sha1:PQVTHSB2ARCJWRZ7DFEUWBRYYGIIXXYS",
+    );
+    header.insert("Content-Type", "application/http;msgtype=response");
+    header.insert("Content-Length", block.len().to_string());
+    offsets.push(output.len());
+    write_record(&mut output, &header, block);
+
+    let mut header = HeaderMap::new();
+    header.insert("WARC-Type", "resource");
+    header.insert("WARC-Date", "2000-12-30T01:02:05Z");
+    header.insert(
+        "WARC-Record-ID",
+        "<urn:uuid:20000001-0002-0003-0004-000000000005>",
+    );
+    header.insert("WARC-Target-URI", "http://example.com/a.txt");
+    header.insert("Content-Type", "text/plain");
+    header.insert("Content-Length", "3");
+    offsets.push(output.len());
+    write_record(&mut output, &header, b"abc");
+    offsets.push(output.len());
+
+    (output, offsets)
+}
+
+#[test_log::test]
+fn index_warc_gzip() {
+    let (data, offsets) = build_warc();
+    let reader = WARCReader::new(data.as_slice()).unwrap();
+    let mut indexer = Indexer::new(reader, "example.warc.gz");
+
+    let entry = indexer.next_entry().unwrap().unwrap();
+    assert_eq!(entry.surt, "com,example)/index.html");
+    assert_eq!(entry.timestamp, "20001230010204");
+    assert_eq!(entry.url, "http://www.example.com/Index.html");
+    assert_eq!(entry.mime.as_deref(), Some("text/html"));
+    assert_eq!(entry.status, Some(200));
+    assert_eq!(
+        entry.digest.as_deref(),
+        Some("PQVTHSB2ARCJWRZ7DFEUWBRYYGIIXXYS")
+    );
+    assert_eq!(entry.offset, offsets[1] as u64);
+    assert_eq!(entry.length, (offsets[2] - offsets[1]) as u64);
+
+    let mut writer = CDXJWriter::new(Vec::new());
+    writer.write_entry(&entry).unwrap();
+    let line = String::from_utf8(writer.into_inner()).unwrap();
+    assert_eq!(
+        line,
+        format!(
+            "com,example)/index.html 20001230010204 {{\"url\":\"http://www.example.com/Index.html\",\
+            \"mime\":\"text/html\",\"status\":\"200\",\"digest\":\"PQVTHSB2ARCJWRZ7DFEUWBRYYGIIXXYS\",\
+            \"length\":\"{}\",\"offset\":\"{}\",\"filename\":\"example.warc.gz\"}}\n",
+            offsets[2] - offsets[1],
+            offsets[1]
+        )
+    );
+
+    let mut writer = CDXWriter::new(Vec::new());
+    writer.write_header().unwrap();
+    writer.write_entry(&entry).unwrap();
+    let lines = String::from_utf8(writer.into_inner()).unwrap();
+    assert_eq!(
+        lines,
+        format!(
+            " CDX N b a m s k r M S V g\n\
+            com,example)/index.html 20001230010204 http://www.example.com/Index.html \
+            text/html 200 PQVTHSB2ARCJWRZ7DFEUWBRYYGIIXXYS - - {} {} example.warc.gz\n",
+            offsets[2] - offsets[1],
+            offsets[1]
+        )
+    );
+
+    let entry = indexer.next_entry().unwrap().unwrap();
+    assert_eq!(entry.surt, "com,example)/a.txt");
+    assert_eq!(entry.mime.as_deref(), Some("text/plain"));
+    assert_eq!(entry.status, None);
+    assert_eq!(entry.offset, offsets[2] as u64);
+    assert_eq!(entry.length, (offsets[3] - offsets[2]) as u64);
+
+    assert!(indexer.next_entry().unwrap().is_none());
+}
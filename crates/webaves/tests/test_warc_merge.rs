@@ -0,0 +1,130 @@
+mod common;
+
+use std::{fs::File, io::Cursor};
+
+use webaves::{
+    compress::{CompressionFormat, CompressionLevel},
+    warc::{
+        RecordMerger, ResourceRecordBuilder, RotatingWARCWriter, WARCReader, WARCRecord,
+        WarcinfoRecordBuilder,
+    },
+};
+
+fn make_records() -> Vec<WARCRecord> {
+    let mut records = Vec::new();
+
+    let warcinfo = WarcinfoRecordBuilder::new()
+        .filename("old.warc")
+        .payload(b"software: test\r\n".as_slice())
+        .build()
+        .unwrap();
+    records.push(warcinfo);
+
+    for index in 0..5 {
+        let record = ResourceRecordBuilder::new(&format!("http://example.com/{}", index))
+            .payload(format!("Hello world {}", index).as_bytes())
+            .build()
+            .unwrap();
+        records.push(record);
+    }
+
+    records
+}
+
+fn read_record(path: &std::path::Path, offset: u64) -> (String, Option<String>) {
+    let mut reader = WARCReader::open_at(File::open(path).unwrap(), offset).unwrap();
+    let metadata = reader.begin_record().unwrap().unwrap();
+    std::io::copy(&mut reader.read_block(), &mut std::io::sink()).unwrap();
+    reader.end_record().unwrap();
+
+    let fields = metadata.fields();
+
+    (
+        fields.get_str("WARC-Type").unwrap().to_string(),
+        fields
+            .get_str("WARC-Filename")
+            .map(|value| value.to_string()),
+    )
+}
+
+#[test_log::test]
+fn merge_stream_copy_members() {
+    let input = common::write_records(&make_records(), CompressionFormat::Gzip);
+    let dir = tempfile::tempdir().unwrap();
+    let mut writer = RotatingWARCWriter::new(dir.path(), "test");
+    writer.set_hostname("example.internal");
+    writer.set_max_record_count(Some(4));
+    writer.set_warcinfo_enabled(false);
+
+    let merger = RecordMerger::new();
+    let mut mappings = merger
+        .merge_stream(Cursor::new(&input), &mut writer)
+        .unwrap();
+    mappings.extend(
+        merger
+            .merge_stream(Cursor::new(&input), &mut writer)
+            .unwrap(),
+    );
+    writer.close().unwrap();
+
+    assert_eq!(mappings.len(), 12);
+    assert_eq!(mappings[0].source_offset, 0);
+    assert_eq!(mappings[6].source_offset, 0);
+
+    let mut paths = mappings
+        .iter()
+        .map(|mapping| mapping.target.path.clone())
+        .collect::<Vec<_>>();
+    paths.dedup();
+    assert_eq!(paths.len(), 3);
+
+    for (index, mapping) in mappings.iter().enumerate() {
+        let output = std::fs::read(&mapping.target.path).unwrap();
+        let start = mapping.target.offset as usize;
+        let end = start + mapping.target.length as usize;
+        let source_end = mappings
+            .get(index + 1)
+            .map(|mapping| mapping.source_offset as usize)
+            .filter(|offset| *offset != 0)
+            .unwrap_or(input.len());
+
+        // The compressed member is copied unchanged.
+        assert_eq!(
+            &output[start..end],
+            &input[mapping.source_offset as usize..source_end]
+        );
+    }
+}
+
+#[test_log::test]
+fn merge_stream_rewrite_warcinfo() {
+    let input = common::write_records(&make_records(), CompressionFormat::Raw);
+    let dir = tempfile::tempdir().unwrap();
+    let mut writer = RotatingWARCWriter::new(dir.path(), "test");
+    writer.set_hostname("example.internal");
+    writer.set_compression(CompressionFormat::Zstd, CompressionLevel::default());
+    writer.set_max_record_count(Some(3));
+    writer.set_warcinfo_enabled(false);
+
+    let mut merger = RecordMerger::new();
+    merger.set_rewrite_warcinfo(true);
+    let mappings = merger
+        .merge_stream(Cursor::new(&input), &mut writer)
+        .unwrap();
+    writer.close().unwrap();
+
+    assert_eq!(mappings.len(), 6);
+
+    let (warc_type, filename) = read_record(&mappings[0].target.path, 0);
+    assert_eq!(warc_type, "warcinfo");
+    assert_eq!(filename.as_deref(), Some(mappings[0].target.filename()));
+    assert!(mappings[0].target.filename().ends_with(".warc.zst"));
+
+    for mapping in &mappings[1..] {
+        let (warc_type, filename) = read_record(&mapping.target.path, mapping.target.offset);
+        assert_eq!(warc_type, "resource");
+        assert!(filename.is_none());
+    }
+
+    assert_ne!(mappings[2].target.path, mappings[3].target.path);
+}
@@ -0,0 +1,142 @@
+use std::io::Read;
+
+use webaves::{
+    compress::CompressionFormat,
+    warc::{ResourceRecordBuilder, WARCError, WARCReader, WARCRecord, WARCWriter},
+};
+
+fn make_record(index: usize) -> WARCRecord {
+    ResourceRecordBuilder::new(&format!("urn:example:{}", index))
+        .content_type("text/plain")
+        .payload(format!("Hello world {}! The quick brown fox.", index).into_bytes())
+        .build()
+        .unwrap()
+}
+
+fn write_records(count: usize, format: CompressionFormat) -> (Vec<u8>, Vec<u64>) {
+    let mut writer = WARCWriter::new_compressed(Vec::new(), format, Default::default());
+    let mut offsets = Vec::new();
+
+    for index in 0..count {
+        offsets.push(writer.get_ref().len() as u64);
+        writer.write_record(&make_record(index)).unwrap();
+    }
+
+    (writer.into_inner(), offsets)
+}
+
+fn read_all(data: &[u8]) -> (Vec<String>, WARCReader<'_, &[u8]>) {
+    let mut reader = WARCReader::new(data).unwrap();
+    reader.set_recovery(true);
+    let mut urls = Vec::new();
+
+    while let Some(metadata) = reader.begin_record().unwrap() {
+        urls.push(
+            metadata
+                .fields()
+                .get_str("WARC-Target-URI")
+                .unwrap()
+                .to_string(),
+        );
+        let mut block = Vec::new();
+        reader.read_block().read_to_end(&mut block).unwrap();
+        reader.end_record().unwrap();
+    }
+
+    (urls, reader)
+}
+
+#[test_log::test]
+fn recover_malformed_header() {
+    let (mut data, offsets) = write_records(3, CompressionFormat::Raw);
+    let position = offsets[1] as usize + 10;
+    data[position..position + 4].copy_from_slice(b"\r\n\x00\x00");
+
+    let mut reader = WARCReader::new(data.as_slice()).unwrap();
+    reader.begin_record().unwrap();
+    std::io::copy(&mut reader.read_block(), &mut std::io::sink()).unwrap();
+    reader.end_record().unwrap();
+    assert!(reader.begin_record().is_err());
+
+    let (urls, mut reader) = read_all(&data);
+    assert_eq!(urls, vec!["urn:example:0", "urn:example:2"]);
+
+    let skipped = reader.take_skipped_ranges();
+    assert_eq!(skipped.len(), 1);
+    assert_eq!(skipped[0].start, offsets[1]);
+    assert_eq!(skipped[0].end, offsets[2]);
+}
+
+#[test_log::test]
+fn recover_wrong_content_length() {
+    let (data, offsets) = write_records(3, CompressionFormat::Raw);
+    let text = String::from_utf8(data).unwrap();
+    let position = offsets[1] as usize;
+    let text = format!(
+        "{}{}",
+        &text[..position],
+        text[position..].replacen("Content-Length: 35", "Content-Length: 30", 1)
+    );
+
+    let (urls, mut reader) = read_all(text.as_bytes());
+    assert_eq!(
+        urls,
+        vec!["urn:example:0", "urn:example:1", "urn:example:2"]
+    );
+
+    let skipped = reader.take_skipped_ranges();
+    assert_eq!(skipped.len(), 1);
+    assert!(matches!(
+        skipped[0].error,
+        WARCError::MalformedFooter { .. }
+    ));
+    assert_eq!(skipped[0].end, offsets[2]);
+}
+
+#[test_log::test]
+fn recover_corrupt_gzip_member() {
+    let (mut data, offsets) = write_records(3, CompressionFormat::Gzip);
+    let position = (offsets[1] + offsets[2]) as usize / 2;
+
+    for byte in &mut data[position..position + 8] {
+        *byte = !*byte;
+    }
+
+    let (urls, mut reader) = read_all(&data);
+    assert_eq!(urls.first().unwrap(), "urn:example:0");
+    assert_eq!(urls.last().unwrap(), "urn:example:2");
+
+    let skipped = reader.take_skipped_ranges();
+    assert_eq!(skipped.len(), 1);
+    assert_eq!(skipped[0].start, offsets[1]);
+    assert_eq!(skipped[0].end, offsets[2]);
+}
+
+#[test_log::test]
+fn recover_truncated_gzip() {
+    let (data, offsets) = write_records(2, CompressionFormat::Gzip);
+    let data = &data[..data.len() - 10];
+
+    let (urls, mut reader) = read_all(data);
+    assert_eq!(urls, vec!["urn:example:0", "urn:example:1"]);
+
+    let skipped = reader.take_skipped_ranges();
+    assert_eq!(skipped.len(), 1);
+    assert_eq!(skipped[0].start, offsets[1]);
+    assert_eq!(skipped[0].end, data.len() as u64);
+}
+
+#[test_log::test]
+fn recover_trailing_garbage_gzip() {
+    let (mut data, _) = write_records(2, CompressionFormat::Gzip);
+    let length = data.len() as u64;
+    data.extend_from_slice(b"garbage garbage garbage");
+
+    let (urls, mut reader) = read_all(&data);
+    assert_eq!(urls, vec!["urn:example:0", "urn:example:1"]);
+
+    let skipped = reader.take_skipped_ranges();
+    assert_eq!(skipped.len(), 1);
+    assert_eq!(skipped[0].start, length);
+    assert_eq!(skipped[0].end, data.len() as u64);
+}
@@ -0,0 +1,242 @@
+mod common;
+
+use std::io::Read;
+
+use common::PAYLOAD_DIGEST;
+use webaves::{
+    compress::CompressionFormat,
+    warc::{
+        dedup::{Deduplicator, DigestStore},
+        extract::ExtractorDispatcher,
+        RevisitIndex, RevisitRecordBuilder, WARCReader, WARCRecord, WARCRecordType, WARCWriter,
+        IDENTICAL_PAYLOAD_DIGEST_PROFILE, SERVER_NOT_MODIFIED_PROFILE,
+    },
+};
+
+fn write_file(path: &std::path::Path, records: &[WARCRecord], dedup: &Deduplicator) {
+    let file = std::fs::File::create(path).unwrap();
+    let mut writer = WARCWriter::new_compressed(file, CompressionFormat::Gzip, Default::default());
+
+    for record in records {
+        dedup.write_record(&mut writer, record).unwrap();
+    }
+
+    writer.into_inner();
+}
+
+fn read_revisits(path: &std::path::Path, index: &RevisitIndex) -> Vec<(String, Vec<u8>)> {
+    let file = std::fs::File::open(path).unwrap();
+    let mut reader = WARCReader::new(std::io::BufReader::new(file)).unwrap();
+    let mut results = Vec::new();
+
+    while let Some(metadata) = reader.begin_record().unwrap() {
+        let mut block_reader = reader.read_block();
+
+        if metadata.record_type() == Some(WARCRecordType::Revisit) {
+            let (combined_metadata, combined_reader) =
+                index.open(&metadata, &mut block_reader).unwrap();
+            assert_eq!(
+                combined_metadata.record_type(),
+                Some(WARCRecordType::Response)
+            );
+
+            let mut extractor = ExtractorDispatcher::new(combined_reader);
+            extractor.add_default_extractors();
+            assert!(extractor.can_accept_any(&combined_metadata));
+            extractor.begin(&combined_metadata).unwrap();
+
+            let mut payload = Vec::new();
+            extractor.read_to_end(&mut payload).unwrap();
+            extractor.finish().unwrap();
+
+            results.push((
+                metadata
+                    .fields()
+                    .get_str("WARC-Record-ID")
+                    .unwrap()
+                    .to_string(),
+                payload,
+            ));
+        } else {
+            std::io::copy(&mut block_reader, &mut std::io::sink()).unwrap();
+        }
+
+        reader.end_record().unwrap();
+    }
+
+    results
+}
+
+#[test_log::test]
+fn revisit_index_resolve() {
+    let dir = tempfile::tempdir().unwrap();
+    let first_path = dir.path().join("first.warc.gz");
+    let second_path = dir.path().join("second.warc.gz");
+    let dedup = Deduplicator::new(DigestStore::open_in_memory().unwrap());
+
+    write_file(
+        &first_path,
+        &[
+            common::make_response(
+                "http://example.com/other",
+                "<urn:uuid:00000001-0002-0003-0004-000000000000>",
+                "2000-12-30T00:00:00Z",
+                b"HTTP/1.1 404 Not Found\r\n\r\n",
+            ),
+            common::make_response(
+                "http://example.com/",
+                "<urn:uuid:00000001-0002-0003-0004-000000000001>",
+                "2000-12-30T01:02:03Z",
+                b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\n\
+                Content-Length: 5\r\nX-Fetch: 1\r\n\r\n",
+            ),
+        ],
+        &dedup,
+    );
+
+    // Revisit record written by the deduplicator with WARC-Refers-To.
+    let second = common::make_response(
+        "http://example.com/",
+        "<urn:uuid:00000001-0002-0003-0004-000000000002>",
+        "2000-12-31T01:02:03Z",
+        b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\n\
+        Transfer-Encoding: chunked\r\nX-Fetch: 2\r\n\r\n",
+    );
+    // Revisit record with only the digest and URI.
+    let third = RevisitRecordBuilder::new("http://example.com/", SERVER_NOT_MODIFIED_PROFILE)
+        .record_id("<urn:uuid:00000001-0002-0003-0004-000000000003>")
+        .field("WARC-Date", "2001-01-01T01:02:03Z")
+        .field("Content-Type", "application/http;msgtype=response")
+        .payload_digest(PAYLOAD_DIGEST)
+        .payload(b"HTTP/1.1 304 Not Modified\r\nX-Fetch: 3\r\n\r\n".as_slice())
+        .build()
+        .unwrap();
+    // Revisit record of a response not in any file.
+    let missing =
+        RevisitRecordBuilder::new("http://example.net/", IDENTICAL_PAYLOAD_DIGEST_PROFILE)
+            .refers_to("<urn:uuid:00000001-0002-0003-0004-000000000009>")
+            .field("Content-Type", "application/http;msgtype=response")
+            .payload(b"".as_slice())
+            .build()
+            .unwrap();
+
+    let file = std::fs::File::create(&second_path).unwrap();
+    let mut writer = WARCWriter::new(file);
+    dedup.write_record(&mut writer, &second).unwrap();
+    writer.write_record(&third).unwrap();
+    writer.into_inner();
+
+    let mut index = RevisitIndex::new();
+    index.add_file(&first_path).unwrap();
+    index.add_file(&second_path).unwrap();
+    assert_eq!(index.len(), 2);
+
+    let mut reader = WARCReader::new(std::fs::File::open(&second_path).unwrap()).unwrap();
+    let metadata = reader.begin_record().unwrap().unwrap();
+    let location = index.find(&metadata).unwrap();
+    assert_eq!(location.path, first_path);
+    assert_eq!(
+        location.record_id,
+        "<urn:uuid:00000001-0002-0003-0004-000000000001>"
+    );
+
+    let mut writer = WARCWriter::new(Vec::new());
+    writer.write_record(&missing).unwrap();
+    let data = writer.into_inner();
+    let mut reader = WARCReader::new(data.as_slice()).unwrap();
+    let metadata = reader.begin_record().unwrap().unwrap();
+    assert!(index.find(&metadata).is_none());
+
+    // Revisit record referring to a record ID not in any file but with a
+    // matching digest and URI.
+    let unknown_id =
+        RevisitRecordBuilder::new("http://example.com/", IDENTICAL_PAYLOAD_DIGEST_PROFILE)
+            .refers_to("<urn:uuid:00000001-0002-0003-0004-000000000008>")
+            .field("Content-Type", "application/http;msgtype=response")
+            .payload_digest(PAYLOAD_DIGEST)
+            .payload(b"".as_slice())
+            .build()
+            .unwrap();
+    let mut writer = WARCWriter::new(Vec::new());
+    writer.write_record(&unknown_id).unwrap();
+    let data = writer.into_inner();
+    let mut reader = WARCReader::new(data.as_slice()).unwrap();
+    let metadata = reader.begin_record().unwrap().unwrap();
+    assert_eq!(
+        index.find(&metadata).unwrap().record_id,
+        "<urn:uuid:00000001-0002-0003-0004-000000000001>"
+    );
+
+    let results = read_revisits(&second_path, &index);
+    assert_eq!(results.len(), 2);
+    assert_eq!(
+        results[0].0,
+        "<urn:uuid:00000001-0002-0003-0004-000000000002>"
+    );
+    assert_eq!(results[0].1, b"Hello");
+    assert_eq!(
+        results[1].0,
+        "<urn:uuid:00000001-0002-0003-0004-000000000003>"
+    );
+    assert_eq!(results[1].1, b"Hello");
+}
+
+#[test_log::test]
+fn revisit_index_combined_header() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("test.warc");
+    let dedup = Deduplicator::new(DigestStore::open_in_memory().unwrap());
+
+    write_file(
+        &path,
+        &[
+            common::make_response(
+                "http://example.com/",
+                "<urn:uuid:00000001-0002-0003-0004-000000000001>",
+                "2000-12-30T01:02:03Z",
+                b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\nX-Fetch: 1\r\n\r\n",
+            ),
+            common::make_response(
+                "http://example.com/",
+                "<urn:uuid:00000001-0002-0003-0004-000000000002>",
+                "2000-12-31T01:02:03Z",
+                b"HTTP/1.1 200 OK\r\nX-Fetch: 2\r\n\r\n",
+            ),
+        ],
+        &dedup,
+    );
+
+    let mut index = RevisitIndex::new();
+    index.add_file(&path).unwrap();
+
+    let file = std::fs::File::open(&path).unwrap();
+    let mut reader = WARCReader::new(std::io::BufReader::new(file)).unwrap();
+    reader.begin_record().unwrap().unwrap();
+    std::io::copy(&mut reader.read_block(), &mut std::io::sink()).unwrap();
+    reader.end_record().unwrap();
+
+    let metadata = reader.begin_record().unwrap().unwrap();
+    let (combined_metadata, mut combined_reader) =
+        index.open(&metadata, reader.read_block()).unwrap();
+    let mut block = Vec::new();
+    combined_reader.read_to_end(&mut block).unwrap();
+
+    assert_eq!(
+        block,
+        b"HTTP/1.1 200 OK\r\nX-Fetch: 2\r\nContent-Length: 5\r\n\r\nHello"
+    );
+    assert_eq!(combined_metadata.block_length(), block.len() as u64);
+
+    let fields = combined_metadata.fields();
+    assert_eq!(fields.get_str("WARC-Type"), Some("response"));
+    assert_eq!(
+        fields.get_str("WARC-Record-ID"),
+        Some("<urn:uuid:00000001-0002-0003-0004-000000000002>")
+    );
+    assert_eq!(
+        fields.get_str("Content-Length"),
+        Some(block.len().to_string().as_str())
+    );
+    assert!(!fields.contains_key("WARC-Profile"));
+    assert!(!fields.contains_key("WARC-Block-Digest"));
+}
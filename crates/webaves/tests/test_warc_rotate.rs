@@ -0,0 +1,148 @@
+mod common;
+
+use std::{fs::File, io::Write};
+
+use webaves::{
+    compress::CompressionFormat,
+    warc::{RotatingWARCWriter, WARCReader, WARCRecordType, OPEN_FILE_SUFFIX},
+};
+
+#[test_log::test]
+fn rotating_warc_writer_record_count() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut writer = RotatingWARCWriter::new(dir.path(), "test");
+    writer.set_hostname("example.internal");
+    writer.set_max_record_count(Some(2));
+
+    assert!(writer.current_path().is_none());
+
+    let mut locations = Vec::new();
+
+    for index in 0..5 {
+        let block = format!("Hello world {}", index);
+        writer
+            .begin_record(&common::make_header(index, block.len()))
+            .unwrap();
+
+        let open_path = format!(
+            "{}{}",
+            writer.current_path().unwrap().to_str().unwrap(),
+            OPEN_FILE_SUFFIX
+        );
+        assert!(std::path::Path::new(&open_path).exists());
+
+        writer.write_block().write_all(block.as_bytes()).unwrap();
+        locations.push(writer.end_record().unwrap());
+    }
+
+    writer.close().unwrap();
+    assert!(writer.current_path().is_none());
+
+    let mut filenames = std::fs::read_dir(dir.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect::<Vec<String>>();
+    filenames.sort();
+
+    assert_eq!(filenames.len(), 3);
+
+    for (serial, filename) in filenames.iter().enumerate() {
+        assert!(filename.starts_with("test-"));
+        assert!(filename.ends_with(&format!("-{:05}-example.internal.warc.gz", serial)));
+
+        let file = File::open(dir.path().join(filename)).unwrap();
+        let mut reader = WARCReader::new(std::io::BufReader::new(file)).unwrap();
+        let metadata = reader.begin_record().unwrap().unwrap();
+        assert_eq!(metadata.record_type(), Some(WARCRecordType::Warcinfo));
+        assert_eq!(
+            metadata.fields().get_str("WARC-Filename"),
+            Some(filename.as_str())
+        );
+    }
+
+    assert_eq!(locations[0].filename(), filenames[0]);
+    assert_eq!(locations[1].filename(), filenames[0]);
+    assert_eq!(locations[2].filename(), filenames[1]);
+    assert_eq!(locations[4].filename(), filenames[2]);
+
+    for (index, location) in locations.iter().enumerate() {
+        assert!(location.offset > 0);
+        assert!(location.length > 0);
+
+        let file = File::open(&location.path).unwrap();
+        let mut reader =
+            WARCReader::open_at(std::io::BufReader::new(file), location.offset).unwrap();
+        let metadata = reader.begin_record().unwrap().unwrap();
+        assert_eq!(metadata.raw_file_offset(), location.offset);
+        assert_eq!(
+            metadata.fields().get_str("WARC-Target-URI"),
+            Some(format!("http://example.com/{}", index).as_str())
+        );
+        let mut block = Vec::new();
+        std::io::copy(&mut reader.read_block(), &mut block).unwrap();
+        assert_eq!(block, format!("Hello world {}", index).as_bytes());
+        reader.end_record().unwrap();
+
+        assert_eq!(
+            reader.raw_file_offset().unwrap(),
+            location.offset + location.length
+        );
+    }
+}
+
+#[test_log::test]
+fn rotating_warc_writer_file_size() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut writer = RotatingWARCWriter::new(dir.path(), "test");
+    writer.set_compression(CompressionFormat::Raw, Default::default());
+    writer.set_max_file_size(Some(1));
+
+    for index in 0..3 {
+        let block = format!("Hello world {}", index);
+        writer
+            .begin_record(&common::make_header(index, block.len()))
+            .unwrap();
+        writer.write_block().write_all(block.as_bytes()).unwrap();
+        let location = writer.end_record().unwrap();
+        assert!(location.filename().ends_with(".warc"));
+        assert_eq!(Some(location.path.as_path()), writer.current_path());
+    }
+
+    writer.close().unwrap();
+
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 3);
+}
+
+#[test_log::test]
+fn rotating_warc_writer_drop() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut writer = RotatingWARCWriter::new(dir.path(), "test");
+    let block = "Hello world";
+    writer
+        .begin_record(&common::make_header(0, block.len()))
+        .unwrap();
+    writer.write_block().write_all(block.as_bytes()).unwrap();
+    let location = writer.end_record().unwrap();
+    drop(writer);
+
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+
+    let file = File::open(&location.path).unwrap();
+    let mut reader = WARCReader::open_at(std::io::BufReader::new(file), location.offset).unwrap();
+    let metadata = reader.begin_record().unwrap().unwrap();
+    assert_eq!(
+        metadata.fields().get_str("WARC-Target-URI"),
+        Some("http://example.com/0")
+    );
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut writer = RotatingWARCWriter::new(dir.path(), "test");
+    writer
+        .begin_record(&common::make_header(0, block.len()))
+        .unwrap();
+    let path = writer.current_path().unwrap().to_path_buf();
+    drop(writer);
+
+    assert!(!path.exists());
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+}
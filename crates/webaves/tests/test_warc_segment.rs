@@ -0,0 +1,176 @@
+use std::io::Read;
+
+use webaves::{
+    header::HeaderMap,
+    warc::{
+        extract::ExtractorDispatcher, RotatingWARCWriter, SegmentIndex, WARCError, WARCReader,
+        WARCRecordType,
+    },
+};
+
+fn make_block(length: usize) -> Vec<u8> {
+    // Pseudorandom data so the compressed size is close to the block size
+    let mut state = 1u32;
+
+    (0..length)
+        .map(|_| {
+            state = state.wrapping_mul(1103515245).wrapping_add(12345);
+            (state >> 16) as u8
+        })
+        .collect()
+}
+
+fn make_header(length: usize) -> HeaderMap {
+    let mut header = HeaderMap::new();
+    header.insert("WARC-Type", "resource");
+    header.insert("WARC-Date", "2000-12-30T01:02:03Z");
+    header.insert(
+        "WARC-Record-ID",
+        "<urn:uuid:00000001-0002-0003-0004-000000000001>",
+    );
+    header.insert("WARC-Target-URI", "http://example.com/big.bin");
+    header.insert("Content-Type", "application/octet-stream");
+    header.insert("Content-Length", length.to_string());
+    header
+}
+
+fn list_files(dir: &std::path::Path) -> Vec<std::path::PathBuf> {
+    let mut paths = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect::<Vec<std::path::PathBuf>>();
+    paths.sort();
+    paths
+}
+
+#[test_log::test]
+fn segmented_record_write_and_reassemble() {
+    let dir = tempfile::tempdir().unwrap();
+    let block = make_block(300_000);
+
+    let mut writer = RotatingWARCWriter::new(dir.path(), "test");
+    writer.set_max_file_size(Some(100_000));
+    let locations = writer
+        .write_segmented_record(&make_header(block.len()), block.as_slice())
+        .unwrap();
+    writer.close().unwrap();
+
+    assert!(locations.len() > 1);
+
+    let paths = list_files(dir.path());
+    assert_eq!(paths.len(), locations.len());
+
+    for (index, location) in locations.iter().enumerate() {
+        assert_eq!(location.path, paths[index]);
+
+        let file = std::fs::File::open(&location.path).unwrap();
+        let mut reader =
+            WARCReader::open_at(std::io::BufReader::new(file), location.offset).unwrap();
+        let metadata = reader.begin_record().unwrap().unwrap();
+        let fields = metadata.fields();
+
+        assert_eq!(
+            fields.get_str("WARC-Segment-Number"),
+            Some((index + 1).to_string().as_str())
+        );
+
+        if index == 0 {
+            assert_eq!(metadata.record_type(), Some(WARCRecordType::Resource));
+        } else {
+            assert_eq!(metadata.record_type(), Some(WARCRecordType::Continuation));
+            assert_eq!(
+                fields.get_str("WARC-Segment-Origin-ID"),
+                Some("<urn:uuid:00000001-0002-0003-0004-000000000001>")
+            );
+            assert_eq!(
+                fields.get_str("WARC-Target-URI"),
+                Some("http://example.com/big.bin")
+            );
+        }
+
+        if index == locations.len() - 1 {
+            assert_eq!(fields.get_str("WARC-Segment-Total-Length"), Some("300000"));
+        } else {
+            assert!(!fields.contains_key("WARC-Segment-Total-Length"));
+        }
+    }
+
+    let mut index = SegmentIndex::new();
+
+    for path in &paths {
+        index.add_file(path).unwrap();
+    }
+
+    let record_id = "<urn:uuid:00000001-0002-0003-0004-000000000001>";
+    assert_eq!(index.record_ids(), vec![record_id]);
+    assert!(index.is_complete(record_id));
+
+    let (metadata, mut block_reader) = index.open(record_id).unwrap();
+    assert_eq!(metadata.block_length(), 300_000);
+    assert!(!metadata.fields().contains_key("WARC-Segment-Number"));
+
+    let mut reassembled = Vec::new();
+    block_reader.read_to_end(&mut reassembled).unwrap();
+    assert!(reassembled == block);
+
+    let (metadata, block_reader) = index.open(record_id).unwrap();
+    let mut extractor = ExtractorDispatcher::new(block_reader);
+    extractor.add_default_extractors();
+    assert!(extractor.can_accept_any(&metadata));
+    extractor.begin(&metadata).unwrap();
+
+    let mut extracted = Vec::new();
+    extractor.read_to_end(&mut extracted).unwrap();
+    assert!(extracted == block);
+}
+
+#[test_log::test]
+fn segmented_record_fits_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let block = make_block(1000);
+
+    let mut writer = RotatingWARCWriter::new(dir.path(), "test");
+    writer.set_max_file_size(Some(100_000));
+    writer.set_digest_algorithm(Some("sha1")).unwrap();
+    let locations = writer
+        .write_segmented_record(&make_header(block.len()), block.as_slice())
+        .unwrap();
+    writer.close().unwrap();
+
+    assert_eq!(locations.len(), 1);
+
+    let file = std::fs::File::open(&locations[0].path).unwrap();
+    let mut reader =
+        WARCReader::open_at(std::io::BufReader::new(file), locations[0].offset).unwrap();
+    let metadata = reader.begin_record().unwrap().unwrap();
+
+    assert!(!metadata.fields().contains_key("WARC-Segment-Number"));
+    assert!(metadata.fields().contains_key("WARC-Block-Digest"));
+}
+
+#[test_log::test]
+fn segmented_record_incomplete() {
+    let dir = tempfile::tempdir().unwrap();
+    let block = make_block(300_000);
+
+    let mut writer = RotatingWARCWriter::new(dir.path(), "test");
+    writer.set_max_file_size(Some(100_000));
+    writer
+        .write_segmented_record(&make_header(block.len()), block.as_slice())
+        .unwrap();
+    writer.close().unwrap();
+
+    let paths = list_files(dir.path());
+    let mut index = SegmentIndex::new();
+
+    for path in &paths[0..paths.len() - 1] {
+        index.add_file(path).unwrap();
+    }
+
+    let record_id = "<urn:uuid:00000001-0002-0003-0004-000000000001>";
+    assert!(!index.is_complete(record_id));
+    assert!(matches!(
+        index.open(record_id),
+        Err(WARCError::IncompleteSegments { .. })
+    ));
+}
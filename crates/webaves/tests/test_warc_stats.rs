@@ -0,0 +1,147 @@
+mod common;
+
+use webaves::{
+    compress::CompressionFormat,
+    warc::{
+        stats::Statistics, RequestRecordBuilder, ResourceRecordBuilder, ResponseRecordBuilder,
+        RevisitRecordBuilder, WARCReader, WARCRecord, WARCWriter, WarcinfoRecordBuilder,
+        IDENTICAL_PAYLOAD_DIGEST_PROFILE,
+    },
+};
+
+fn make_records() -> Vec<WARCRecord> {
+    let mut records = Vec::new();
+
+    let warcinfo = WarcinfoRecordBuilder::new()
+        .field("WARC-Date", "2022-01-02T00:00:00Z")
+        .payload(b"software: test\r\n".as_slice())
+        .build()
+        .unwrap();
+    records.push(warcinfo);
+
+    for (url, date, response) in [
+        (
+            "http://example.com/",
+            "2022-01-03T00:00:00Z",
+            "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\n\r\n<p>Hello</p>",
+        ),
+        (
+            "http://www.Example.net/missing",
+            "2022-01-01T00:00:00Z",
+            "HTTP/1.1 404 Not Found\r\nContent-Type: text/html\r\n\r\n",
+        ),
+    ] {
+        let request = RequestRecordBuilder::new(url)
+            .field("WARC-Date", date)
+            .payload(b"GET / HTTP/1.1\r\n\r\n".as_slice())
+            .build()
+            .unwrap();
+        let response = ResponseRecordBuilder::new(url)
+            .field("WARC-Date", date)
+            .payload(response.as_bytes())
+            .build()
+            .unwrap();
+
+        records.push(request);
+        records.push(response);
+    }
+
+    let resource = ResourceRecordBuilder::new("http://example.com/image.png")
+        .field("WARC-Date", "2022-01-02T00:00:00Z")
+        .field("Content-Type", "image/png")
+        .payload(b"PNG".as_slice())
+        .build()
+        .unwrap();
+    records.push(resource);
+
+    let revisit =
+        RevisitRecordBuilder::new("http://example.com/", IDENTICAL_PAYLOAD_DIGEST_PROFILE)
+            .field("WARC-Date", "2022-01-04T00:00:00Z")
+            .payload(Vec::new())
+            .build()
+            .unwrap();
+    records.push(revisit);
+
+    records
+}
+
+#[test_log::test]
+fn statistics_add_reader() {
+    let data = common::write_records(&make_records(), CompressionFormat::Gzip);
+    let mut stats = Statistics::new();
+    stats
+        .add_reader(&mut WARCReader::new(data.as_slice()).unwrap())
+        .unwrap();
+
+    assert_eq!(stats.record_count, 7);
+    assert_eq!(stats.record_types["request"], 2);
+    assert_eq!(stats.record_types["response"], 2);
+    assert_eq!(stats.record_types["warcinfo"], 1);
+    assert_eq!(stats.revisit_count, 1);
+    assert_eq!(stats.mime_types["text/html"], 2);
+    assert_eq!(stats.mime_types["image/png"], 1);
+    assert_eq!(stats.mime_types["warc/revisit"], 1);
+    assert_eq!(stats.status_classes["2xx"], 1);
+    assert_eq!(stats.status_classes["4xx"], 1);
+    assert_eq!(stats.hosts["example.com"], 3);
+    assert_eq!(stats.hosts["www.example.net"], 1);
+    assert!(stats.digest_algorithms["sha1"] >= 7);
+    assert_eq!(stats.compressed_bytes, data.len() as u64);
+    assert_eq!(
+        stats.uncompressed_bytes,
+        common::write_records(&make_records(), CompressionFormat::Raw).len() as u64
+    );
+    assert_eq!(
+        stats.first_date.unwrap().to_rfc3339(),
+        "2022-01-01T00:00:00+00:00"
+    );
+    assert_eq!(
+        stats.last_date.unwrap().to_rfc3339(),
+        "2022-01-04T00:00:00+00:00"
+    );
+
+    let json = serde_json::to_value(&stats).unwrap();
+    assert_eq!(json["first_date"], "2022-01-01T00:00:00Z");
+}
+
+#[test_log::test]
+fn statistics_add_reader_zstd_dictionary() {
+    let mut writer =
+        WARCWriter::new_compressed(Vec::new(), CompressionFormat::Zstd, Default::default());
+    writer
+        .write_zstd_dictionary(b"HTTP/1.1 200 OK\r\nContent-Type: text/html\r\n".repeat(16))
+        .unwrap();
+
+    for record in make_records() {
+        writer.write_record(&record).unwrap();
+    }
+
+    let data = writer.into_inner();
+    let mut stats = Statistics::new();
+    stats
+        .add_reader(&mut WARCReader::new(data.as_slice()).unwrap())
+        .unwrap();
+
+    assert_eq!(stats.record_count, 7);
+    assert_eq!(stats.compressed_bytes, data.len() as u64);
+}
+
+#[test]
+fn statistics_merge() {
+    let data = common::write_records(&make_records(), CompressionFormat::Raw);
+    let mut stats = Statistics::new();
+    stats
+        .add_reader(&mut WARCReader::new(data.as_slice()).unwrap())
+        .unwrap();
+
+    let mut merged = Statistics::new();
+    merged.merge(&stats);
+    assert_eq!(merged, stats);
+
+    merged.merge(&stats);
+    assert_eq!(merged.record_count, 14);
+    assert_eq!(merged.hosts["example.com"], 6);
+    assert_eq!(merged.uncompressed_bytes, 2 * data.len() as u64);
+    assert_eq!(merged.first_date, stats.first_date);
+    assert_eq!(merged.last_date, stats.last_date);
+}
@@ -0,0 +1,201 @@
+mod common;
+
+use std::io::Write;
+
+use webaves::{
+    compress::{CompressionFormat, Compressor},
+    warc::{
+        validate::{Problem, ProblemKind, Severity, Validator},
+        WARCRecord,
+    },
+};
+
+fn make_response(record_id: &str) -> WARCRecord {
+    common::make_response(
+        "http://example.com/",
+        record_id,
+        "2000-12-30T01:02:03Z",
+        common::HTTP_OK_HEADER,
+    )
+}
+
+fn validate(data: &[u8]) -> Vec<Problem> {
+    let mut validator = Validator::new();
+    validator.validate_file("test.warc", data).unwrap();
+    validator.finish()
+}
+
+fn kinds(problems: &[Problem]) -> Vec<ProblemKind> {
+    problems.iter().map(|problem| problem.kind).collect()
+}
+
+#[test_log::test]
+fn validate_valid_file() {
+    let data = common::write_records(
+        &[
+            make_response("<urn:uuid:00000001-0002-0003-0004-000000000001>"),
+            make_response("<urn:uuid:00000001-0002-0003-0004-000000000002>"),
+        ],
+        CompressionFormat::Gzip,
+    );
+
+    let problems = validate(&data);
+    assert!(problems.is_empty(), "{:?}", problems);
+}
+
+#[test_log::test]
+fn validate_digest_mismatch() {
+    let data = common::write_records(
+        &[make_response(
+            "<urn:uuid:00000001-0002-0003-0004-000000000001>",
+        )],
+        CompressionFormat::Raw,
+    );
+    let text = String::from_utf8(data).unwrap().replace("Hello", "Jello");
+
+    let problems = validate(text.as_bytes());
+    assert_eq!(
+        kinds(&problems),
+        vec![
+            ProblemKind::BlockDigestMismatch,
+            ProblemKind::PayloadDigestMismatch
+        ]
+    );
+    assert_eq!(problems[0].offset, 0);
+    assert_eq!(problems[0].severity, Severity::Error);
+    assert_eq!(
+        problems[0].record_id.as_deref(),
+        Some("<urn:uuid:00000001-0002-0003-0004-000000000001>")
+    );
+}
+
+#[test_log::test]
+fn validate_header_fields() {
+    let mut record = make_response("<urn:uuid:00000001-0002-0003-0004-000000000001>");
+    record.header.insert(
+        "WARC-Record-ID",
+        "urn:uuid:00000001-0002-0003-0004-000000000001",
+    );
+    record.header.insert("WARC-Date", "2000-12-30 01:02:03");
+    record.header.insert("WARC-IP-Address", "example");
+    record.header.insert(
+        "WARC-Concurrent-To",
+        "<urn:uuid:00000001-0002-0003-0004-00000000000f>",
+    );
+    let data = common::write_records(&[record], CompressionFormat::Raw);
+
+    let problems = validate(&data);
+    let kinds = kinds(&problems);
+
+    assert!(kinds.contains(&ProblemKind::InvalidRecordId));
+    assert!(kinds.contains(&ProblemKind::InvalidDate));
+    assert!(kinds.contains(&ProblemKind::InvalidField));
+    assert!(kinds.contains(&ProblemKind::DanglingReference));
+}
+
+#[test_log::test]
+fn validate_missing_field_and_duplicate_id() {
+    let mut record = make_response("<urn:uuid:00000001-0002-0003-0004-000000000001>");
+    let mut header = webaves::header::HeaderMap::new();
+
+    for pair in record.header.iter() {
+        if pair.name.text != "WARC-Target-URI" {
+            header.append(pair.name.text.as_str(), pair.value.text.as_str());
+        }
+    }
+    record.header = header;
+
+    let data = common::write_records(&[record.clone(), record], CompressionFormat::Raw);
+
+    let problems = validate(&data);
+    assert_eq!(
+        kinds(&problems),
+        vec![
+            ProblemKind::MissingField,
+            ProblemKind::MissingField,
+            ProblemKind::DuplicateRecordId
+        ]
+    );
+}
+
+#[test_log::test]
+fn validate_wrong_content_length() {
+    let data = common::write_records(
+        &[make_response(
+            "<urn:uuid:00000001-0002-0003-0004-000000000001>",
+        )],
+        CompressionFormat::Raw,
+    );
+    let text = String::from_utf8(data)
+        .unwrap()
+        .replace("Content-Length: 43", "Content-Length: 40");
+
+    let problems = validate(text.as_bytes());
+    assert_eq!(
+        kinds(&problems).last(),
+        Some(&ProblemKind::WrongContentLength)
+    );
+}
+
+#[test_log::test]
+fn validate_trailing_data() {
+    let mut data = common::write_records(
+        &[make_response(
+            "<urn:uuid:00000001-0002-0003-0004-000000000001>",
+        )],
+        CompressionFormat::Raw,
+    );
+    let length = data.len() as u64;
+    data.extend_from_slice(b"garbage\r\n");
+
+    let problems = validate(&data);
+    assert_eq!(kinds(&problems), vec![ProblemKind::TrailingData]);
+    assert_eq!(problems[0].offset, length);
+
+    let problems = validate(b"garbage\r\n");
+    assert_eq!(kinds(&problems), vec![ProblemKind::NotWarc]);
+}
+
+#[test_log::test]
+fn validate_trailing_data_gzip() {
+    let mut data = common::write_records(
+        &[make_response(
+            "<urn:uuid:00000001-0002-0003-0004-000000000001>",
+        )],
+        CompressionFormat::Gzip,
+    );
+    let length = data.len() as u64;
+    data.extend_from_slice(b"garbage\r\n");
+
+    let problems = validate(&data);
+    assert_eq!(kinds(&problems), vec![ProblemKind::TrailingData]);
+    assert_eq!(problems[0].offset, length);
+    assert_eq!(problems[0].record_id, None);
+}
+
+#[test_log::test]
+fn validate_compression_member() {
+    let data = common::write_records(
+        &[
+            make_response("<urn:uuid:00000001-0002-0003-0004-000000000001>"),
+            make_response("<urn:uuid:00000001-0002-0003-0004-000000000002>"),
+        ],
+        CompressionFormat::Raw,
+    );
+    let mut compressor =
+        Compressor::new(Vec::new(), CompressionFormat::Gzip, Default::default()).unwrap();
+    compressor.write_all(&data).unwrap();
+    let data = compressor.finish().unwrap();
+
+    let problems = validate(&data);
+    assert_eq!(
+        kinds(&problems),
+        vec![
+            ProblemKind::CompressionMember,
+            ProblemKind::CompressionMember
+        ]
+    );
+    assert!(problems
+        .iter()
+        .all(|problem| problem.severity == Severity::Warning));
+}